@@ -0,0 +1,883 @@
+//! A strategy that selects one version per package name and backtracks when
+//! the versions it picked cannot satisfy every requirement together.
+//!
+//! Dependency ranges, peer ranges, and overrides are all requirements on a
+//! package name. Names are decided in the order they are first needed, each
+//! taking its most preferred version that satisfies every requirement known so
+//! far. When a later requirement rules a decision out, the search returns to
+//! the most recent decision that contributed to the conflict (conflict-directed
+//! backjumping) and tries its next version. When no decision is left, the
+//! failures found on the way form the [`Derivation`] returned in
+//! [`ResolutionError::Conflict`]. See `docs/specs/core/resolver/SPEC.md`,
+//! "Backtracking strategy".
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fmt;
+
+use super::{
+    package_key, DependencyEdge, DependencyRequest, DependencyRequestKind, PackageMetadataProvider,
+    PeerEdge, ResolutionError, ResolutionStrategy, ResolvedDependencyGraph, ResolvedPackage,
+    ResolvedRequest,
+};
+
+/// Who placed a [`Requirement`] on a package.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Requirer {
+    /// A direct request of the project.
+    Project(DependencyRequestKind),
+    /// A dependency or peer dependency of a selected package version.
+    Package {
+        package_name: String,
+        version: String,
+    },
+    /// An override, which replaces every other requirement on the package.
+    Override,
+}
+
+/// A range or tag some requirer asks a package to satisfy.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Requirement {
+    pub required_by: Requirer,
+    pub package_name: String,
+    pub requested: String,
+    /// A `peerDependencies` entry: it constrains the version of a package
+    /// in the graph, and adds the package only when the peer is required.
+    pub peer: bool,
+}
+
+/// Why resolution found no version set, as a tree of failed decisions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Derivation {
+    /// No version of the package satisfies these requirements together.
+    Incompatible {
+        package_name: String,
+        requirements: Vec<Requirement>,
+    },
+    /// The version selected for a package does not satisfy a requirement
+    /// placed on it afterwards.
+    Excluded {
+        package_name: String,
+        version: String,
+        requirement: Requirement,
+    },
+    /// Every candidate version of a package failed, each for its own reason.
+    Exhausted {
+        package_name: String,
+        attempts: Vec<(String, Derivation)>,
+    },
+}
+
+/// Resolves one version per package name, backtracking on conflicts.
+#[derive(Debug, Default)]
+pub struct BacktrackingResolutionStrategy {
+    /// Range each overridden package must satisfy instead of its requests.
+    overrides: HashMap<String, String>,
+}
+
+impl BacktrackingResolutionStrategy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Make every listed package satisfy the override's range in place of
+    /// the ranges its dependents request, like npm's `overrides`.
+    pub fn with_overrides(mut self, overrides: impl IntoIterator<Item = (String, String)>) -> Self {
+        self.overrides.extend(overrides);
+        self
+    }
+}
+
+impl ResolutionStrategy for BacktrackingResolutionStrategy {
+    fn resolve<M: PackageMetadataProvider>(
+        &self,
+        requests: Vec<DependencyRequest>,
+        metadata: &M,
+    ) -> Result<ResolvedDependencyGraph, ResolutionError> {
+        Solver::new(metadata, &self.overrides).solve(requests)
+    }
+}
+
+/// A conflict and the packages whose decisions led to it.
+struct Failure {
+    derivation: Box<Derivation>,
+    culprits: BTreeSet<String>,
+}
+
+/// A decision on one package name: the versions it may take and how far the
+/// search has tried them.
+struct Frame {
+    package_name: String,
+    versions: Vec<String>,
+    next: usize,
+    /// Lengths of the requirement log and the needed list before any of its
+    /// versions was selected, which undoing the decision truncates back to.
+    log_len: usize,
+    needed_len: usize,
+    attempts: Vec<(String, Derivation)>,
+    culprits: BTreeSet<String>,
+}
+
+struct Solver<'a, M> {
+    metadata: &'a M,
+    overrides: &'a HashMap<String, String>,
+    /// Every requirement placed so far, in order.
+    log: Vec<Requirement>,
+    /// Indexes into `log` of the requirements on each package name.
+    by_name: HashMap<String, Vec<usize>>,
+    /// Package names with a requirement that is not an optional peer one, in
+    /// the order they gained it.
+    needed: Vec<String>,
+    needed_set: HashSet<String>,
+    selected: HashMap<String, String>,
+    /// Versions each `(package_name, requested)` may select, most preferred
+    /// first. Cached for the whole search; metadata does not change.
+    candidates: HashMap<(String, String), Vec<String>>,
+    /// `(name@version, peer name)` of each peer that `peerDependenciesMeta`
+    /// marks optional.
+    optional_peers: HashSet<(String, String)>,
+}
+
+impl<'a, M: PackageMetadataProvider> Solver<'a, M> {
+    fn new(metadata: &'a M, overrides: &'a HashMap<String, String>) -> Self {
+        Self {
+            metadata,
+            overrides,
+            log: Vec::new(),
+            by_name: HashMap::new(),
+            needed: Vec::new(),
+            needed_set: HashSet::new(),
+            selected: HashMap::new(),
+            candidates: HashMap::new(),
+            optional_peers: HashSet::new(),
+        }
+    }
+
+    fn solve(
+        mut self,
+        requests: Vec<DependencyRequest>,
+    ) -> Result<ResolvedDependencyGraph, ResolutionError> {
+        for request in requests {
+            // Nothing is selected yet, so a direct request cannot conflict.
+            self.require(Requirement {
+                required_by: Requirer::Project(request.kind),
+                package_name: request.package_name,
+                requested: request.requested,
+                peer: false,
+            })?;
+        }
+        let mut frames: Vec<Frame> = Vec::new();
+        loop {
+            let Some(package_name) = self.next_undecided() else {
+                return Ok(self.graph());
+            };
+            let requirements = self.effective_requirements(&package_name);
+            let versions = self.satisfying_versions(&package_name, &requirements)?;
+            let mut failure = if versions.is_empty() {
+                Some(Failure {
+                    culprits: requirers(&requirements),
+                    derivation: Box::new(Derivation::Incompatible {
+                        package_name,
+                        requirements,
+                    }),
+                })
+            } else {
+                frames.push(Frame {
+                    package_name,
+                    versions,
+                    next: 0,
+                    log_len: self.log.len(),
+                    needed_len: self.needed.len(),
+                    attempts: Vec::new(),
+                    culprits: BTreeSet::new(),
+                });
+                None
+            };
+            // Select the next version of the innermost decision, unwinding
+            // past the decisions a conflict does not involve.
+            loop {
+                if let Some(conflict) = failure.take() {
+                    self.unwind(&mut frames, conflict).map_err(|failure| {
+                        ResolutionError::Conflict {
+                            derivation: failure.derivation,
+                        }
+                    })?;
+                }
+                let Some(frame) = frames.last_mut() else {
+                    break;
+                };
+                let package_name = frame.package_name.clone();
+                let version = frame.versions[frame.next].clone();
+                frame.next += 1;
+                match self.select(&package_name, &version)? {
+                    Some(conflict) => failure = Some(conflict),
+                    None => break,
+                }
+            }
+        }
+    }
+
+    /// Undo decisions until one involved in `failure` has a version left to
+    /// try. Returns the failure when every decision is exhausted.
+    fn unwind(&mut self, frames: &mut Vec<Frame>, mut failure: Failure) -> Result<(), Failure> {
+        while let Some(mut frame) = frames.pop() {
+            self.undo(&frame);
+            if !failure.culprits.contains(&frame.package_name) {
+                // Another version of this package would fail the same way.
+                continue;
+            }
+            let tried = frame.versions[frame.next - 1].clone();
+            frame.culprits.extend(
+                failure
+                    .culprits
+                    .into_iter()
+                    .filter(|culprit| *culprit != frame.package_name),
+            );
+            frame.attempts.push((tried, *failure.derivation));
+            if frame.next < frame.versions.len() {
+                frames.push(frame);
+                return Ok(());
+            }
+            // Every version failed, so the conflict lies with whatever made
+            // this package needed with these candidates.
+            let mut culprits = frame.culprits;
+            culprits.extend(requirers(&self.effective_requirements(&frame.package_name)));
+            let derivation = if frame.attempts.len() == 1 {
+                frame.attempts.remove(0).1
+            } else {
+                Derivation::Exhausted {
+                    package_name: frame.package_name,
+                    attempts: frame.attempts,
+                }
+            };
+            failure = Failure {
+                derivation: Box::new(derivation),
+                culprits,
+            };
+        }
+        Err(failure)
+    }
+
+    fn undo(&mut self, frame: &Frame) {
+        self.selected.remove(&frame.package_name);
+        while self.log.len() > frame.log_len {
+            if let Some(requirement) = self.log.pop() {
+                if let Some(indexes) = self.by_name.get_mut(&requirement.package_name) {
+                    indexes.pop();
+                }
+            }
+        }
+        for package_name in self.needed.drain(frame.needed_len..) {
+            self.needed_set.remove(&package_name);
+        }
+    }
+
+    /// Select `version` of `package_name` and place its requirements.
+    fn select(
+        &mut self,
+        package_name: &str,
+        version: &str,
+    ) -> Result<Option<Failure>, ResolutionError> {
+        self.selected
+            .insert(package_name.to_string(), version.to_string());
+        let required_by = Requirer::Package {
+            package_name: package_name.to_string(),
+            version: version.to_string(),
+        };
+        let dependencies = self
+            .metadata
+            .dependencies_for_version(package_name, version)?
+            .into_iter()
+            .map(|dependency| (dependency.package_name, dependency.requested, None));
+        let peers = self
+            .metadata
+            .peer_dependencies_for_version(package_name, version)?
+            .into_iter()
+            .map(|peer| (peer.package_name, peer.requested, Some(peer.optional)));
+        for (dependency_name, requested, peer) in dependencies.chain(peers).collect::<Vec<_>>() {
+            let failure = self.require(Requirement {
+                required_by: required_by.clone(),
+                package_name: dependency_name.clone(),
+                requested,
+                peer: peer.is_some(),
+            })?;
+            if failure.is_some() {
+                return Ok(failure);
+            }
+            match peer {
+                Some(true) => {
+                    self.optional_peers
+                        .insert((package_key(package_name, version), dependency_name));
+                }
+                // A required peer is installed when nothing else needs it.
+                Some(false) if self.needed_set.insert(dependency_name.clone()) => {
+                    self.needed.push(dependency_name);
+                }
+                _ => {}
+            }
+        }
+        Ok(None)
+    }
+
+    /// Place `requirement`, checking it against the version already selected
+    /// for its package, if any.
+    fn require(&mut self, requirement: Requirement) -> Result<Option<Failure>, ResolutionError> {
+        let package_name = requirement.package_name.clone();
+        self.by_name
+            .entry(package_name.clone())
+            .or_default()
+            .push(self.log.len());
+        self.log.push(requirement.clone());
+        if !requirement.peer && self.needed_set.insert(package_name.clone()) {
+            self.needed.push(package_name.clone());
+        }
+        let Some(version) = self.selected.get(&package_name).cloned() else {
+            return Ok(None);
+        };
+        if self.overrides.contains_key(&package_name)
+            || self
+                .candidates_for(&package_name, &requirement.requested)?
+                .contains(&version)
+        {
+            return Ok(None);
+        }
+        let requirements = self.effective_requirements(&package_name);
+        if self
+            .satisfying_versions(&package_name, &requirements)?
+            .is_empty()
+        {
+            return Ok(Some(Failure {
+                culprits: requirers(&requirements),
+                derivation: Box::new(Derivation::Incompatible {
+                    package_name,
+                    requirements,
+                }),
+            }));
+        }
+        // Another version would do, so the selection is as much to blame as
+        // the new requirement.
+        let mut culprits = requirers(std::slice::from_ref(&requirement));
+        culprits.insert(package_name.clone());
+        Ok(Some(Failure {
+            culprits,
+            derivation: Box::new(Derivation::Excluded {
+                package_name,
+                version,
+                requirement,
+            }),
+        }))
+    }
+
+    /// The first needed package name that has no version yet.
+    fn next_undecided(&self) -> Option<String> {
+        self.needed
+            .iter()
+            .find(|package_name| !self.selected.contains_key(*package_name))
+            .cloned()
+    }
+
+    /// The requirements a version of `package_name` must satisfy: the
+    /// override alone when there is one, otherwise every requirement placed.
+    fn effective_requirements(&self, package_name: &str) -> Vec<Requirement> {
+        if let Some(requested) = self.overrides.get(package_name) {
+            return vec![Requirement {
+                required_by: Requirer::Override,
+                package_name: package_name.to_string(),
+                requested: requested.clone(),
+                peer: false,
+            }];
+        }
+        self.by_name
+            .get(package_name)
+            .into_iter()
+            .flatten()
+            .map(|index| self.log[*index].clone())
+            .collect()
+    }
+
+    /// Versions of `package_name` that satisfy every one of `requirements`,
+    /// in the preference order of the first.
+    fn satisfying_versions(
+        &mut self,
+        package_name: &str,
+        requirements: &[Requirement],
+    ) -> Result<Vec<String>, ResolutionError> {
+        let Some((first, rest)) = requirements.split_first() else {
+            return Ok(Vec::new());
+        };
+        let mut versions = self
+            .candidates_for(package_name, &first.requested)?
+            .to_vec();
+        for requirement in rest {
+            let allowed = self.candidates_for(package_name, &requirement.requested)?;
+            versions.retain(|version| allowed.contains(version));
+        }
+        Ok(versions)
+    }
+
+    /// The versions `requested` may select for `package_name`. A request no
+    /// version satisfies offers none, which the search treats as a conflict.
+    fn candidates_for(
+        &mut self,
+        package_name: &str,
+        requested: &str,
+    ) -> Result<&[String], ResolutionError> {
+        let key = (package_name.to_string(), requested.to_string());
+        if !self.candidates.contains_key(&key) {
+            let versions = match self.metadata.candidate_versions(package_name, requested) {
+                Ok(versions) => versions,
+                Err(ResolutionError::VersionSelection { .. }) => Vec::new(),
+                Err(error) => return Err(error),
+            };
+            self.candidates.insert(key.clone(), versions);
+        }
+        Ok(self.candidates[&key].as_slice())
+    }
+
+    /// The selected packages in the order they were first needed, with the
+    /// requests and edges their requirements place. A peer requirement is an
+    /// edge to whatever version of the peer was selected, and a request only
+    /// on a package that nothing else requires.
+    fn graph(self) -> ResolvedDependencyGraph {
+        let mut packages = self
+            .needed
+            .iter()
+            .map(|package_name| ResolvedPackage {
+                package_name: package_name.clone(),
+                version: self.selected[package_name].clone(),
+                requests: Vec::new(),
+                dependencies: Vec::new(),
+                peer_dependencies: Vec::new(),
+            })
+            .collect::<Vec<_>>();
+        let indexes = packages
+            .iter()
+            .enumerate()
+            .map(|(index, package)| (package_key(&package.package_name, &package.version), index))
+            .collect::<HashMap<_, _>>();
+        for requirement in self.log.iter().filter(|requirement| !requirement.peer) {
+            let resolved_version = &self.selected[&requirement.package_name];
+            let kind = match &requirement.required_by {
+                Requirer::Project(kind) => *kind,
+                _ => DependencyRequestKind::Transitive,
+            };
+            let target = indexes[&package_key(&requirement.package_name, resolved_version)];
+            packages[target].add_request(ResolvedRequest {
+                requested: requirement.requested.clone(),
+                kind,
+            });
+            if let Requirer::Package {
+                package_name,
+                version,
+            } = &requirement.required_by
+            {
+                let parent = indexes[&package_key(package_name, version)];
+                packages[parent].add_dependency(DependencyEdge {
+                    package_name: requirement.package_name.clone(),
+                    requested: requirement.requested.clone(),
+                    resolved_version: resolved_version.clone(),
+                });
+            }
+        }
+        let peer_only = packages
+            .iter()
+            .enumerate()
+            .filter(|(_, package)| package.requests.is_empty())
+            .map(|(index, _)| index)
+            .collect::<HashSet<_>>();
+        for requirement in self.log.iter().filter(|requirement| requirement.peer) {
+            let Requirer::Package {
+                package_name,
+                version,
+            } = &requirement.required_by
+            else {
+                continue;
+            };
+            let resolved_version = self.selected.get(&requirement.package_name);
+            if let Some(resolved_version) = resolved_version {
+                let target = indexes[&package_key(&requirement.package_name, resolved_version)];
+                if peer_only.contains(&target) {
+                    packages[target].add_request(ResolvedRequest {
+                        requested: requirement.requested.clone(),
+                        kind: DependencyRequestKind::Transitive,
+                    });
+                }
+            }
+            let parent = indexes[&package_key(package_name, version)];
+            packages[parent].add_peer_dependency(PeerEdge {
+                package_name: requirement.package_name.clone(),
+                requested: requirement.requested.clone(),
+                optional: self.optional_peers.contains(&(
+                    package_key(package_name, version),
+                    requirement.package_name.clone(),
+                )),
+                resolved_version: resolved_version.cloned(),
+            });
+        }
+        ResolvedDependencyGraph { packages }
+    }
+}
+
+/// Packages whose selected versions placed `requirements`.
+fn requirers(requirements: &[Requirement]) -> BTreeSet<String> {
+    requirements
+        .iter()
+        .filter_map(|requirement| match &requirement.required_by {
+            Requirer::Package { package_name, .. } => Some(package_name.clone()),
+            Requirer::Project(_) | Requirer::Override => None,
+        })
+        .collect()
+}
+
+impl fmt::Display for Requirement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let peer = if self.peer { "peer " } else { "" };
+        match &self.required_by {
+            Requirer::Project(_) => write!(
+                f,
+                "the project needs {peer}{}@{}",
+                self.package_name, self.requested
+            ),
+            Requirer::Package {
+                package_name,
+                version,
+            } => write!(
+                f,
+                "{package_name}@{version} needs {peer}{}@{}",
+                self.package_name, self.requested
+            ),
+            Requirer::Override => write!(
+                f,
+                "overrides pin {} to {}",
+                self.package_name, self.requested
+            ),
+        }
+    }
+}
+
+impl fmt::Display for Derivation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Incompatible {
+                package_name,
+                requirements,
+            } => match requirements.as_slice() {
+                [] => write!(f, "nothing requires {package_name}"),
+                [only] => write!(f, "{only}, which no version of {package_name} satisfies"),
+                [first, second] => write!(f, "{first}, but {second}"),
+                [rest @ .., last] => {
+                    for requirement in rest {
+                        write!(f, "{requirement}, ")?;
+                    }
+                    write!(
+                        f,
+                        "and {last}, which no version of {package_name} satisfies at once"
+                    )
+                }
+            },
+            Self::Excluded {
+                package_name,
+                version,
+                requirement,
+            } => write!(
+                f,
+                "{requirement}, but {package_name}@{version} was selected"
+            ),
+            Self::Exhausted {
+                package_name,
+                attempts,
+            } => {
+                write!(f, "no version of {package_name} works:")?;
+                for (version, derivation) in attempts {
+                    let nested = derivation.to_string().replace('\n', "\n  ");
+                    write!(f, "\n  {package_name}@{version}: {nested}")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::resolver::semver;
+    use crate::core::resolver::{DependencyDeclaration, PeerDeclaration};
+    use std::collections::BTreeMap;
+
+    type Release = (&'static str, Vec<&'static str>, Vec<&'static str>);
+
+    /// Packages by name, each release with its dependency and peer specs.
+    struct MemoryProvider {
+        packages: BTreeMap<&'static str, Vec<Release>>,
+    }
+
+    impl MemoryProvider {
+        fn new(packages: &[(&'static str, Vec<Release>)]) -> Self {
+            Self {
+                packages: packages.iter().cloned().collect(),
+            }
+        }
+
+        fn release(&self, package_name: &str, version: &str) -> &Release {
+            self.packages[package_name]
+                .iter()
+                .find(|release| release.0 == version)
+                .unwrap()
+        }
+    }
+
+    impl PackageMetadataProvider for MemoryProvider {
+        fn select_version(
+            &self,
+            package_name: &str,
+            requested: &str,
+        ) -> Result<String, ResolutionError> {
+            self.candidate_versions(package_name, requested)?
+                .into_iter()
+                .next()
+                .ok_or_else(|| {
+                    ResolutionError::version_selection(
+                        package_name,
+                        requested,
+                        semver::SemverError::UnsatisfiedRange {
+                            range: requested.to_string(),
+                        },
+                    )
+                })
+        }
+
+        fn candidate_versions(
+            &self,
+            package_name: &str,
+            requested: &str,
+        ) -> Result<Vec<String>, ResolutionError> {
+            let satisfying = self.packages[package_name]
+                .iter()
+                .map(|release| release.0)
+                .filter(|version| semver::satisfies(version, requested).unwrap());
+            Ok(semver::rsort(satisfying)
+                .unwrap()
+                .into_iter()
+                .map(str::to_string)
+                .collect())
+        }
+
+        fn dependencies_for_version(
+            &self,
+            package_name: &str,
+            version: &str,
+        ) -> Result<Vec<DependencyDeclaration>, ResolutionError> {
+            self.release(package_name, version)
+                .1
+                .iter()
+                .map(|spec| DependencyDeclaration::from_spec(*spec))
+                .collect()
+        }
+
+        fn peer_dependencies_for_version(
+            &self,
+            package_name: &str,
+            version: &str,
+        ) -> Result<Vec<PeerDeclaration>, ResolutionError> {
+            // A `?` suffix marks a peer optional.
+            self.release(package_name, version)
+                .2
+                .iter()
+                .map(|spec| match spec.strip_suffix('?') {
+                    Some(spec) => PeerDeclaration::from_spec(spec, true),
+                    None => PeerDeclaration::from_spec(*spec, false),
+                })
+                .collect()
+        }
+    }
+
+    fn direct(package_name: &str, requested: &str) -> DependencyRequest {
+        DependencyRequest::new(
+            package_name,
+            requested,
+            DependencyRequestKind::DirectProduction,
+        )
+    }
+
+    fn resolved(graph: &ResolvedDependencyGraph) -> Vec<String> {
+        graph
+            .packages()
+            .iter()
+            .map(|package| package_key(&package.package_name, &package.version))
+            .collect()
+    }
+
+    #[test]
+    fn backtracks_to_an_older_version_whose_dependencies_fit() {
+        let provider = MemoryProvider::new(&[
+            (
+                "a",
+                vec![
+                    ("1.0.0", vec!["b@^1.0.0"], vec![]),
+                    ("1.1.0", vec!["b@^2.0.0"], vec![]),
+                ],
+            ),
+            (
+                "b",
+                vec![("1.0.0", vec![], vec![]), ("2.0.0", vec![], vec![])],
+            ),
+            ("c", vec![("1.0.0", vec!["b@^1.0.0"], vec![])]),
+        ]);
+
+        let graph = BacktrackingResolutionStrategy::new()
+            .resolve(
+                vec![direct("a", "^1.0.0"), direct("c", "^1.0.0")],
+                &provider,
+            )
+            .unwrap();
+
+        assert_eq!(resolved(&graph), vec!["a@1.0.0", "c@1.0.0", "b@1.0.0"]);
+        let b = graph.package("b", "1.0.0").unwrap();
+        assert_eq!(b.requests.len(), 1);
+        assert_eq!(b.requests[0].kind, DependencyRequestKind::Transitive);
+        assert_eq!(graph.package("c", "1.0.0").unwrap().dependencies.len(), 1);
+    }
+
+    #[test]
+    fn explains_conflicts_no_version_set_can_satisfy() {
+        let provider = MemoryProvider::new(&[
+            ("a", vec![("2.0.0", vec!["b@^3.0.0"], vec![])]),
+            (
+                "b",
+                vec![("2.0.0", vec![], vec![]), ("3.0.0", vec![], vec![])],
+            ),
+            ("c", vec![("1.0.0", vec!["b@^2.0.0"], vec![])]),
+        ]);
+
+        let error = BacktrackingResolutionStrategy::new()
+            .resolve(
+                vec![direct("a", "^2.0.0"), direct("c", "^1.0.0")],
+                &provider,
+            )
+            .unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            "dependency conflict: a@2.0.0 needs b@^3.0.0, but c@1.0.0 needs b@^2.0.0"
+        );
+    }
+
+    #[test]
+    fn explains_each_version_tried_when_all_of_them_fail() {
+        let provider = MemoryProvider::new(&[
+            (
+                "a",
+                vec![
+                    ("2.0.0", vec!["b@^3.0.0"], vec![]),
+                    ("2.1.0", vec!["b@^3.0.0"], vec![]),
+                ],
+            ),
+            (
+                "b",
+                vec![("2.0.0", vec![], vec![]), ("3.0.0", vec![], vec![])],
+            ),
+            ("c", vec![("1.0.0", vec!["b@^2.0.0"], vec![])]),
+        ]);
+
+        let error = BacktrackingResolutionStrategy::new()
+            .resolve(
+                vec![direct("a", "^2.0.0"), direct("c", "^1.0.0")],
+                &provider,
+            )
+            .unwrap_err();
+
+        let ResolutionError::Conflict { derivation } = &error else {
+            panic!("expected a conflict, got {error:?}");
+        };
+        assert!(matches!(
+            &**derivation,
+            Derivation::Exhausted { package_name, attempts }
+                if package_name == "a" && attempts.len() == 2
+        ));
+        assert_eq!(
+            error.to_string(),
+            "dependency conflict: no version of a works:\n  \
+             a@2.1.0: a@2.1.0 needs b@^3.0.0, but c@1.0.0 needs b@^2.0.0\n  \
+             a@2.0.0: a@2.0.0 needs b@^3.0.0, but c@1.0.0 needs b@^2.0.0"
+        );
+    }
+
+    #[test]
+    fn peer_ranges_constrain_the_graph_and_required_peers_are_installed() {
+        let provider = MemoryProvider::new(&[
+            (
+                "plugin",
+                vec![
+                    ("1.0.0", vec![], vec!["host@^2.0.0"]),
+                    ("1.1.0", vec![], vec!["host@^1.0.0"]),
+                ],
+            ),
+            (
+                "host",
+                vec![("1.0.0", vec![], vec![]), ("2.0.0", vec![], vec![])],
+            ),
+            ("widget", vec![("1.0.0", vec![], vec!["host@^1.0.0?"])]),
+        ]);
+        let strategy = BacktrackingResolutionStrategy::new();
+
+        let graph = strategy
+            .resolve(
+                vec![direct("plugin", "^1.0.0"), direct("host", "^2.0.0")],
+                &provider,
+            )
+            .unwrap();
+        assert_eq!(resolved(&graph), vec!["plugin@1.0.0", "host@2.0.0"]);
+        let plugin = graph.package("plugin", "1.0.0").unwrap();
+        assert!(plugin.dependencies.is_empty());
+        assert_eq!(
+            plugin.peer_dependencies[0].resolved_version.as_deref(),
+            Some("2.0.0")
+        );
+
+        let graph = strategy
+            .resolve(vec![direct("plugin", "^1.0.0")], &provider)
+            .unwrap();
+        assert_eq!(resolved(&graph), vec!["plugin@1.1.0", "host@1.0.0"]);
+        assert_eq!(
+            graph.package("host", "1.0.0").unwrap().requests[0].requested,
+            "^1.0.0"
+        );
+
+        let graph = strategy
+            .resolve(vec![direct("widget", "^1.0.0")], &provider)
+            .unwrap();
+        assert_eq!(resolved(&graph), vec!["widget@1.0.0"]);
+        let peer = &graph.package("widget", "1.0.0").unwrap().peer_dependencies[0];
+        assert!(peer.optional && peer.resolved_version.is_none());
+        assert!(graph.peer_problems().is_empty());
+    }
+
+    #[test]
+    fn overrides_replace_the_ranges_dependents_request() {
+        let provider = MemoryProvider::new(&[
+            ("a", vec![("1.0.0", vec!["b@^2.0.0"], vec![])]),
+            (
+                "b",
+                vec![("1.0.0", vec![], vec![]), ("2.0.0", vec![], vec![])],
+            ),
+        ]);
+
+        let graph = BacktrackingResolutionStrategy::new()
+            .with_overrides([("b".to_string(), "1.0.0".to_string())])
+            .resolve(vec![direct("a", "^1.0.0")], &provider)
+            .unwrap();
+        assert_eq!(resolved(&graph), vec!["a@1.0.0", "b@1.0.0"]);
+        let edge = &graph.package("a", "1.0.0").unwrap().dependencies[0];
+        assert_eq!(edge.requested, "^2.0.0");
+        assert_eq!(edge.resolved_version, "1.0.0");
+
+        let error = BacktrackingResolutionStrategy::new()
+            .with_overrides([("b".to_string(), "^9.0.0".to_string())])
+            .resolve(vec![direct("a", "^1.0.0")], &provider)
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "dependency conflict: overrides pin b to ^9.0.0, which no version of b satisfies"
+        );
+    }
+}
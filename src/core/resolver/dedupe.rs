@@ -0,0 +1,490 @@
+//! A post-pass over a resolved graph that moves every request onto the fewest
+//! distinct versions of each package name.
+//!
+//! Only versions the graph already selected are considered, so the pass asks
+//! the metadata nothing but which of them are deprecated: a version that stays
+//! in the graph keeps the dependencies it was resolved with. See
+//! `docs/specs/core/resolver/SPEC.md`, "Deduplication".
+
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use super::{
+    package_key, registry_target, semver, DependencyEdge, DependencyRequestKind,
+    PackageMetadataProvider, PeerEdge, ResolvedDependencyGraph, ResolvedPackage, ResolvedRequest,
+};
+
+/// Rewrite `graph` so each package name keeps the fewest of its selected
+/// versions that together satisfy every request on it, dropping packages no
+/// longer reachable from a direct request.
+///
+/// A request a version satisfies is served by it when the version is kept;
+/// a request that is not a semver range (a dist-tag, git, or tarball spec)
+/// is only served by the version it already resolved to.
+pub fn dedupe_graph<M: PackageMetadataProvider>(
+    graph: &ResolvedDependencyGraph,
+    metadata: &M,
+) -> ResolvedDependencyGraph {
+    let mut names = Vec::new();
+    let mut versions_by_name = HashMap::<&str, Vec<&ResolvedPackage>>::new();
+    for package in graph.packages() {
+        let versions = versions_by_name
+            .entry(package.package_name.as_str())
+            .or_insert_with(|| {
+                names.push(package.package_name.as_str());
+                Vec::new()
+            });
+        versions.push(package);
+    }
+    let mut assigned = HashMap::<(String, String), String>::new();
+    for name in names {
+        for (requested, version) in cover(&versions_by_name[name], metadata) {
+            assigned.insert((name.to_string(), requested), version);
+        }
+    }
+    rebuild(graph, &assigned)
+}
+
+/// The version each request on one package name moves to, choosing the
+/// fewest versions greedily: repeatedly keep the version that serves the
+/// most requests still unserved, preferring a version that is not deprecated
+/// and then the higher version on a tie.
+fn cover<M: PackageMetadataProvider>(
+    packages: &[&ResolvedPackage],
+    metadata: &M,
+) -> Vec<(String, String)> {
+    let mut requests = Vec::<(&str, &str)>::new();
+    for package in packages {
+        for request in &package.requests {
+            if !requests
+                .iter()
+                .any(|(requested, _)| *requested == request.requested)
+            {
+                requests.push((request.requested.as_str(), package.version.as_str()));
+            }
+        }
+    }
+    if packages.len() < 2 {
+        return requests
+            .into_iter()
+            .map(|(requested, version)| (requested.to_string(), version.to_string()))
+            .collect();
+    }
+
+    let served = packages
+        .iter()
+        .map(|package| {
+            requests
+                .iter()
+                .enumerate()
+                .filter(|(_, (requested, current))| serves(package, requested, current))
+                .map(|(index, _)| index)
+                .collect::<HashSet<_>>()
+        })
+        .collect::<Vec<_>>();
+    let current = packages
+        .iter()
+        .map(|package| !metadata.is_deprecated(&package.package_name, &package.version))
+        .collect::<Vec<_>>();
+    let mut unserved = (0..requests.len()).collect::<HashSet<_>>();
+    let mut moves = Vec::new();
+    while !unserved.is_empty() {
+        // Every request is served by the version it resolved to, so some
+        // version always serves at least one unserved request.
+        let Some(best) = (0..packages.len()).max_by(|left, right| {
+            let count = |index: usize| served[index].intersection(&unserved).count();
+            count(*left)
+                .cmp(&count(*right))
+                .then_with(|| current[*left].cmp(&current[*right]))
+                .then_with(|| compare_versions(&packages[*left].version, &packages[*right].version))
+                .then_with(|| right.cmp(left))
+        }) else {
+            break;
+        };
+        let mut newly_served = served[best]
+            .intersection(&unserved)
+            .copied()
+            .collect::<Vec<_>>();
+        newly_served.sort_unstable();
+        for index in newly_served {
+            unserved.remove(&index);
+            moves.push((
+                requests[index].0.to_string(),
+                packages[best].version.clone(),
+            ));
+        }
+    }
+    moves
+}
+
+/// Whether `package` can serve `requested`, which currently resolves to the
+/// version `current`.
+fn serves(package: &ResolvedPackage, requested: &str, current: &str) -> bool {
+    if package.version == current {
+        return true;
+    }
+    // An `npm:` alias may only move between versions of the package it names,
+    // which every request `package` already serves agrees on.
+    let (registry_name, range) = registry_target(&package.package_name, requested);
+    package.requests.iter().all(|request| {
+        registry_target(&package.package_name, &request.requested).0 == registry_name
+    }) && semver::satisfies(&package.version, &range).unwrap_or(false)
+}
+
+/// Orders semver versions ascending, with unparseable ones first.
+fn compare_versions(left: &str, right: &str) -> Ordering {
+    match (
+        left.parse::<semver::Version>(),
+        right.parse::<semver::Version>(),
+    ) {
+        (Ok(left), Ok(right)) => left.cmp(&right),
+        (Ok(_), Err(_)) => Ordering::Greater,
+        (Err(_), Ok(_)) => Ordering::Less,
+        (Err(_), Err(_)) => Ordering::Equal,
+    }
+}
+
+/// Rebuild `graph` with every request moved as `assigned` says, keeping
+/// packages in their original order and only those reachable from a direct
+/// request.
+fn rebuild(
+    graph: &ResolvedDependencyGraph,
+    assigned: &HashMap<(String, String), String>,
+) -> ResolvedDependencyGraph {
+    let target = |package_name: &str, requested: &str| {
+        assigned
+            .get(&(package_name.to_string(), requested.to_string()))
+            .cloned()
+    };
+    let indexes = graph
+        .packages()
+        .iter()
+        .enumerate()
+        .map(|(index, package)| (package_key(&package.package_name, &package.version), index))
+        .collect::<HashMap<_, _>>();
+
+    let mut packages = graph
+        .packages()
+        .iter()
+        .map(|package| ResolvedPackage {
+            package_name: package.package_name.clone(),
+            version: package.version.clone(),
+            requests: Vec::new(),
+            dependencies: Vec::new(),
+            peer_dependencies: Vec::new(),
+        })
+        .collect::<Vec<_>>();
+    let mut reachable = vec![false; packages.len()];
+    let mut worklist = VecDeque::new();
+    let mut linked_peers = Vec::new();
+    for package in graph.packages() {
+        for request in &package.requests {
+            if request.kind == DependencyRequestKind::Transitive {
+                continue;
+            }
+            let Some(version) = target(&package.package_name, &request.requested) else {
+                continue;
+            };
+            let index = indexes[&package_key(&package.package_name, &version)];
+            packages[index].add_request(request.clone());
+            if !reachable[index] {
+                reachable[index] = true;
+                worklist.push_back(index);
+            }
+        }
+    }
+    while let Some(index) = worklist.pop_front() {
+        for dependency in &graph.packages()[index].dependencies {
+            let Some(version) = target(&dependency.package_name, &dependency.requested) else {
+                continue;
+            };
+            let dependency_index = indexes[&package_key(&dependency.package_name, &version)];
+            packages[index].add_dependency(DependencyEdge {
+                package_name: dependency.package_name.clone(),
+                requested: dependency.requested.clone(),
+                resolved_version: version,
+            });
+            packages[dependency_index].add_request(ResolvedRequest {
+                requested: dependency.requested.clone(),
+                kind: DependencyRequestKind::Transitive,
+            });
+            if !reachable[dependency_index] {
+                reachable[dependency_index] = true;
+                worklist.push_back(dependency_index);
+            }
+        }
+        for peer in &graph.packages()[index].peer_dependencies {
+            // A peer installed for its own range moves like a dependency.
+            let installed = peer
+                .resolved_version
+                .as_ref()
+                .and(target(&peer.package_name, &peer.requested));
+            let Some(version) = installed else {
+                linked_peers.push((index, peer));
+                continue;
+            };
+            let peer_index = indexes[&package_key(&peer.package_name, &version)];
+            packages[index].add_peer_dependency(PeerEdge {
+                resolved_version: Some(version),
+                ..peer.clone()
+            });
+            packages[peer_index].add_request(ResolvedRequest {
+                requested: peer.requested.clone(),
+                kind: DependencyRequestKind::Transitive,
+            });
+            if !reachable[peer_index] {
+                reachable[peer_index] = true;
+                worklist.push_back(peer_index);
+            }
+        }
+    }
+    // Any other peer links to the version it linked to while that stays,
+    // otherwise to the version of the peer that does.
+    for (index, peer) in linked_peers {
+        let resolved_version = peer.resolved_version.as_ref().and_then(|version| {
+            let kept = graph
+                .packages()
+                .iter()
+                .zip(&reachable)
+                .filter(|(package, reachable)| {
+                    **reachable && package.package_name == peer.package_name
+                })
+                .map(|(package, _)| &package.version)
+                .collect::<Vec<_>>();
+            kept.iter()
+                .find(|kept| **kept == version)
+                .or(kept.first())
+                .map(|kept| kept.to_string())
+        });
+        packages[index].add_peer_dependency(PeerEdge {
+            resolved_version,
+            ..peer.clone()
+        });
+    }
+
+    ResolvedDependencyGraph {
+        packages: packages
+            .into_iter()
+            .zip(reachable)
+            .filter_map(|(package, reachable)| reachable.then_some(package))
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::resolver::{DependencyDeclaration, ResolutionError};
+
+    fn package(
+        package_name: &str,
+        version: &str,
+        requests: &[(&str, DependencyRequestKind)],
+        dependencies: &[(&str, &str, &str)],
+    ) -> ResolvedPackage {
+        ResolvedPackage {
+            package_name: package_name.to_string(),
+            version: version.to_string(),
+            requests: requests
+                .iter()
+                .map(|(requested, kind)| ResolvedRequest {
+                    requested: requested.to_string(),
+                    kind: *kind,
+                })
+                .collect(),
+            dependencies: dependencies
+                .iter()
+                .map(
+                    |(package_name, requested, resolved_version)| DependencyEdge {
+                        package_name: package_name.to_string(),
+                        requested: requested.to_string(),
+                        resolved_version: resolved_version.to_string(),
+                    },
+                )
+                .collect(),
+            peer_dependencies: Vec::new(),
+        }
+    }
+
+    fn keys(graph: &ResolvedDependencyGraph) -> Vec<String> {
+        graph
+            .packages()
+            .iter()
+            .map(|package| package_key(&package.package_name, &package.version))
+            .collect()
+    }
+
+    /// Metadata that only knows which `<name>@<version>` keys are deprecated.
+    struct Deprecations(&'static [&'static str]);
+
+    impl PackageMetadataProvider for Deprecations {
+        fn select_version(
+            &self,
+            package_name: &str,
+            _requested: &str,
+        ) -> Result<String, ResolutionError> {
+            Err(ResolutionError::MissingMetadata {
+                package_name: package_name.to_string(),
+            })
+        }
+
+        fn dependencies_for_version(
+            &self,
+            _package_name: &str,
+            _version: &str,
+        ) -> Result<Vec<DependencyDeclaration>, ResolutionError> {
+            Ok(Vec::new())
+        }
+
+        fn is_deprecated(&self, package_name: &str, version: &str) -> bool {
+            self.0
+                .contains(&package_key(package_name, version).as_str())
+        }
+    }
+
+    const DIRECT: DependencyRequestKind = DependencyRequestKind::DirectProduction;
+    const TRANSITIVE: DependencyRequestKind = DependencyRequestKind::Transitive;
+
+    #[test]
+    fn moves_requests_onto_a_version_that_satisfies_them_all() {
+        let graph = ResolvedDependencyGraph {
+            packages: vec![
+                package(
+                    "a",
+                    "1.0.0",
+                    &[("^1.0.0", DIRECT)],
+                    &[("shared", "^1.2.0", "1.2.0")],
+                ),
+                package(
+                    "b",
+                    "1.0.0",
+                    &[("^1.0.0", DIRECT)],
+                    &[("shared", "^1.4.0", "1.4.0")],
+                ),
+                package(
+                    "shared",
+                    "1.2.0",
+                    &[("^1.2.0", TRANSITIVE)],
+                    &[("old", "^1.0.0", "1.0.0")],
+                ),
+                package("shared", "1.4.0", &[("^1.4.0", TRANSITIVE)], &[]),
+                package("old", "1.0.0", &[("^1.0.0", TRANSITIVE)], &[]),
+            ],
+        };
+
+        let deduped = dedupe_graph(&graph, &Deprecations(&[]));
+
+        assert_eq!(keys(&deduped), vec!["a@1.0.0", "b@1.0.0", "shared@1.4.0"]);
+        assert_eq!(
+            deduped.package("a", "1.0.0").unwrap().dependencies[0].resolved_version,
+            "1.4.0"
+        );
+        let shared = deduped.package("shared", "1.4.0").unwrap();
+        assert_eq!(
+            shared
+                .requests
+                .iter()
+                .map(|request| request.requested.as_str())
+                .collect::<Vec<_>>(),
+            vec!["^1.2.0", "^1.4.0"]
+        );
+    }
+
+    #[test]
+    fn keeps_the_fewest_versions_when_no_single_one_satisfies_every_range() {
+        let graph = ResolvedDependencyGraph {
+            packages: vec![
+                package(
+                    "a",
+                    "1.0.0",
+                    &[("^1.0.0", DIRECT)],
+                    &[("shared", "~1.0.0", "1.0.5"), ("shared", "^2.0.0", "2.1.0")],
+                ),
+                package("shared", "1.0.5", &[("~1.0.0", TRANSITIVE)], &[]),
+                package("shared", "2.1.0", &[("^2.0.0", TRANSITIVE)], &[]),
+                package(
+                    "b",
+                    "1.0.0",
+                    &[("^1.0.0", DIRECT)],
+                    &[("shared", ">=1.0.0", "1.3.0")],
+                ),
+                package("shared", "1.3.0", &[(">=1.0.0", TRANSITIVE)], &[]),
+                package(
+                    "c",
+                    "1.0.0",
+                    &[("^1.0.0", DIRECT)],
+                    &[("shared", "github:o/r", "0.0.0-git")],
+                ),
+                package("shared", "0.0.0-git", &[("github:o/r", TRANSITIVE)], &[]),
+            ],
+        };
+
+        let deduped = dedupe_graph(&graph, &Deprecations(&[]));
+
+        // `>=1.0.0` joins the highest version it can share; the git request
+        // only ever keeps its own version.
+        assert_eq!(
+            keys(&deduped),
+            vec![
+                "a@1.0.0",
+                "shared@1.0.5",
+                "shared@2.1.0",
+                "b@1.0.0",
+                "c@1.0.0",
+                "shared@0.0.0-git",
+            ]
+        );
+        assert_eq!(
+            deduped.package("b", "1.0.0").unwrap().dependencies[0].resolved_version,
+            "2.1.0"
+        );
+    }
+
+    #[test]
+    fn prefers_a_version_that_is_not_deprecated_when_both_serve_as_many() {
+        let graph = ResolvedDependencyGraph {
+            packages: vec![
+                package(
+                    "a",
+                    "1.0.0",
+                    &[("^1.0.0", DIRECT)],
+                    &[("shared", "^1.0.0", "1.2.0")],
+                ),
+                package(
+                    "b",
+                    "1.0.0",
+                    &[("^1.0.0", DIRECT)],
+                    &[("shared", ">=1.1.0 <2.0.0", "1.1.0")],
+                ),
+                package("shared", "1.2.0", &[("^1.0.0", TRANSITIVE)], &[]),
+                package("shared", "1.1.0", &[(">=1.1.0 <2.0.0", TRANSITIVE)], &[]),
+            ],
+        };
+
+        assert_eq!(
+            keys(&dedupe_graph(&graph, &Deprecations(&[]))),
+            vec!["a@1.0.0", "b@1.0.0", "shared@1.2.0"]
+        );
+        assert_eq!(
+            keys(&dedupe_graph(&graph, &Deprecations(&["shared@1.2.0"]))),
+            vec!["a@1.0.0", "b@1.0.0", "shared@1.1.0"]
+        );
+    }
+
+    #[test]
+    fn leaves_a_graph_without_duplicates_unchanged() {
+        let graph = ResolvedDependencyGraph {
+            packages: vec![
+                package(
+                    "a",
+                    "1.0.0",
+                    &[("^1.0.0", DIRECT)],
+                    &[("b", "^2.0.0", "2.0.0")],
+                ),
+                package("b", "2.0.0", &[("^2.0.0", TRANSITIVE)], &[]),
+            ],
+        };
+
+        assert_eq!(dedupe_graph(&graph, &Deprecations(&[])), graph);
+    }
+}
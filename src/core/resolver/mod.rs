@@ -1,11 +1,18 @@
-use std::collections::{HashMap, VecDeque};
+use std::collections::{HashMap, HashSet, VecDeque};
 
 use thiserror::Error;
 
 use crate::core::resolver::semver::SemverError;
 use crate::util::parse_library_name;
 
+mod backtracking;
+mod dedupe;
 pub mod semver;
+pub mod source;
+
+pub use backtracking::{BacktrackingResolutionStrategy, Derivation, Requirement, Requirer};
+pub use dedupe::dedupe_graph;
+use source::{NpmAlias, RequestSource};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum DependencyRequestKind {
@@ -39,15 +46,35 @@ impl DependencyRequest {
         kind: DependencyRequestKind,
     ) -> Result<Self, ResolutionError> {
         let dependency = dependency.into();
-        reject_npm_alias(&dependency)?;
         let (package_name, requested) = parse_library_name(dependency.clone());
         if package_name.trim().is_empty() {
             return Err(ResolutionError::InvalidDependencyDeclaration {
                 declaration: dependency,
             });
         }
+        validate_npm_alias(&dependency, &requested)?;
         Ok(Self::new(package_name, requested, kind))
     }
+
+    /// Where this request is fetched from, read from its requested text.
+    pub fn source(&self) -> RequestSource {
+        RequestSource::parse(&self.requested)
+    }
+
+    /// The registry package and range this request selects from: the alias
+    /// target for an `npm:` alias, otherwise the request itself.
+    pub fn registry_target(&self) -> (String, String) {
+        registry_target(&self.package_name, &self.requested)
+    }
+}
+
+/// The registry package and range that `requested` selects from when
+/// `package_name` requests it.
+pub fn registry_target(package_name: &str, requested: &str) -> (String, String) {
+    match NpmAlias::parse(requested) {
+        Some(alias) => (alias.package_name, alias.range),
+        None => (package_name.to_string(), requested.to_string()),
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -66,17 +93,45 @@ impl DependencyDeclaration {
 
     pub fn from_spec(dependency: impl Into<String>) -> Result<Self, ResolutionError> {
         let dependency = dependency.into();
-        reject_npm_alias(&dependency)?;
         let (package_name, requested) = parse_library_name(dependency.clone());
         if package_name.trim().is_empty() {
             return Err(ResolutionError::InvalidDependencyDeclaration {
                 declaration: dependency,
             });
         }
+        validate_npm_alias(&dependency, &requested)?;
         Ok(Self::new(package_name, requested))
     }
 }
 
+/// A `peerDependencies` entry: a package the declaring package expects to
+/// share with whatever depends on it, rather than own.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PeerDeclaration {
+    pub package_name: String,
+    pub requested: String,
+    /// Marked `optional` under `peerDependenciesMeta`: never installed for
+    /// the peer's sake, and not reported when missing.
+    pub optional: bool,
+}
+
+impl PeerDeclaration {
+    pub fn from_spec(
+        dependency: impl Into<String>,
+        optional: bool,
+    ) -> Result<Self, ResolutionError> {
+        let DependencyDeclaration {
+            package_name,
+            requested,
+        } = DependencyDeclaration::from_spec(dependency)?;
+        Ok(Self {
+            package_name,
+            requested,
+            optional,
+        })
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ResolvedRequest {
     pub requested: String,
@@ -90,15 +145,40 @@ pub struct DependencyEdge {
     pub resolved_version: String,
 }
 
+/// A peer dependency of a resolved package and the version it links to, or
+/// `None` when the graph holds no version of the peer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PeerEdge {
+    pub package_name: String,
+    pub requested: String,
+    pub optional: bool,
+    pub resolved_version: Option<String>,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ResolvedPackage {
     pub package_name: String,
     pub version: String,
     pub requests: Vec<ResolvedRequest>,
     pub dependencies: Vec<DependencyEdge>,
+    pub peer_dependencies: Vec<PeerEdge>,
 }
 
 impl ResolvedPackage {
+    /// `(package_name, version)` of every package this one links to: its
+    /// dependencies, then the peers that resolved.
+    pub(crate) fn edges(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.dependencies
+            .iter()
+            .map(|edge| (edge.package_name.as_str(), edge.resolved_version.as_str()))
+            .chain(self.peer_dependencies.iter().filter_map(|edge| {
+                Some((
+                    edge.package_name.as_str(),
+                    edge.resolved_version.as_deref()?,
+                ))
+            }))
+    }
+
     fn add_request(&mut self, request: ResolvedRequest) {
         if !self.requests.contains(&request) {
             self.requests.push(request);
@@ -110,6 +190,34 @@ impl ResolvedPackage {
             self.dependencies.push(edge);
         }
     }
+
+    fn add_peer_dependency(&mut self, edge: PeerEdge) {
+        if !self.peer_dependencies.contains(&edge) {
+            self.peer_dependencies.push(edge);
+        }
+    }
+}
+
+/// A peer dependency the resolved graph leaves unmet.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum PeerProblem {
+    /// No version of a required peer is installed.
+    #[error("missing peer: {package_name}@{version} needs peer {peer_name}@{requested}, which is not installed")]
+    Missing {
+        package_name: String,
+        version: String,
+        peer_name: String,
+        requested: String,
+    },
+    /// The version of the peer the package links to is outside its range.
+    #[error("incompatible peer: {package_name}@{version} needs peer {peer_name}@{requested}, but {peer_name}@{resolved_version} is installed")]
+    Incompatible {
+        package_name: String,
+        version: String,
+        peer_name: String,
+        requested: String,
+        resolved_version: String,
+    },
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -127,6 +235,88 @@ impl ResolvedDependencyGraph {
             .iter()
             .find(|package| package.package_name == package_name && package.version == version)
     }
+
+    /// The shortest chain of packages from a direct request to `package_name`
+    /// at `version`, both ends included, or `None` when the graph does not
+    /// hold that package. A directly requested package is its own path.
+    pub fn path_to(&self, package_name: &str, version: &str) -> Option<Vec<&ResolvedPackage>> {
+        self.package(package_name, version)?;
+        let mut previous: HashMap<String, Option<usize>> = HashMap::new();
+        let mut queue = VecDeque::new();
+        for (index, package) in self.packages.iter().enumerate() {
+            let direct = package
+                .requests
+                .iter()
+                .any(|request| request.kind != DependencyRequestKind::Transitive);
+            if direct {
+                previous.insert(package_key(&package.package_name, &package.version), None);
+                queue.push_back(index);
+            }
+        }
+        while let Some(index) = queue.pop_front() {
+            let package = &self.packages[index];
+            if package.package_name == package_name && package.version == version {
+                let mut path = vec![package];
+                let mut key = package_key(&package.package_name, &package.version);
+                while let Some(Some(parent)) = previous.get(&key) {
+                    let parent = &self.packages[*parent];
+                    path.push(parent);
+                    key = package_key(&parent.package_name, &parent.version);
+                }
+                path.reverse();
+                return Some(path);
+            }
+            for (dependency_name, resolved_version) in package.edges() {
+                let key = package_key(dependency_name, resolved_version);
+                if previous.contains_key(&key) {
+                    continue;
+                }
+                let Some(next) = self.packages.iter().position(|candidate| {
+                    candidate.package_name == dependency_name
+                        && candidate.version == resolved_version
+                }) else {
+                    continue;
+                };
+                previous.insert(key, Some(index));
+                queue.push_back(next);
+            }
+        }
+        None
+    }
+
+    /// Every peer dependency left unmet, in graph order: a required peer no
+    /// version of which is installed, or one that links to a version outside
+    /// its range.
+    pub fn peer_problems(&self) -> Vec<PeerProblem> {
+        let mut problems = Vec::new();
+        for package in &self.packages {
+            for edge in &package.peer_dependencies {
+                match &edge.resolved_version {
+                    None if edge.optional => {}
+                    None => problems.push(PeerProblem::Missing {
+                        package_name: package.package_name.clone(),
+                        version: package.version.clone(),
+                        peer_name: edge.package_name.clone(),
+                        requested: edge.requested.clone(),
+                    }),
+                    Some(resolved_version) => {
+                        let (_, range) = registry_target(&edge.package_name, &edge.requested);
+                        // Tags and source specs have no range to check.
+                        if !semver::satisfies(resolved_version, &range).unwrap_or(true) {
+                            problems.push(PeerProblem::Incompatible {
+                                package_name: package.package_name.clone(),
+                                version: package.version.clone(),
+                                peer_name: edge.package_name.clone(),
+                                requested: edge.requested.clone(),
+                                resolved_version: resolved_version.clone(),
+                            });
+                        }
+                    }
+                }
+            }
+        }
+        problems
+    }
 }
 
 pub trait PackageMetadataProvider {
@@ -141,6 +331,34 @@ pub trait PackageMetadataProvider {
         package_name: &str,
         version: &str,
     ) -> Result<Vec<DependencyDeclaration>, ResolutionError>;
+
+    /// Every version `requested` may resolve to, most preferred first.
+    /// Strategies that backtrack try them in order; the default offers only
+    /// the selected version.
+    fn candidate_versions(
+        &self,
+        package_name: &str,
+        requested: &str,
+    ) -> Result<Vec<String>, ResolutionError> {
+        Ok(vec![self.select_version(package_name, requested)?])
+    }
+
+    /// The `peerDependencies` of a version. A peer links to the version its
+    /// dependent's parent sees; a required one no version of which is in the
+    /// graph is installed for it.
+    fn peer_dependencies_for_version(
+        &self,
+        _package_name: &str,
+        _version: &str,
+    ) -> Result<Vec<PeerDeclaration>, ResolutionError> {
+        Ok(Vec::new())
+    }
+
+    /// Whether a version is deprecated. Deduplication keeps a current version
+    /// over a deprecated one that serves as many requests.
+    fn is_deprecated(&self, _package_name: &str, _version: &str) -> bool {
+        false
+    }
 }
 
 pub trait ResolutionStrategy {
@@ -171,60 +389,138 @@ impl ResolutionStrategy for FifoResolutionStrategy {
             .map(|request| PendingRequest {
                 request,
                 requested_by: None,
+                peer: false,
             })
             .collect::<VecDeque<_>>();
         let mut packages: Vec<ResolvedPackage> = Vec::new();
         let mut package_indexes: HashMap<String, usize> = HashMap::new();
+        // Peers wait until the worklist drains, so they link to what the
+        // graph settled on rather than to whatever was resolved first.
+        let mut peers: VecDeque<(usize, PeerDeclaration)> = VecDeque::new();
+
+        loop {
+            while let Some(pending) = worklist.pop_front() {
+                let selected = metadata
+                    .select_version(&pending.request.package_name, &pending.request.requested);
+                let version = match selected {
+                    Ok(version) => Some(version),
+                    // A peer that cannot be installed is reported, not fatal.
+                    Err(
+                        ResolutionError::VersionSelection { .. }
+                        | ResolutionError::MissingMetadata { .. },
+                    ) if pending.peer => None,
+                    Err(error) => return Err(error),
+                };
+
+                if let Some(parent_key) = pending.requested_by.as_ref() {
+                    let parent_index =
+                        package_indexes.get(parent_key).copied().ok_or_else(|| {
+                            ResolutionError::ParentPackageMissing {
+                                package_key: parent_key.clone(),
+                            }
+                        })?;
+                    let parent = &mut packages[parent_index];
+                    if pending.peer {
+                        parent.add_peer_dependency(PeerEdge {
+                            package_name: pending.request.package_name.clone(),
+                            requested: pending.request.requested.clone(),
+                            optional: false,
+                            resolved_version: version.clone(),
+                        });
+                    } else if let Some(version) = &version {
+                        parent.add_dependency(DependencyEdge {
+                            package_name: pending.request.package_name.clone(),
+                            requested: pending.request.requested.clone(),
+                            resolved_version: version.clone(),
+                        });
+                    }
+                }
+                let Some(version) = version else {
+                    continue;
+                };
+                let package_key = package_key(&pending.request.package_name, &version);
 
-        while let Some(pending) = worklist.pop_front() {
-            let version = metadata
-                .select_version(&pending.request.package_name, &pending.request.requested)?;
-            let package_key = package_key(&pending.request.package_name, &version);
+                let request = ResolvedRequest {
+                    requested: pending.request.requested.clone(),
+                    kind: pending.request.kind,
+                };
 
-            if let Some(parent_key) = pending.requested_by.as_ref() {
-                let parent_index = package_indexes.get(parent_key).copied().ok_or_else(|| {
-                    ResolutionError::ParentPackageMissing {
-                        package_key: parent_key.clone(),
-                    }
-                })?;
-                packages[parent_index].add_dependency(DependencyEdge {
+                if let Some(package_index) = package_indexes.get(&package_key).copied() {
+                    packages[package_index].add_request(request);
+                    continue;
+                }
+
+                let package_index = packages.len();
+                package_indexes.insert(package_key.clone(), package_index);
+                packages.push(ResolvedPackage {
                     package_name: pending.request.package_name.clone(),
-                    requested: pending.request.requested.clone(),
-                    resolved_version: version.clone(),
+                    version,
+                    requests: vec![request],
+                    dependencies: Vec::new(),
+                    peer_dependencies: Vec::new(),
                 });
-            }
 
-            let request = ResolvedRequest {
-                requested: pending.request.requested.clone(),
-                kind: pending.request.kind,
-            };
-
-            if let Some(package_index) = package_indexes.get(&package_key).copied() {
-                packages[package_index].add_request(request);
-                continue;
+                let package = &packages[package_index];
+                let dependencies =
+                    metadata.dependencies_for_version(&package.package_name, &package.version)?;
+                for dependency in dependencies {
+                    worklist.push_back(PendingRequest {
+                        request: DependencyRequest::new(
+                            dependency.package_name,
+                            dependency.requested,
+                            DependencyRequestKind::Transitive,
+                        ),
+                        requested_by: Some(package_key.clone()),
+                        peer: false,
+                    });
+                }
+                peers.extend(
+                    metadata
+                        .peer_dependencies_for_version(&package.package_name, &package.version)?
+                        .into_iter()
+                        .map(|peer| (package_index, peer)),
+                );
             }
 
-            let package_index = packages.len();
-            package_indexes.insert(package_key.clone(), package_index);
-            packages.push(ResolvedPackage {
-                package_name: pending.request.package_name.clone(),
-                version,
-                requests: vec![request],
-                dependencies: Vec::new(),
-            });
-
-            let package = &packages[package_index];
-            let dependencies =
-                metadata.dependencies_for_version(&package.package_name, &package.version)?;
-            for dependency in dependencies {
-                worklist.push_back(PendingRequest {
-                    request: DependencyRequest::new(
-                        dependency.package_name,
-                        dependency.requested,
-                        DependencyRequestKind::Transitive,
-                    ),
-                    requested_by: Some(package_key.clone()),
-                });
+            // Link each peer to the version its dependent's parent sees, or
+            // install a required one the graph lacks. A peer several packages
+            // lack is installed once; the others link to it next round.
+            let mut installing = HashSet::new();
+            let mut deferred = VecDeque::new();
+            while let Some((index, peer)) = peers.pop_front() {
+                if let Some(version) = visible_version(&packages, index, &peer.package_name) {
+                    packages[index].add_peer_dependency(PeerEdge {
+                        package_name: peer.package_name,
+                        requested: peer.requested,
+                        optional: peer.optional,
+                        resolved_version: Some(version),
+                    });
+                } else if peer.optional {
+                    packages[index].add_peer_dependency(PeerEdge {
+                        package_name: peer.package_name,
+                        requested: peer.requested,
+                        optional: true,
+                        resolved_version: None,
+                    });
+                } else if installing.contains(&peer.package_name) {
+                    deferred.push_back((index, peer));
+                } else {
+                    installing.insert(peer.package_name.clone());
+                    let package = &packages[index];
+                    worklist.push_back(PendingRequest {
+                        request: DependencyRequest::new(
+                            peer.package_name,
+                            peer.requested,
+                            DependencyRequestKind::Transitive,
+                        ),
+                        requested_by: Some(package_key(&package.package_name, &package.version)),
+                        peer: true,
+                    });
+                }
+            }
+            peers = deferred;
+            if worklist.is_empty() {
+                break;
             }
         }
 
@@ -232,6 +528,50 @@ impl ResolutionStrategy for FifoResolutionStrategy {
     }
 }
 
+/// The version of `peer_name` that the parent of `packages[index]` sees: the
+/// project's own for a direct dependency, otherwise the first dependent's
+/// dependency or peer of that name. With no such version, the one the flat
+/// layout installs: the project's own, then the first in the graph.
+fn visible_version(packages: &[ResolvedPackage], index: usize, peer_name: &str) -> Option<String> {
+    let package = &packages[index];
+    let is_direct = |package: &ResolvedPackage| {
+        package
+            .requests
+            .iter()
+            .any(|request| request.kind != DependencyRequestKind::Transitive)
+    };
+    let project_version = || {
+        packages
+            .iter()
+            .find(|candidate| candidate.package_name == peer_name && is_direct(candidate))
+            .map(|candidate| candidate.version.clone())
+    };
+    if is_direct(package) {
+        if let Some(version) = project_version() {
+            return Some(version);
+        }
+    }
+    let from_parent = packages
+        .iter()
+        .filter(|parent| {
+            parent
+                .edges()
+                .any(|edge| edge == (package.package_name.as_str(), package.version.as_str()))
+        })
+        .find_map(|parent| {
+            parent
+                .edges()
+                .find(|(dependency_name, _)| *dependency_name == peer_name)
+                .map(|(_, version)| version.to_string())
+        });
+    from_parent.or_else(project_version).or_else(|| {
+        packages
+            .iter()
+            .find(|candidate| candidate.package_name == peer_name)
+            .map(|candidate| candidate.version.clone())
+    })
+}
+
 pub fn resolve_dependency_graph<M: PackageMetadataProvider>(
     requests: Vec<DependencyRequest>,
     metadata: &M,
@@ -253,13 +593,13 @@ pub enum ResolutionError {
     InvalidDependencyDeclaration { declaration: String },
     #[error("resolved parent package {package_key} is missing from graph")]
     ParentPackageMissing { package_key: String },
-    #[error(
-        "npm alias dependency declarations are not supported: {package_key} maps to alias {alias_target}"
-    )]
-    NpmAliasNotSupported {
+    #[error("invalid npm alias {alias_target} in {package_key}: it names no package")]
+    InvalidNpmAlias {
         package_key: String,
         alias_target: String,
     },
+    #[error("dependency conflict: {derivation}")]
+    Conflict { derivation: Box<Derivation> },
 }
 
 impl ResolutionError {
@@ -280,6 +620,8 @@ impl ResolutionError {
 struct PendingRequest {
     request: DependencyRequest,
     requested_by: Option<String>,
+    /// Installs a peer of `requested_by` that the graph lacked.
+    peer: bool,
 }
 
 fn normalize_requested(requested: String) -> String {
@@ -294,56 +636,28 @@ fn package_key(package_name: &str, version: &str) -> String {
     format!("{package_name}@{version}")
 }
 
-/// Reject npm alias dependency declarations (issue #125).
-///
-/// An npm alias is a dependency map value whose range text begins with the
-/// `npm:` scheme, matched ASCII case-insensitively (for example
-/// `"foo": "npm:bar@1.2.3"` or `"foo": "NPM:bar@1.2.3"`). RPM does not resolve
-/// aliases today, so it must reject them as input errors at the declaration
-/// boundary — before any network fetch, lockfile write, or install side effect
-/// — instead of silently misinterpreting `"foo@npm:bar@1.2.3"` as a lookup for
-/// a nonexistent package. The case-insensitive scheme match mirrors npm's own
-/// `npm-package-arg`, which tests `spec.toLowerCase().startsWith('npm:')`.
-///
-/// Detection runs on the combined `name@range` declaration *before*
-/// `parse_library_name` splits it. `parse_library_name` splits on the *last*
-/// `@`, so for `"foo@npm:bar@1.2.3"` it would yield `foo@npm:bar` / `1.2.3`
-/// and hide the alias prefix in the package name. A well-formed non-alias
-/// declaration — either `name@range` or `@scope/name@range` — can never
-/// contain the literal `@npm:`, because `npm:` is not a valid version-range
-/// prefix, so finding `@npm:` in the combined declaration identifies an alias
-/// unambiguously without over-matching a range that merely contains `npm:` at
-/// a non-prefix position. `package_key` is the original combined declaration
-/// and names the offending package; `alias_target` is the alias range text.
-fn reject_npm_alias(dependency: &str) -> Result<(), ResolutionError> {
-    if let Some(alias_start) = find_alias_marker(dependency) {
-        let alias_target = dependency[alias_start + 1..].to_string();
-        return Err(ResolutionError::NpmAliasNotSupported {
-            package_key: dependency.to_string(),
-            alias_target,
-        });
+/// Check that an `npm:` alias names a package. The alias itself is resolved
+/// by the metadata provider, which selects the target package's versions and
+/// installs the result under the requesting name; `package_key` is the
+/// combined declaration and `alias_target` the alias text.
+fn validate_npm_alias(dependency: &str, requested: &str) -> Result<(), ResolutionError> {
+    match NpmAlias::parse(requested) {
+        Some(alias) if alias.package_name.trim().is_empty() => {
+            Err(ResolutionError::InvalidNpmAlias {
+                package_key: dependency.to_string(),
+                alias_target: requested.to_string(),
+            })
+        }
+        _ => Ok(()),
     }
-    Ok(())
-}
-
-/// The `@npm:` marker that identifies an alias in an assembled declaration.
-const NPM_ALIAS_MARKER: &str = "@npm:";
-
-/// Find the first `@npm:` marker in `dependency`, matching ASCII
-/// case-insensitively so `@NPM:`/`@Npm:` aliases are detected too. Returns the
-/// byte offset of the leading `@` of the marker, mirroring `str::find`.
-fn find_alias_marker(dependency: &str) -> Option<usize> {
-    dependency
-        .as_bytes()
-        .windows(NPM_ALIAS_MARKER.len())
-        .position(|window| window.eq_ignore_ascii_case(NPM_ALIAS_MARKER.as_bytes()))
 }
 
 #[cfg(test)]
 mod tests {
     use super::{
-        resolve_dependency_graph, DependencyDeclaration, DependencyRequest, DependencyRequestKind,
-        PackageMetadataProvider, ResolutionError,
+        registry_target, resolve_dependency_graph, DependencyDeclaration, DependencyRequest,
+        DependencyRequestKind, PackageMetadataProvider, PeerDeclaration, PeerEdge, PeerProblem,
+        ResolutionError, ResolvedDependencyGraph,
     };
     use crate::registry::Registry;
     use crate::util::test_support::fixture_path;
@@ -409,6 +723,24 @@ mod tests {
                 .map(DependencyDeclaration::from_spec)
                 .collect()
         }
+
+        fn peer_dependencies_for_version(
+            &self,
+            package_name: &str,
+            version: &str,
+        ) -> Result<Vec<PeerDeclaration>, ResolutionError> {
+            let registry = self.registries.get(package_name).ok_or_else(|| {
+                ResolutionError::MissingMetadata {
+                    package_name: package_name.to_string(),
+                }
+            })?;
+
+            registry
+                .get_peer_dependencies_for_version(version)
+                .into_iter()
+                .map(|(peer, optional)| PeerDeclaration::from_spec(peer, optional))
+                .collect()
+        }
     }
 
     struct FailingSelectionProvider {
@@ -506,10 +838,40 @@ mod tests {
         assert_eq!(shared.requests.len(), 1);
         assert_eq!(shared.requests[0].kind, DependencyRequestKind::Transitive);
         assert!(shared.dependencies.is_empty());
+
+        let path = graph
+            .path_to("@rpm-fixture/shared", "1.0.0")
+            .unwrap()
+            .iter()
+            .map(|package| package.package_name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(path, vec!["@rpm-fixture/alpha", "@rpm-fixture/shared"]);
+        assert_eq!(
+            graph.path_to("@rpm-fixture/beta", "1.0.0").unwrap().len(),
+            1
+        );
+        assert!(graph.path_to("@rpm-fixture/shared", "2.0.0").is_none());
+    }
+
+    /// One `name@version requested range` line per package, sorted: the
+    /// order registry metadata lists dependencies in is not fixed.
+    fn resolved_package_lines(graph: &ResolvedDependencyGraph) -> String {
+        let mut lines = graph
+            .packages()
+            .iter()
+            .map(|package| {
+                format!(
+                    "{}@{} requested {}\n",
+                    package.package_name, package.version, package.requests[0].requested
+                )
+            })
+            .collect::<Vec<_>>();
+        lines.sort();
+        lines.concat()
     }
 
     #[test]
-    fn peer_dependency_metadata_is_preserved_without_enqueue_or_install_failure() {
+    fn required_peer_the_graph_lacks_is_installed_as_a_peer_edge() {
         let root = fixture_path(&["registry", "peer-preserve", "metadata"]);
         let provider = FixtureMetadataProvider::from_fixture_root(&root);
 
@@ -523,9 +885,7 @@ mod tests {
             )],
             &provider,
         )
-        .expect(
-            "unmet peer requirement must not fail resolution under the non-peer-aware strategy",
-        );
+        .expect("a required peer is installed, not a resolution failure");
 
         let expected = fs::read_to_string(fixture_path(&[
             "registry",
@@ -534,22 +894,8 @@ mod tests {
             "resolved-packages.txt",
         ]))
         .expect("expected resolved package list should be readable");
-        let resolved = graph
-            .packages()
-            .iter()
-            .map(|package| {
-                format!(
-                    "{}@{} requested {}",
-                    package.package_name, package.version, package.requests[0].requested
-                )
-            })
-            .collect::<Vec<_>>()
-            .join("\n");
-        assert_eq!(format!("{resolved}\n"), expected);
+        assert_eq!(resolved_package_lines(&graph), expected);
 
-        // The peer target must NOT be enqueued as an ordinary dependency: the
-        // graph contains only the consumer.
-        assert_eq!(graph.packages().len(), 1);
         let consumer = graph
             .package("@rpm-fixture/peer-consumer", "1.0.0")
             .expect("peer consumer resolves to a graph node");
@@ -557,10 +903,93 @@ mod tests {
             consumer.dependencies.is_empty(),
             "peerDependencies must not become ordinary dependency edges"
         );
-        assert!(
-            graph.package("@rpm-fixture/peer-target", "1.0.0").is_none(),
-            "peer target must not appear in the resolved graph before a peer-aware strategy exists"
+        assert_eq!(
+            consumer.peer_dependencies,
+            vec![PeerEdge {
+                package_name: "@rpm-fixture/peer-target".to_string(),
+                requested: "^1.0.0".to_string(),
+                optional: false,
+                resolved_version: Some("1.0.0".to_string()),
+            }]
+        );
+        assert_eq!(
+            graph
+                .path_to("@rpm-fixture/peer-target", "1.0.0")
+                .unwrap()
+                .len(),
+            2
+        );
+        assert!(graph.peer_problems().is_empty());
+    }
+
+    #[test]
+    fn peers_link_to_the_version_their_parent_sees_and_report_unmet_ranges() {
+        let root = fixture_path(&["registry", "peer-aware", "metadata"]);
+        let provider = FixtureMetadataProvider::from_fixture_root(&root);
+        let direct = |package_name: &str| {
+            DependencyRequest::new(
+                package_name,
+                "^1.0.0",
+                DependencyRequestKind::DirectProduction,
+            )
+        };
+
+        let graph = resolve_dependency_graph(
+            vec![
+                direct("@rpm-fixture/host"),
+                direct("@rpm-fixture/app"),
+                direct("@rpm-fixture/legacy"),
+                direct("@rpm-fixture/needy"),
+            ],
+            &provider,
+        )
+        .expect("unmet peers are reported, not a resolution failure");
+
+        let expected = fs::read_to_string(fixture_path(&[
+            "registry",
+            "peer-aware",
+            "expected",
+            "resolved-packages.txt",
+        ]))
+        .expect("expected resolved package list should be readable");
+        assert_eq!(resolved_package_lines(&graph), expected);
+
+        // The plugin's parent depends on host@2.0.0, so that is its peer,
+        // not the project's host@1.0.0. Its optional peer stays unresolved.
+        let plugin = graph.package("@rpm-fixture/plugin", "1.0.0").unwrap();
+        let host = |edge: &&PeerEdge| edge.package_name == "@rpm-fixture/host";
+        let edge = plugin.peer_dependencies.iter().find(host).unwrap();
+        assert_eq!(edge.resolved_version.as_deref(), Some("2.0.0"));
+        let edge = plugin
+            .peer_dependencies
+            .iter()
+            .find(|edge| !host(edge))
+            .unwrap();
+        assert!(edge.optional && edge.resolved_version.is_none());
+
+        // The legacy package is a direct dependency, so it sees the project's
+        // host@1.0.0; nothing provides the needy package's peer.
+        assert_eq!(
+            graph.peer_problems(),
+            vec![
+                PeerProblem::Incompatible {
+                    package_name: "@rpm-fixture/legacy".to_string(),
+                    version: "1.0.0".to_string(),
+                    peer_name: "@rpm-fixture/host".to_string(),
+                    requested: "^2.0.0".to_string(),
+                    resolved_version: "1.0.0".to_string(),
+                },
+                PeerProblem::Missing {
+                    package_name: "@rpm-fixture/needy".to_string(),
+                    version: "1.0.0".to_string(),
+                    peer_name: "@rpm-fixture/absent".to_string(),
+                    requested: "^1.0.0".to_string(),
+                },
+            ]
         );
+        for problem in graph.peer_problems() {
+            assert_eq!(problem.to_string().lines().count(), 1);
+        }
     }
 
     #[test]
@@ -628,111 +1057,82 @@ mod tests {
     }
 
     // An npm alias declaration (a dependency map value whose range text begins
-    // with `npm:`, for example `"foo": "npm:bar@1.2.3"`) is rejected as an
-    // input error before resolution, rather than being silently misread as a
-    // lookup for a nonexistent package (issue #125).
+    // with `npm:`, for example `"foo": "npm:bar@1.2.3"`) keeps the requesting
+    // name as the package name and selects versions of the alias target.
 
     #[test]
-    fn direct_request_rejects_root_manifest_npm_alias() {
-        let error = DependencyRequest::from_spec(
+    fn declarations_keep_npm_aliases_under_the_requesting_name() {
+        let request = DependencyRequest::from_spec(
             "foo@npm:bar@1.2.3",
             DependencyRequestKind::DirectProduction,
         )
-        .expect_err("root-manifest npm alias should be rejected");
-
-        assert!(matches!(
-            error,
-            ResolutionError::NpmAliasNotSupported {
-                ref package_key,
-                ref alias_target,
-            } if package_key == "foo@npm:bar@1.2.3"
-                && alias_target == "npm:bar@1.2.3"
-        ));
-        let message = error.to_string();
-        assert!(
-            message.contains("npm alias"),
-            "error should name the alias syntax, got {message:?}"
-        );
-        assert!(
-            message.contains("foo@npm:bar@1.2.3"),
-            "error should identify the offending package, got {message:?}"
+        .expect("root-manifest npm alias should parse");
+        assert_eq!(request.package_name, "foo");
+        assert_eq!(request.requested, "npm:bar@1.2.3");
+        assert_eq!(
+            request.registry_target(),
+            ("bar".to_string(), "1.2.3".to_string())
         );
-    }
 
-    #[test]
-    fn declaration_rejects_transitive_npm_alias() {
         // Transitive registry dependency edges flow through the same
-        // `from_spec` boundary after being assembled as `name@range`; an alias
-        // value must be rejected here too, before any fetch or lockfile write.
-        let error = DependencyDeclaration::from_spec("transitive-dep@npm:other@^2.0.0")
-            .expect_err("transitive npm alias should be rejected");
-
-        assert!(matches!(
-            error,
-            ResolutionError::NpmAliasNotSupported {
-                ref package_key,
-                ref alias_target,
-            } if package_key == "transitive-dep@npm:other@^2.0.0"
-                && alias_target == "npm:other@^2.0.0"
-        ));
+        // `from_spec` boundary after being assembled as `name@range`.
+        let declaration = DependencyDeclaration::from_spec("@scope/dep@npm:@other/lib@^2.0.0")
+            .expect("transitive npm alias should parse");
+        assert_eq!(declaration.package_name, "@scope/dep");
+        assert_eq!(
+            registry_target(&declaration.package_name, &declaration.requested),
+            ("@other/lib".to_string(), "^2.0.0".to_string())
+        );
     }
 
     #[test]
-    fn non_prefix_npm_substring_is_not_rejected() {
-        // A range that merely contains `npm:` at a non-prefix position must
-        // flow through normally; only a value that *starts with* `npm:` is an
-        // alias. `1.2.3` is not a real range, but it reaches `from_spec`
-        // intact and must not trip the alias guard.
+    fn non_prefix_npm_substring_is_not_an_alias() {
+        // A range that merely contains `npm:` at a non-prefix position is not
+        // an alias; only a value that *starts with* `npm:` is.
         let request = DependencyRequest::from_spec(
             "not-alias@1.2.3",
             DependencyRequestKind::DirectProduction,
         )
-        .expect("non-prefix npm substring must not be rejected");
+        .expect("non-prefix npm substring must parse");
         assert_eq!(request.package_name, "not-alias");
-        assert_eq!(request.requested, "1.2.3");
+        assert_eq!(
+            request.registry_target(),
+            ("not-alias".to_string(), "1.2.3".to_string())
+        );
     }
 
     #[test]
-    fn npm_alias_marker_is_matched_case_insensitively() {
+    fn npm_alias_scheme_is_matched_case_insensitively() {
         // npm's own `npm-package-arg` tests `spec.toLowerCase().startsWith("npm:")`,
-        // so `NPM:` and `Npm:` are aliases too. Detection must not require an
-        // exact-case `npm:` marker, otherwise the declaration degrades to the
-        // misleading MissingMetadata failure that rejection is meant to replace.
-        for &mixed_case_marker in &["@NPM:", "@Npm:", "@nPm:"] {
-            let spec = format!("foo{mixed_case_marker}bar@1.2.3");
-            let expected_alias = format!("{}bar@1.2.3", &mixed_case_marker[1..]);
-
-            let error =
+        // so `NPM:` and `Npm:` are aliases too.
+        for &scheme in &["NPM:", "Npm:", "nPm:"] {
+            let spec = format!("foo@{scheme}bar@1.2.3");
+            let request =
                 DependencyRequest::from_spec(&spec, DependencyRequestKind::DirectProduction)
-                    .expect_err("mixed-case root-manifest alias must be rejected");
-            assert!(
-                matches!(
-                    error,
-                    ResolutionError::NpmAliasNotSupported {
-                        ref package_key,
-                        ref alias_target,
-                    } if package_key == &spec && alias_target == &expected_alias
-                ),
-                "for {spec:?}: expected NpmAliasNotSupported, got {error:?}"
-            );
-
-            let error = DependencyDeclaration::from_spec(&spec)
-                .expect_err("mixed-case transitive alias must be rejected");
-            assert!(
-                matches!(
-                    error,
-                    ResolutionError::NpmAliasNotSupported {
-                        ref package_key,
-                        ref alias_target,
-                    } if package_key == &spec && alias_target == &expected_alias
-                ),
-                "for {spec:?}: expected NpmAliasNotSupported, got {error:?}"
+                    .unwrap();
+            assert_eq!(
+                request.registry_target(),
+                ("bar".to_string(), "1.2.3".to_string()),
+                "for {spec:?}"
             );
         }
     }
 
+    #[test]
+    fn npm_alias_without_a_package_is_rejected() {
+        let error = DependencyDeclaration::from_spec("foo@npm:")
+            .expect_err("an alias naming no package should be rejected");
+        assert!(matches!(
+            error,
+            ResolutionError::InvalidNpmAlias {
+                ref package_key,
+                ref alias_target,
+            } if package_key == "foo@npm:" && alias_target == "npm:"
+        ));
+    }
+
     // M5 compatibility fixtures (issue #137). These cover the scenarios not
-    // already exercised by the peer-preserve (#134/#135) and optional-preserve
+    // already exercised by the peer (#134/#135) and optional-preserve
     // (#133) fixtures above: dist-tag edge cases, prerelease exclusion, and
     // engines/os/cpu preservation without filtering. Each scenario follows the
     // same offline-registry + expected-list contract.
@@ -0,0 +1,302 @@
+//! Where a dependency request is fetched from.
+//!
+//! Most requests name a registry range or dist-tag. A request whose text is a
+//! git URL or a hosted shorthand (`github:org/lib#v1.2.0`, `org/lib`,
+//! `git+https://host/lib.git#semver:^1`) is fetched from that repository, and
+//! one naming a tarball (`https://host/lib-1.0.0.tgz`,
+//! `file:vendor/lib-1.0.0.tgz`) is installed from that tarball instead. A
+//! `file:` or `link:` path to a package directory installs that directory.
+//! An `npm:<name>@<range>` alias is a registry request for another package
+//! name; see `docs/specs/core/resolver/SPEC.md`.
+
+use crate::util::parse_library_name;
+
+/// The source a request's text names.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RequestSource {
+    Registry,
+    Git(GitSource),
+    /// An `http://` or `https://` tarball URL.
+    Remote(String),
+    /// A tarball file, by its path after `file:` (relative paths are relative
+    /// to the project root).
+    File(String),
+    /// A package directory to copy, by its path after `file:`.
+    Directory(String),
+    /// A package directory to symlink, by its path after `link:`.
+    Link(String),
+}
+
+/// Extensions that make a `file:` path a tarball rather than a directory.
+const TARBALL_EXTENSIONS: [&str; 3] = [".tgz", ".tar.gz", ".tar"];
+
+impl RequestSource {
+    pub fn parse(requested: &str) -> Self {
+        if let Some(git) = GitSource::parse(requested) {
+            return Self::Git(git);
+        }
+        let requested = requested.trim();
+        if requested.starts_with("https://") || requested.starts_with("http://") {
+            return Self::Remote(requested.to_string());
+        }
+        if let Some(path) = requested.strip_prefix("file:") {
+            let path = path.strip_prefix("//").unwrap_or(path);
+            if TARBALL_EXTENSIONS
+                .iter()
+                .any(|extension| path.to_ascii_lowercase().ends_with(extension))
+            {
+                return Self::File(path.to_string());
+            }
+            return Self::Directory(path.to_string());
+        }
+        if let Some(path) = requested.strip_prefix("link:") {
+            return Self::Link(path.to_string());
+        }
+        Self::Registry
+    }
+
+    pub fn is_registry(&self) -> bool {
+        matches!(self, Self::Registry)
+    }
+}
+
+/// An `npm:<name>@<range>` alias: the registry package installed under the
+/// name that requested it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NpmAlias {
+    pub package_name: String,
+    /// The range or tag, `latest` when the alias names none.
+    pub range: String,
+}
+
+impl NpmAlias {
+    /// Parse request text as an alias, or `None` when it does not start with
+    /// `npm:`. The scheme is matched ASCII case-insensitively, as npm does.
+    pub fn parse(requested: &str) -> Option<Self> {
+        let requested = requested.trim();
+        let target = requested
+            .get(..4)
+            .filter(|scheme| scheme.eq_ignore_ascii_case("npm:"))
+            .map(|_| &requested[4..])?;
+        let (package_name, range) = parse_library_name(target.to_string());
+        Some(Self {
+            package_name,
+            range: if range.is_empty() {
+                "latest".to_string()
+            } else {
+                range
+            },
+        })
+    }
+}
+
+/// A git repository and the commit-ish to check out of it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GitSource {
+    /// URL handed to `git`, without the `git+` prefix or `#` fragment.
+    pub url: String,
+    pub committish: GitCommittish,
+}
+
+/// The `#fragment` of a git request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GitCommittish {
+    /// No fragment: the remote's `HEAD`.
+    Default,
+    /// A branch, tag, or commit SHA.
+    Ref(String),
+    /// `#semver:<range>`: the highest tag satisfying the range.
+    Semver(String),
+}
+
+/// Hosted shorthands and the clone URL prefix they expand to.
+const HOSTED_SHORTHANDS: [(&str, &str); 3] = [
+    ("github:", "https://github.com/"),
+    ("gitlab:", "https://gitlab.com/"),
+    ("bitbucket:", "https://bitbucket.org/"),
+];
+
+/// Transports that may follow a `git+` prefix.
+const GIT_PLUS_SCHEMES: [&str; 4] = ["https://", "http://", "ssh://", "file://"];
+
+impl GitSource {
+    /// Parse request text as a git source, or `None` when it names a registry
+    /// range or tag.
+    pub fn parse(requested: &str) -> Option<Self> {
+        let requested = requested.trim();
+        let (location, fragment) = match requested.split_once('#') {
+            Some((location, fragment)) => (location, Some(fragment)),
+            None => (requested, None),
+        };
+        let url = git_url(location)?;
+        let committish = match fragment.map(str::trim) {
+            None | Some("") => GitCommittish::Default,
+            Some(fragment) => match fragment.strip_prefix("semver:") {
+                Some(range) => GitCommittish::Semver(range.trim().to_string()),
+                None => GitCommittish::Ref(fragment.to_string()),
+            },
+        };
+        Some(Self { url, committish })
+    }
+}
+
+fn git_url(location: &str) -> Option<String> {
+    if let Some(url) = location.strip_prefix("git+") {
+        if !GIT_PLUS_SCHEMES
+            .iter()
+            .any(|scheme| url.starts_with(scheme))
+        {
+            return None;
+        }
+        return Some(scp_form(url).unwrap_or_else(|| url.to_string()));
+    }
+    if location.starts_with("git://") {
+        return Some(location.to_string());
+    }
+    for (prefix, base) in HOSTED_SHORTHANDS {
+        if let Some(path) = location.strip_prefix(prefix) {
+            return hosted_path(path).map(|path| format!("{base}{path}.git"));
+        }
+    }
+    // npm reads a bare `owner/repo` as a GitHub shorthand.
+    hosted_path(location).map(|path| format!("https://github.com/{path}.git"))
+}
+
+/// `owner/repo` with no further segments, with a trailing `.git` dropped.
+fn hosted_path(path: &str) -> Option<String> {
+    let (owner, repo) = path.split_once('/')?;
+    let repo = repo.strip_suffix(".git").unwrap_or(repo);
+    let valid = |segment: &str| {
+        !segment.is_empty()
+            && !segment.starts_with('.')
+            && segment
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+    };
+    (valid(owner) && valid(repo)).then(|| format!("{owner}/{repo}"))
+}
+
+/// `ssh://git@host:org/repo.git` is scp syntax wearing a scheme; git only
+/// accepts it without one (`git@host:org/repo.git`).
+fn scp_form(url: &str) -> Option<String> {
+    let rest = url.strip_prefix("ssh://")?;
+    let authority_end = rest.find('/').unwrap_or(rest.len());
+    let (host, path) = rest[..authority_end].rsplit_once(':')?;
+    if path.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    Some(format!("{host}:{path}{}", &rest[authority_end..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn git(url: &str, committish: GitCommittish) -> RequestSource {
+        RequestSource::Git(GitSource {
+            url: url.to_string(),
+            committish,
+        })
+    }
+
+    #[test]
+    fn parses_git_urls_and_hosted_shorthands() {
+        assert_eq!(
+            RequestSource::parse("github:org/lib#v1.2.0"),
+            git(
+                "https://github.com/org/lib.git",
+                GitCommittish::Ref("v1.2.0".to_string())
+            )
+        );
+        assert_eq!(
+            RequestSource::parse("org/lib"),
+            git("https://github.com/org/lib.git", GitCommittish::Default)
+        );
+        assert_eq!(
+            RequestSource::parse("git+https://example.com/lib.git#semver:^1"),
+            git(
+                "https://example.com/lib.git",
+                GitCommittish::Semver("^1".to_string())
+            )
+        );
+        assert_eq!(
+            RequestSource::parse("git+file:///srv/repo.git"),
+            git("file:///srv/repo.git", GitCommittish::Default)
+        );
+        assert_eq!(
+            RequestSource::parse("git+ssh://git@example.com:org/lib.git#main"),
+            git(
+                "git@example.com:org/lib.git",
+                GitCommittish::Ref("main".to_string())
+            )
+        );
+        assert_eq!(
+            RequestSource::parse("git+ssh://git@example.com:2222/lib.git"),
+            git("ssh://git@example.com:2222/lib.git", GitCommittish::Default)
+        );
+    }
+
+    #[test]
+    fn parses_tarballs_and_local_directories() {
+        assert_eq!(
+            RequestSource::parse("https://host/foo-1.0.0.tgz"),
+            RequestSource::Remote("https://host/foo-1.0.0.tgz".to_string())
+        );
+        assert_eq!(
+            RequestSource::parse("file:vendor/foo-1.0.0.tgz"),
+            RequestSource::File("vendor/foo-1.0.0.tgz".to_string())
+        );
+        assert_eq!(
+            RequestSource::parse("file:///srv/foo.tar.gz"),
+            RequestSource::File("/srv/foo.tar.gz".to_string())
+        );
+        assert_eq!(
+            RequestSource::parse("file:../shared"),
+            RequestSource::Directory("../shared".to_string())
+        );
+        assert_eq!(
+            RequestSource::parse("link:../tool"),
+            RequestSource::Link("../tool".to_string())
+        );
+        assert_eq!(
+            RequestSource::parse("git+https://host/foo.git"),
+            RequestSource::Git(GitSource {
+                url: "https://host/foo.git".to_string(),
+                committish: GitCommittish::Default,
+            })
+        );
+    }
+
+    #[test]
+    fn parses_npm_aliases() {
+        let alias = |package_name: &str, range: &str| NpmAlias {
+            package_name: package_name.to_string(),
+            range: range.to_string(),
+        };
+        assert_eq!(
+            NpmAlias::parse("npm:bar@^1.2.0"),
+            Some(alias("bar", "^1.2.0"))
+        );
+        assert_eq!(
+            NpmAlias::parse("NPM:@scope/bar@2.x"),
+            Some(alias("@scope/bar", "2.x"))
+        );
+        assert_eq!(NpmAlias::parse("npm:bar"), Some(alias("bar", "latest")));
+        assert_eq!(NpmAlias::parse("^1.0.0"), None);
+        assert_eq!(RequestSource::parse("npm:bar@^1"), RequestSource::Registry);
+    }
+
+    #[test]
+    fn registry_ranges_and_tags_are_not_git() {
+        for requested in [
+            "^1.2.0",
+            "latest",
+            ">=1 <2",
+            "1.x",
+            "./lib",
+            "git+ftp://x/y",
+        ] {
+            assert_eq!(RequestSource::parse(requested), RequestSource::Registry);
+        }
+    }
+}
@@ -0,0 +1,347 @@
+use std::{
+    io::{Error, ErrorKind},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use chrono::{DateTime, Utc};
+
+use super::{
+    metadata_cache::{MetadataCache, DEFAULT_METADATA_MAX_AGE},
+    transport::build_http_client,
+};
+use crate::npmrc::NpmConfig;
+
+/// Default number of tarball downloads an install keeps in flight.
+pub const DEFAULT_DOWNLOAD_CONCURRENCY: usize = 16;
+
+/// Whether registry reads may reach the network.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum NetworkMode {
+    /// Revalidate stale metadata and download tarballs as needed.
+    #[default]
+    Online,
+    /// Use any cached metadata or verified cached tarball, regardless of age,
+    /// and reach the network only on a cache miss.
+    PreferOffline,
+    /// Never reach the network; a cache miss is an error.
+    Offline,
+}
+
+impl NetworkMode {
+    /// Whether cached data is used before asking the network.
+    pub fn prefers_cache(self) -> bool {
+        matches!(self, Self::PreferOffline | Self::Offline)
+    }
+}
+
+/// Where `--verify-signatures` reads the registry signing keys from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SignatureKeySource {
+    /// The registry's `/-/npm/v1/keys` document.
+    Registry,
+    /// A keys document on disk, in the same format.
+    File(PathBuf),
+}
+
+/// Which resolver an install runs, from the `.npmrc` key
+/// `resolution-strategy`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ResolutionStrategyKind {
+    /// First-in, first-out selection followed by deduplication.
+    #[default]
+    Fifo,
+    /// Backtrack over candidate versions and honor manifest `overrides`.
+    Backtracking,
+}
+
+impl ResolutionStrategyKind {
+    /// Read `resolution-strategy` (`fifo` or `backtracking`) from `config`;
+    /// an unset key selects [`Self::Fifo`].
+    pub fn from_config(config: &NpmConfig) -> std::io::Result<Self> {
+        match config.get("resolution-strategy").map(str::trim) {
+            None | Some("fifo") => Ok(Self::Fifo),
+            Some("backtracking") => Ok(Self::Backtracking),
+            Some(value) => Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("invalid resolution-strategy in .npmrc: {value}"),
+            )),
+        }
+    }
+}
+
+/// The `minimum-release-age` policy: registry versions published less than
+/// `age` ago are not selected, except for packages `exclude` names.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MinimumReleaseAge {
+    pub age: Duration,
+    /// Package names, or `@scope/*` for every package of a scope.
+    pub exclude: Vec<String>,
+}
+
+impl MinimumReleaseAge {
+    /// Read `minimum-release-age` and `minimum-release-age-exclude` from
+    /// `config`. The age is a number of minutes, or a number followed by `m`,
+    /// `h` or `d`; `0` or an unset key disables the policy. The exclude list
+    /// is separated by commas or whitespace.
+    pub fn from_config(config: &NpmConfig) -> std::io::Result<Option<Self>> {
+        let Some(value) = config.get("minimum-release-age") else {
+            return Ok(None);
+        };
+        let age = parse_release_age(value).ok_or_else(|| {
+            Error::new(
+                ErrorKind::InvalidInput,
+                format!("invalid minimum-release-age in .npmrc: {value}"),
+            )
+        })?;
+        if age.is_zero() {
+            return Ok(None);
+        }
+        let exclude = config
+            .get("minimum-release-age-exclude")
+            .unwrap_or_default()
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|pattern| !pattern.is_empty())
+            .map(str::to_string)
+            .collect();
+        Ok(Some(Self { age, exclude }))
+    }
+
+    /// Whether `package_name` is exempt from the policy.
+    pub fn excludes(&self, package_name: &str) -> bool {
+        self.exclude
+            .iter()
+            .any(|pattern| match pattern.strip_suffix("/*") {
+                Some(scope) => package_name
+                    .strip_prefix(scope)
+                    .is_some_and(|rest| rest.starts_with('/')),
+                None => pattern == package_name,
+            })
+    }
+
+    /// The newest publish time a version may have to be selected at `now`.
+    pub fn cutoff(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+        chrono::Duration::from_std(self.age)
+            .ok()
+            .and_then(|age| now.checked_sub_signed(age))
+            .unwrap_or(DateTime::<Utc>::MIN_UTC)
+    }
+}
+
+fn parse_release_age(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    let (count, unit_secs) = match value.char_indices().last()? {
+        (index, 'm') => (&value[..index], 60),
+        (index, 'h') => (&value[..index], 60 * 60),
+        (index, 'd') => (&value[..index], 24 * 60 * 60),
+        _ => (value, 60),
+    };
+    count
+        .trim()
+        .parse::<u64>()
+        .ok()?
+        .checked_mul(unit_secs)
+        .map(Duration::from_secs)
+}
+
+/// Registry access settings shared by every metadata and tarball request of
+/// one install: the merged `.npmrc` configuration, the network mode, and, when
+/// enabled, the on-disk metadata cache.
+///
+/// Clones share one pooled HTTP client, so concurrent requests to the same
+/// registry reuse its connections.
+#[derive(Debug, Clone)]
+pub struct HttpRegistryClient {
+    http: reqwest::Client,
+    config: NpmConfig,
+    network_mode: NetworkMode,
+    metadata_cache: Option<MetadataCache>,
+    download_concurrency: usize,
+}
+
+impl Default for HttpRegistryClient {
+    fn default() -> Self {
+        Self::new(NpmConfig::default())
+    }
+}
+
+impl HttpRegistryClient {
+    /// An online client without a metadata cache. Its HTTP client ignores the
+    /// proxy and TLS settings in `config`; see [`Self::from_config`].
+    pub fn new(config: NpmConfig) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            config,
+            network_mode: NetworkMode::Online,
+            metadata_cache: None,
+            download_concurrency: DEFAULT_DOWNLOAD_CONCURRENCY,
+        }
+    }
+
+    /// An online client whose HTTP client applies the proxy settings of
+    /// `config` (`proxy`, `https-proxy`, `noproxy`, falling back to
+    /// `HTTP_PROXY`, `HTTPS_PROXY` and `NO_PROXY`) and its `cafile` and
+    /// `strict-ssl` keys.
+    pub fn from_config(config: NpmConfig) -> std::io::Result<Self> {
+        Self::from_config_with_env(config, &|name| std::env::var(name).ok())
+    }
+
+    pub(crate) fn from_config_with_env(
+        config: NpmConfig,
+        env: &dyn Fn(&str) -> Option<String>,
+    ) -> std::io::Result<Self> {
+        let http = build_http_client(&config, env)?;
+        Ok(Self {
+            http,
+            ..Self::new(config)
+        })
+    }
+
+    /// A client for a project: `.npmrc` is read from the user and project
+    /// files, and metadata is cached under `<project_root>/.rpm/.metadata`.
+    ///
+    /// The `.npmrc` key `metadata-max-age` (seconds) sets how long a cached
+    /// document is reused without revalidation; it defaults to five minutes,
+    /// and `0` revalidates on every read.
+    pub fn for_project(project_root: &Path) -> std::io::Result<Self> {
+        let config = NpmConfig::load(project_root)?;
+        let max_age = metadata_max_age(&config)?;
+        let cache = MetadataCache::new(project_root.join(".rpm").join(".metadata"), max_age);
+        Ok(Self::from_config(config)?.with_metadata_cache(cache))
+    }
+
+    pub fn with_metadata_cache(mut self, metadata_cache: MetadataCache) -> Self {
+        self.metadata_cache = Some(metadata_cache);
+        self
+    }
+
+    pub fn with_network_mode(mut self, network_mode: NetworkMode) -> Self {
+        self.network_mode = network_mode;
+        self
+    }
+
+    /// Limit the number of tarball downloads in flight; `0` is treated as `1`.
+    pub fn with_download_concurrency(mut self, download_concurrency: usize) -> Self {
+        self.download_concurrency = download_concurrency.max(1);
+        self
+    }
+
+    pub fn download_concurrency(&self) -> usize {
+        self.download_concurrency
+    }
+
+    pub(crate) fn http(&self) -> &reqwest::Client {
+        &self.http
+    }
+
+    pub fn config(&self) -> &NpmConfig {
+        &self.config
+    }
+
+    pub fn network_mode(&self) -> NetworkMode {
+        self.network_mode
+    }
+
+    pub fn metadata_cache(&self) -> Option<&MetadataCache> {
+        self.metadata_cache.as_ref()
+    }
+}
+
+fn metadata_max_age(config: &NpmConfig) -> std::io::Result<Duration> {
+    let Some(value) = config.get("metadata-max-age") else {
+        return Ok(DEFAULT_METADATA_MAX_AGE);
+    };
+    value
+        .trim()
+        .parse::<u64>()
+        .map(Duration::from_secs)
+        .map_err(|_| {
+            Error::new(
+                ErrorKind::InvalidInput,
+                format!("invalid metadata-max-age in .npmrc: {value}"),
+            )
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn metadata_max_age_defaults_and_parses_seconds() {
+        assert_eq!(
+            metadata_max_age(&NpmConfig::default()).unwrap(),
+            DEFAULT_METADATA_MAX_AGE
+        );
+        assert_eq!(
+            metadata_max_age(&NpmConfig::parse("metadata-max-age=0\n")).unwrap(),
+            Duration::ZERO
+        );
+        assert_eq!(
+            metadata_max_age(&NpmConfig::parse("metadata-max-age = 3600\n")).unwrap(),
+            Duration::from_secs(3600)
+        );
+        let error = metadata_max_age(&NpmConfig::parse("metadata-max-age=soon\n")).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn resolution_strategy_defaults_to_fifo_and_rejects_unknown_values() {
+        let strategy = |text: &str| ResolutionStrategyKind::from_config(&NpmConfig::parse(text));
+        assert_eq!(strategy("").unwrap(), ResolutionStrategyKind::Fifo);
+        assert_eq!(
+            strategy("resolution-strategy=fifo\n").unwrap(),
+            ResolutionStrategyKind::Fifo
+        );
+        assert_eq!(
+            strategy("resolution-strategy = backtracking\n").unwrap(),
+            ResolutionStrategyKind::Backtracking
+        );
+        assert_eq!(
+            strategy("resolution-strategy=sat\n").unwrap_err().kind(),
+            ErrorKind::InvalidInput
+        );
+    }
+
+    #[test]
+    fn minimum_release_age_parses_units_and_exclusions() {
+        let age = |value: &str| {
+            MinimumReleaseAge::from_config(&NpmConfig::parse(&format!(
+                "minimum-release-age={value}\n"
+            )))
+        };
+        assert_eq!(
+            MinimumReleaseAge::from_config(&NpmConfig::default()).unwrap(),
+            None
+        );
+        assert_eq!(age("0").unwrap(), None);
+        assert_eq!(
+            age("90").unwrap().unwrap().age,
+            Duration::from_secs(90 * 60)
+        );
+        assert_eq!(
+            age("12h").unwrap().unwrap().age,
+            Duration::from_secs(12 * 3600)
+        );
+        assert_eq!(
+            age("3d").unwrap().unwrap().age,
+            Duration::from_secs(3 * 86400)
+        );
+        assert_eq!(age("soon").unwrap_err().kind(), ErrorKind::InvalidInput);
+
+        let policy = MinimumReleaseAge::from_config(&NpmConfig::parse(
+            "minimum-release-age=1d\nminimum-release-age-exclude=@acme/*, internal-lib\n",
+        ))
+        .unwrap()
+        .unwrap();
+        assert!(policy.excludes("@acme/ui"));
+        assert!(policy.excludes("internal-lib"));
+        assert!(!policy.excludes("@acme-other/ui"));
+        assert!(!policy.excludes("internal-lib-extra"));
+        let now = "2026-03-02T00:00:00Z".parse::<DateTime<Utc>>().unwrap();
+        assert_eq!(
+            policy.cutoff(now),
+            "2026-03-01T00:00:00Z".parse::<DateTime<Utc>>().unwrap()
+        );
+    }
+}
@@ -1 +1,4 @@
 pub const REGISTRY_PATH: &str = "https://registry.npmjs.org";
+
+/// Path of a registry's signing keys document, below its base URL.
+pub const REGISTRY_KEYS_PATH: &str = "/-/npm/v1/keys";
@@ -0,0 +1,261 @@
+use std::{
+    future::Future,
+    io::{Error, ErrorKind},
+    path::{Component, Path, PathBuf},
+};
+
+use super::{
+    client::{NetworkMode, DEFAULT_DOWNLOAD_CONCURRENCY},
+    constants::REGISTRY_KEYS_PATH,
+    RegistryClient, TarballBody,
+};
+use crate::registry::{Registry, RegistryKeys};
+use tokio::fs;
+
+/// A registry served from a local directory laid out like a registry's URL
+/// space:
+///
+/// ```text
+/// <root>/left-pad.json                        full packument of `left-pad`
+/// <root>/@scope/tool.json                     full packument of `@scope/tool`
+/// <root>/@scope/tool/-/tool-1.0.0.tgz         a tarball
+/// ```
+///
+/// A `dist.tarball` URL is resolved by its path below the root, so documents
+/// copied from a registry work unchanged: `https://host/@scope/tool/-/tool-1.0.0.tgz`
+/// reads `<root>/@scope/tool/-/tool-1.0.0.tgz`. A relative URL is resolved
+/// against the root, and a `file://` URL must name a file below the root. The
+/// registry signing keys live at `<root>/-/npm/v1/keys.json`.
+#[derive(Debug, Clone)]
+pub struct DirectoryRegistryClient {
+    root: PathBuf,
+    download_concurrency: usize,
+}
+
+impl DirectoryRegistryClient {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            download_concurrency: DEFAULT_DOWNLOAD_CONCURRENCY,
+        }
+    }
+
+    /// Limit the number of tarball reads in flight; `0` is treated as `1`.
+    pub fn with_download_concurrency(mut self, download_concurrency: usize) -> Self {
+        self.download_concurrency = download_concurrency.max(1);
+        self
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Path of the packument for `lib_name` below the root.
+    pub fn packument_path(&self, lib_name: &str) -> std::io::Result<PathBuf> {
+        let relative = format!("{lib_name}.json");
+        self.resolve(&relative)
+            .ok_or_else(|| invalid_path(&format!("invalid package name {lib_name}")))
+    }
+
+    /// Path a `dist.tarball` URL points at below the root. A `file://` URL
+    /// names its path directly, which must lie below the root too.
+    pub fn tarball_path(&self, tarball_url: &str) -> std::io::Result<PathBuf> {
+        let invalid = || invalid_path(&format!("invalid tarball URL {tarball_url}"));
+        if let Some(path) = tarball_url.strip_prefix("file://") {
+            let path = PathBuf::from(decode(path)?);
+            let relative = path.strip_prefix(&self.root).map_err(|_| invalid())?;
+            return self
+                .resolve(&relative.to_string_lossy())
+                .ok_or_else(invalid);
+        }
+        let relative = match tarball_url.split_once("://") {
+            Some((_, rest)) => rest.split_once('/').map(|(_, path)| path).unwrap_or(""),
+            None => tarball_url,
+        };
+        let relative = relative.split(['?', '#']).next().unwrap_or_default();
+        self.resolve(&decode(relative)?).ok_or_else(invalid)
+    }
+
+    /// Join `relative` to the root, refusing paths that could leave it.
+    fn resolve(&self, relative: &str) -> Option<PathBuf> {
+        let relative = Path::new(relative.trim_start_matches('/'));
+        let mut components = relative.components().peekable();
+        components.peek()?;
+        if components.all(|component| matches!(component, Component::Normal(_))) {
+            Some(self.root.join(relative))
+        } else {
+            None
+        }
+    }
+
+    async fn read_packument(&self, lib_name: &str) -> std::io::Result<Registry> {
+        let path = self.packument_path(lib_name)?;
+        let bytes = fs::read(&path).await.map_err(|error| {
+            Error::new(
+                error.kind(),
+                format!("failed to read registry {}: {error}", path.display()),
+            )
+        })?;
+        serde_json::from_slice(&bytes).map_err(|error| {
+            Error::new(
+                ErrorKind::InvalidData,
+                format!("failed to parse registry {}: {error}", path.display()),
+            )
+        })
+    }
+
+    async fn read_registry_keys(&self) -> std::io::Result<RegistryKeys> {
+        let path = self.root.join(format!(
+            "{}.json",
+            REGISTRY_KEYS_PATH.trim_start_matches('/')
+        ));
+        RegistryKeys::load(&path).await
+    }
+
+    async fn open_tarball(&self, tarball_url: &str) -> std::io::Result<TarballBody> {
+        let path = self.tarball_path(tarball_url)?;
+        let file = fs::File::open(&path).await.map_err(|error| {
+            Error::new(
+                error.kind(),
+                format!(
+                    "failed to download {tarball_url}: {}: {error}",
+                    path.display()
+                ),
+            )
+        })?;
+        Ok(TarballBody::from_file(tarball_url, file))
+    }
+}
+
+impl RegistryClient for DirectoryRegistryClient {
+    fn network_mode(&self) -> NetworkMode {
+        NetworkMode::Online
+    }
+
+    fn download_concurrency(&self) -> usize {
+        self.download_concurrency
+    }
+
+    fn get_registry(
+        &self,
+        lib_name: &str,
+    ) -> impl Future<Output = std::io::Result<Registry>> + Send {
+        self.read_packument(lib_name)
+    }
+
+    fn get_tarball(
+        &self,
+        tarball_url: &str,
+    ) -> impl Future<Output = std::io::Result<TarballBody>> + Send {
+        self.open_tarball(tarball_url)
+    }
+
+    fn get_registry_keys(&self) -> impl Future<Output = std::io::Result<RegistryKeys>> + Send {
+        self.read_registry_keys()
+    }
+}
+
+fn decode(path: &str) -> std::io::Result<String> {
+    urlencoding::decode(path)
+        .map(|decoded| decoded.into_owned())
+        .map_err(|error| invalid_path(&format!("invalid URL path {path}: {error}")))
+}
+
+fn invalid_path(message: &str) -> Error {
+    Error::new(ErrorKind::InvalidInput, message.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::test_support::TempProject;
+
+    #[test]
+    fn resolves_packument_and_tarball_paths_below_root() {
+        let client = DirectoryRegistryClient::new("/srv/registry");
+
+        assert_eq!(
+            client.packument_path("@scope/tool").unwrap(),
+            PathBuf::from("/srv/registry/@scope/tool.json")
+        );
+        assert_eq!(
+            client
+                .tarball_path("https://registry.npmjs.org/@scope/tool/-/tool-1.0.0.tgz")
+                .unwrap(),
+            PathBuf::from("/srv/registry/@scope/tool/-/tool-1.0.0.tgz")
+        );
+        assert_eq!(
+            client
+                .tarball_path("http://127.0.0.1:4873/%40scope%2Ftool/-/tool-1.0.0.tgz?cache=0")
+                .unwrap(),
+            PathBuf::from("/srv/registry/@scope/tool/-/tool-1.0.0.tgz")
+        );
+        assert_eq!(
+            client
+                .tarball_path("left-pad/-/left-pad-1.3.0.tgz")
+                .unwrap(),
+            PathBuf::from("/srv/registry/left-pad/-/left-pad-1.3.0.tgz")
+        );
+        assert_eq!(
+            client
+                .tarball_path("file:///srv/registry/left-pad/-/left-pad-1.3.0.tgz")
+                .unwrap(),
+            PathBuf::from("/srv/registry/left-pad/-/left-pad-1.3.0.tgz")
+        );
+    }
+
+    #[test]
+    fn refuses_paths_that_leave_the_root() {
+        let client = DirectoryRegistryClient::new("/srv/registry");
+
+        for url in [
+            "https://registry.example/../secret.tgz",
+            "https://registry.example/%2E%2E/secret.tgz",
+            "https://registry.example/",
+            "file:///tmp/pkg.tgz",
+            "file:///srv/registry/../secret.tgz",
+            "file:///srv/registry/%2E%2E/secret.tgz",
+            "file:///srv/registry",
+        ] {
+            let error = client.tarball_path(url).unwrap_err();
+            assert_eq!(error.kind(), ErrorKind::InvalidInput, "{url}");
+        }
+        assert_eq!(
+            client.packument_path("../outside").unwrap_err().kind(),
+            ErrorKind::InvalidInput
+        );
+    }
+
+    #[tokio::test]
+    async fn reads_packuments_and_streams_tarballs_from_disk() {
+        let project = TempProject::new("directory-registry").unwrap();
+        project
+            .write(
+                "@scope/tool.json",
+                r#"{"name":"@scope/tool","dist-tags":{"latest":"1.0.0"},"versions":{"1.0.0":{"dist":{"tarball":"https://registry.example/@scope/tool/-/tool-1.0.0.tgz"}}}}"#,
+            )
+            .unwrap();
+        project
+            .write("@scope/tool/-/tool-1.0.0.tgz", "tarball bytes")
+            .unwrap();
+        let client = DirectoryRegistryClient::new(project.path());
+
+        let registry = client.get_registry("@scope/tool").await.unwrap();
+        assert!(!registry.is_abbreviated());
+        assert_eq!(registry.select_version("^1.0.0").unwrap(), "1.0.0");
+
+        let mut body = client
+            .get_tarball("https://registry.example/@scope/tool/-/tool-1.0.0.tgz")
+            .await
+            .unwrap();
+        let mut bytes = Vec::new();
+        while let Some(chunk) = body.read_chunk().await.unwrap() {
+            bytes.extend_from_slice(chunk);
+        }
+        assert_eq!(bytes, b"tarball bytes");
+
+        let missing = client.get_registry("missing").await.unwrap_err();
+        assert_eq!(missing.kind(), ErrorKind::NotFound);
+        assert!(missing.to_string().contains("missing.json"));
+    }
+}
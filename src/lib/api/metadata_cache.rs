@@ -0,0 +1,207 @@
+use std::{
+    fs,
+    io::{Error, ErrorKind, Write},
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+
+/// Default time a cached packument is reused without revalidation.
+pub const DEFAULT_METADATA_MAX_AGE: Duration = Duration::from_secs(300);
+
+/// On-disk cache of registry metadata responses.
+///
+/// Entries live under `.rpm/.metadata`, separate from the tarball cache, and
+/// keep the response validators (`ETag`, `Last-Modified`) so a stale entry can
+/// be revalidated with a conditional request instead of downloaded again.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MetadataCache {
+    dir: PathBuf,
+    max_age: Duration,
+}
+
+/// A stored metadata response.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct CachedPackument {
+    pub(crate) url: String,
+    pub(crate) etag: Option<String>,
+    pub(crate) last_modified: Option<String>,
+    pub(crate) fetched_at: u64,
+    pub(crate) abbreviated: bool,
+    pub(crate) body: serde_json::Value,
+}
+
+impl MetadataCache {
+    pub fn new(dir: impl Into<PathBuf>, max_age: Duration) -> Self {
+        Self {
+            dir: dir.into(),
+            max_age,
+        }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn max_age(&self) -> Duration {
+        self.max_age
+    }
+
+    /// Read the entry for `key` if it was stored for `url`.
+    ///
+    /// A missing, unreadable, or corrupt entry, or one stored for a different
+    /// registry URL, is a cache miss rather than an error: the cache only saves
+    /// round trips and never decides what gets installed.
+    pub(crate) fn read(&self, key: &str, url: &str) -> Option<CachedPackument> {
+        let bytes = fs::read(self.entry_path(key)).ok()?;
+        serde_json::from_slice::<CachedPackument>(&bytes)
+            .ok()
+            .filter(|entry| entry.url == url)
+    }
+
+    /// Read the entry for `key` whichever registry URL it was stored for.
+    pub(crate) fn read_entry(&self, key: &str) -> Option<CachedPackument> {
+        let bytes = fs::read(self.entry_path(key)).ok()?;
+        serde_json::from_slice(&bytes).ok()
+    }
+
+    /// Whether `entry` is young enough to use without revalidation.
+    pub(crate) fn is_fresh(&self, entry: &CachedPackument) -> bool {
+        now_secs().saturating_sub(entry.fetched_at) < self.max_age.as_secs()
+    }
+
+    /// Store `entry` under `key`, staging the file and publishing it by rename
+    /// so readers never observe a partial entry.
+    pub(crate) fn write(&self, key: &str, entry: &CachedPackument) -> std::io::Result<()> {
+        fs::create_dir_all(&self.dir).map_err(|error| {
+            Error::new(
+                error.kind(),
+                format!(
+                    "failed to create metadata cache {}: {error}",
+                    self.dir.display()
+                ),
+            )
+        })?;
+        let path = self.entry_path(key);
+        let staging_path = self
+            .dir
+            .join(format!(".rpm-metadata-{}-{key}.tmp", std::process::id()));
+        let bytes = serde_json::to_vec(entry)
+            .map_err(|error| Error::new(ErrorKind::InvalidData, error.to_string()))?;
+        let result = fs::File::create(&staging_path)
+            .and_then(|mut file| file.write_all(&bytes))
+            .and_then(|()| fs::rename(&staging_path, &path));
+        result.map_err(|error| {
+            let _ = fs::remove_file(&staging_path);
+            Error::new(
+                error.kind(),
+                format!("failed to write metadata cache {}: {error}", path.display()),
+            )
+        })
+    }
+
+    fn entry_path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{key}.json"))
+    }
+}
+
+/// Cache key for a package document: the package name with `/` replaced by
+/// `-` (as in tarball cache names), plus the version segment when present and
+/// a `.full` suffix for full documents.
+pub(crate) fn metadata_cache_key(lib_name: &str, version: &str, full: bool) -> String {
+    let mut key = lib_name.replace('/', "-");
+    if !version.is_empty() {
+        key.push('@');
+        key.push_str(&version.replace('/', "-"));
+    }
+    if full {
+        key.push_str(".full");
+    }
+    key
+}
+
+pub(crate) fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::test_support::TempProject;
+
+    fn entry(url: &str, fetched_at: u64) -> CachedPackument {
+        CachedPackument {
+            url: url.to_string(),
+            etag: Some("\"v1\"".to_string()),
+            last_modified: None,
+            fetched_at,
+            abbreviated: true,
+            body: serde_json::json!({ "name": "express" }),
+        }
+    }
+
+    #[test]
+    fn write_then_read_round_trips_entry_for_same_url() {
+        let project = TempProject::new("metadata-cache").unwrap();
+        let cache = MetadataCache::new(project.path().join(".metadata"), Duration::from_secs(60));
+        let stored = entry("https://registry.example/express/", now_secs());
+
+        cache.write("express", &stored).unwrap();
+
+        assert_eq!(
+            cache.read("express", "https://registry.example/express/"),
+            Some(stored)
+        );
+        assert_eq!(
+            cache.read("express", "https://mirror.example/express/"),
+            None
+        );
+        assert_eq!(
+            cache.read("missing", "https://registry.example/missing/"),
+            None
+        );
+        assert!(fs::read_dir(cache.dir()).unwrap().all(|entry| !entry
+            .unwrap()
+            .file_name()
+            .to_string_lossy()
+            .ends_with(".tmp")));
+    }
+
+    #[test]
+    fn corrupt_entry_is_a_cache_miss() {
+        let project = TempProject::new("metadata-cache-corrupt").unwrap();
+        project.write(".metadata/express.json", "{").unwrap();
+        let cache = MetadataCache::new(project.path().join(".metadata"), Duration::from_secs(60));
+
+        assert_eq!(
+            cache.read("express", "https://registry.example/express/"),
+            None
+        );
+    }
+
+    #[test]
+    fn freshness_follows_max_age() {
+        let cache = MetadataCache::new("unused", Duration::from_secs(60));
+
+        assert!(cache.is_fresh(&entry("url", now_secs())));
+        assert!(!cache.is_fresh(&entry("url", now_secs() - 120)));
+        assert!(!MetadataCache::new("unused", Duration::ZERO).is_fresh(&entry("url", now_secs())));
+    }
+
+    #[test]
+    fn cache_keys_separate_scoped_names_versions_and_formats() {
+        assert_eq!(metadata_cache_key("@corp/tool", "", false), "@corp-tool");
+        assert_eq!(
+            metadata_cache_key("@corp/tool", "", true),
+            "@corp-tool.full"
+        );
+        assert_eq!(
+            metadata_cache_key("express", "4.18.2", false),
+            "express@4.18.2"
+        );
+    }
+}
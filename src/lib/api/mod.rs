@@ -1,567 +1,774 @@
+mod client;
 mod constants;
-
-use constants::REGISTRY_PATH;
-
-use crate::registry::Registry;
-use std::io::Error;
+mod directory;
+mod metadata_cache;
+mod registry_client;
+mod server;
+mod tarball;
 #[cfg(test)]
-use std::{fs, io::ErrorKind, path::PathBuf};
+pub(crate) mod test_support;
+mod transport;
+
+pub use client::{
+    HttpRegistryClient, MinimumReleaseAge, NetworkMode, ResolutionStrategyKind, SignatureKeySource,
+    DEFAULT_DOWNLOAD_CONCURRENCY,
+};
+pub use directory::DirectoryRegistryClient;
+pub use metadata_cache::{MetadataCache, DEFAULT_METADATA_MAX_AGE};
+pub use registry_client::RegistryClient;
+pub use server::{bind_registry_listener, CacheRegistryServer, ServedRequest};
+pub use tarball::TarballBody;
+
+use constants::{REGISTRY_KEYS_PATH, REGISTRY_PATH};
+use metadata_cache::{metadata_cache_key, now_secs, CachedPackument};
+
+use crate::{
+    npmrc::NpmConfig,
+    registry::{Registry, RegistryKeys},
+};
+use serde::Deserialize;
+use std::{
+    fmt,
+    io::{Error, ErrorKind},
+};
+
+/// Media type of npm's abbreviated ("corgi") install metadata document.
+const ABBREVIATED_PACKUMENT: &str = "application/vnd.npm.install-v1+json";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PackumentFormat {
+    Abbreviated,
+    Full,
+}
 
-pub async fn get_registry(lib_name: &str, version: &str) -> std::io::Result<Registry> {
-    #[cfg(test)]
-    if let Some(registry) = read_registry_fixture(lib_name)? {
-        test_support::record_metadata_read(lib_name);
-        return Ok(registry);
+impl PackumentFormat {
+    fn accept_header(self) -> &'static str {
+        match self {
+            Self::Abbreviated => {
+                "application/vnd.npm.install-v1+json; q=1.0, application/json; q=0.8, */*"
+            }
+            Self::Full => "application/json",
+        }
     }
+}
 
-    let request_url = registry_lookup_url(lib_name, version);
-    let registry = reqwest::get(&request_url)
-        .await
-        .map_err(|error| Error::other(format!("failed to fetch registry {request_url}: {error}")))?
-        .json::<Registry>()
-        .await
-        .map_err(|error| {
-            Error::other(format!(
-                "failed to parse registry response for {lib_name}: {error}"
-            ))
-        })?;
-    Ok(registry)
+/// Fetch package metadata, preferring the abbreviated packument.
+///
+/// The abbreviated document is requested because it is a fraction of the
+/// full document's size. When it lacks fields install needs for the version
+/// it locks (see `Registry::requires_full_document`), install fetches the
+/// full document for that package. Registries that ignore the `Accept`
+/// header answer with the full document, which is used as-is.
+pub async fn get_registry(
+    client: &HttpRegistryClient,
+    lib_name: &str,
+    version: &str,
+) -> std::io::Result<Registry> {
+    fetch_packument(client, lib_name, version, PackumentFormat::Abbreviated).await
+}
+
+/// Fetch the full package document, for callers that need fields such as
+/// `time` that the abbreviated packument omits.
+pub async fn get_full_registry(
+    client: &HttpRegistryClient,
+    lib_name: &str,
+    version: &str,
+) -> std::io::Result<Registry> {
+    fetch_packument(client, lib_name, version, PackumentFormat::Full).await
 }
 
-pub async fn get_tarball(tarball_url: &str) -> std::io::Result<Vec<u8>> {
-    #[cfg(test)]
-    if std::env::var_os("RPM_REGISTRY_FIXTURE_ROOT").is_some() {
-        test_support::record_tarball_download(&package_key_from_tarball_url(tarball_url));
-        return fixture_tarball(tarball_url);
+/// Fetch one package document, consulting the client's metadata cache.
+///
+/// A cached entry younger than the cache max-age is used without a request;
+/// in `PreferOffline` and `Offline` mode any cached entry is, and in `Offline`
+/// mode a miss fails with an offline cache miss instead of a request.
+/// An older entry is revalidated with `If-None-Match`/`If-Modified-Since`; a
+/// `304 Not Modified` reuses the stored body and restarts its max-age. Cache
+/// writes are best effort: a cache that cannot be written never fails the
+/// fetch.
+async fn fetch_packument(
+    client: &HttpRegistryClient,
+    lib_name: &str,
+    version: &str,
+    format: PackumentFormat,
+) -> std::io::Result<Registry> {
+    let config = client.config();
+    let request_url = registry_lookup_url(config, lib_name, version);
+    let cache = client.metadata_cache();
+    let cache_key = metadata_cache_key(lib_name, version, format == PackumentFormat::Full);
+    let cached = cache.and_then(|cache| cache.read(&cache_key, &request_url));
+    if let (Some(cache), Some(entry)) = (cache, &cached) {
+        if cache.is_fresh(entry) || client.network_mode().prefers_cache() {
+            return packument_from_entry(lib_name, entry);
+        }
+    }
+    if client.network_mode() == NetworkMode::Offline {
+        return Err(offline_cache_miss(lib_name));
     }
 
-    let response = reqwest::get(tarball_url)
-        .await
-        .map_err(|error| Error::other(format!("failed to download {tarball_url}: {error}")))?
+    let mut request = registry_request(client, &request_url)
+        .header(reqwest::header::ACCEPT, format.accept_header());
+    if let Some(entry) = &cached {
+        if let Some(etag) = &entry.etag {
+            request = request.header(reqwest::header::IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = &entry.last_modified {
+            request = request.header(reqwest::header::IF_MODIFIED_SINCE, last_modified);
+        }
+    }
+    let response = request.send().await.map_err(|error| {
+        Error::other(format!("failed to fetch registry {request_url}: {error}"))
+    })?;
+
+    if response.status() == reqwest::StatusCode::NOT_MODIFIED {
+        if let (Some(cache), Some(mut entry)) = (cache, cached) {
+            entry.fetched_at = now_secs();
+            let _ = cache.write(&cache_key, &entry);
+            return packument_from_entry(lib_name, &entry);
+        }
+    }
+    if !response.status().is_success() {
+        return Err(Error::other(format!(
+            "failed to fetch registry {request_url}: HTTP {}",
+            response.status()
+        )));
+    }
+
+    let header = |name: reqwest::header::HeaderName| {
+        response
+            .headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string)
+    };
+    let etag = header(reqwest::header::ETAG);
+    let last_modified = header(reqwest::header::LAST_MODIFIED);
+    let abbreviated = is_abbreviated_response(response.headers());
+    let bytes = response
         .bytes()
         .await
-        .map_err(|error| Error::other(format!("failed to read {tarball_url}: {error}")))?;
-    Ok(response.to_vec())
+        .map_err(|error| Error::other(format!("failed to read registry {request_url}: {error}")))?;
+    let body = serde_json::from_slice::<serde_json::Value>(&bytes).map_err(|error| {
+        Error::other(format!(
+            "failed to parse registry response for {lib_name}: {error}"
+        ))
+    })?;
+    let entry = CachedPackument {
+        url: request_url,
+        etag,
+        last_modified,
+        fetched_at: now_secs(),
+        abbreviated,
+        body,
+    };
+    let registry = packument_from_entry(lib_name, &entry)?;
+    if let Some(cache) = cache {
+        let _ = cache.write(&cache_key, &entry);
+    }
+    Ok(registry)
 }
 
-pub async fn get_registry_text(lib_name: &str, version: &str) -> std::io::Result<String> {
-    let request_url = registry_lookup_url(lib_name, version);
-    reqwest::get(&request_url)
-        .await
-        .map_err(|error| Error::other(format!("failed to fetch registry {request_url}: {error}")))?
-        .text()
-        .await
-        .map_err(|error| Error::other(format!("failed to read registry {request_url}: {error}")))
+/// Payload of the error an offline read returns for data the local caches do
+/// not hold; `key` names the package or tarball.
+#[derive(Debug)]
+pub(crate) struct OfflineCacheMiss {
+    pub(crate) key: String,
 }
 
-/// Assemble the registry lookup URL for a package name and version segment.
-///
-/// npm serves a scoped packument at a single path segment, so the scoped name
-/// (`@scope/name`) must be percent-encoded with `/` as `%2F` (for example
-/// `@babel/core` → `/@babel%2Fcore`). Unscoped names carry no `/` and remain
-/// byte-identical. The version segment stays a separate path component. The
-/// package identity stays verbatim everywhere else; only this registry lookup
-/// path encodes the name (see `docs/specs/core/registry/SPEC.md`).
-fn registry_lookup_url(lib_name: &str, version: &str) -> String {
-    let encoded_name = lib_name.replace('/', "%2F");
-    format!("{}/{encoded_name}/{version}", REGISTRY_PATH)
+impl fmt::Display for OfflineCacheMiss {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "offline cache miss: {}", self.key)
+    }
 }
 
-#[cfg(test)]
-fn read_registry_fixture(lib_name: &str) -> std::io::Result<Option<Registry>> {
-    let Some(root) = std::env::var_os("RPM_REGISTRY_FIXTURE_ROOT") else {
-        return Ok(None);
-    };
-    let file_name = format!("{}.json", lib_name.replace('/', "__"));
-    let path = PathBuf::from(root).join(file_name);
-    let fixture = fs::read_to_string(&path).map_err(|error| {
-        Error::new(
-            error.kind(),
-            format!(
-                "failed to read registry fixture {}: {error}",
-                path.display()
-            ),
-        )
-    })?;
-    serde_json::from_str(&fixture).map(Some).map_err(|error| {
-        Error::new(
-            ErrorKind::InvalidData,
-            format!(
-                "failed to parse registry fixture {}: {error}",
-                path.display()
-            ),
-        )
-    })
+impl std::error::Error for OfflineCacheMiss {}
+
+/// Error for data an offline read could not find in the local caches.
+pub(crate) fn offline_cache_miss(key: &str) -> Error {
+    Error::new(
+        ErrorKind::NotFound,
+        OfflineCacheMiss {
+            key: key.to_string(),
+        },
+    )
 }
 
-#[cfg(test)]
-fn fixture_tarball(tarball_url: &str) -> std::io::Result<Vec<u8>> {
-    use flate2::{write::GzEncoder, Compression};
-    use std::io::Write;
-    use tar::{Builder, Header};
-
-    let package_name = package_name_from_tarball_url(tarball_url)?;
-    let spec = read_fixture_tarball_spec(&package_name);
-    let package_json = build_fixture_package_json(&package_name, spec.as_ref());
-    let extra_files = spec
-        .as_ref()
-        .map(|spec| spec.files.as_slice())
-        .unwrap_or(&[]);
-
-    let encoder = GzEncoder::new(Vec::new(), Compression::default());
-    let mut builder = Builder::new(encoder);
-
-    let mut header = Header::new_gnu();
-    header.set_size(package_json.len() as u64);
-    header.set_cksum();
-    builder.append_data(&mut header, "package/package.json", package_json.as_bytes())?;
-
-    // When a fixture tarball spec declares extra files (for example a binary
-    // target reachable through a `bin` field), append each one under the
-    // `package/` prefix so the install extraction step strips the prefix and
-    // lands them at `node_modules/<package>/<file>`, where `link_bins` expects
-    // them. A missing spec keeps the legacy minimal archive unchanged.
-    for file in extra_files {
-        let archive_path = format!("package/{}", file.path);
-        let mut header = Header::new_gnu();
-        header.set_size(file.contents.len() as u64);
-        header.set_mode(file.mode);
-        header.set_cksum();
-        builder.append_data(&mut header, &archive_path, file.contents.as_bytes())?;
+/// Whether `error` is an offline cache miss rather than a real failure.
+pub(crate) fn is_offline_cache_miss(error: &Error) -> bool {
+    error
+        .get_ref()
+        .is_some_and(|inner| inner.is::<OfflineCacheMiss>())
+}
+
+fn packument_from_entry(lib_name: &str, entry: &CachedPackument) -> std::io::Result<Registry> {
+    let mut registry = Registry::deserialize(&entry.body).map_err(|error| {
+        Error::other(format!(
+            "failed to parse registry response for {lib_name}: {error}"
+        ))
+    })?;
+    if entry.abbreviated {
+        registry.mark_abbreviated();
     }
+    Ok(registry)
+}
 
-    builder.finish()?;
-    let mut encoder = builder.into_inner()?;
-    encoder.flush()?;
-    encoder.finish()
+fn is_abbreviated_response(headers: &reqwest::header::HeaderMap) -> bool {
+    headers
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.contains(ABBREVIATED_PACKUMENT))
+}
+
+/// Start downloading a tarball. The body is streamed by the caller, so the
+/// response is never buffered whole; a non-success status is an error.
+pub async fn get_tarball(
+    client: &HttpRegistryClient,
+    tarball_url: &str,
+) -> std::io::Result<TarballBody> {
+    let response = registry_request(client, tarball_url)
+        .send()
+        .await
+        .map_err(|error| Error::other(format!("failed to download {tarball_url}: {error}")))?;
+    if !response.status().is_success() {
+        return Err(Error::other(format!(
+            "failed to download {tarball_url}: HTTP {}",
+            response.status()
+        )));
+    }
+    Ok(TarballBody::from_response(tarball_url, response))
 }
 
-/// Optional fixture-side description of a synthetic tarball's contents.
+/// Fetch the registry's signing keys, which `--verify-signatures` checks
+/// against unless it is given a keys file.
 ///
-/// The legacy path serves a minimal `package/package.json` with only a `name`
-/// field. Specs live at `<fixture-root>/tarballs/<package-name>.json` so a
-/// fixture can declare a `bin` field and the binary target files the install
-/// pipeline must extract alongside the manifest. Only tests that need `.bin`
-/// linking or other non-trivial tarball contents provide a spec; every other
-/// fixture stays on the minimal default.
-#[cfg(test)]
-#[derive(Debug, serde::Deserialize)]
-struct FixtureTarballSpec {
-    #[serde(default)]
-    bin: Option<serde_json::Value>,
-    #[serde(default)]
-    files: Vec<FixtureTarballFile>,
+/// Registry keys are fetched from the default registry (`registry=`); packages
+/// from a scoped registry signed with other keys need a keys file that
+/// lists them.
+pub async fn get_registry_keys(client: &HttpRegistryClient) -> std::io::Result<RegistryKeys> {
+    let url = format!(
+        "{}{REGISTRY_KEYS_PATH}",
+        client.config().registry().unwrap_or(REGISTRY_PATH)
+    );
+    if client.network_mode() == NetworkMode::Offline {
+        return Err(Error::new(
+            ErrorKind::NotFound,
+            format!("offline install cannot fetch registry keys {url}; pass --registry-keys"),
+        ));
+    }
+    let response = registry_request(client, &url)
+        .send()
+        .await
+        .map_err(|error| Error::other(format!("failed to fetch registry keys {url}: {error}")))?;
+    if !response.status().is_success() {
+        return Err(Error::other(format!(
+            "failed to fetch registry keys {url}: HTTP {}",
+            response.status()
+        )));
+    }
+    let bytes = response
+        .bytes()
+        .await
+        .map_err(|error| Error::other(format!("failed to read registry keys {url}: {error}")))?;
+    RegistryKeys::from_json(&bytes, &url)
 }
 
-#[cfg(test)]
-#[derive(Debug, serde::Deserialize)]
-struct FixtureTarballFile {
-    path: String,
-    contents: String,
-    #[serde(default = "default_fixture_file_mode")]
-    mode: u32,
+pub async fn get_registry_text(
+    client: &HttpRegistryClient,
+    lib_name: &str,
+    version: &str,
+) -> std::io::Result<String> {
+    let request_url = registry_lookup_url(client.config(), lib_name, version);
+    registry_request(client, &request_url)
+        .send()
+        .await
+        .map_err(|error| Error::other(format!("failed to fetch registry {request_url}: {error}")))?
+        .text()
+        .await
+        .map_err(|error| Error::other(format!("failed to read registry {request_url}: {error}")))
 }
 
-#[cfg(test)]
-fn default_fixture_file_mode() -> u32 {
-    0o644
+/// The registry base URL `rpm publish` uploads `package_name` to:
+/// `publishConfig.registry` when set, then the `.npmrc` registry for the
+/// package, then the public npm registry. Never ends in `/`.
+pub fn publish_registry(
+    config: &NpmConfig,
+    package_name: &str,
+    publish_config_registry: Option<&str>,
+) -> String {
+    publish_config_registry
+        .map(|registry| registry.trim().trim_end_matches('/'))
+        .filter(|registry| !registry.is_empty())
+        .or_else(|| config.registry_for_package(package_name))
+        .unwrap_or(REGISTRY_PATH)
+        .to_string()
 }
 
-/// Read the optional tarball spec for `package_name` from the fixture registry
-/// root. Returns `None` when the root is unset or no spec file exists, so the
-/// minimal-archive default path stays intact for every fixture that does not
-/// opt in.
-#[cfg(test)]
-fn read_fixture_tarball_spec(package_name: &str) -> Option<FixtureTarballSpec> {
-    let root = std::env::var_os("RPM_REGISTRY_FIXTURE_ROOT")?;
-    let file_name = format!("{}.json", package_name.replace('/', "__"));
-    let path = PathBuf::from(root).join("tarballs").join(file_name);
-    let contents = match fs::read_to_string(&path) {
-        Ok(contents) => contents,
-        Err(error) if error.kind() == ErrorKind::NotFound => return None,
-        Err(error) => panic!("{} did not deserialize: {error}", path.display()),
+/// PUT an npm publish document for `package_name` to `registry` (a base URL
+/// from [`publish_registry`]), with the `.npmrc` credentials for that URL.
+///
+/// A rejected upload reports the HTTP status and the registry's `error` or
+/// `reason` message; `401`/`403` map to `PermissionDenied` and `409` (the
+/// version exists) to `AlreadyExists`.
+pub async fn publish_package(
+    client: &HttpRegistryClient,
+    registry: &str,
+    package_name: &str,
+    document: &serde_json::Value,
+) -> std::io::Result<()> {
+    let url = format!("{registry}/{}", package_name.replace('/', "%2F"));
+    let failure = |detail: String| format!("failed to publish {package_name} to {url}: {detail}");
+    let response = with_credentials(client, client.http().put(&url), &url)
+        .json(document)
+        .send()
+        .await
+        .map_err(|error| Error::other(failure(error.to_string())))?;
+    let status = response.status();
+    if status.is_success() {
+        return Ok(());
+    }
+    let body = response.text().await.unwrap_or_default();
+    let reason = serde_json::from_str::<serde_json::Value>(&body)
+        .ok()
+        .and_then(|value| {
+            ["error", "reason"]
+                .iter()
+                .find_map(|key| value.get(key)?.as_str().map(str::to_string))
+        });
+    let kind = match status.as_u16() {
+        401 | 403 => ErrorKind::PermissionDenied,
+        409 => ErrorKind::AlreadyExists,
+        _ => ErrorKind::Other,
     };
-    serde_json::from_str(&contents)
-        .unwrap_or_else(|error| panic!("{} should be valid JSON: {error}", path.display()))
+    let detail = match reason {
+        Some(reason) => format!("HTTP {status}: {reason}"),
+        None => format!("HTTP {status}"),
+    };
+    Err(Error::new(kind, failure(detail)))
 }
 
-/// Build the `package/package.json` body for a fixture tarball. Without a spec
-/// it is the legacy minimal `{"name":"..."}` object; with a spec the declared
-/// `bin` fields are merged in so `link_bins` reads them after extraction.
-#[cfg(test)]
-fn build_fixture_package_json(package_name: &str, spec: Option<&FixtureTarballSpec>) -> String {
-    let Some(spec) = spec else {
-        return format!(r#"{{"name":"{package_name}"}}"#);
-    };
-    let mut root = serde_json::Map::new();
-    root.insert(
-        "name".to_string(),
-        serde_json::Value::String(package_name.to_string()),
-    );
-    if let Some(bin) = &spec.bin {
-        root.insert("bin".to_string(), bin.clone());
-    }
-    serde_json::Value::Object(root).to_string()
+/// Build a GET request for a registry URL on the client's shared connection
+/// pool, attaching the `.npmrc` credentials scoped to that URL's host.
+/// Credentials only travel in the `Authorization` header; error messages
+/// report the URL, never the header value.
+fn registry_request(client: &HttpRegistryClient, url: &str) -> reqwest::RequestBuilder {
+    with_credentials(client, client.http().get(url), url)
 }
 
-#[cfg(test)]
-fn package_name_from_tarball_url(tarball_url: &str) -> std::io::Result<String> {
-    let path = tarball_url
-        .split_once("://")
-        .and_then(|(_, rest)| rest.split_once('/').map(|(_, path)| path))
-        .ok_or_else(|| {
-            Error::new(
-                ErrorKind::InvalidInput,
-                format!("invalid fixture tarball URL: {tarball_url}"),
-            )
-        })?;
-    let parts = path
-        .split('/')
-        .filter(|part| !part.is_empty())
-        .collect::<Vec<_>>();
-    match parts.as_slice() {
-        [scope, name, ..] if scope.starts_with('@') => Ok(format!("{scope}/{name}")),
-        [name, ..] => Ok((*name).to_string()),
-        _ => Err(Error::new(
-            ErrorKind::InvalidInput,
-            format!("invalid fixture tarball URL path: {tarball_url}"),
-        )),
+fn with_credentials(
+    client: &HttpRegistryClient,
+    request: reqwest::RequestBuilder,
+    url: &str,
+) -> reqwest::RequestBuilder {
+    match client.config().credentials_for_url(url) {
+        Some(credentials) => request.header(
+            reqwest::header::AUTHORIZATION,
+            credentials.authorization_header(),
+        ),
+        None => request,
     }
 }
 
-/// Derive the selected `package-name@version` a fixture tarball URL stands for.
+/// Assemble the registry lookup URL for a package name and version segment.
 ///
-/// Install deduplication is keyed by the selected package and version, not by
-/// the requested range, so download measurement records the same unit. URLs
-/// that do not follow the fixture layout fall back to the raw URL, which keeps
-/// distinct downloads distinct instead of silently merging counters.
-#[cfg(test)]
-fn package_key_from_tarball_url(tarball_url: &str) -> String {
-    let Ok(package_name) = package_name_from_tarball_url(tarball_url) else {
-        return tarball_url.to_string();
-    };
-    let unscoped = package_name
-        .rsplit('/')
-        .next()
-        .unwrap_or(package_name.as_str());
-    let version = tarball_url
-        .rsplit('/')
-        .next()
-        .and_then(|file_name| file_name.strip_suffix(".tgz"))
-        .and_then(|stem| stem.strip_prefix(&format!("{unscoped}-")))
-        .filter(|version| !version.is_empty());
-    match version {
-        Some(version) => format!("{package_name}@{version}"),
-        None => tarball_url.to_string(),
-    }
+/// npm serves a scoped packument at a single path segment, so the scoped name
+/// (`@scope/name`) must be percent-encoded with `/` as `%2F` (for example
+/// `@babel/core` → `/@babel%2Fcore`). Unscoped names carry no `/` and remain
+/// byte-identical. The version segment stays a separate path component. The
+/// package identity stays verbatim everywhere else; only this registry lookup
+/// path encodes the name (see `docs/specs/core/registry/SPEC.md`).
+///
+/// The base URL comes from `.npmrc`: a matching `@scope:registry=` for scoped
+/// names, then `registry=`, then the public npm registry.
+fn registry_lookup_url(config: &NpmConfig, lib_name: &str, version: &str) -> String {
+    let registry = config
+        .registry_for_package(lib_name)
+        .unwrap_or(REGISTRY_PATH);
+    let encoded_name = lib_name.replace('/', "%2F");
+    format!("{registry}/{encoded_name}/{version}")
 }
 
-/// Tarball download counters for the fixture registry API.
-///
-/// ADR 0005 places installer measurement on the fake registry API that serves
-/// deterministic fixture responses, recording calls by package name and
-/// selected version. These counters are test-only; production downloads never
-/// reach them.
 #[cfg(test)]
-pub(crate) mod test_support {
-    // A documented `std::sync::Mutex` guards the counter map because libtest
-    // runs test functions on parallel OS threads, so a `thread_local` or
-    // `RefCell` counter would be per-thread state that silently miscounts
-    // downloads across concurrently running tests.
-    #![allow(clippy::disallowed_types)]
-
-    use std::{
-        collections::HashMap,
-        sync::{Mutex, OnceLock},
-    };
-
-    fn counts() -> &'static Mutex<HashMap<String, u32>> {
-        static COUNTS: OnceLock<Mutex<HashMap<String, u32>>> = OnceLock::new();
-        COUNTS.get_or_init(|| Mutex::new(HashMap::new()))
-    }
-
-    fn locked_counts() -> std::sync::MutexGuard<'static, HashMap<String, u32>> {
-        counts().lock().unwrap_or_else(|error| error.into_inner())
-    }
+mod tests {
+    use super::*;
+    use std::fs;
 
-    pub(crate) fn record_tarball_download(package_key: &str) {
-        *locked_counts().entry(package_key.to_string()).or_insert(0) += 1;
+    #[test]
+    fn registry_lookup_url_percent_encodes_scoped_name_segment() {
+        let url = registry_lookup_url(&NpmConfig::default(), "@babel/core", "2.3.1");
+        assert_eq!(url, "https://registry.npmjs.org/@babel%2Fcore/2.3.1");
+        assert!(url.contains("%2F"));
     }
 
-    /// Clear recorded downloads. Tests must call this while holding the shared
-    /// install test env lock so counts never leak between tests.
-    pub(crate) fn reset_tarball_download_counts() {
-        locked_counts().clear();
+    #[test]
+    fn registry_lookup_url_leaves_unscoped_name_unchanged() {
+        let url = registry_lookup_url(&NpmConfig::default(), "express", "4.18.2");
+        assert_eq!(url, "https://registry.npmjs.org/express/4.18.2");
+        assert!(!url.contains("%2F"));
     }
 
-    pub(crate) fn tarball_download_count(package_key: &str) -> u32 {
-        locked_counts().get(package_key).copied().unwrap_or(0)
-    }
+    #[test]
+    fn registry_lookup_url_routes_scopes_to_configured_registries() {
+        let config = NpmConfig::parse(
+            "registry=https://mirror.example/npm/\n@corp:registry=https://npm.corp.example/\n",
+        );
 
-    /// Recorded downloads as a stable, sorted `(package@version, count)` list
-    /// so expected download counts stay reviewable in test output.
-    pub(crate) fn recorded_tarball_downloads() -> Vec<(String, u32)> {
-        let mut recorded = locked_counts()
-            .iter()
-            .map(|(key, count)| (key.clone(), *count))
-            .collect::<Vec<_>>();
-        recorded.sort();
-        recorded
+        assert_eq!(
+            registry_lookup_url(&config, "@corp/tool", ""),
+            "https://npm.corp.example/@corp%2Ftool/"
+        );
+        assert_eq!(
+            registry_lookup_url(&config, "@babel/core", "7.0.0"),
+            "https://mirror.example/npm/@babel%2Fcore/7.0.0"
+        );
+        assert_eq!(
+            registry_lookup_url(&config, "express", ""),
+            "https://mirror.example/npm/express/"
+        );
     }
 
-    // Metadata read counters. A registry metadata document covers every version
-    // of a package, so version selection happens after the fetch. Metadata reads
-    // are therefore counted by package name, while tarball downloads are counted
-    // by the selected `package@version`.
-    fn metadata_counts() -> &'static Mutex<HashMap<String, u32>> {
-        static METADATA_COUNTS: OnceLock<Mutex<HashMap<String, u32>>> = OnceLock::new();
-        METADATA_COUNTS.get_or_init(|| Mutex::new(HashMap::new()))
-    }
+    #[test]
+    fn registry_request_attaches_credentials_only_for_matching_host() {
+        let client = HttpRegistryClient::new(NpmConfig::parse(
+            "//npm.corp.example/:_authToken=secret-token\n\
+             //other.example/:_auth=dXNlcjpwYXNz\n",
+        ));
+        let authorization = |url: &str| {
+            registry_request(&client, url)
+                .build()
+                .unwrap()
+                .headers()
+                .get(reqwest::header::AUTHORIZATION)
+                .map(|value| value.to_str().unwrap().to_string())
+        };
 
-    fn locked_metadata_counts() -> std::sync::MutexGuard<'static, HashMap<String, u32>> {
-        metadata_counts()
-            .lock()
-            .unwrap_or_else(|error| error.into_inner())
+        assert_eq!(
+            authorization("https://npm.corp.example/@corp%2Ftool/").as_deref(),
+            Some("Bearer secret-token")
+        );
+        assert_eq!(
+            authorization("https://other.example/tool/-/tool-1.0.0.tgz").as_deref(),
+            Some("Basic dXNlcjpwYXNz")
+        );
+        assert_eq!(authorization("https://registry.npmjs.org/express/"), None);
     }
 
-    pub(crate) fn record_metadata_read(package_name: &str) {
-        *locked_metadata_counts()
-            .entry(package_name.to_string())
-            .or_insert(0) += 1;
+    #[test]
+    fn abbreviated_packuments_are_detected_from_content_type() {
+        let headers = |content_type: &str| {
+            let mut headers = reqwest::header::HeaderMap::new();
+            headers.insert(reqwest::header::CONTENT_TYPE, content_type.parse().unwrap());
+            headers
+        };
+
+        assert!(is_abbreviated_response(&headers(
+            "application/vnd.npm.install-v1+json; charset=utf-8"
+        )));
+        assert!(!is_abbreviated_response(&headers("application/json")));
+        assert!(!is_abbreviated_response(&reqwest::header::HeaderMap::new()));
+        assert!(PackumentFormat::Abbreviated
+            .accept_header()
+            .starts_with(ABBREVIATED_PACKUMENT));
+        assert_eq!(PackumentFormat::Full.accept_header(), "application/json");
     }
 
-    /// Clear recorded metadata reads. Tests must call this while holding the
-    /// shared install test env lock so counts never leak between tests.
-    pub(crate) fn reset_metadata_read_counts() {
-        locked_metadata_counts().clear();
+    #[tokio::test]
+    async fn metadata_cache_revalidates_with_etag_and_skips_fresh_entries() {
+        use crate::util::test_support::{spawn_http_server, TempProject, TestResponse};
+
+        let project = TempProject::new("api-metadata-cache").unwrap();
+        let body = r#"{"name":"express","dist-tags":{"latest":"1.0.0"},"versions":{"1.0.0":{"dist":{"tarball":"https://registry.example/express/-/express-1.0.0.tgz"}}}}"#;
+        let (base_url, server) = spawn_http_server(vec![
+            TestResponse::new(200, body)
+                .header("content-type", "application/vnd.npm.install-v1+json")
+                .header("etag", "\"v1\""),
+            TestResponse::new(304, ""),
+        ])
+        .await;
+        let config = NpmConfig::parse(&format!("registry={base_url}\n"));
+        let cache_dir = project.path().join(".rpm").join(".metadata");
+        let revalidating = HttpRegistryClient::new(config.clone())
+            .with_metadata_cache(MetadataCache::new(&cache_dir, std::time::Duration::ZERO));
+
+        let first = get_registry(&revalidating, "express", "").await.unwrap();
+        let second = get_registry(&revalidating, "express", "").await.unwrap();
+        let fresh = HttpRegistryClient::new(config)
+            .with_metadata_cache(MetadataCache::new(&cache_dir, DEFAULT_METADATA_MAX_AGE));
+        let third = get_registry(&fresh, "express", "").await.unwrap();
+
+        let requests = server.await.unwrap();
+        assert_eq!(requests.len(), 2);
+        assert!(!requests[0].contains("if-none-match"));
+        assert!(requests[1].contains("if-none-match: \"v1\""));
+        for registry in [first, second, third] {
+            assert!(registry.is_abbreviated());
+            assert_eq!(registry.select_version("^1.0.0").unwrap(), "1.0.0");
+        }
+        assert!(cache_dir.join("express.json").is_file());
     }
 
-    pub(crate) fn metadata_read_count(package_name: &str) -> u32 {
-        locked_metadata_counts()
-            .get(package_name)
-            .copied()
-            .unwrap_or(0)
-    }
+    #[tokio::test]
+    async fn get_tarball_streams_body_in_chunks_and_rejects_error_status() {
+        use crate::util::test_support::{spawn_http_server, TestResponse};
 
-    /// Recorded metadata reads as a stable, sorted `(package-name, count)` list
-    /// so expected read counts stay reviewable in test output.
-    pub(crate) fn recorded_metadata_reads() -> Vec<(String, u32)> {
-        let mut recorded = locked_metadata_counts()
-            .iter()
-            .map(|(name, count)| (name.clone(), *count))
+        let body = (0..1_000_000_u32)
+            .map(|index| (index % 251) as u8)
             .collect::<Vec<_>>();
-        recorded.sort();
-        recorded
-    }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::util::test_support::fixture_path;
-    use std::{
-        ffi::OsString,
-        io,
-        path::PathBuf,
-        thread,
-        time::{SystemTime, UNIX_EPOCH},
-    };
-
-    struct FixtureRoot {
-        previous: Option<OsString>,
-        lock_path: PathBuf,
-    }
-
-    impl FixtureRoot {
-        fn set(path: impl AsRef<std::path::Path>) -> Self {
-            let lock_path = acquire_env_lock().expect("fixture env lock should be available");
-            let previous = std::env::var_os("RPM_REGISTRY_FIXTURE_ROOT");
-            std::env::set_var("RPM_REGISTRY_FIXTURE_ROOT", path.as_ref());
-            Self {
-                previous,
-                lock_path,
-            }
+        let (base_url, server) = spawn_http_server(vec![
+            TestResponse::new(200, body.clone()),
+            TestResponse::new(404, "not found"),
+        ])
+        .await;
+        let client = HttpRegistryClient::default();
+
+        let url = format!("{base_url}/big/-/big-1.0.0.tgz");
+        let mut tarball = get_tarball(&client, &url).await.unwrap();
+        let mut chunks = 0;
+        let mut received = Vec::new();
+        while let Some(chunk) = tarball.read_chunk().await.unwrap() {
+            chunks += 1;
+            received.extend_from_slice(chunk);
         }
+        let missing_url = format!("{base_url}/missing/-/missing-1.0.0.tgz");
+        let error = get_tarball(&client, &missing_url).await.unwrap_err();
 
-        fn unset() -> Self {
-            let lock_path = acquire_env_lock().expect("fixture env lock should be available");
-            let previous = std::env::var_os("RPM_REGISTRY_FIXTURE_ROOT");
-            std::env::remove_var("RPM_REGISTRY_FIXTURE_ROOT");
-            Self {
-                previous,
-                lock_path,
-            }
-        }
+        server.await.unwrap();
+        assert_eq!(received, body);
+        assert!(chunks > 1, "expected a streamed body, got {chunks} chunk");
+        assert_eq!(
+            error.to_string(),
+            format!("failed to download {missing_url}: HTTP 404 Not Found")
+        );
     }
 
-    impl Drop for FixtureRoot {
-        fn drop(&mut self) {
-            match &self.previous {
-                Some(value) => std::env::set_var("RPM_REGISTRY_FIXTURE_ROOT", value),
-                None => std::env::remove_var("RPM_REGISTRY_FIXTURE_ROOT"),
-            }
-            let _ = fs::remove_dir(&self.lock_path);
+    #[tokio::test]
+    async fn offline_reads_use_stale_cache_and_report_misses_without_requests() {
+        use crate::util::test_support::TempProject;
+
+        let project = TempProject::new("api-offline").unwrap();
+        let config = NpmConfig::parse("registry=http://127.0.0.1:9\n");
+        let url = registry_lookup_url(&config, "express", "");
+        let cache = MetadataCache::new(project.path().join(".metadata"), std::time::Duration::ZERO);
+        cache
+            .write(
+                &metadata_cache_key("express", "", false),
+                &CachedPackument {
+                    url,
+                    etag: None,
+                    last_modified: None,
+                    fetched_at: 0,
+                    abbreviated: true,
+                    body: serde_json::json!({
+                        "name": "express",
+                        "dist-tags": { "latest": "1.0.0" },
+                        "versions": { "1.0.0": { "dist": { "tarball": "http://127.0.0.1:9/express.tgz" } } }
+                    }),
+                },
+            )
+            .unwrap();
+
+        for mode in [NetworkMode::Offline, NetworkMode::PreferOffline] {
+            let client = HttpRegistryClient::new(config.clone())
+                .with_metadata_cache(cache.clone())
+                .with_network_mode(mode);
+            let registry = get_registry(&client, "express", "").await.unwrap();
+            assert_eq!(registry.select_version("latest").unwrap(), "1.0.0");
         }
-    }
 
-    fn acquire_env_lock() -> io::Result<PathBuf> {
-        let path = std::env::temp_dir().join("rpm-install-test-env-lock");
-        loop {
-            match fs::create_dir(&path) {
-                Ok(()) => return Ok(path),
-                Err(error) if error.kind() == io::ErrorKind::AlreadyExists => {
-                    thread::sleep(std::time::Duration::from_millis(10));
-                }
-                Err(error) => return Err(error),
-            }
-        }
+        let offline = HttpRegistryClient::new(config)
+            .with_metadata_cache(cache)
+            .with_network_mode(NetworkMode::Offline);
+        let error = get_registry(&offline, "left-pad", "").await.unwrap_err();
+        assert!(is_offline_cache_miss(&error));
+        assert_eq!(error.to_string(), "offline cache miss: left-pad");
+        // Only the typed payload counts, not a message that reads like one.
+        assert!(!is_offline_cache_miss(&Error::new(
+            ErrorKind::NotFound,
+            "offline cache miss: left-pad"
+        )));
     }
 
-    fn temp_dir(prefix: &str) -> PathBuf {
-        let nanos = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .map(|duration| duration.as_nanos())
-            .unwrap_or(0);
-        let path = std::env::temp_dir().join(format!("rpm-api-{prefix}-{nanos}"));
-        fs::create_dir_all(&path).unwrap();
-        path
+    #[tokio::test]
+    async fn requests_go_through_the_configured_proxy_unless_no_proxy_matches() {
+        use crate::util::test_support::{spawn_http_server, TestResponse};
+
+        let body = r#"{"name":"left-pad","dist-tags":{"latest":"1.0.0"},"versions":{"1.0.0":{"dist":{"tarball":"http://registry.example.invalid/left-pad/-/left-pad-1.0.0.tgz"}}}}"#;
+        let (proxy_url, proxy) = spawn_http_server(vec![
+            TestResponse::new(200, body).header("content-type", "application/json"),
+            TestResponse::new(200, "tarball bytes"),
+        ])
+        .await;
+        let no_env = |_: &str| None;
+        let proxied = HttpRegistryClient::from_config_with_env(
+            NpmConfig::parse(
+                "registry=http://registry.example.invalid/
+",
+            ),
+            &|name: &str| (name == "HTTP_PROXY").then(|| proxy_url.clone()),
+        )
+        .unwrap();
+
+        let registry = get_registry(&proxied, "left-pad", "").await.unwrap();
+        let mut tarball = get_tarball(
+            &proxied,
+            "http://registry.example.invalid/left-pad/-/left-pad-1.0.0.tgz",
+        )
+        .await
+        .unwrap();
+        while tarball.read_chunk().await.unwrap().is_some() {}
+        let requests = proxy.await.unwrap();
+        assert_eq!(registry.select_version("latest").unwrap(), "1.0.0");
+        assert!(requests[0].starts_with("GET http://registry.example.invalid/left-pad/ "));
+        assert!(requests[1]
+            .starts_with("GET http://registry.example.invalid/left-pad/-/left-pad-1.0.0.tgz "));
+
+        let (registry_url, direct) = spawn_http_server(vec![
+            TestResponse::new(200, body).header("content-type", "application/json")
+        ])
+        .await;
+        let bypassing = HttpRegistryClient::from_config_with_env(
+            NpmConfig::parse(&format!(
+                "registry={registry_url}
+proxy=http://127.0.0.1:9
+noproxy=127.0.0.1
+"
+            )),
+            &no_env,
+        )
+        .unwrap();
+        get_registry(&bypassing, "left-pad", "").await.unwrap();
+        assert!(direct.await.unwrap()[0].starts_with("GET /left-pad/ "));
     }
 
     #[tokio::test]
-    async fn get_registry_reads_scoped_fixture_from_env_root() {
-        let _fixture_root =
-            FixtureRoot::set(fixture_path(&["registry", "shared-transitive", "metadata"]));
-
-        let registry = get_registry("@rpm-fixture/alpha", "^1.0.0")
+    async fn https_registries_use_cafile_and_strict_ssl() {
+        use crate::util::test_support::{fixture_path, spawn_https_server, TestResponse};
+
+        let body = r#"{"name":"left-pad","dist-tags":{"latest":"1.0.0"},"versions":{"1.0.0":{"dist":{"tarball":"https://localhost/left-pad/-/left-pad-1.0.0.tgz"}}}}"#;
+        let response = || TestResponse::new(200, body).header("content-type", "application/json");
+        let (base_url, server) = spawn_https_server(vec![response(), response(), response()]).await;
+        let client = |extra: &str| {
+            HttpRegistryClient::from_config_with_env(
+                NpmConfig::parse(&format!("registry={base_url}/\n{extra}")),
+                &|_| None,
+            )
+            .unwrap()
+        };
+        let cafile = fixture_path(&["tls", "localhost.crt"]);
+
+        let untrusted = get_registry(&client(""), "left-pad", "").await.unwrap_err();
+        let trusted = get_registry(
+            &client(&format!("cafile={}\n", cafile.display())),
+            "left-pad",
+            "",
+        )
+        .await
+        .unwrap();
+        let unchecked = get_registry(&client("strict-ssl=false\n"), "left-pad", "")
             .await
-            .expect("fixture registry should load");
-
-        assert_eq!(registry.name, "@rpm-fixture/alpha");
-        assert_eq!(registry.select_version("^1.0.0").unwrap(), "1.0.0");
+            .unwrap();
+
+        let requests = server.await.unwrap();
+        assert!(untrusted.to_string().contains(&base_url), "{untrusted}");
+        assert_eq!(requests[0], "tls handshake failed");
+        assert!(requests[1].starts_with("GET /left-pad/ "));
+        assert!(requests[2].starts_with("GET /left-pad/ "));
+        for registry in [trusted, unchecked] {
+            assert_eq!(registry.select_version("latest").unwrap(), "1.0.0");
+        }
     }
 
     #[tokio::test]
-    async fn get_registry_counts_metadata_reads_by_package_name() {
-        let _fixture_root =
-            FixtureRoot::set(fixture_path(&["registry", "shared-transitive", "metadata"]));
-        test_support::reset_metadata_read_counts();
-
-        get_registry("@rpm-fixture/alpha", "").await.unwrap();
-        get_registry("@rpm-fixture/beta", "").await.unwrap();
-        get_registry("@rpm-fixture/shared", "").await.unwrap();
-        get_registry("@rpm-fixture/alpha", "").await.unwrap();
-
-        assert_eq!(test_support::metadata_read_count("@rpm-fixture/alpha"), 2);
-        assert_eq!(test_support::metadata_read_count("@rpm-fixture/beta"), 1);
-        assert_eq!(test_support::metadata_read_count("@rpm-fixture/shared"), 1);
-        assert_eq!(
-            test_support::recorded_metadata_reads(),
-            vec![
-                ("@rpm-fixture/alpha".to_string(), 2),
-                ("@rpm-fixture/beta".to_string(), 1),
-                ("@rpm-fixture/shared".to_string(), 1),
-            ],
-        );
-    }
+    async fn signature_keys_come_from_the_registry_keys_endpoint() {
+        use crate::util::test_support::{fixture_path, spawn_http_server, TestResponse};
 
-    #[test]
-    fn read_registry_fixture_returns_none_without_env_root() {
-        let _fixture_root = FixtureRoot::unset();
+        let keys = fs::read(fixture_path(&["signatures", "keys.json"])).unwrap();
+        let (base_url, server) = spawn_http_server(vec![TestResponse::new(200, keys)]).await;
+        let config = NpmConfig::parse(&format!(
+            "registry={base_url}/\n//{}/:_authToken=keys-token\n",
+            base_url.trim_start_matches("http://")
+        ));
 
-        assert!(read_registry_fixture("@rpm-fixture/alpha")
-            .expect("missing env should not fail")
-            .is_none());
-    }
+        let client = HttpRegistryClient::new(config.clone());
+        get_registry_keys(&client).await.unwrap();
+        let requests = server.await.unwrap();
+        assert!(requests[0].starts_with("GET /-/npm/v1/keys "));
+        assert!(requests[0].contains("authorization: Bearer keys-token"));
 
-    #[test]
-    fn read_registry_fixture_reports_missing_and_invalid_fixtures() {
-        let temp = temp_dir("invalid-fixture");
-        let _fixture_root = FixtureRoot::set(&temp);
-
-        let missing = read_registry_fixture("@scope/missing")
-            .expect_err("missing fixture should include path context");
-        assert!(missing.to_string().contains("@scope__missing.json"));
-
-        fs::write(temp.join("@scope__broken.json"), "{").unwrap();
-        let invalid =
-            read_registry_fixture("@scope/broken").expect_err("invalid fixture JSON should fail");
-        assert_eq!(invalid.kind(), ErrorKind::InvalidData);
-        assert!(invalid.to_string().contains("@scope__broken.json"));
-        let _ = fs::remove_dir_all(temp);
+        let offline = client.with_network_mode(NetworkMode::Offline);
+        let error = get_registry_keys(&offline).await.unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::NotFound);
+        assert!(error.to_string().ends_with("pass --registry-keys"));
     }
 
     #[test]
-    fn fixture_tarball_builds_minimal_package_archive() {
-        let bytes =
-            fixture_tarball("https://registry.example.invalid/@rpm-fixture/alpha/-/alpha.tgz")
-                .expect("fixture tarball should be generated");
-
-        let decoder = flate2::read::GzDecoder::new(bytes.as_slice());
-        let mut archive = tar::Archive::new(decoder);
-        let package_json = archive
-            .entries()
-            .unwrap()
-            .find_map(|entry| {
-                let mut entry = entry.unwrap();
-                if entry.path().unwrap() == std::path::Path::new("package/package.json") {
-                    let mut text = String::new();
-                    use std::io::Read;
-                    entry.read_to_string(&mut text).unwrap();
-                    Some(text)
-                } else {
-                    None
-                }
-            })
-            .expect("package.json should exist in generated archive");
-
-        assert_eq!(package_json, r#"{"name":"@rpm-fixture/alpha"}"#);
-    }
+    fn publish_registry_prefers_publish_config_then_npmrc() {
+        let config = NpmConfig::parse(
+            "registry=https://main.example/\n@corp:registry=https://corp.example/npm/\n",
+        );
 
-    #[test]
-    fn package_key_from_tarball_url_records_selected_package_and_version() {
         assert_eq!(
-            package_key_from_tarball_url(
-                "https://registry.example.invalid/@rpm-fixture/shared/-/shared-1.0.0.tgz"
-            ),
-            "@rpm-fixture/shared@1.0.0"
+            publish_registry(&config, "@corp/tool", Some("https://override.example/")),
+            "https://override.example"
         );
         assert_eq!(
-            package_key_from_tarball_url(
-                "https://registry.example.invalid/alpha/-/alpha-2.1.0.tgz"
-            ),
-            "alpha@2.1.0"
+            publish_registry(&config, "@corp/tool", Some(" ")),
+            "https://corp.example/npm"
         );
-        assert_eq!(package_key_from_tarball_url("not-a-url"), "not-a-url");
         assert_eq!(
-            package_key_from_tarball_url("https://registry.example.invalid/alpha/-/alpha.tgz"),
-            "https://registry.example.invalid/alpha/-/alpha.tgz"
+            publish_registry(&config, "tool", None),
+            "https://main.example"
+        );
+        assert_eq!(
+            publish_registry(&NpmConfig::default(), "tool", None),
+            REGISTRY_PATH
         );
     }
 
-    #[test]
-    fn package_name_from_tarball_url_rejects_invalid_fixture_urls() {
-        let error = package_name_from_tarball_url("not-a-url").unwrap_err();
-        assert_eq!(error.kind(), ErrorKind::InvalidInput);
-        assert!(error.to_string().contains("invalid fixture tarball URL"));
-
-        let error = package_name_from_tarball_url("https://registry.example.invalid/").unwrap_err();
-        assert_eq!(error.kind(), ErrorKind::InvalidInput);
-        assert!(error
-            .to_string()
-            .contains("invalid fixture tarball URL path"));
-    }
-
-    #[test]
-    fn registry_lookup_url_percent_encodes_scoped_name_segment() {
-        let url = registry_lookup_url("@babel/core", "2.3.1");
-        assert_eq!(url, "https://registry.npmjs.org/@babel%2Fcore/2.3.1");
-        assert!(url.contains("%2F"));
-    }
+    #[tokio::test]
+    async fn publish_package_puts_the_document_with_credentials() {
+        use crate::util::test_support::{spawn_http_server, TestResponse};
+
+        let (base_url, server) = spawn_http_server(vec![
+            TestResponse::new(201, r#"{"ok":true}"#),
+            TestResponse::new(
+                409,
+                r#"{"error":"cannot publish over the previously published versions: 1.0.0."}"#,
+            ),
+            TestResponse::new(403, "forbidden"),
+        ])
+        .await;
+        let client = HttpRegistryClient::new(NpmConfig::parse(&format!(
+            "//{}/:_authToken=publish-token\n",
+            base_url.trim_start_matches("http://")
+        )));
+        let document = serde_json::json!({"name": "@corp/tool"});
+
+        publish_package(&client, &base_url, "@corp/tool", &document)
+            .await
+            .unwrap();
+        let conflict = publish_package(&client, &base_url, "@corp/tool", &document)
+            .await
+            .unwrap_err();
+        let forbidden = publish_package(&client, &base_url, "@corp/tool", &document)
+            .await
+            .unwrap_err();
 
-    #[test]
-    fn registry_lookup_url_leaves_unscoped_name_unchanged() {
-        let url = registry_lookup_url("express", "4.18.2");
-        assert_eq!(url, "https://registry.npmjs.org/express/4.18.2");
-        assert!(!url.contains("%2F"));
+        assert_eq!(conflict.kind(), ErrorKind::AlreadyExists);
+        assert_eq!(
+            conflict.to_string(),
+            format!(
+                "failed to publish @corp/tool to {base_url}/@corp%2Ftool: HTTP 409 Conflict: \
+                 cannot publish over the previously published versions: 1.0.0."
+            )
+        );
+        assert_eq!(forbidden.kind(), ErrorKind::PermissionDenied);
+        assert!(forbidden.to_string().ends_with("HTTP 403 Forbidden"));
+
+        let requests = server.await.unwrap();
+        assert!(requests[0].starts_with("PUT /@corp%2Ftool "));
+        assert!(requests[0].contains("authorization: Bearer publish-token"));
+        assert!(requests[0].contains("content-type: application/json"));
+        assert!(requests[0].ends_with(r#"{"name":"@corp/tool"}"#));
+        assert!(!conflict.to_string().contains("publish-token"));
     }
 }
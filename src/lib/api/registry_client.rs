@@ -0,0 +1,81 @@
+use std::future::Future;
+
+use super::{client::NetworkMode, HttpRegistryClient, TarballBody};
+use crate::registry::{Registry, RegistryKeys};
+
+/// Source of package metadata and tarballs for an install.
+///
+/// [`HttpRegistryClient`] talks to an npm-compatible registry;
+/// [`DirectoryRegistryClient`](super::DirectoryRegistryClient) reads the same
+/// documents from a local directory. The install pipeline only depends on this
+/// trait, so embedders can run installs against either, or their own source.
+pub trait RegistryClient: Sync {
+    /// Whether reads may reach the network. Sources that never do report
+    /// [`NetworkMode::Online`], so the tarball cache is refreshed from them.
+    fn network_mode(&self) -> NetworkMode;
+
+    /// How many tarball downloads an install keeps in flight.
+    fn download_concurrency(&self) -> usize;
+
+    /// The package document for `lib_name`.
+    fn get_registry(
+        &self,
+        lib_name: &str,
+    ) -> impl Future<Output = std::io::Result<Registry>> + Send;
+
+    /// The full package document for `lib_name`, with the `time` and
+    /// `maintainers` an install does not read. Sources that only hold full
+    /// documents need not override this.
+    fn get_full_registry(
+        &self,
+        lib_name: &str,
+    ) -> impl Future<Output = std::io::Result<Registry>> + Send {
+        self.get_registry(lib_name)
+    }
+
+    /// The tarball a package document's `dist.tarball` points at.
+    fn get_tarball(
+        &self,
+        tarball_url: &str,
+    ) -> impl Future<Output = std::io::Result<TarballBody>> + Send;
+
+    /// The registry's published signing keys (`/-/npm/v1/keys`), which
+    /// `--verify-signatures` checks `dist.signatures` against unless a keys
+    /// file is given.
+    fn get_registry_keys(&self) -> impl Future<Output = std::io::Result<RegistryKeys>> + Send;
+}
+
+impl RegistryClient for HttpRegistryClient {
+    fn network_mode(&self) -> NetworkMode {
+        HttpRegistryClient::network_mode(self)
+    }
+
+    fn download_concurrency(&self) -> usize {
+        HttpRegistryClient::download_concurrency(self)
+    }
+
+    fn get_registry(
+        &self,
+        lib_name: &str,
+    ) -> impl Future<Output = std::io::Result<Registry>> + Send {
+        super::get_registry(self, lib_name, "")
+    }
+
+    fn get_full_registry(
+        &self,
+        lib_name: &str,
+    ) -> impl Future<Output = std::io::Result<Registry>> + Send {
+        super::get_full_registry(self, lib_name, "")
+    }
+
+    fn get_tarball(
+        &self,
+        tarball_url: &str,
+    ) -> impl Future<Output = std::io::Result<TarballBody>> + Send {
+        super::get_tarball(self, tarball_url)
+    }
+
+    fn get_registry_keys(&self) -> impl Future<Output = std::io::Result<RegistryKeys>> + Send {
+        super::get_registry_keys(self)
+    }
+}
@@ -0,0 +1,461 @@
+use std::{
+    fmt,
+    io::Error,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+
+use super::{
+    metadata_cache::{metadata_cache_key, MetadataCache, DEFAULT_METADATA_MAX_AGE},
+    ABBREVIATED_PACKUMENT,
+};
+use crate::registry::tarball_cache_file_name;
+
+/// Largest request head the server reads before answering `400`.
+const MAX_REQUEST_HEAD: usize = 16 * 1024;
+
+/// Serves a project's `.rpm` caches as a read-only npm-compatible registry.
+///
+/// Packuments come from the metadata cache and tarballs from the tarball
+/// cache, at the URLs `registry_lookup_url` and npm tarball URLs use:
+///
+/// ```text
+/// GET /left-pad/                          packument of `left-pad`
+/// GET /@scope%2Ftool/                     packument of `@scope/tool`
+/// GET /@scope/tool/-/tool-1.0.0.tgz       a cached tarball
+/// ```
+///
+/// The cached full document is preferred over the abbreviated one. Every
+/// `dist.tarball` in a served packument is rewritten to this server, using the
+/// request's `Host`, so clients never reach the original registry. Versions
+/// whose tarball is not cached are still listed; downloading them is a `404`.
+#[derive(Debug, Clone)]
+pub struct CacheRegistryServer {
+    metadata: MetadataCache,
+    tarball_dir: PathBuf,
+}
+
+/// A request the server answered, handed to the caller of
+/// [`CacheRegistryServer::serve`] to log.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServedRequest {
+    pub method: String,
+    pub target: String,
+    pub status: u16,
+}
+
+struct Response {
+    status: u16,
+    content_type: &'static str,
+    body: ResponseBody,
+}
+
+enum ResponseBody {
+    Bytes(Vec<u8>),
+    File(tokio::fs::File, u64),
+}
+
+impl CacheRegistryServer {
+    pub fn new(metadata_dir: impl Into<PathBuf>, tarball_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            metadata: MetadataCache::new(metadata_dir, DEFAULT_METADATA_MAX_AGE),
+            tarball_dir: tarball_dir.into(),
+        }
+    }
+
+    /// A server for the caches of the project at `project_root`.
+    pub fn for_project(project_root: &Path) -> Self {
+        let rpm_dir = project_root.join(".rpm");
+        Self::new(rpm_dir.join(".metadata"), rpm_dir.join(".cache"))
+    }
+
+    /// Accept connections on `listener` until accepting fails. Each connection
+    /// serves one request, which is passed to `on_request` once answered.
+    pub async fn serve(
+        self,
+        listener: TcpListener,
+        on_request: impl Fn(&ServedRequest) + Send + Sync + 'static,
+    ) -> std::io::Result<()> {
+        let local_addr = listener.local_addr()?;
+        let server = Arc::new(self);
+        let on_request = Arc::new(on_request);
+        loop {
+            let (stream, _) = listener.accept().await.map_err(|error| {
+                Error::new(
+                    error.kind(),
+                    format!("failed to accept registry connection: {error}"),
+                )
+            })?;
+            let server = Arc::clone(&server);
+            let on_request = Arc::clone(&on_request);
+            tokio::spawn(async move {
+                // A client that hangs up mid-request only loses its own
+                // response.
+                let _ = server
+                    .handle_connection(stream, local_addr, on_request.as_ref())
+                    .await;
+            });
+        }
+    }
+
+    async fn handle_connection(
+        &self,
+        mut stream: TcpStream,
+        local_addr: SocketAddr,
+        on_request: &(impl Fn(&ServedRequest) + Sync),
+    ) -> std::io::Result<()> {
+        let mut head = Vec::new();
+        let mut chunk = [0_u8; 4096];
+        let response = loop {
+            let read = stream.read(&mut chunk).await?;
+            if read == 0 {
+                return Ok(());
+            }
+            head.extend_from_slice(&chunk[..read]);
+            if head.windows(4).any(|window| window == b"\r\n\r\n") {
+                let (response, served) = self
+                    .respond(&String::from_utf8_lossy(&head), local_addr)
+                    .await;
+                if let Some(served) = served {
+                    on_request(&served);
+                }
+                break response;
+            }
+            if head.len() > MAX_REQUEST_HEAD {
+                break error_response(400, "request head too large");
+            }
+        };
+
+        let head_only = head.starts_with(b"HEAD ");
+        let length = match &response.body {
+            ResponseBody::Bytes(bytes) => bytes.len() as u64,
+            ResponseBody::File(_, length) => *length,
+        };
+        let reply = format!(
+            "HTTP/1.1 {} {}\r\ncontent-type: {}\r\ncontent-length: {length}\r\nconnection: close\r\n\r\n",
+            response.status,
+            reason_phrase(response.status),
+            response.content_type,
+        );
+        stream.write_all(reply.as_bytes()).await?;
+        if !head_only {
+            match response.body {
+                ResponseBody::Bytes(bytes) => stream.write_all(&bytes).await?,
+                ResponseBody::File(mut file, _) => {
+                    tokio::io::copy(&mut file, &mut stream).await?;
+                }
+            }
+        }
+        stream.shutdown().await
+    }
+
+    /// The response to a request head, with the request it answered unless
+    /// the request line could not be parsed.
+    async fn respond(
+        &self,
+        head: &str,
+        local_addr: SocketAddr,
+    ) -> (Response, Option<ServedRequest>) {
+        let mut lines = head.lines();
+        let mut request_line = lines.next().unwrap_or_default().split(' ');
+        let (Some(method), Some(target)) = (request_line.next(), request_line.next()) else {
+            return (error_response(400, "malformed request line"), None);
+        };
+        let host = lines
+            .filter_map(|line| line.split_once(':'))
+            .find(|(name, _)| name.trim().eq_ignore_ascii_case("host"))
+            .map(|(_, value)| value.trim().to_string())
+            .unwrap_or_else(|| local_addr.to_string());
+
+        let response = match method {
+            "GET" | "HEAD" => self.route(target, &format!("http://{host}")).await,
+            _ => error_response(405, "method not allowed"),
+        };
+        let served = ServedRequest {
+            method: method.to_string(),
+            target: target.to_string(),
+            status: response.status,
+        };
+        (response, Some(served))
+    }
+
+    async fn route(&self, target: &str, base_url: &str) -> Response {
+        let path = target.split(['?', '#']).next().unwrap_or_default();
+        let Ok(path) = urlencoding::decode(path) else {
+            return error_response(400, "invalid URL encoding");
+        };
+        let path = path.trim_start_matches('/');
+        match path.split_once("/-/") {
+            Some((name, file_name)) if is_package_name(name) => self.tarball(name, file_name).await,
+            Some(_) => error_response(404, "not found"),
+            None => {
+                let name = path.trim_end_matches('/');
+                if is_package_name(name) {
+                    self.packument(name, base_url)
+                } else {
+                    error_response(404, "not found")
+                }
+            }
+        }
+    }
+
+    fn packument(&self, name: &str, base_url: &str) -> Response {
+        let cached = [true, false].into_iter().find_map(|full| {
+            self.metadata
+                .read_entry(&metadata_cache_key(name, "", full))
+                .filter(|entry| {
+                    entry.body.get("name").and_then(|value| value.as_str()) == Some(name)
+                })
+        });
+        let Some(mut entry) = cached else {
+            return error_response(404, "not found");
+        };
+        rewrite_tarball_urls(&mut entry.body, name, base_url);
+        Response {
+            status: 200,
+            content_type: if entry.abbreviated {
+                ABBREVIATED_PACKUMENT
+            } else {
+                "application/json"
+            },
+            body: ResponseBody::Bytes(entry.body.to_string().into_bytes()),
+        }
+    }
+
+    async fn tarball(&self, name: &str, file_name: &str) -> Response {
+        let basename = name.rsplit('/').next().unwrap_or(name);
+        let Some(version) = file_name
+            .strip_prefix(basename)
+            .and_then(|rest| rest.strip_prefix('-'))
+            .and_then(|rest| rest.strip_suffix(".tgz"))
+            .filter(|version| !version.is_empty() && !version.contains('/'))
+        else {
+            return error_response(404, "not found");
+        };
+        let path = self
+            .tarball_dir
+            .join(tarball_cache_file_name(name, version));
+        let Ok(file) = tokio::fs::File::open(&path).await else {
+            return error_response(404, "not found");
+        };
+        match file.metadata().await {
+            Ok(metadata) if metadata.is_file() => Response {
+                status: 200,
+                content_type: "application/octet-stream",
+                body: ResponseBody::File(file, metadata.len()),
+            },
+            _ => error_response(404, "not found"),
+        }
+    }
+}
+
+/// Point every `versions.*.dist.tarball` of `body` at `base_url`.
+fn rewrite_tarball_urls(body: &mut serde_json::Value, name: &str, base_url: &str) {
+    let basename = name.rsplit('/').next().unwrap_or(name);
+    let Some(versions) = body
+        .get_mut("versions")
+        .and_then(|versions| versions.as_object_mut())
+    else {
+        return;
+    };
+    for (version, metadata) in versions {
+        if let Some(dist) = metadata
+            .get_mut("dist")
+            .and_then(|dist| dist.as_object_mut())
+        {
+            dist.insert(
+                "tarball".to_string(),
+                format!("{base_url}/{name}/-/{basename}-{version}.tgz").into(),
+            );
+        }
+    }
+}
+
+/// `name` or `@scope/name`, without empty, `.` or `..` segments.
+fn is_package_name(name: &str) -> bool {
+    let segments = name.split('/').collect::<Vec<_>>();
+    let shape_ok = match segments.as_slice() {
+        [name] => !name.starts_with('@'),
+        [scope, _] => scope.starts_with('@') && scope.len() > 1,
+        _ => false,
+    };
+    shape_ok
+        && segments
+            .iter()
+            .all(|segment| !segment.is_empty() && *segment != "." && *segment != "..")
+}
+
+impl fmt::Display for ServedRequest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} {}", self.method, self.target, self.status)
+    }
+}
+
+fn error_response(status: u16, message: &str) -> Response {
+    Response {
+        status,
+        content_type: "application/json",
+        body: ResponseBody::Bytes(
+            serde_json::json!({ "error": message })
+                .to_string()
+                .into_bytes(),
+        ),
+    }
+}
+
+fn reason_phrase(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        _ => "Unknown",
+    }
+}
+
+/// Bind `host:port` for [`CacheRegistryServer::serve`], naming the address in
+/// errors.
+pub async fn bind_registry_listener(host: &str, port: u16) -> std::io::Result<TcpListener> {
+    TcpListener::bind((host, port)).await.map_err(|error| {
+        Error::new(
+            error.kind(),
+            format!("failed to listen on {host}:{port}: {error}"),
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{api::metadata_cache::CachedPackument, util::test_support::TempProject};
+
+    fn server_with_packument(project: &TempProject, full: bool) -> CacheRegistryServer {
+        let server = CacheRegistryServer::for_project(project.path());
+        server
+            .metadata
+            .write(
+                &metadata_cache_key("@scope/tool", "", full),
+                &CachedPackument {
+                    url: "https://registry.npmjs.org/@scope%2Ftool/".to_string(),
+                    etag: None,
+                    last_modified: None,
+                    fetched_at: 0,
+                    abbreviated: !full,
+                    body: serde_json::json!({
+                        "name": "@scope/tool",
+                        "dist-tags": { "latest": "1.0.0" },
+                        "versions": { "1.0.0": { "dist": {
+                            "tarball": "https://registry.npmjs.org/@scope/tool/-/tool-1.0.0.tgz",
+                            "integrity": "sha512-abc"
+                        } } }
+                    }),
+                },
+            )
+            .unwrap();
+        server
+    }
+
+    fn body(response: Response) -> String {
+        match response.body {
+            ResponseBody::Bytes(bytes) => String::from_utf8(bytes).unwrap(),
+            ResponseBody::File(..) => panic!("expected an in-memory body"),
+        }
+    }
+
+    #[tokio::test]
+    async fn serves_cached_packuments_with_rewritten_tarball_urls() {
+        let project = TempProject::new("registry-server-packument").unwrap();
+        let server = server_with_packument(&project, false);
+
+        for target in ["/@scope%2Ftool/", "/@scope%2ftool", "/@scope/tool"] {
+            let response = server.route(target, "http://10.0.0.5:4873").await;
+            assert_eq!(response.status, 200, "{target}");
+            assert_eq!(response.content_type, ABBREVIATED_PACKUMENT);
+            let body: serde_json::Value = serde_json::from_str(&body(response)).unwrap();
+            assert_eq!(
+                body["versions"]["1.0.0"]["dist"]["tarball"],
+                "http://10.0.0.5:4873/@scope/tool/-/tool-1.0.0.tgz"
+            );
+            assert_eq!(body["versions"]["1.0.0"]["dist"]["integrity"], "sha512-abc");
+        }
+
+        server_with_packument(&project, true);
+        let response = server.route("/@scope%2Ftool/", "http://host").await;
+        assert_eq!(response.content_type, "application/json");
+    }
+
+    #[tokio::test]
+    async fn serves_cached_tarballs_and_rejects_everything_else() {
+        let project = TempProject::new("registry-server-tarball").unwrap();
+        let server = server_with_packument(&project, false);
+        project
+            .write(".rpm/.cache/@scope-tool@1.0.0.tgz", "tarball bytes")
+            .unwrap();
+
+        let response = server
+            .route("/@scope/tool/-/tool-1.0.0.tgz", "http://host")
+            .await;
+        assert_eq!(response.status, 200);
+        assert!(matches!(response.body, ResponseBody::File(_, 13)));
+
+        for target in [
+            "/@scope/tool/-/tool-2.0.0.tgz",
+            "/@scope/tool/-/other-1.0.0.tgz",
+            "/../secret/-/secret-1.0.0.tgz",
+            "/left-pad/",
+            "/a/b/c",
+            "/",
+        ] {
+            assert_eq!(
+                server.route(target, "http://host").await.status,
+                404,
+                "{target}"
+            );
+        }
+        assert_eq!(server.route("/%FF/", "http://host").await.status, 400);
+    }
+
+    #[tokio::test]
+    async fn hands_each_answered_request_to_the_caller() {
+        let project = TempProject::new("registry-server-log").unwrap();
+        let server = server_with_packument(&project, false);
+        let listener = bind_registry_listener("127.0.0.1", 0).await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let (sender, mut served) = tokio::sync::mpsc::unbounded_channel();
+        tokio::spawn(server.serve(listener, move |request| {
+            let _ = sender.send(request.clone());
+        }));
+
+        for head in [
+            "GET /@scope%2Ftool/ HTTP/1.1\r\nHost: x\r\n\r\n",
+            "POST /left-pad/ HTTP/1.1\r\n\r\n",
+            "GARBAGE\r\n\r\n",
+        ] {
+            let mut stream = TcpStream::connect(address).await.unwrap();
+            stream.write_all(head.as_bytes()).await.unwrap();
+            let mut reply = Vec::new();
+            stream.read_to_end(&mut reply).await.unwrap();
+        }
+
+        assert_eq!(
+            served.recv().await.unwrap().to_string(),
+            "GET /@scope%2Ftool/ 200"
+        );
+        assert_eq!(
+            served.recv().await.unwrap(),
+            ServedRequest {
+                method: "POST".to_string(),
+                target: "/left-pad/".to_string(),
+                status: 405,
+            }
+        );
+        // A request line that does not parse is answered but not handed on.
+        assert!(served.try_recv().is_err());
+    }
+}
@@ -0,0 +1,86 @@
+use std::io::Error;
+
+use tokio::{fs, io::AsyncReadExt};
+
+/// How much of a file-backed body one `read_chunk` call reads.
+const FILE_CHUNK_SIZE: usize = 64 * 1024;
+
+/// A tarball response body, read chunk by chunk so a download never has to be
+/// held in memory as a whole.
+#[derive(Debug)]
+pub struct TarballBody {
+    url: String,
+    source: TarballSource,
+    /// The chunk `read_chunk` last handed out, reused across reads.
+    buffer: Vec<u8>,
+}
+
+#[derive(Debug)]
+enum TarballSource {
+    Http(Box<reqwest::Response>),
+    File(fs::File),
+    #[cfg(test)]
+    Buffered(Option<Vec<u8>>),
+}
+
+impl TarballBody {
+    pub(crate) fn from_response(url: &str, response: reqwest::Response) -> Self {
+        Self {
+            url: url.to_string(),
+            source: TarballSource::Http(Box::new(response)),
+            buffer: Vec::new(),
+        }
+    }
+
+    pub(crate) fn from_file(url: &str, file: fs::File) -> Self {
+        Self {
+            url: url.to_string(),
+            source: TarballSource::File(file),
+            buffer: vec![0; FILE_CHUNK_SIZE],
+        }
+    }
+
+    /// A body that yields `bytes` as a single chunk.
+    #[cfg(test)]
+    pub(crate) fn from_bytes(url: &str, bytes: Vec<u8>) -> Self {
+        Self {
+            url: url.to_string(),
+            source: TarballSource::Buffered(Some(bytes)),
+            buffer: Vec::new(),
+        }
+    }
+
+    /// The next chunk of the body, or `None` once it is exhausted. The chunk
+    /// borrows the body, so it must be consumed before the next read.
+    pub async fn read_chunk(&mut self) -> std::io::Result<Option<&[u8]>> {
+        let Self {
+            url,
+            source,
+            buffer,
+        } = self;
+        match source {
+            TarballSource::Http(response) => {
+                let chunk = response
+                    .chunk()
+                    .await
+                    .map_err(|error| Error::other(format!("failed to read {url}: {error}")))?;
+                Ok(chunk.map(|chunk| {
+                    buffer.clear();
+                    buffer.extend_from_slice(&chunk);
+                    buffer.as_slice()
+                }))
+            }
+            TarballSource::File(file) => {
+                let read = file.read(buffer).await.map_err(|error| {
+                    Error::new(error.kind(), format!("failed to read {url}: {error}"))
+                })?;
+                Ok((read > 0).then(|| &buffer[..read]))
+            }
+            #[cfg(test)]
+            TarballSource::Buffered(bytes) => Ok(bytes.take().map(|bytes| {
+                *buffer = bytes;
+                buffer.as_slice()
+            })),
+        }
+    }
+}
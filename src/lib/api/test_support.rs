@@ -0,0 +1,471 @@
+//! Fixture registry and call counters for installer tests.
+//!
+//! ADR 0005 places installer measurement on the fake registry API that serves
+//! deterministic fixture responses, recording calls by package name and
+//! selected version. [`FixtureRegistryClient`] is that API: it lays a fixture
+//! registry out as a [`DirectoryRegistryClient`] and counts the reads it
+//! serves. Production clients never reach the counters.
+
+// A documented `std::sync::Mutex` guards the counter maps because libtest
+// runs test functions on parallel OS threads, so a `thread_local` or
+// `RefCell` counter would be per-thread state that silently miscounts
+// downloads across concurrently running tests.
+#![allow(clippy::disallowed_types)]
+
+use std::{
+    collections::HashMap,
+    fs,
+    future::Future,
+    io::{self, Error, ErrorKind},
+    path::Path,
+    sync::{Mutex, OnceLock},
+};
+
+use super::{client::NetworkMode, DirectoryRegistryClient, RegistryClient, TarballBody};
+use crate::{
+    registry::{Registry, RegistryKeys},
+    util::test_support::TempProject,
+};
+
+/// A [`DirectoryRegistryClient`] over a copy of a fixture registry, recording
+/// every metadata read and tarball download.
+///
+/// A fixture registry holds one packument per package, named with `/`
+/// replaced by `__` (`@rpm-fixture__alpha.json`), and optional tarball specs
+/// under `tarballs/`. Each `dist.tarball` is generated from its spec when the
+/// client is created, so the copy is an ordinary directory registry.
+pub(crate) struct FixtureRegistryClient {
+    _layout: TempProject,
+    directory: DirectoryRegistryClient,
+}
+
+impl FixtureRegistryClient {
+    pub(crate) fn new(fixture_root: &Path) -> io::Result<Self> {
+        let layout = TempProject::new("fixture-registry")?;
+        let directory = DirectoryRegistryClient::new(layout.path());
+        for entry in fs::read_dir(fixture_root)? {
+            let path = entry?.path();
+            let Some(file_name) = path.file_name().and_then(|name| name.to_str()) else {
+                continue;
+            };
+            let Some(stem) = file_name.strip_suffix(".json") else {
+                continue;
+            };
+            if !path.is_file() {
+                continue;
+            }
+            let bytes = fs::read(&path)?;
+            layout.write(format!("{}.json", stem.replace("__", "/")), &bytes)?;
+            // A document that does not parse is copied as-is, so reading it
+            // reports the parse error the way a broken registry would.
+            let Ok(document) = serde_json::from_slice::<serde_json::Value>(&bytes) else {
+                continue;
+            };
+            for tarball_url in tarball_urls(&document) {
+                let tarball = fixture_tarball(fixture_root, tarball_url)?;
+                fs::create_dir_all(directory.tarball_path(tarball_url)?.parent().unwrap())?;
+                fs::write(directory.tarball_path(tarball_url)?, tarball)?;
+            }
+        }
+        Ok(Self {
+            _layout: layout,
+            directory,
+        })
+    }
+
+    pub(crate) fn with_download_concurrency(mut self, download_concurrency: usize) -> Self {
+        self.directory = self
+            .directory
+            .with_download_concurrency(download_concurrency);
+        self
+    }
+}
+
+impl RegistryClient for FixtureRegistryClient {
+    fn network_mode(&self) -> NetworkMode {
+        self.directory.network_mode()
+    }
+
+    fn download_concurrency(&self) -> usize {
+        self.directory.download_concurrency()
+    }
+
+    fn get_registry(&self, lib_name: &str) -> impl Future<Output = io::Result<Registry>> + Send {
+        record_metadata_read(lib_name);
+        self.directory.get_registry(lib_name)
+    }
+
+    fn get_tarball(
+        &self,
+        tarball_url: &str,
+    ) -> impl Future<Output = io::Result<TarballBody>> + Send {
+        record_tarball_download(&package_key_from_tarball_url(tarball_url));
+        self.directory.get_tarball(tarball_url)
+    }
+
+    fn get_registry_keys(&self) -> impl Future<Output = io::Result<RegistryKeys>> + Send {
+        self.directory.get_registry_keys()
+    }
+}
+
+/// Every `dist.tarball` a packument names, per version and at the root.
+fn tarball_urls(document: &serde_json::Value) -> Vec<&str> {
+    let versions = document
+        .get("versions")
+        .and_then(|versions| versions.as_object())
+        .into_iter()
+        .flat_map(|versions| versions.values());
+    std::iter::once(document)
+        .chain(versions)
+        .filter_map(|version| version.pointer("/dist/tarball")?.as_str())
+        .collect()
+}
+
+fn fixture_tarball(fixture_root: &Path, tarball_url: &str) -> io::Result<Vec<u8>> {
+    use flate2::{write::GzEncoder, Compression};
+    use std::io::Write;
+    use tar::{Builder, Header};
+
+    let package_name = package_name_from_tarball_url(tarball_url)?;
+    let spec = read_fixture_tarball_spec(fixture_root, &package_name);
+    let package_json = build_fixture_package_json(&package_name, spec.as_ref());
+    let extra_files = spec
+        .as_ref()
+        .map(|spec| spec.files.as_slice())
+        .unwrap_or(&[]);
+
+    let encoder = GzEncoder::new(Vec::new(), Compression::default());
+    let mut builder = Builder::new(encoder);
+
+    let mut header = Header::new_gnu();
+    header.set_size(package_json.len() as u64);
+    header.set_cksum();
+    builder.append_data(&mut header, "package/package.json", package_json.as_bytes())?;
+
+    // When a fixture tarball spec declares extra files (for example a binary
+    // target reachable through a `bin` field), append each one under the
+    // `package/` prefix so the install extraction step strips the prefix and
+    // lands them at `node_modules/<package>/<file>`, where `link_bins` expects
+    // them. A missing spec keeps the legacy minimal archive unchanged.
+    for file in extra_files {
+        let archive_path = format!("package/{}", file.path);
+        let mut header = Header::new_gnu();
+        header.set_size(file.contents.len() as u64);
+        header.set_mode(file.mode);
+        header.set_cksum();
+        builder.append_data(&mut header, &archive_path, file.contents.as_bytes())?;
+    }
+
+    builder.finish()?;
+    let mut encoder = builder.into_inner()?;
+    encoder.flush()?;
+    encoder.finish()
+}
+
+/// Optional fixture-side description of a synthetic tarball's contents.
+///
+/// The legacy path serves a minimal `package/package.json` with only a `name`
+/// field. Specs live at `<fixture-root>/tarballs/<package-name>.json` so a
+/// fixture can declare a `bin` field and the binary target files the install
+/// pipeline must extract alongside the manifest. Only tests that need `.bin`
+/// linking or other non-trivial tarball contents provide a spec; every other
+/// fixture stays on the minimal default.
+#[derive(Debug, serde::Deserialize)]
+struct FixtureTarballSpec {
+    #[serde(default)]
+    bin: Option<serde_json::Value>,
+    #[serde(default)]
+    files: Vec<FixtureTarballFile>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct FixtureTarballFile {
+    path: String,
+    contents: String,
+    #[serde(default = "default_fixture_file_mode")]
+    mode: u32,
+}
+
+fn default_fixture_file_mode() -> u32 {
+    0o644
+}
+
+/// Read the optional tarball spec for `package_name` from the fixture registry
+/// root. Returns `None` when no spec file exists, so the minimal-archive
+/// default path stays intact for every fixture that does not opt in.
+fn read_fixture_tarball_spec(
+    fixture_root: &Path,
+    package_name: &str,
+) -> Option<FixtureTarballSpec> {
+    let file_name = format!("{}.json", package_name.replace('/', "__"));
+    let path = fixture_root.join("tarballs").join(file_name);
+    let contents = match fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(error) if error.kind() == ErrorKind::NotFound => return None,
+        Err(error) => panic!("{} did not deserialize: {error}", path.display()),
+    };
+    serde_json::from_str(&contents)
+        .unwrap_or_else(|error| panic!("{} should be valid JSON: {error}", path.display()))
+}
+
+/// Build the `package/package.json` body for a fixture tarball. Without a spec
+/// it is the legacy minimal `{"name":"..."}` object; with a spec the declared
+/// `bin` fields are merged in so `link_bins` reads them after extraction.
+fn build_fixture_package_json(package_name: &str, spec: Option<&FixtureTarballSpec>) -> String {
+    let Some(spec) = spec else {
+        return format!(r#"{{"name":"{package_name}"}}"#);
+    };
+    let mut root = serde_json::Map::new();
+    root.insert(
+        "name".to_string(),
+        serde_json::Value::String(package_name.to_string()),
+    );
+    if let Some(bin) = &spec.bin {
+        root.insert("bin".to_string(), bin.clone());
+    }
+    serde_json::Value::Object(root).to_string()
+}
+
+fn package_name_from_tarball_url(tarball_url: &str) -> io::Result<String> {
+    let path = tarball_url
+        .split_once("://")
+        .and_then(|(_, rest)| rest.split_once('/').map(|(_, path)| path))
+        .ok_or_else(|| {
+            Error::new(
+                ErrorKind::InvalidInput,
+                format!("invalid fixture tarball URL: {tarball_url}"),
+            )
+        })?;
+    let parts = path
+        .split('/')
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>();
+    match parts.as_slice() {
+        [scope, name, ..] if scope.starts_with('@') => Ok(format!("{scope}/{name}")),
+        [name, ..] => Ok((*name).to_string()),
+        _ => Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!("invalid fixture tarball URL path: {tarball_url}"),
+        )),
+    }
+}
+
+/// Derive the selected `package-name@version` a fixture tarball URL stands for.
+///
+/// Install deduplication is keyed by the selected package and version, not by
+/// the requested range, so download measurement records the same unit. URLs
+/// that do not follow the fixture layout fall back to the raw URL, which keeps
+/// distinct downloads distinct instead of silently merging counters.
+fn package_key_from_tarball_url(tarball_url: &str) -> String {
+    let Ok(package_name) = package_name_from_tarball_url(tarball_url) else {
+        return tarball_url.to_string();
+    };
+    let unscoped = package_name
+        .rsplit('/')
+        .next()
+        .unwrap_or(package_name.as_str());
+    let version = tarball_url
+        .rsplit('/')
+        .next()
+        .and_then(|file_name| file_name.strip_suffix(".tgz"))
+        .and_then(|stem| stem.strip_prefix(&format!("{unscoped}-")))
+        .filter(|version| !version.is_empty());
+    match version {
+        Some(version) => format!("{package_name}@{version}"),
+        None => tarball_url.to_string(),
+    }
+}
+
+fn counts() -> &'static Mutex<HashMap<String, u32>> {
+    static COUNTS: OnceLock<Mutex<HashMap<String, u32>>> = OnceLock::new();
+    COUNTS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+fn locked_counts() -> std::sync::MutexGuard<'static, HashMap<String, u32>> {
+    counts().lock().unwrap_or_else(|error| error.into_inner())
+}
+
+pub(crate) fn record_tarball_download(package_key: &str) {
+    *locked_counts().entry(package_key.to_string()).or_insert(0) += 1;
+}
+
+/// Clear recorded downloads. Tests must call this while holding the shared
+/// install test env lock so counts never leak between tests.
+pub(crate) fn reset_tarball_download_counts() {
+    locked_counts().clear();
+}
+
+pub(crate) fn tarball_download_count(package_key: &str) -> u32 {
+    locked_counts().get(package_key).copied().unwrap_or(0)
+}
+
+/// Recorded downloads as a stable, sorted `(package@version, count)` list
+/// so expected download counts stay reviewable in test output.
+pub(crate) fn recorded_tarball_downloads() -> Vec<(String, u32)> {
+    let mut recorded = locked_counts()
+        .iter()
+        .map(|(key, count)| (key.clone(), *count))
+        .collect::<Vec<_>>();
+    recorded.sort();
+    recorded
+}
+
+// Metadata read counters. A registry metadata document covers every version
+// of a package, so version selection happens after the fetch. Metadata reads
+// are therefore counted by package name, while tarball downloads are counted
+// by the selected `package@version`.
+fn metadata_counts() -> &'static Mutex<HashMap<String, u32>> {
+    static METADATA_COUNTS: OnceLock<Mutex<HashMap<String, u32>>> = OnceLock::new();
+    METADATA_COUNTS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+fn locked_metadata_counts() -> std::sync::MutexGuard<'static, HashMap<String, u32>> {
+    metadata_counts()
+        .lock()
+        .unwrap_or_else(|error| error.into_inner())
+}
+
+pub(crate) fn record_metadata_read(package_name: &str) {
+    *locked_metadata_counts()
+        .entry(package_name.to_string())
+        .or_insert(0) += 1;
+}
+
+/// Clear recorded metadata reads. Tests must call this while holding the
+/// shared install test env lock so counts never leak between tests.
+pub(crate) fn reset_metadata_read_counts() {
+    locked_metadata_counts().clear();
+}
+
+pub(crate) fn metadata_read_count(package_name: &str) -> u32 {
+    locked_metadata_counts()
+        .get(package_name)
+        .copied()
+        .unwrap_or(0)
+}
+
+/// Recorded metadata reads as a stable, sorted `(package-name, count)` list
+/// so expected read counts stay reviewable in test output.
+pub(crate) fn recorded_metadata_reads() -> Vec<(String, u32)> {
+    let mut recorded = locked_metadata_counts()
+        .iter()
+        .map(|(name, count)| (name.clone(), *count))
+        .collect::<Vec<_>>();
+    recorded.sort();
+    recorded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::test_support::fixture_path;
+
+    #[tokio::test]
+    async fn serves_fixture_packuments_and_counts_reads_by_package_name() {
+        reset_metadata_read_counts();
+        let client = FixtureRegistryClient::new(&fixture_path(&[
+            "registry",
+            "shared-transitive",
+            "metadata",
+        ]))
+        .unwrap();
+
+        for name in [
+            "@rpm-fixture/alpha",
+            "@rpm-fixture/beta",
+            "@rpm-fixture/shared",
+            "@rpm-fixture/alpha",
+        ] {
+            client.get_registry(name).await.unwrap();
+        }
+
+        let alpha = client.get_registry("@rpm-fixture/alpha").await.unwrap();
+        assert_eq!(alpha.name, "@rpm-fixture/alpha");
+        assert_eq!(alpha.select_version("^1.0.0").unwrap(), "1.0.0");
+        assert_eq!(metadata_read_count("@rpm-fixture/beta"), 1);
+        assert_eq!(
+            recorded_metadata_reads(),
+            vec![
+                ("@rpm-fixture/alpha".to_string(), 3),
+                ("@rpm-fixture/beta".to_string(), 1),
+                ("@rpm-fixture/shared".to_string(), 1),
+            ],
+        );
+    }
+
+    #[tokio::test]
+    async fn reports_missing_and_invalid_fixture_packuments() {
+        let fixture = TempProject::new("invalid-fixture").unwrap();
+        fixture.write("@scope__broken.json", "{").unwrap();
+        let client = FixtureRegistryClient::new(fixture.path()).unwrap();
+
+        let missing = client.get_registry("@scope/missing").await.unwrap_err();
+        assert_eq!(missing.kind(), ErrorKind::NotFound);
+        let invalid = client.get_registry("@scope/broken").await.unwrap_err();
+        assert_eq!(invalid.kind(), ErrorKind::InvalidData);
+        assert!(invalid.to_string().contains("broken.json"));
+    }
+
+    #[test]
+    fn fixture_tarball_builds_minimal_package_archive() {
+        let bytes = fixture_tarball(
+            Path::new("/nonexistent"),
+            "https://registry.example.invalid/@rpm-fixture/alpha/-/alpha.tgz",
+        )
+        .expect("fixture tarball should be generated");
+
+        let decoder = flate2::read::GzDecoder::new(bytes.as_slice());
+        let mut archive = tar::Archive::new(decoder);
+        let package_json = archive
+            .entries()
+            .unwrap()
+            .find_map(|entry| {
+                let mut entry = entry.unwrap();
+                if entry.path().unwrap() == Path::new("package/package.json") {
+                    let mut text = String::new();
+                    use std::io::Read;
+                    entry.read_to_string(&mut text).unwrap();
+                    Some(text)
+                } else {
+                    None
+                }
+            })
+            .expect("package.json should exist in generated archive");
+
+        assert_eq!(package_json, r#"{"name":"@rpm-fixture/alpha"}"#);
+    }
+
+    #[test]
+    fn package_key_from_tarball_url_records_selected_package_and_version() {
+        assert_eq!(
+            package_key_from_tarball_url(
+                "https://registry.example.invalid/@rpm-fixture/shared/-/shared-1.0.0.tgz"
+            ),
+            "@rpm-fixture/shared@1.0.0"
+        );
+        assert_eq!(
+            package_key_from_tarball_url(
+                "https://registry.example.invalid/alpha/-/alpha-2.1.0.tgz"
+            ),
+            "alpha@2.1.0"
+        );
+        assert_eq!(package_key_from_tarball_url("not-a-url"), "not-a-url");
+        assert_eq!(
+            package_key_from_tarball_url("https://registry.example.invalid/alpha/-/alpha.tgz"),
+            "https://registry.example.invalid/alpha/-/alpha.tgz"
+        );
+    }
+
+    #[test]
+    fn package_name_from_tarball_url_rejects_invalid_fixture_urls() {
+        let error = package_name_from_tarball_url("not-a-url").unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidInput);
+        assert!(error.to_string().contains("invalid fixture tarball URL"));
+
+        let error = package_name_from_tarball_url("https://registry.example.invalid/").unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidInput);
+        assert!(error
+            .to_string()
+            .contains("invalid fixture tarball URL path"));
+    }
+}
@@ -0,0 +1,230 @@
+use std::{
+    fs,
+    io::{Error, ErrorKind},
+};
+
+use reqwest::{Certificate, NoProxy, Proxy};
+
+use crate::npmrc::NpmConfig;
+
+/// Proxy settings for registry requests, resolved the way npm does: each
+/// `.npmrc` key falls back to its environment variable.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct ProxySettings {
+    /// Proxy for `http:` URLs: `proxy`, else `HTTP_PROXY`.
+    pub(crate) http: Option<String>,
+    /// Proxy for `https:` URLs: `https-proxy`, else `HTTPS_PROXY`, else the
+    /// `http:` proxy.
+    pub(crate) https: Option<String>,
+    /// Hosts reached directly: `noproxy`, else `NO_PROXY`.
+    pub(crate) no_proxy: Option<String>,
+}
+
+impl ProxySettings {
+    pub(crate) fn resolve(config: &NpmConfig, env: &dyn Fn(&str) -> Option<String>) -> Self {
+        let setting = |key: &str, variable: &str| {
+            config
+                .get(key)
+                .map(str::to_string)
+                .or_else(|| env(variable))
+                .or_else(|| env(&variable.to_ascii_lowercase()))
+                .map(|value| value.trim().to_string())
+                .filter(|value| !value.is_empty())
+        };
+        let http = setting("proxy", "HTTP_PROXY");
+        let https = setting("https-proxy", "HTTPS_PROXY").or_else(|| http.clone());
+        let no_proxy = setting("noproxy", "NO_PROXY");
+        Self {
+            http,
+            https,
+            no_proxy,
+        }
+    }
+}
+
+/// Build the HTTP client every registry request of an install shares, applying
+/// the proxy settings and the `cafile` and `strict-ssl` `.npmrc` keys.
+///
+/// `reqwest`'s own environment proxy lookup is turned off, so the resolution
+/// in [`ProxySettings::resolve`] is the only one that applies.
+pub(crate) fn build_http_client(
+    config: &NpmConfig,
+    env: &dyn Fn(&str) -> Option<String>,
+) -> std::io::Result<reqwest::Client> {
+    let mut builder = reqwest::Client::builder().no_proxy();
+
+    let proxies = ProxySettings::resolve(config, env);
+    let no_proxy = || proxies.no_proxy.as_deref().and_then(NoProxy::from_string);
+    if let Some(url) = &proxies.http {
+        let proxy = Proxy::http(url.as_str()).map_err(|error| invalid_proxy("http", &error))?;
+        builder = builder.proxy(proxy.no_proxy(no_proxy()));
+    }
+    if let Some(url) = &proxies.https {
+        let proxy = Proxy::https(url.as_str()).map_err(|error| invalid_proxy("https", &error))?;
+        builder = builder.proxy(proxy.no_proxy(no_proxy()));
+    }
+
+    if let Some(path) = config.get("cafile").filter(|path| !path.is_empty()) {
+        for certificate in read_cafile(path)? {
+            builder = builder.add_root_certificate(certificate);
+        }
+    }
+    if !strict_ssl(config)? {
+        builder = builder.danger_accept_invalid_certs(true);
+    }
+
+    builder
+        .build()
+        .map_err(|error| Error::other(format!("failed to configure HTTP client: {error}")))
+}
+
+/// The proxy URL may carry credentials, so it is never echoed in the error.
+fn invalid_proxy(scheme: &str, error: &reqwest::Error) -> Error {
+    Error::new(
+        ErrorKind::InvalidInput,
+        format!("invalid proxy URL for {scheme} requests: {error}"),
+    )
+}
+
+fn strict_ssl(config: &NpmConfig) -> std::io::Result<bool> {
+    match config.get("strict-ssl").map(str::trim) {
+        None | Some("true") => Ok(true),
+        Some("false") => Ok(false),
+        Some(value) => Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!("invalid strict-ssl in .npmrc: {value}"),
+        )),
+    }
+}
+
+/// Read every PEM certificate in the `cafile` bundle.
+fn read_cafile(path: &str) -> std::io::Result<Vec<Certificate>> {
+    let contents = fs::read_to_string(path).map_err(|error| {
+        Error::new(
+            error.kind(),
+            format!("failed to read cafile {path}: {error}"),
+        )
+    })?;
+    let certificates = pem_certificates(&contents)
+        .into_iter()
+        .map(|pem| {
+            Certificate::from_pem(pem.as_bytes()).map_err(|error| {
+                Error::new(
+                    ErrorKind::InvalidData,
+                    format!("invalid certificate in cafile {path}: {error}"),
+                )
+            })
+        })
+        .collect::<std::io::Result<Vec<_>>>()?;
+    if certificates.is_empty() {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            format!("cafile {path} contains no PEM certificates"),
+        ));
+    }
+    Ok(certificates)
+}
+
+/// Split a PEM bundle into its certificate blocks.
+fn pem_certificates(contents: &str) -> Vec<String> {
+    const BEGIN: &str = "-----BEGIN CERTIFICATE-----";
+    const END: &str = "-----END CERTIFICATE-----";
+
+    let mut certificates = Vec::new();
+    let mut rest = contents;
+    while let Some(start) = rest.find(BEGIN) {
+        let Some(length) = rest[start..].find(END) else {
+            break;
+        };
+        let end = start + length + END.len();
+        certificates.push(rest[start..end].to_string());
+        rest = &rest[end..];
+    }
+    certificates
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn env(values: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+        let values = values
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect::<HashMap<_, _>>();
+        move |name| values.get(name).cloned()
+    }
+
+    #[test]
+    fn npmrc_proxy_settings_win_over_environment() {
+        let settings = ProxySettings::resolve(
+            &NpmConfig::parse("https-proxy=http://corp-proxy:8080\nnoproxy=.internal\n"),
+            &env(&[
+                ("HTTPS_PROXY", "http://env-proxy:3128"),
+                ("HTTP_PROXY", "http://env-http:3128"),
+                ("NO_PROXY", "localhost"),
+            ]),
+        );
+
+        assert_eq!(settings.https.as_deref(), Some("http://corp-proxy:8080"));
+        assert_eq!(settings.http.as_deref(), Some("http://env-http:3128"));
+        assert_eq!(settings.no_proxy.as_deref(), Some(".internal"));
+    }
+
+    #[test]
+    fn https_falls_back_to_http_proxy_and_lowercase_variables() {
+        let settings = ProxySettings::resolve(
+            &NpmConfig::default(),
+            &env(&[("http_proxy", "http://lower:3128"), ("no_proxy", "a,b")]),
+        );
+
+        assert_eq!(
+            settings,
+            ProxySettings {
+                http: Some("http://lower:3128".to_string()),
+                https: Some("http://lower:3128".to_string()),
+                no_proxy: Some("a,b".to_string()),
+            }
+        );
+        assert_eq!(
+            ProxySettings::resolve(&NpmConfig::parse("proxy=\n"), &env(&[])),
+            ProxySettings::default()
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_settings_without_echoing_proxy_credentials() {
+        let error = build_http_client(
+            &NpmConfig::parse("https-proxy=http://user:secret@[bad\n"),
+            &env(&[]),
+        )
+        .unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidInput);
+        assert!(!error.to_string().contains("secret"));
+
+        let error =
+            build_http_client(&NpmConfig::parse("strict-ssl=maybe\n"), &env(&[])).unwrap_err();
+        assert_eq!(error.to_string(), "invalid strict-ssl in .npmrc: maybe");
+
+        let error = build_http_client(&NpmConfig::parse("cafile=/missing/ca.pem\n"), &env(&[]))
+            .unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::NotFound);
+        assert!(error.to_string().contains("/missing/ca.pem"));
+    }
+
+    #[test]
+    fn splits_pem_bundles() {
+        let bundle = "junk\n-----BEGIN CERTIFICATE-----\nAAA\n-----END CERTIFICATE-----\n\
+                      -----BEGIN CERTIFICATE-----\nBBB\n-----END CERTIFICATE-----\n\
+                      -----BEGIN CERTIFICATE-----\nunterminated";
+
+        assert_eq!(
+            pem_certificates(bundle),
+            vec![
+                "-----BEGIN CERTIFICATE-----\nAAA\n-----END CERTIFICATE-----",
+                "-----BEGIN CERTIFICATE-----\nBBB\n-----END CERTIFICATE-----",
+            ]
+        );
+    }
+}
@@ -10,11 +10,97 @@ pub enum Command {
         #[structopt(short, long, help = "install dev libraries")]
         dev: bool,
     },
+    #[structopt(
+        name = "dedupe",
+        about = "rewrite rpm.lock onto the fewest versions of each package"
+    )]
+    Dedupe,
+    #[structopt(
+        name = "info",
+        alias = "view",
+        about = "show registry metadata of a package version"
+    )]
+    Info {
+        #[structopt(help = "package to describe, as <name>[@<range or tag>]")]
+        package: String,
+        #[structopt(help = "fields to print, as dotted paths such as dist.tarball")]
+        fields: Vec<String>,
+        #[structopt(long, help = "print JSON")]
+        json: bool,
+    },
     #[structopt(
         name = "install",
         about = "install libraries using rpm.lock file(when it not founded using package.json file)"
     )]
-    Install,
+    Install {
+        #[structopt(
+            long,
+            help = "install only from rpm.lock and the local caches, never the network"
+        )]
+        offline: bool,
+        #[structopt(
+            long,
+            conflicts_with = "offline",
+            help = "use cached metadata and tarballs first, fetching only on a miss"
+        )]
+        prefer_offline: bool,
+        #[structopt(
+            long,
+            help = "maximum number of tarball downloads in flight (default 16)"
+        )]
+        network_concurrency: Option<usize>,
+        #[structopt(
+            long,
+            help = "verify registry signatures of every package before it is cached"
+        )]
+        verify_signatures: bool,
+        #[structopt(
+            long,
+            requires = "verify-signatures",
+            parse(from_os_str),
+            help = "registry keys file to verify signatures with, instead of the registry's keys"
+        )]
+        registry_keys: Option<std::path::PathBuf>,
+        #[structopt(
+            long,
+            parse(try_from_str = working_process::parse_before),
+            help = "resolve only versions published before this date (YYYY-MM-DD or RFC 3339)"
+        )]
+        before: Option<chrono::DateTime<chrono::Utc>>,
+    },
+    #[structopt(
+        name = "pack",
+        about = "build the publishable <name>-<version>.tgz of this package"
+    )]
+    Pack {
+        #[structopt(
+            long,
+            parse(from_os_str),
+            help = "directory to write the tarball to (default: the project directory)"
+        )]
+        pack_destination: Option<std::path::PathBuf>,
+    },
+    #[structopt(
+        name = "publish",
+        about = "pack this package and publish it to its registry"
+    )]
+    Publish {
+        #[structopt(
+            long,
+            help = "dist-tag to publish under (default: publishConfig.tag, then latest)"
+        )]
+        tag: Option<String>,
+        #[structopt(
+            long,
+            help = "pack and report what would be published without uploading"
+        )]
+        dry_run: bool,
+    },
+    #[structopt(name = "registry", about = "work with registries")]
+    Registry {
+        #[structopt(subcommand)]
+        cmd: RegistryCommand,
+    },
     #[structopt(name = "run", about = "run scripts")]
     Run {
         #[structopt(help = "run scripts")]
@@ -29,3 +115,21 @@ pub enum Command {
     #[structopt(about = "display version of rpm")]
     Version,
 }
+
+#[derive(Debug, StructOpt)]
+pub enum RegistryCommand {
+    #[structopt(
+        name = "serve",
+        about = "serve the packuments and tarballs in the .rpm caches as a registry"
+    )]
+    Serve {
+        #[structopt(
+            long,
+            default_value = "127.0.0.1",
+            help = "address to listen on (0.0.0.0 to serve the network)"
+        )]
+        host: String,
+        #[structopt(long, default_value = "4873", help = "port to listen on")]
+        port: u16,
+    },
+}
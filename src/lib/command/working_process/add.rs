@@ -1,21 +1,38 @@
 use std::{
-    collections::{HashMap, HashSet, VecDeque},
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque},
     future::Future,
     io::{Error, ErrorKind, Write},
     path::Path,
 };
 
-use tokio::time::sleep;
+use chrono::{DateTime, Utc};
+use futures_util::stream::{FuturesUnordered, StreamExt};
 
 use crate::{
-    api,
+    api::{
+        self, HttpRegistryClient, MinimumReleaseAge, NetworkMode, RegistryClient,
+        ResolutionStrategyKind, SignatureKeySource,
+    },
+    command::working_process::{
+        git::build_git_package,
+        install::InstallOptions,
+        report::{DeprecatedPackage, HeldBackPackage, InstallReport},
+        source::{
+            cache_tarball, fetch_remote_package, read_local_manifest, read_local_tarball,
+            SourcePackage,
+        },
+    },
     core::resolver::{
-        resolve_dependency_graph, DependencyDeclaration, DependencyRequest, DependencyRequestKind,
-        PackageMetadataProvider, ResolutionError, ResolvedPackage,
+        dedupe_graph, registry_target, resolve_dependency_graph,
+        source::{NpmAlias, RequestSource},
+        BacktrackingResolutionStrategy, DependencyDeclaration, DependencyRequest,
+        DependencyRequestKind, PackageMetadataProvider, PeerDeclaration, ResolutionError,
+        ResolutionStrategy, ResolvedDependencyGraph, ResolvedPackage,
     },
-    lockfile::{LockFile, Relationship},
+    git,
+    lockfile::{LockEntry, LockFile, PackageSource, Relationship},
     package_manifest::PackageManifest,
-    registry::Registry,
+    registry::{cached_tarball_matches, Registry, RegistryKeys, Signature},
 };
 
 #[derive(Clone)]
@@ -25,11 +42,16 @@ struct LockedInstallPackage {
     requested: String,
     version: String,
     relationship: Relationship,
+    source: Option<PackageSource>,
+    alias_of: Option<String>,
+    deprecated: Option<String>,
     tarball: Option<String>,
     integrity: Option<String>,
     shasum: Option<String>,
     scripts: Option<HashMap<String, String>>,
     dependencies: Vec<String>,
+    /// `peerDependencies` specs, each with whether it is optional.
+    peer_dependencies: Vec<(String, bool)>,
 }
 
 pub async fn add(
@@ -38,55 +60,292 @@ pub async fn add(
     libs: Vec<String>,
     dev: bool,
     write_manifest: bool,
-) -> std::io::Result<()> {
-    add_with_cache_dir(
+) -> std::io::Result<InstallReport> {
+    let client = HttpRegistryClient::for_project(Path::new("."))?;
+    add_in(
         pkg,
         lockfile,
         libs,
         dev,
         write_manifest,
-        Path::new("./.rpm/.cache"),
+        Path::new("."),
+        &client,
     )
     .await
 }
 
-pub(crate) async fn add_with_cache_dir(
+/// Resolve `libs` into `lockfile` for the project at `project_root`, caching
+/// their tarballs in its `.rpm/.cache`. `file:` paths are relative to
+/// `project_root`, and its `.npmrc` picks the resolver.
+pub(crate) async fn add_in(
     pkg: &mut PackageManifest,
     lockfile: &mut LockFile,
     libs: Vec<String>,
     dev: bool,
     write_manifest: bool,
-    cache_dir: &Path,
-) -> std::io::Result<()> {
+    project_root: &Path,
+    client: &impl RegistryClient,
+) -> std::io::Result<InstallReport> {
+    let options = InstallOptions::from_npmrc(project_root)?;
     let request_kind = direct_request_kind(dev);
     let requests = libs
         .into_iter()
         .map(|dependency| DependencyRequest::from_spec(dependency, request_kind))
         .collect::<Result<Vec<_>, _>>()
         .map_err(resolution_error_to_io)?;
+    resolve_requests_in(
+        pkg,
+        lockfile,
+        requests,
+        write_manifest,
+        project_root,
+        client,
+        &options,
+    )
+    .await
+}
+
+/// Resolve direct `requests` of either kind into `lockfile` together, so a
+/// peer of one dependency can link to another, whichever list declares it.
+/// `options` pick the resolver and the release-age and signature policies.
+pub(crate) async fn resolve_requests_in(
+    pkg: &mut PackageManifest,
+    lockfile: &mut LockFile,
+    requests: Vec<DependencyRequest>,
+    write_manifest: bool,
+    project_root: &Path,
+    client: &impl RegistryClient,
+    options: &InstallOptions,
+) -> std::io::Result<InstallReport> {
+    let cache_dir = project_root.join(".rpm").join(".cache");
     let mut metadata = InstallMetadata::from_lockfile(lockfile);
+    metadata.release_age = options
+        .minimum_release_age
+        .as_ref()
+        .map(|policy| (policy.clone(), policy.cutoff(Utc::now())));
+    metadata.signature_keys = options.signature_keys.clone();
+    // Abbreviated packuments carry no publish times, so a cutoff needs the
+    // full documents.
+    let full_documents = metadata.before.is_some() || metadata.release_age.is_some();
+    let strategy = options.resolution_strategy;
+    // Only the backtracking strategy can honor overrides, and it may pick any
+    // candidate, so its walk covers every one of them.
+    let overrides = match strategy {
+        ResolutionStrategyKind::Fifo => HashMap::new(),
+        ResolutionStrategyKind::Backtracking => pkg.get_overrides().into_iter().collect(),
+    };
+    let ignored_overrides = match strategy {
+        ResolutionStrategyKind::Fifo => pkg
+            .get_overrides()
+            .into_iter()
+            .map(|(package_name, _)| package_name)
+            .collect(),
+        ResolutionStrategyKind::Backtracking => Vec::new(),
+    };
+    let walk = match strategy {
+        ResolutionStrategyKind::Fifo => MetadataWalk::Selected,
+        ResolutionStrategyKind::Backtracking => MetadataWalk::Candidates {
+            overrides: &overrides,
+        },
+    };
 
-    populate_metadata(&mut metadata, &requests, |package_name| async move {
-        api::get_registry(&package_name, "").await
-    })
-    .await?;
-    let graph = resolve_dependency_graph(requests, &metadata).map_err(resolution_error_to_io)?;
+    // Git and tarball packages declare their dependencies in their own
+    // package.json, so the walk pauses at unlocked ones until they are fetched.
+    loop {
+        populate_metadata(
+            &mut metadata,
+            &requests,
+            &walk,
+            METADATA_FETCH_CONCURRENCY,
+            |package_name| async move {
+                if full_documents {
+                    client.get_full_registry(&package_name).await
+                } else {
+                    client.get_registry(&package_name).await
+                }
+            },
+        )
+        .await?;
+        let source_requests = metadata.take_deferred_source_requests();
+        if source_requests.is_empty() {
+            break;
+        }
+        fetch_source_requests(
+            &mut metadata,
+            source_requests,
+            project_root,
+            &cache_dir,
+            client,
+        )
+        .await?;
+    }
+    let graph = match strategy {
+        // Requests that FIFO resolution landed on different versions of a
+        // package move onto the fewest of them that satisfy every range.
+        ResolutionStrategyKind::Fifo => resolve_dependency_graph(requests, &metadata)
+            .map(|graph| dedupe_graph(&graph, &metadata)),
+        ResolutionStrategyKind::Backtracking => BacktrackingResolutionStrategy::new()
+            .with_overrides(overrides)
+            .resolve(requests, &metadata),
+    }
+    .map_err(resolution_error_to_io)?;
+    fetch_install_script_documents(&mut metadata, &graph, client).await?;
 
     lockfile.set_project_metadata(pkg.get_name(), pkg.get_version());
-    apply_resolved_graph(
+    let mut report = apply_resolved_graph(
         pkg,
         lockfile,
-        graph.packages(),
+        &graph,
         &metadata,
         write_manifest,
-        cache_dir,
+        project_root,
+        client,
     )
-    .await
+    .await?;
+    report.ignored_overrides = ignored_overrides;
+    Ok(report)
+}
+
+/// Replace the abbreviated document of each registry package whose resolved
+/// version has install scripts the document omits, so they can be locked.
+/// Locked entries that already record their scripts need no metadata.
+async fn fetch_install_script_documents(
+    metadata: &mut InstallMetadata,
+    graph: &ResolvedDependencyGraph,
+    client: &impl RegistryClient,
+) -> std::io::Result<()> {
+    let mut registry_names = BTreeSet::new();
+    for package in graph.packages() {
+        let locked_scripts = metadata
+            .locked_package_for_resolved(package)
+            .is_some_and(|locked_package| locked_package.scripts.is_some());
+        if locked_scripts
+            || metadata
+                .source_package_for_version(&package.package_name, &package.version)
+                .is_some()
+        {
+            continue;
+        }
+        let registry_name = metadata.registry_name(&package.package_name, &package.version);
+        if metadata
+            .registries
+            .get(registry_name)
+            .is_some_and(|registry| registry.requires_full_document(&package.version))
+        {
+            registry_names.insert(registry_name.to_string());
+        }
+    }
+    for registry_name in registry_names {
+        let registry = client
+            .get_full_registry(&registry_name)
+            .await
+            .map_err(|error| phase_error("fetch", error))?;
+        metadata.registries.insert(registry_name, registry);
+    }
+    Ok(())
+}
+
+/// Fetch each unlocked git, tarball or directory request and cache its
+/// tarball, so its manifest can feed resolution. A git request resolves to a
+/// commit first; a directory is read in place and has no tarball to cache.
+async fn fetch_source_requests(
+    metadata: &mut InstallMetadata,
+    requests: Vec<DependencyRequest>,
+    project_root: &Path,
+    cache_dir: &Path,
+    client: &impl RegistryClient,
+) -> std::io::Result<()> {
+    if client.network_mode() == NetworkMode::Offline {
+        let keys = requests
+            .iter()
+            .filter(|request| {
+                matches!(
+                    request.source(),
+                    RequestSource::Git(_) | RequestSource::Remote(_)
+                )
+            })
+            .map(|request| format!("{}@{}", request.package_name, request.requested))
+            .collect::<BTreeSet<_>>();
+        if !keys.is_empty() {
+            return Err(phase_error(
+                "fetch",
+                offline_missing_error("git and remote tarball dependencies", &keys),
+            ));
+        }
+    }
+    for request in requests {
+        let package_name = &request.package_name;
+        let package = async {
+            let package = match request.source() {
+                RequestSource::Registry => return Ok(None),
+                RequestSource::Git(source) => {
+                    let committish = git::resolve_committish(&source).await?;
+                    build_git_package(package_name, &source.url, &committish, client).await?
+                }
+                RequestSource::Remote(url) => {
+                    fetch_remote_package(&url, package_name, cache_dir, client).await?
+                }
+                RequestSource::File(path) => {
+                    let bytes = read_local_tarball(project_root, &path)?;
+                    SourcePackage::from_tarball(PackageSource::File, format!("file:{path}"), bytes)?
+                }
+                RequestSource::Directory(path) => SourcePackage::local(
+                    PackageSource::Directory,
+                    format!("file:{path}"),
+                    &read_local_manifest(project_root, &path)?,
+                )?,
+                RequestSource::Link(path) => SourcePackage::local(
+                    PackageSource::Link,
+                    format!("link:{path}"),
+                    &read_local_manifest(project_root, &path)?,
+                )?,
+            };
+            if let Some(bytes) = &package.bytes {
+                cache_tarball(cache_dir, package_name, &package.version, bytes)?;
+            }
+            Ok(Some(package))
+        }
+        .await
+        .map_err(|error: std::io::Error| phase_error("fetch", error))?;
+        if let Some(package) = package {
+            metadata.insert_source_package(request.package_name, request.requested, package);
+        }
+    }
+    Ok(())
+}
+
+/// Which versions of each request `populate_metadata` walks.
+enum MetadataWalk<'a> {
+    /// The version FIFO selection picks.
+    Selected,
+    /// Every candidate version, as backtracking may settle on any of them. A
+    /// package with an override is walked by the override's range instead of
+    /// the ranges its dependents request.
+    Candidates {
+        overrides: &'a HashMap<String, String>,
+    },
 }
 
+/// How many registry metadata reads `populate_metadata` keeps in flight.
+const METADATA_FETCH_CONCURRENCY: usize = 16;
+
+/// Load registry metadata for every package reachable from `requests`.
+///
+/// Fetches run concurrently, up to `concurrency` at a time, while the worklist
+/// keeps walking requests whose metadata is already known. Requests for a
+/// package whose read is pending wait for that read, so each package is still
+/// fetched at most once and the resulting metadata does not depend on the
+/// order in which reads complete.
+///
+/// Required peers are walked too, since resolution may install them. A peer
+/// whose metadata cannot be read, or that no version satisfies, is left for
+/// resolution to report instead of failing the walk; so is any request no
+/// candidate satisfies when `walk` covers every candidate.
 async fn populate_metadata<F, Fut>(
     metadata: &mut InstallMetadata,
     requests: &[DependencyRequest],
+    walk: &MetadataWalk<'_>,
+    concurrency: usize,
     mut fetch_registry: F,
 ) -> std::io::Result<()>
 where
@@ -94,101 +353,263 @@ where
     Fut: Future<Output = std::io::Result<Registry>>,
 {
     let mut visited = HashSet::new();
-    let mut worklist = requests.iter().cloned().collect::<VecDeque<_>>();
-
-    while let Some(request) = worklist.pop_front() {
-        let package_name = request.package_name.clone();
-        let needs_registry_metadata = metadata
-            .locked_package_for_request(&package_name, &request.requested)
-            .map(|locked| locked.scripts.is_none())
-            .unwrap_or(true);
-        if needs_registry_metadata && !metadata.has_registry(&package_name) {
-            let registry = fetch_registry(package_name.clone())
-                .await
-                .map_err(|error| phase_error("fetch", error))?;
-            metadata.insert_registry(package_name.clone(), registry);
+    // Each request with whether it is only a peer's.
+    let mut worklist = requests
+        .iter()
+        .cloned()
+        .map(|request| (request, false))
+        .collect::<VecDeque<_>>();
+    let mut queued_fetches = VecDeque::new();
+    let mut waiting = HashMap::<String, Vec<(DependencyRequest, bool)>>::new();
+    let mut in_flight = FuturesUnordered::new();
+    // Offline cache misses are collected instead of failing on the first one,
+    // so the error lists everything that must be cached before retrying.
+    let mut missed_packages = HashSet::new();
+    let mut offline_misses = BTreeSet::new();
+
+    loop {
+        while let Some((mut request, peer)) = worklist.pop_front() {
+            if let MetadataWalk::Candidates { overrides } = walk {
+                if let Some(range) = overrides.get(&request.package_name) {
+                    request.requested = range.clone();
+                }
+            }
+            let package_name = request.package_name.clone();
+            let locked = metadata.locked_package_for_request(&package_name, &request.requested);
+            let is_registry = request.source().is_registry();
+            if !is_registry
+                && locked.is_none()
+                && !metadata.has_source_package(&package_name, &request.requested)
+            {
+                if !peer {
+                    metadata.defer_source_request(request);
+                }
+                continue;
+            }
+            let needs_registry_metadata = is_registry
+                && locked
+                    .map(|locked| locked.scripts.is_none())
+                    .unwrap_or(true);
+            // An `npm:` alias reads the metadata of the package it names.
+            let (registry_name, _) = request.registry_target();
+            if needs_registry_metadata && !metadata.has_registry(&registry_name) {
+                if missed_packages.contains(&registry_name) {
+                    if !peer {
+                        offline_misses.insert(format!("{package_name}@{}", request.requested));
+                    }
+                    continue;
+                }
+                let pending = waiting.entry(registry_name.clone()).or_default();
+                if pending.is_empty() {
+                    queued_fetches.push_back(registry_name);
+                }
+                pending.push((request, peer));
+                continue;
+            }
+
+            let versions = match walk {
+                MetadataWalk::Selected => metadata
+                    .select_version(&package_name, &request.requested)
+                    .map(|version| vec![version]),
+                MetadataWalk::Candidates { .. } => {
+                    metadata.candidate_versions(&package_name, &request.requested)
+                }
+            };
+            let versions = match versions {
+                Ok(versions) => versions,
+                Err(_) if peer => continue,
+                Err(ResolutionError::VersionSelection { .. })
+                    if matches!(walk, MetadataWalk::Candidates { .. }) =>
+                {
+                    continue
+                }
+                Err(error) => return Err(resolution_error_to_io(error)),
+            };
+            for version in versions {
+                if is_registry && registry_name != package_name {
+                    metadata.insert_alias(&package_name, &version, registry_name.clone());
+                }
+                let package_key = format!("{package_name}@{version}");
+                if !visited.insert(package_key) {
+                    continue;
+                }
+
+                let dependencies = metadata
+                    .dependencies_for_version(&package_name, &version)
+                    .map_err(resolution_error_to_io)?;
+                for dependency in dependencies {
+                    worklist.push_back((
+                        DependencyRequest::new(
+                            dependency.package_name,
+                            dependency.requested,
+                            DependencyRequestKind::Transitive,
+                        ),
+                        false,
+                    ));
+                }
+                let peers = metadata
+                    .peer_dependencies_for_version(&package_name, &version)
+                    .map_err(resolution_error_to_io)?;
+                for peer in peers.into_iter().filter(|peer| !peer.optional) {
+                    worklist.push_back((
+                        DependencyRequest::new(
+                            peer.package_name,
+                            peer.requested,
+                            DependencyRequestKind::Transitive,
+                        ),
+                        true,
+                    ));
+                }
+            }
         }
 
-        let version = metadata
-            .select_version(&package_name, &request.requested)
-            .map_err(resolution_error_to_io)?;
-        let package_key = format!("{package_name}@{version}");
-        if !visited.insert(package_key) {
-            continue;
+        while in_flight.len() < concurrency.max(1) {
+            let Some(package_name) = queued_fetches.pop_front() else {
+                break;
+            };
+            let fetch = fetch_registry(package_name.clone());
+            in_flight.push(async move { (package_name, fetch.await) });
         }
 
-        let dependencies = metadata
-            .dependencies_for_version(&package_name, &version)
-            .map_err(resolution_error_to_io)?;
-        for dependency in dependencies {
-            worklist.push_back(DependencyRequest::new(
-                dependency.package_name,
-                dependency.requested,
-                DependencyRequestKind::Transitive,
-            ));
+        let Some((package_name, result)) = in_flight.next().await else {
+            break;
+        };
+        let pending = waiting.remove(&package_name).unwrap_or_default();
+        match result {
+            Ok(registry) => {
+                metadata.insert_registry(package_name, registry);
+                worklist.extend(pending);
+            }
+            // Resolution reports a peer it has no metadata for as missing.
+            Err(_) if pending.iter().all(|(_, peer)| *peer) => {}
+            Err(error) if api::is_offline_cache_miss(&error) => {
+                offline_misses.extend(
+                    pending
+                        .iter()
+                        .filter(|(_, peer)| !peer)
+                        .map(|(request, _)| {
+                            format!("{}@{}", request.package_name, request.requested)
+                        }),
+                );
+                missed_packages.insert(package_name);
+            }
+            Err(error) => return Err(phase_error("fetch", error)),
         }
     }
 
+    if !offline_misses.is_empty() {
+        return Err(phase_error(
+            "fetch",
+            offline_missing_error("registry metadata", &offline_misses),
+        ));
+    }
     Ok(())
 }
 
 async fn apply_resolved_graph(
     pkg: &mut PackageManifest,
     lockfile: &mut LockFile,
-    packages: &[ResolvedPackage],
+    graph: &ResolvedDependencyGraph,
     metadata: &InstallMetadata,
     write_manifest: bool,
-    cache_dir: &Path,
-) -> std::io::Result<()> {
+    project_root: &Path,
+    client: &impl RegistryClient,
+) -> std::io::Result<InstallReport> {
+    let packages = graph.packages();
+    let cache_dir = &project_root.join(".rpm").join(".cache");
+    let mut downloads = Vec::new();
     for package in packages {
-        print!("installing {}@{}...", package.package_name, package.version);
-        std::io::stdout().flush()?;
-        sleep(std::time::Duration::from_millis(1)).await;
-        print!("\r\x1b[K");
+        if let Some(download) = tarball_download(package, metadata)? {
+            downloads.push(download);
+        }
+    }
+
+    if client.network_mode() == NetworkMode::Offline {
+        let missing = downloads
+            .iter()
+            .filter(|download| {
+                !cached_tarball_matches(
+                    cache_dir,
+                    &download.key,
+                    download.integrity.as_deref(),
+                    download.shasum.as_deref(),
+                )
+            })
+            .map(|download| download.key.clone())
+            .collect::<BTreeSet<_>>();
+        if !missing.is_empty() {
+            return Err(phase_error(
+                "fetch",
+                offline_missing_error("tarballs", &missing),
+            ));
+        }
+    }
 
+    if let Some(source) = &metadata.signature_keys {
+        verify_signatures(&downloads, metadata, source, client).await?;
+    }
+    download_tarballs(&downloads, project_root, cache_dir, client).await?;
+
+    // Lockfile and manifest entries are recorded only after every tarball is
+    // cached, in graph order, so the result does not depend on the order in
+    // which downloads finished.
+    let mut report = InstallReport::default();
+    for package in packages {
         let requested = requested_for_lockfile(package, metadata);
         let relationship = relationship_for_package(package);
+        let deprecated = metadata.deprecation_for(package);
         if let Some(locked_package) = metadata.locked_package_for_resolved(package) {
-            if let Some(tarball) = &locked_package.tarball {
-                Registry::download_verified_tarball_url_to_dir(
-                    &locked_package.key,
-                    tarball,
-                    cache_dir,
-                    locked_package.integrity.as_deref(),
-                    locked_package.shasum.as_deref(),
-                )
-                .await
-                .map_err(download_error_to_phase)?;
-            }
             let scripts = match &locked_package.scripts {
                 Some(scripts) => Some(scripts.clone()),
                 None => Some(
                     metadata
-                        .registry_io(&package.package_name)?
+                        .registry_io(
+                            metadata.registry_name(&package.package_name, &locked_package.version),
+                        )?
                         .get_scripts_for_version(&locked_package.version)
                         .unwrap_or_default(),
                 ),
             };
             lockfile.add_dependency_entry(
                 &locked_package.key,
-                locked_package.package_name.clone(),
-                requested.clone(),
-                locked_package.version.clone(),
-                relationship,
-                locked_package.tarball.clone(),
-                locked_package.integrity.clone(),
-                locked_package.shasum.clone(),
-                scripts,
-                &locked_package.dependencies,
+                LockEntry {
+                    name: locked_package.package_name.clone(),
+                    requested: requested.clone(),
+                    version: locked_package.version.clone(),
+                    relationship,
+                    source: locked_package.source,
+                    alias_of: locked_package.alias_of.clone(),
+                    deprecated: deprecated.clone(),
+                    tarball: locked_package.tarball.clone(),
+                    integrity: locked_digest(locked_package.source, &locked_package.integrity),
+                    shasum: locked_digest(locked_package.source, &locked_package.shasum),
+                    scripts,
+                    dependencies: locked_package.dependencies.clone(),
+                },
+            );
+        } else if let Some(source_package) =
+            metadata.source_package_for_version(&package.package_name, &package.version)
+        {
+            lockfile.add_dependency_entry(
+                &format!("{}@{}", package.package_name, package.version),
+                LockEntry {
+                    name: package.package_name.clone(),
+                    requested: requested.clone(),
+                    version: package.version.clone(),
+                    relationship,
+                    source: Some(source_package.source),
+                    tarball: Some(source_package.tarball.clone()),
+                    integrity: source_package.integrity.clone(),
+                    shasum: source_package.shasum.clone(),
+                    scripts: Some(source_package.scripts.clone()),
+                    dependencies: source_package.dependencies.clone(),
+                    ..Default::default()
+                },
             );
         } else {
             let key = format!("{}@{}", package.package_name, package.version);
-            let registry = metadata.registry_io(&package.package_name)?;
-            registry
-                .download_tarball_to_dir(&key, &package.version, cache_dir)
-                .await
-                .map_err(download_error_to_phase)?;
-
+            let registry_name = metadata.registry_name(&package.package_name, &package.version);
+            let registry = metadata.registry_io(registry_name)?;
             let dependencies = package
                 .dependencies
                 .iter()
@@ -198,26 +619,357 @@ async fn apply_resolved_graph(
 
             lockfile.add_dependency_entry(
                 &key,
-                package.package_name.clone(),
-                requested.clone(),
-                package.version.clone(),
-                relationship,
-                dist.map(|dist| dist.tarball.clone()),
-                dist.and_then(|dist| dist.integrity.clone()),
-                dist.and_then(|dist| dist.shasum.clone()),
-                registry.get_scripts_for_version(&package.version),
-                &dependencies,
+                LockEntry {
+                    name: package.package_name.clone(),
+                    requested: requested.clone(),
+                    version: package.version.clone(),
+                    relationship,
+                    source: None,
+                    alias_of: (registry_name != package.package_name)
+                        .then(|| registry_name.to_string()),
+                    deprecated: deprecated.clone(),
+                    tarball: dist.map(|dist| dist.tarball.clone()),
+                    integrity: dist.and_then(|dist| dist.integrity.clone()),
+                    shasum: dist.and_then(|dist| dist.shasum.clone()),
+                    // A document without `scripts` for this version declares no
+                    // hooks; recording that lets a later install (and `--offline`)
+                    // skip the metadata read for this entry.
+                    scripts: Some(
+                        registry
+                            .get_scripts_for_version(&package.version)
+                            .unwrap_or_default(),
+                    ),
+                    dependencies,
+                },
             );
         }
 
+        let peers = package
+            .peer_dependencies
+            .iter()
+            .map(|peer| {
+                (
+                    format!("{}@{}", peer.package_name, peer.requested),
+                    peer.optional,
+                )
+            })
+            .collect::<Vec<_>>();
+        lockfile.set_peer_dependencies(
+            &format!("{}@{}", package.package_name, package.version),
+            &peers,
+        );
+
         if write_manifest {
             maybe_update_manifest(pkg, package, &requested);
         }
+        if let Some(message) = deprecated {
+            let path = graph
+                .path_to(&package.package_name, &package.version)
+                .unwrap_or_default()
+                .iter()
+                .map(|package| format!("{}@{}", package.package_name, package.version))
+                .collect();
+            report.deprecated.push(DeprecatedPackage {
+                package_name: package.package_name.clone(),
+                version: package.version.clone(),
+                message,
+                path,
+            });
+        }
+        if let Some(held_back) = metadata.held_back_for(package) {
+            report.held_back.push(held_back);
+        }
+    }
+    report.peer_problems = graph.peer_problems();
+
+    Ok(report)
+}
+
+/// A tarball the install must have in the cache, with the values it is
+/// verified against. An `npm:` alias is cached and verified as the package it
+/// names.
+struct TarballDownload {
+    key: String,
+    package_name: String,
+    version: String,
+    /// Where a package from outside the registry is fetched again from.
+    source: Option<PackageSource>,
+    url: String,
+    integrity: Option<String>,
+    shasum: Option<String>,
+    /// `dist.signatures` of the registry document; `None` for a locked
+    /// package, whose lockfile entry does not record them.
+    signatures: Option<Vec<Signature>>,
+}
+
+/// The tarball `package` installs from: the locked one when the lockfile pins
+/// the package, otherwise the registry `dist` of the selected version. `None`
+/// when a locked entry records no tarball, and for a local directory, which
+/// is linked from the directory itself.
+fn tarball_download(
+    package: &ResolvedPackage,
+    metadata: &InstallMetadata,
+) -> std::io::Result<Option<TarballDownload>> {
+    if let Some(locked_package) = metadata.locked_package_for_resolved(package) {
+        return Ok(locked_package
+            .tarball
+            .as_ref()
+            .filter(|_| {
+                !locked_package
+                    .source
+                    .is_some_and(PackageSource::is_local_directory)
+            })
+            .map(|tarball| {
+                let package_name = locked_package
+                    .alias_of
+                    .clone()
+                    .unwrap_or_else(|| locked_package.package_name.clone());
+                TarballDownload {
+                    key: format!("{package_name}@{}", locked_package.version),
+                    package_name,
+                    version: locked_package.version.clone(),
+                    source: locked_package.source,
+                    url: tarball.clone(),
+                    integrity: locked_digest(locked_package.source, &locked_package.integrity),
+                    shasum: locked_digest(locked_package.source, &locked_package.shasum),
+                    signatures: None,
+                }
+            }));
+    }
+    if let Some(source_package) =
+        metadata.source_package_for_version(&package.package_name, &package.version)
+    {
+        if source_package.source.is_local_directory() {
+            return Ok(None);
+        }
+        return Ok(Some(TarballDownload {
+            key: format!("{}@{}", package.package_name, package.version),
+            package_name: package.package_name.clone(),
+            version: package.version.clone(),
+            source: Some(source_package.source),
+            url: source_package.tarball.clone(),
+            integrity: source_package.integrity.clone(),
+            shasum: source_package.shasum.clone(),
+            signatures: None,
+        }));
+    }
+    let registry_name = metadata.registry_name(&package.package_name, &package.version);
+    let key = format!("{registry_name}@{}", package.version);
+    let dist = metadata
+        .registry_io(registry_name)?
+        .get_dist_for_version(&package.version)
+        .ok_or_else(|| {
+            phase_error(
+                "fetch",
+                Error::new(
+                    ErrorKind::InvalidData,
+                    format!("missing tarball URL for {key}"),
+                ),
+            )
+        })?;
+    Ok(Some(TarballDownload {
+        key,
+        package_name: registry_name.to_string(),
+        version: package.version.clone(),
+        source: None,
+        url: dist.tarball.clone(),
+        integrity: dist.integrity.clone(),
+        shasum: dist.shasum.clone(),
+        signatures: Some(dist.signatures.clone().unwrap_or_default()),
+    }))
+}
+
+/// Check every download's registry signature over
+/// `{name}@{version}:{integrity}` against the keys from `source` before any
+/// tarball is fetched, so an unsigned or tampered package never enters the
+/// cache. A locked package's signatures come from its registry document, read
+/// here when the install has not loaded it already. Packages from outside the
+/// registry have no registry signature and are skipped.
+async fn verify_signatures(
+    downloads: &[TarballDownload],
+    metadata: &InstallMetadata,
+    source: &SignatureKeySource,
+    client: &impl RegistryClient,
+) -> std::io::Result<()> {
+    let keys = match source {
+        SignatureKeySource::Registry => client.get_registry_keys().await,
+        SignatureKeySource::File(path) => RegistryKeys::load(path).await,
+    }
+    .map_err(|error| phase_error("fetch", error))?;
+    for download in downloads {
+        if download.source.is_some() {
+            continue;
+        }
+        let signatures = match &download.signatures {
+            Some(signatures) => signatures.clone(),
+            None => {
+                let fetched;
+                let registry = match metadata.registry_io(&download.package_name) {
+                    Ok(registry) => registry,
+                    Err(_) => {
+                        fetched = client
+                            .get_registry(&download.package_name)
+                            .await
+                            .map_err(|error| phase_error("fetch", error))?;
+                        &fetched
+                    }
+                };
+                registry
+                    .get_dist_for_version(&download.version)
+                    .and_then(|dist| dist.signatures.clone())
+                    .unwrap_or_default()
+            }
+        };
+        keys.verify(
+            &format!("{}@{}", download.package_name, download.version),
+            download.integrity.as_deref(),
+            &signatures,
+        )
+        .map_err(|error| phase_error("integrity", error))?;
     }
+    Ok(())
+}
 
+/// Download, verify, and cache `downloads`, keeping at most the client's
+/// download concurrency in flight. The first failure stops the stage; reads
+/// still in flight are dropped and leave no published cache entry.
+async fn download_tarballs(
+    downloads: &[TarballDownload],
+    project_root: &Path,
+    cache_dir: &Path,
+    client: &impl RegistryClient,
+) -> std::io::Result<()> {
+    let mut queued = downloads.iter();
+    let mut in_flight = FuturesUnordered::new();
+    let mut completed = 0;
+    loop {
+        while in_flight.len() < client.download_concurrency() {
+            let Some(download) = queued.next() else {
+                break;
+            };
+            in_flight.push(async move {
+                match download.source {
+                    Some(source) => {
+                        fetch_source_tarball(download, source, project_root, cache_dir, client)
+                            .await
+                    }
+                    None => {
+                        Registry::download_verified_tarball_url_to_dir(
+                            &download.key,
+                            &download.url,
+                            cache_dir,
+                            download.integrity.as_deref(),
+                            download.shasum.as_deref(),
+                            client,
+                        )
+                        .await
+                    }
+                }
+                .map(|()| download)
+            });
+        }
+        let Some(result) = in_flight.next().await else {
+            break;
+        };
+        let download = result.map_err(download_error_to_phase)?;
+        completed += 1;
+        print!(
+            "\r\x1b[Kinstalling {} ({completed}/{})...",
+            download.key,
+            downloads.len()
+        );
+        std::io::stdout().flush()?;
+    }
+    if completed > 0 {
+        print!("\r\x1b[K");
+    }
     Ok(())
 }
 
+/// A locked digest to check a cached tarball against. Git packages are pinned
+/// by commit alone, so a digest an older lockfile recorded for one is ignored.
+fn locked_digest(source: Option<PackageSource>, digest: &Option<String>) -> Option<String> {
+    digest
+        .clone()
+        .filter(|_| source != Some(PackageSource::Git))
+}
+
+/// Make sure the cache holds the tarball of a package from outside the
+/// registry. A cached tarball that matches the locked integrity is reused in
+/// every network mode; otherwise the tarball is fetched again from its source
+/// (a git package is re-packed from its locked commit) and must match;
+/// a git package only has to keep its locked version.
+async fn fetch_source_tarball(
+    download: &TarballDownload,
+    source: PackageSource,
+    project_root: &Path,
+    cache_dir: &Path,
+    client: &impl RegistryClient,
+) -> std::io::Result<()> {
+    let integrity = download.integrity.as_deref();
+    let shasum = download.shasum.as_deref();
+    if cached_tarball_matches(cache_dir, &download.key, integrity, shasum) {
+        return Ok(());
+    }
+    let url = &download.url;
+    let package = match source {
+        PackageSource::Remote => {
+            return Registry::download_verified_tarball_url_to_dir(
+                &download.key,
+                url,
+                cache_dir,
+                integrity,
+                shasum,
+                client,
+            )
+            .await;
+        }
+        PackageSource::Git => {
+            let (repository, commit) = git::parse_locked_tarball(url).ok_or_else(|| {
+                Error::new(
+                    ErrorKind::InvalidData,
+                    format!("invalid git tarball {url} for {}", download.key),
+                )
+            })?;
+            build_git_package(&download.package_name, repository, commit, client).await?
+        }
+        PackageSource::File => {
+            let path = url.strip_prefix("file:").unwrap_or(url);
+            let bytes = read_local_tarball(project_root, path)?;
+            SourcePackage::from_tarball(source, url.clone(), bytes)?
+        }
+        PackageSource::Directory | PackageSource::Link => return Ok(()),
+    };
+    let fetched_integrity = package.integrity.as_deref().unwrap_or_default();
+    if package.version != download.version
+        || integrity.is_some_and(|integrity| integrity != fetched_integrity)
+    {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            format!(
+                "integrity check failed for {}: {url} is now {}@{} with {fetched_integrity}",
+                download.key, download.package_name, package.version
+            ),
+        ));
+    }
+    cache_tarball(
+        cache_dir,
+        &download.package_name,
+        &package.version,
+        package.bytes.as_deref().unwrap_or_default(),
+    )
+}
+
+fn offline_missing_error(what: &str, keys: &BTreeSet<String>) -> std::io::Error {
+    Error::new(
+        ErrorKind::NotFound,
+        format!(
+            "offline install is missing cached {what} for: {}",
+            keys.iter().cloned().collect::<Vec<_>>().join(", ")
+        ),
+    )
+}
+
 fn maybe_update_manifest(pkg: &mut PackageManifest, package: &ResolvedPackage, requested: &str) {
     let manifest_version = manifest_version_from_requested(requested, &package.version);
     match direct_request_kind_for_package(package) {
@@ -312,9 +1064,13 @@ fn download_error_to_phase(error: std::io::Error) -> std::io::Error {
 
 fn manifest_version_from_requested(requested: &str, resolved: &str) -> String {
     if requested == "latest" {
-        resolved.to_string()
-    } else {
-        requested.to_string()
+        return resolved.to_string();
+    }
+    match NpmAlias::parse(requested) {
+        Some(alias) if alias.range == "latest" => {
+            format!("npm:{}@{resolved}", alias.package_name)
+        }
+        _ => requested.to_string(),
     }
 }
 
@@ -323,11 +1079,26 @@ struct InstallMetadata {
     registries: HashMap<String, Registry>,
     locked_by_request: HashMap<(String, String), LockedInstallPackage>,
     locked_by_version: HashMap<(String, String), LockedInstallPackage>,
+    /// Version each fetched git or tarball request resolved to.
+    source_versions_by_request: HashMap<(String, String), String>,
+    source_packages_by_version: HashMap<(String, String), SourcePackage>,
+    deferred_source_requests: BTreeMap<(String, String), DependencyRequest>,
+    /// Registry package each `npm:` alias version installs.
+    aliases: HashMap<(String, String), String>,
+    /// Only registry versions published before this are selected.
+    before: Option<DateTime<Utc>>,
+    /// The `minimum-release-age` policy and the cutoff it sets for this run.
+    release_age: Option<(MinimumReleaseAge, DateTime<Utc>)>,
+    /// Where `--verify-signatures` reads its keys, when it is set.
+    signature_keys: Option<SignatureKeySource>,
 }
 
 impl InstallMetadata {
     fn from_lockfile(lockfile: &LockFile) -> Self {
-        let mut metadata = Self::default();
+        let mut metadata = Self {
+            before: lockfile.get_before(),
+            ..Self::default()
+        };
 
         for (key, dependency) in lockfile.get_packages() {
             let package_name = package_name_from_lock_key(key);
@@ -337,12 +1108,19 @@ impl InstallMetadata {
                 requested: dependency.get_requested(),
                 version: dependency.get_version(),
                 relationship: dependency.get_relationship(),
+                source: dependency.get_source(),
+                alias_of: dependency.get_alias_of(),
+                deprecated: dependency.get_deprecated(),
                 tarball: dependency.get_tarball(),
                 integrity: dependency.get_integrity(),
                 shasum: dependency.get_shasum(),
                 scripts: dependency.get_scripts(),
                 dependencies: dependency.get_dependencies(),
+                peer_dependencies: dependency.get_peer_dependencies(),
             };
+            if let Some(alias_of) = &locked_package.alias_of {
+                metadata.insert_alias(&package_name, &locked_package.version, alias_of.clone());
+            }
             metadata.locked_by_request.insert(
                 (package_name.clone(), locked_package.requested.clone()),
                 locked_package.clone(),
@@ -364,6 +1142,61 @@ impl InstallMetadata {
         self.registries.contains_key(package_name)
     }
 
+    fn insert_alias(&mut self, package_name: &str, version: &str, registry_name: String) {
+        self.aliases.insert(
+            (package_name.to_string(), version.to_string()),
+            registry_name,
+        );
+    }
+
+    /// The registry package `package_name` at `version` is: the alias target
+    /// for an `npm:` alias, otherwise the package itself.
+    fn registry_name<'a>(&'a self, package_name: &'a str, version: &str) -> &'a str {
+        self.aliases
+            .get(&(package_name.to_string(), version.to_string()))
+            .map(String::as_str)
+            .unwrap_or(package_name)
+    }
+
+    fn defer_source_request(&mut self, request: DependencyRequest) {
+        self.deferred_source_requests.insert(
+            (request.package_name.clone(), request.requested.clone()),
+            request,
+        );
+    }
+
+    fn take_deferred_source_requests(&mut self) -> Vec<DependencyRequest> {
+        std::mem::take(&mut self.deferred_source_requests)
+            .into_values()
+            .collect()
+    }
+
+    fn insert_source_package(
+        &mut self,
+        package_name: String,
+        requested: String,
+        package: SourcePackage,
+    ) {
+        self.source_versions_by_request
+            .insert((package_name.clone(), requested), package.version.clone());
+        self.source_packages_by_version
+            .insert((package_name, package.version.clone()), package);
+    }
+
+    fn has_source_package(&self, package_name: &str, requested: &str) -> bool {
+        self.source_versions_by_request
+            .contains_key(&(package_name.to_string(), requested.to_string()))
+    }
+
+    fn source_package_for_version(
+        &self,
+        package_name: &str,
+        version: &str,
+    ) -> Option<&SourcePackage> {
+        self.source_packages_by_version
+            .get(&(package_name.to_string(), version.to_string()))
+    }
+
     fn locked_package_for_request(
         &self,
         package_name: &str,
@@ -389,12 +1222,84 @@ impl InstallMetadata {
         package
             .requests
             .iter()
-            .find_map(|request| {
+            .filter_map(|request| {
                 self.locked_package_for_request(&package.package_name, &request.requested)
             })
+            // Deduplication may move a locked request onto another version.
+            .find(|locked| locked.version == package.version)
             .or_else(|| self.locked_package_for_version(&package.package_name, &package.version))
     }
 
+    /// The deprecation message of `package`: the locked one for a locked
+    /// package, otherwise the registry's. Packages from outside the registry
+    /// are never deprecated.
+    fn deprecation_for(&self, package: &ResolvedPackage) -> Option<String> {
+        if let Some(locked_package) = self.locked_package_for_resolved(package) {
+            return locked_package.deprecated.clone();
+        }
+        if self
+            .source_package_for_version(&package.package_name, &package.version)
+            .is_some()
+        {
+            return None;
+        }
+        let registry_name = self.registry_name(&package.package_name, &package.version);
+        self.registries
+            .get(registry_name)?
+            .get_deprecation_for_version(&package.version)
+            .map(str::to_string)
+    }
+
+    /// The newest publish time a version of registry package `package_name`
+    /// may have: the earlier of `--before` and the `minimum-release-age`
+    /// cutoff, unless the policy excludes the package.
+    fn cutoff_for(&self, package_name: &str) -> Option<DateTime<Utc>> {
+        let release_age_cutoff = self
+            .release_age
+            .as_ref()
+            .filter(|(policy, _)| !policy.excludes(package_name))
+            .map(|(_, cutoff)| *cutoff);
+        match (self.before, release_age_cutoff) {
+            (Some(before), Some(cutoff)) => Some(before.min(cutoff)),
+            (before, cutoff) => before.or(cutoff),
+        }
+    }
+
+    /// The newer version `package` would have resolved to without the
+    /// `minimum-release-age` policy, when the policy held it back: that
+    /// version was published at or after the policy's cutoff. A package that
+    /// deduplication or backtracking moved below an older newest version was
+    /// not held back. Locked, excluded and non-registry packages never are.
+    fn held_back_for(&self, package: &ResolvedPackage) -> Option<HeldBackPackage> {
+        let (policy, cutoff) = self.release_age.as_ref()?;
+        if policy.excludes(&package.package_name)
+            || self.locked_package_for_resolved(package).is_some()
+            || self
+                .source_package_for_version(&package.package_name, &package.version)
+                .is_some()
+        {
+            return None;
+        }
+        package.requests.iter().find_map(|request| {
+            let (registry_name, range) = registry_target(&package.package_name, &request.requested);
+            let registry = self.registries.get(&registry_name)?;
+            let newest = registry.select_version_before(&range, self.before).ok()?;
+            if newest == package.version {
+                return None;
+            }
+            let published = registry.time.as_ref()?.version(&newest)?;
+            if published < *cutoff {
+                return None;
+            }
+            Some(HeldBackPackage {
+                package_name: package.package_name.clone(),
+                version: package.version.clone(),
+                newest,
+                published,
+            })
+        })
+    }
+
     fn registry(&self, package_name: &str) -> Result<&Registry, ResolutionError> {
         self.registries
             .get(package_name)
@@ -417,10 +1322,40 @@ impl PackageMetadataProvider for InstallMetadata {
         if let Some(locked_package) = self.locked_package_for_request(package_name, requested) {
             return Ok(locked_package.version.clone());
         }
+        if let Some(version) = self
+            .source_versions_by_request
+            .get(&(package_name.to_string(), requested.to_string()))
+        {
+            return Ok(version.clone());
+        }
 
-        let registry = self.registry(package_name)?;
+        let (registry_name, range) = registry_target(package_name, requested);
+        let registry = self.registry(&registry_name)?;
         registry
-            .select_version(requested)
+            .select_version_before(&range, self.cutoff_for(&registry_name))
+            .map_err(|source| ResolutionError::version_selection(package_name, requested, source))
+    }
+
+    fn candidate_versions(
+        &self,
+        package_name: &str,
+        requested: &str,
+    ) -> Result<Vec<String>, ResolutionError> {
+        // Locked and source requests have exactly one answer.
+        if self
+            .locked_package_for_request(package_name, requested)
+            .is_some()
+            || self
+                .source_versions_by_request
+                .contains_key(&(package_name.to_string(), requested.to_string()))
+        {
+            return Ok(vec![self.select_version(package_name, requested)?]);
+        }
+
+        let (registry_name, range) = registry_target(package_name, requested);
+        let registry = self.registry(&registry_name)?;
+        registry
+            .candidate_versions_before(&range, self.cutoff_for(&registry_name))
             .map_err(|source| ResolutionError::version_selection(package_name, requested, source))
     }
 
@@ -437,14 +1372,58 @@ impl PackageMetadataProvider for InstallMetadata {
                 .map(DependencyDeclaration::from_spec)
                 .collect();
         }
+        if let Some(source_package) = self.source_package_for_version(package_name, version) {
+            return source_package
+                .dependencies
+                .iter()
+                .cloned()
+                .map(DependencyDeclaration::from_spec)
+                .collect();
+        }
 
-        let registry = self.registry(package_name)?;
+        let registry = self.registry(self.registry_name(package_name, version))?;
         registry
             .get_dependencies_for_version(version)
             .into_iter()
             .map(DependencyDeclaration::from_spec)
             .collect()
     }
+
+    fn peer_dependencies_for_version(
+        &self,
+        package_name: &str,
+        version: &str,
+    ) -> Result<Vec<PeerDeclaration>, ResolutionError> {
+        let peers =
+            if let Some(locked_package) = self.locked_package_for_version(package_name, version) {
+                locked_package.peer_dependencies.clone()
+            } else if self
+                .source_package_for_version(package_name, version)
+                .is_some()
+            {
+                Vec::new()
+            } else {
+                self.registry(self.registry_name(package_name, version))?
+                    .get_peer_dependencies_for_version(version)
+            };
+        peers
+            .into_iter()
+            .map(|(peer, optional)| PeerDeclaration::from_spec(peer, optional))
+            .collect()
+    }
+
+    fn is_deprecated(&self, package_name: &str, version: &str) -> bool {
+        if let Some(locked_package) = self.locked_package_for_version(package_name, version) {
+            return locked_package.deprecated.is_some();
+        }
+        let registry_name = self.registry_name(package_name, version);
+        self.source_package_for_version(package_name, version)
+            .is_none()
+            && self
+                .registries
+                .get(registry_name)
+                .is_some_and(|registry| registry.get_deprecation_for_version(version).is_some())
+    }
 }
 
 fn package_name_from_lock_key(key: &str) -> String {
@@ -457,25 +1436,24 @@ fn package_name_from_lock_key(key: &str) -> String {
 #[cfg(test)]
 mod tests {
     use super::{
-        add_with_cache_dir, direct_request_kind, manifest_version_from_requested,
-        populate_metadata, relationship_for_package, requested_for_lockfile,
-        resolution_error_to_io, InstallMetadata,
+        add_in, direct_request_kind, manifest_version_from_requested, populate_metadata,
+        relationship_for_package, requested_for_lockfile, resolution_error_to_io, InstallMetadata,
+        MetadataWalk,
     };
     use crate::{
-        api,
+        api::{self, test_support::FixtureRegistryClient},
         core::resolver::{
             resolve_dependency_graph, DependencyRequest, DependencyRequestKind, ResolutionError,
             ResolvedPackage, ResolvedRequest,
         },
-        lockfile::{LockFile, Relationship},
+        lockfile::{LockEntry, LockFile, Relationship},
         package_manifest::PackageManifest,
         registry::Registry,
         util::test_support::{fixture_path, TempProject},
     };
     use std::{
-        cell::RefCell,
+        cell::{Cell, RefCell},
         collections::HashMap,
-        ffi::OsString,
         fs, io,
         path::{Path, PathBuf},
         rc::Rc,
@@ -532,14 +1510,15 @@ mod tests {
         fs::create_dir_all(cache_path.parent().unwrap()).unwrap();
         fs::write(&cache_path, "not a directory").unwrap();
 
-        let _env = FixtureInstallEnv::new(&fixture_root.join("registry"));
-        let error = add_with_cache_dir(
+        let client = FixtureRegistryClient::new(&fixture_root.join("registry")).unwrap();
+        let error = add_in(
             &mut package_manifest,
             &mut lockfile,
             libs,
             false,
             false,
-            &cache_path,
+            project_root,
+            &client,
         )
         .await
         .unwrap_err();
@@ -568,16 +1547,22 @@ mod tests {
         let fetches_for_loader = Rc::clone(&fetches);
         let mut metadata = InstallMetadata::default();
 
-        populate_metadata(&mut metadata, &requests, |package_name| {
-            let root = root.clone();
-            let package_name = package_name.to_string();
-            let fetches = Rc::clone(&fetches_for_loader);
-            async move {
-                let count = fetches.borrow().get(&package_name).copied().unwrap_or(0) + 1;
-                fetches.borrow_mut().insert(package_name.clone(), count);
-                Ok(load_registry_fixture(&root, &package_name))
-            }
-        })
+        populate_metadata(
+            &mut metadata,
+            &requests,
+            &MetadataWalk::Selected,
+            4,
+            |package_name| {
+                let root = root.clone();
+                let package_name = package_name.to_string();
+                let fetches = Rc::clone(&fetches_for_loader);
+                async move {
+                    let count = fetches.borrow().get(&package_name).copied().unwrap_or(0) + 1;
+                    fetches.borrow_mut().insert(package_name.clone(), count);
+                    Ok(load_registry_fixture(&root, &package_name))
+                }
+            },
+        )
         .await
         .expect("metadata preload should succeed");
 
@@ -589,6 +1574,76 @@ mod tests {
         assert_eq!(fetches.borrow().get("@rpm-fixture/shared"), Some(&1));
     }
 
+    #[tokio::test]
+    async fn preload_overlaps_reads_up_to_the_concurrency_limit() {
+        let root = fixture_path(&["registry", "shared-transitive", "metadata"]);
+        let requests = vec![
+            DependencyRequest::new(
+                "@rpm-fixture/alpha",
+                "^1.0.0",
+                DependencyRequestKind::DirectProduction,
+            ),
+            DependencyRequest::new(
+                "@rpm-fixture/beta",
+                "^1.0.0",
+                DependencyRequestKind::DirectProduction,
+            ),
+        ];
+        let mut resolved_by_limit = Vec::new();
+
+        for (concurrency, expected_peak) in [(1, 1), (2, 2), (16, 2)] {
+            let in_flight = Rc::new(Cell::new(0usize));
+            let peak = Rc::new(Cell::new(0usize));
+            let fetches = Rc::new(RefCell::new(Vec::<String>::new()));
+            let mut metadata = InstallMetadata::default();
+
+            populate_metadata(
+                &mut metadata,
+                &requests,
+                &MetadataWalk::Selected,
+                concurrency,
+                |package_name| {
+                    let root = root.clone();
+                    let (in_flight, peak, fetches) =
+                        (Rc::clone(&in_flight), Rc::clone(&peak), Rc::clone(&fetches));
+                    async move {
+                        in_flight.set(in_flight.get() + 1);
+                        peak.set(peak.get().max(in_flight.get()));
+                        tokio::time::sleep(Duration::from_millis(5)).await;
+                        in_flight.set(in_flight.get() - 1);
+                        fetches.borrow_mut().push(package_name.clone());
+                        Ok(load_registry_fixture(&root, &package_name))
+                    }
+                },
+            )
+            .await
+            .expect("metadata preload should succeed");
+
+            assert_eq!(peak.get(), expected_peak, "concurrency {concurrency}");
+            let mut fetched = fetches.borrow().clone();
+            fetched.sort();
+            assert_eq!(
+                fetched,
+                vec![
+                    "@rpm-fixture/alpha",
+                    "@rpm-fixture/beta",
+                    "@rpm-fixture/shared"
+                ]
+            );
+            let graph = resolve_dependency_graph(requests.clone(), &metadata)
+                .expect("graph should resolve");
+            resolved_by_limit.push(
+                graph
+                    .packages()
+                    .iter()
+                    .map(|package| format!("{}@{}", package.package_name, package.version))
+                    .collect::<Vec<_>>(),
+            );
+        }
+
+        assert!(resolved_by_limit.windows(2).all(|pair| pair[0] == pair[1]));
+    }
+
     #[tokio::test]
     async fn apply_resolved_graph_downloads_shared_transitive_package_once() {
         let _guard = TestEnvLock::acquire().unwrap();
@@ -601,31 +1656,35 @@ mod tests {
         let project_root = package_path.parent().unwrap();
         let mut package_manifest = PackageManifest::read_from_path(&package_path).unwrap();
         let mut lockfile = LockFile::load_from_path(project_root.join("rpm.lock")).unwrap();
-        let libs = package_manifest
+        // In name order, as install passes them, so the shared package records
+        // alpha's range.
+        let mut libs = package_manifest
             .get_dependencies()
             .into_iter()
             .map(|(library_name, version)| format!("{library_name}@{version}"))
             .collect::<Vec<_>>();
-        let cache_dir = project_root.join(".rpm").join(".cache");
+        libs.sort();
 
-        let _env = FixtureInstallEnv::new(&fixture_root.join("registry"));
+        let client = FixtureRegistryClient::new(&fixture_root.join("registry")).unwrap();
         api::test_support::reset_tarball_download_counts();
-        add_with_cache_dir(
+        add_in(
             &mut package_manifest,
             &mut lockfile,
             libs,
             false,
             false,
-            &cache_dir,
+            project_root,
+            &client,
         )
         .await
         .expect("divergent range fixture should install offline");
 
-        // `@rpm-fixture/shared@1.0.0` is reached through two textually different
-        // requested ranges (`~1.0.0` via alpha, `^1.0.0` via beta) that converge
-        // on one selected version, so the deduped graph must download it exactly
-        // once. Keying dedupe on the requested range instead of the selected
-        // package/version would download it twice here.
+        // `@rpm-fixture/shared` is reached through two textually different
+        // requested ranges (`~1.0.0` via alpha, `^1.0.0` via beta). They select
+        // 1.0.0 and 1.1.0, which dedupe collapses onto 1.0.0 before fetching,
+        // so only 1.0.0 is downloaded, exactly once. Keying downloads on the
+        // requested range instead of the selected package/version would
+        // download it twice here.
         for package_key in [
             "@rpm-fixture/alpha@1.0.0",
             "@rpm-fixture/beta@1.0.0",
@@ -648,25 +1707,16 @@ mod tests {
                 ("@rpm-fixture/beta@1.0.0".to_string(), 1),
                 ("@rpm-fixture/shared@1.0.0".to_string(), 1),
             ],
-            "install of the divergent range fixture should download each selected \
+            "install of the divergent range fixture should download each kept \
              package/version exactly once and nothing else"
         );
 
         // Guard the fixture's *input* shape: alpha must declare
         // `@rpm-fixture/shared` as `~1.0.0` while beta declares it as `^1.0.0`,
-        // so the two requested range strings stay textually different while
-        // selecting version `1.0.0`. If the fixture ever collapsed to one
-        // shared range, the download assertions above would stop
-        // distinguishing version-keyed dedupe from range-keyed dedupe.
-        //
-        // This is deliberately asserted against the registry fixtures rather
-        // than the resolved lockfile. A package reached through several parents
-        // records only one parent's range in `requested`
-        // (`requested_for_lockfile` takes `requests.first()`), and which parent
-        // lands first follows the direct dependency order that
-        // `PackageManifest::get_dependencies` reads out of a `HashMap`, so it
-        // is not stable across processes. The lockfile also cannot detect a
-        // collapse to a single range, because it only ever records one winner.
+        // so the two requested range strings stay textually different. If the
+        // fixture ever collapsed to one shared range, the download assertions
+        // above would stop exercising dedupe. The lockfile cannot detect such a
+        // collapse, because it records only one parent's range in `requested`.
         assert_eq!(
             fixture_declared_range(
                 &fixture_root,
@@ -686,30 +1736,18 @@ mod tests {
             "fixture must keep beta requesting `@rpm-fixture/shared` on `^1.0.0`"
         );
 
-        // The direct dependency entries are keyed by their own package names and
-        // are unaffected by shared-transitive resolution order, so they stay
-        // exact. `@rpm-fixture/shared@1.0.0` is checked for presence only, for
-        // the ordering reason described above.
-        let mut direct_from_lock = lockfile
+        let mut from_lock = lockfile
             .get_packages()
             .into_iter()
-            .filter(|(key, _)| key.as_str() != "@rpm-fixture/shared@1.0.0")
             .map(|(key, dependency)| format!("{key} requested {}", dependency.get_requested()))
             .collect::<Vec<_>>();
-        direct_from_lock.sort();
-        let mut expected_direct =
+        from_lock.sort();
+        let mut expected =
             read_expected_lines(&fixture_root.join("expected").join("resolved-packages.txt"));
-        expected_direct.sort();
+        expected.sort();
         assert_eq!(
-            direct_from_lock, expected_direct,
-            "install must record the fixture's direct dependency entries in the lockfile"
-        );
-        assert!(
-            lockfile
-                .get_packages()
-                .into_iter()
-                .any(|(key, _)| key.as_str() == "@rpm-fixture/shared@1.0.0"),
-            "install must record the deduped shared transitive package in the lockfile"
+            from_lock, expected,
+            "install must record every fixture entry, including the deduped shared package"
         );
     }
 
@@ -730,22 +1768,22 @@ mod tests {
             .into_iter()
             .map(|(library_name, version)| format!("{library_name}@{version}"))
             .collect::<Vec<_>>();
-        let cache_dir = project_root.join(".rpm").join(".cache");
 
-        let _env = FixtureInstallEnv::new(&fixture_root.join("registry"));
+        let client = FixtureRegistryClient::new(&fixture_root.join("registry")).unwrap();
         api::test_support::reset_metadata_read_counts();
-        add_with_cache_dir(
+        add_in(
             &mut package_manifest,
             &mut lockfile,
             libs,
             false,
             false,
-            &cache_dir,
+            project_root,
+            &client,
         )
         .await
         .expect("divergent range fixture should install offline");
 
-        // `add_with_cache_dir` fetches each package's metadata exactly once:
+        // `add_in` fetches each package's metadata exactly once:
         // `populate_metadata` dedupes by package name before version selection,
         // and it loads metadata through `api::get_registry`, which the fake
         // registry harness records. This proves the measurement harness observes
@@ -805,11 +1843,17 @@ mod tests {
         )];
         let mut metadata = InstallMetadata::from_lockfile(&lockfile);
 
-        populate_metadata(&mut metadata, &requests, |package_name| {
-            let registry_root = registry_root.clone();
-            let package_name = package_name.to_string();
-            async move { Ok(load_registry_fixture(&registry_root, &package_name)) }
-        })
+        populate_metadata(
+            &mut metadata,
+            &requests,
+            &MetadataWalk::Selected,
+            4,
+            |package_name| {
+                let registry_root = registry_root.clone();
+                let package_name = package_name.to_string();
+                async move { Ok(load_registry_fixture(&registry_root, &package_name)) }
+            },
+        )
         .await
         .expect("locked fixture metadata should preload");
 
@@ -853,6 +1897,7 @@ mod tests {
                 },
             ],
             dependencies: Vec::new(),
+            peer_dependencies: Vec::new(),
         };
 
         assert_eq!(relationship_for_package(&package), Relationship::Dev);
@@ -871,16 +1916,14 @@ mod tests {
         ]))
         .unwrap();
         lockfile.add_dependency_entry(
-            &"shared@1.0.0".to_string(),
-            "shared".to_string(),
-            "^1.0.0".to_string(),
-            "1.0.0".to_string(),
-            Relationship::Direct,
-            None,
-            None,
-            None,
-            None,
-            &[],
+            "shared@1.0.0",
+            LockEntry {
+                name: "shared".to_string(),
+                requested: "^1.0.0".to_string(),
+                version: "1.0.0".to_string(),
+                relationship: Relationship::Direct,
+                ..Default::default()
+            },
         );
         let metadata = InstallMetadata::from_lockfile(&lockfile);
         let package = ResolvedPackage {
@@ -891,6 +1934,7 @@ mod tests {
                 kind: DependencyRequestKind::DirectDevelopment,
             }],
             dependencies: Vec::new(),
+            peer_dependencies: Vec::new(),
         };
 
         assert_eq!(relationship_for_package(&package), Relationship::Dev);
@@ -898,16 +1942,14 @@ mod tests {
         assert_eq!(requested, "^1.0.0".to_string());
 
         lockfile.add_dependency_entry(
-            &"shared@1.0.0".to_string(),
-            "shared".to_string(),
-            requested,
-            "1.0.0".to_string(),
-            relationship_for_package(&package),
-            None,
-            None,
-            None,
-            None,
-            &[],
+            "shared@1.0.0",
+            LockEntry {
+                name: "shared".to_string(),
+                requested,
+                version: "1.0.0".to_string(),
+                relationship: relationship_for_package(&package),
+                ..Default::default()
+            },
         );
         assert_eq!(
             lockfile
@@ -936,18 +1978,21 @@ mod tests {
             .into_iter()
             .map(|(library_name, version)| format!("{library_name}@{version}"))
             .collect::<Vec<_>>();
-        let _env = FixtureInstallEnv::new(&fixture_root.join("registry"));
+        let client = FixtureRegistryClient::new(&fixture_root.join("registry")).unwrap();
+        let project = TempProject::new("add-locked-records").unwrap();
 
         tokio::runtime::Builder::new_current_thread()
             .enable_all()
             .build()
             .unwrap()
-            .block_on(super::add(
+            .block_on(add_in(
                 &mut package_manifest,
                 &mut lockfile,
                 libs,
                 false,
                 false,
+                project.path(),
+                &client,
             ))
             .unwrap();
 
@@ -982,21 +2027,20 @@ mod tests {
             .into_iter()
             .map(|(library_name, version)| format!("{library_name}@{version}"))
             .collect::<Vec<_>>();
-        let _env = FixtureInstallEnv::new(&fixture_root.join("registry"));
-        let cache_dir =
-            std::env::temp_dir().join(format!("rpm-legacy-lock-cache-{}", std::process::id()));
+        let client = FixtureRegistryClient::new(&fixture_root.join("registry")).unwrap();
+        let project = TempProject::new("add-legacy-lock").unwrap();
 
-        add_with_cache_dir(
+        add_in(
             &mut package_manifest,
             &mut lockfile,
             libs,
             false,
             false,
-            &cache_dir,
+            project.path(),
+            &client,
         )
         .await
         .unwrap();
-        let _ = fs::remove_dir_all(cache_dir);
 
         assert_eq!(
             lockfile
@@ -1023,6 +2067,14 @@ mod tests {
         );
         assert_eq!(manifest_version_from_requested("^1.2.0", "1.4.0"), "^1.2.0");
         assert_eq!(manifest_version_from_requested("latest", "1.4.0"), "1.4.0");
+        assert_eq!(
+            manifest_version_from_requested("npm:bar@^1.2.0", "1.4.0"),
+            "npm:bar@^1.2.0"
+        );
+        assert_eq!(
+            manifest_version_from_requested("npm:bar", "1.4.0"),
+            "npm:bar@1.4.0"
+        );
     }
 
     struct TestEnvLock {
@@ -1049,27 +2101,4 @@ mod tests {
             let _ = fs::remove_dir(&self.path);
         }
     }
-
-    struct FixtureInstallEnv {
-        previous_fixture_root: Option<OsString>,
-    }
-
-    impl FixtureInstallEnv {
-        fn new(registry_root: &Path) -> Self {
-            let previous_fixture_root = std::env::var_os("RPM_REGISTRY_FIXTURE_ROOT");
-            std::env::set_var("RPM_REGISTRY_FIXTURE_ROOT", registry_root);
-            Self {
-                previous_fixture_root,
-            }
-        }
-    }
-
-    impl Drop for FixtureInstallEnv {
-        fn drop(&mut self) {
-            match &self.previous_fixture_root {
-                Some(value) => std::env::set_var("RPM_REGISTRY_FIXTURE_ROOT", value),
-                None => std::env::remove_var("RPM_REGISTRY_FIXTURE_ROOT"),
-            }
-        }
-    }
 }
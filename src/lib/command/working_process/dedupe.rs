@@ -0,0 +1,519 @@
+use std::{
+    collections::HashMap,
+    io::{Error, ErrorKind},
+    path::Path,
+};
+
+use crate::{
+    api::ResolutionStrategyKind,
+    command::working_process::install::InstallOptions,
+    core::resolver::{
+        dedupe_graph, registry_target, resolve_dependency_graph, semver,
+        BacktrackingResolutionStrategy, DependencyDeclaration, DependencyRequest,
+        DependencyRequestKind, PackageMetadataProvider, PeerDeclaration, ResolutionError,
+        ResolutionStrategy, ResolvedPackage,
+    },
+    lockfile::{Dependency, LockFile, Relationship},
+    package_manifest::PackageManifest,
+};
+
+/// What `rpm dedupe` changed in `rpm.lock`.
+#[derive(Debug, Default)]
+pub struct DedupeOutcome {
+    /// Entries kept, each now serving every request that it satisfies.
+    pub kept: usize,
+    /// Keys of the entries dropped, in lockfile order.
+    pub removed: Vec<String>,
+}
+
+/// `rpm dedupe`: move every request in the `rpm.lock` of the project at
+/// `project_root` onto the fewest locked versions of each package, and drop
+/// the entries nothing reaches any more. Resolution reads only the lockfile,
+/// so nothing is fetched; `node_modules` follows on the next install. The
+/// `.npmrc` `resolution-strategy` picks the resolver, as it does for install,
+/// and the backtracking one applies the manifest's `overrides`.
+pub fn dedupe(project_root: &Path) -> std::io::Result<DedupeOutcome> {
+    let package_manifest = PackageManifest::read_from_path(project_root.join("package.json"))?;
+    let lockfile_path = project_root.join("rpm.lock");
+    let mut lockfile = LockFile::load_from_path(&lockfile_path)?;
+    if lockfile.get_packages().is_empty() {
+        return Ok(DedupeOutcome::default());
+    }
+
+    let mut requests = Vec::new();
+    for (dependencies, kind) in [
+        (
+            package_manifest.get_dependencies(),
+            DependencyRequestKind::DirectProduction,
+        ),
+        (
+            package_manifest.get_dev_dependencies(),
+            DependencyRequestKind::DirectDevelopment,
+        ),
+    ] {
+        let mut dependencies = dependencies;
+        dependencies.sort();
+        for (package_name, requested) in dependencies {
+            requests.push(
+                DependencyRequest::from_spec(format!("{package_name}@{requested}"), kind)
+                    .map_err(resolution_error_to_io)?,
+            );
+        }
+    }
+    let metadata = LockedMetadata {
+        lockfile: &lockfile,
+    };
+    let graph = match InstallOptions::from_npmrc(project_root)?.resolution_strategy {
+        ResolutionStrategyKind::Fifo => resolve_dependency_graph(requests, &metadata)
+            .map(|graph| dedupe_graph(&graph, &metadata)),
+        ResolutionStrategyKind::Backtracking => BacktrackingResolutionStrategy::new()
+            .with_overrides(package_manifest.get_overrides())
+            .resolve(requests, &metadata),
+    }
+    .map_err(resolution_error_to_io)?;
+
+    // Install keys every entry `<name>@<version>`, so that is the entry a
+    // resolved package keeps; any other entry for the same version goes.
+    let mut keep = HashMap::new();
+    for package in graph.packages() {
+        let key = format!("{}@{}", package.package_name, package.version);
+        let locked_requested = lockfile
+            .get_dependency(&key)
+            .ok_or_else(|| missing_entry_error(&key))?
+            .get_requested();
+        keep.insert(
+            key,
+            (
+                requested_for(package, &locked_requested),
+                relationship_for(package),
+            ),
+        );
+    }
+    let removed = lockfile
+        .get_packages()
+        .into_iter()
+        .filter(|(key, _)| !keep.contains_key(*key))
+        .map(|(key, _)| key.clone())
+        .collect::<Vec<_>>();
+    let kept = keep.len();
+    lockfile.retain_packages(keep);
+    lockfile.save_to_path(&lockfile_path)?;
+    Ok(DedupeOutcome { kept, removed })
+}
+
+/// The range the entry for `package` records: its direct request if it has
+/// one, otherwise the range it was locked for while something still asks for
+/// it, otherwise the first range that reaches it.
+fn requested_for(package: &ResolvedPackage, locked_requested: &str) -> String {
+    let direct = [
+        DependencyRequestKind::DirectProduction,
+        DependencyRequestKind::DirectDevelopment,
+    ]
+    .into_iter()
+    .find_map(|kind| package.requests.iter().find(|request| request.kind == kind));
+    direct
+        .or_else(|| {
+            package
+                .requests
+                .iter()
+                .find(|request| request.requested == locked_requested)
+        })
+        .or_else(|| package.requests.first())
+        .map(|request| request.requested.clone())
+        .unwrap_or_else(|| package.version.clone())
+}
+
+fn relationship_for(package: &ResolvedPackage) -> Relationship {
+    let has = |kind| package.requests.iter().any(|request| request.kind == kind);
+    if has(DependencyRequestKind::DirectProduction) {
+        Relationship::Direct
+    } else if has(DependencyRequestKind::DirectDevelopment) {
+        Relationship::Dev
+    } else {
+        Relationship::Transitive
+    }
+}
+
+/// Package metadata read from `rpm.lock` alone. A request selects the entry
+/// locked for it, or else the highest locked registry version its range
+/// allows, and may fall back to the other locked versions that range allows;
+/// dependencies are the ones each entry records.
+struct LockedMetadata<'a> {
+    lockfile: &'a LockFile,
+}
+
+impl PackageMetadataProvider for LockedMetadata<'_> {
+    fn select_version(
+        &self,
+        package_name: &str,
+        requested: &str,
+    ) -> Result<String, ResolutionError> {
+        if let Some((_, dependency)) = self
+            .lockfile
+            .get_dependency_for_request(package_name, requested)
+        {
+            return Ok(dependency.get_version());
+        }
+
+        self.satisfying_versions(package_name, requested)?
+            .into_iter()
+            .next()
+            .ok_or_else(|| {
+                ResolutionError::version_selection(
+                    package_name,
+                    requested,
+                    semver::SemverError::UnsatisfiedRange {
+                        range: registry_target(package_name, requested).1,
+                    },
+                )
+            })
+    }
+
+    fn candidate_versions(
+        &self,
+        package_name: &str,
+        requested: &str,
+    ) -> Result<Vec<String>, ResolutionError> {
+        let mut versions = self.satisfying_versions(package_name, requested)?;
+        if let Some((_, dependency)) = self
+            .lockfile
+            .get_dependency_for_request(package_name, requested)
+        {
+            let locked = dependency.get_version();
+            versions.retain(|version| *version != locked);
+            versions.insert(0, locked);
+        }
+        Ok(versions)
+    }
+
+    fn dependencies_for_version(
+        &self,
+        package_name: &str,
+        version: &str,
+    ) -> Result<Vec<DependencyDeclaration>, ResolutionError> {
+        self.locked(package_name, version)?
+            .get_dependencies()
+            .into_iter()
+            .map(DependencyDeclaration::from_spec)
+            .collect()
+    }
+
+    fn peer_dependencies_for_version(
+        &self,
+        package_name: &str,
+        version: &str,
+    ) -> Result<Vec<PeerDeclaration>, ResolutionError> {
+        self.locked(package_name, version)?
+            .get_peer_dependencies()
+            .into_iter()
+            .map(|(peer, optional)| PeerDeclaration::from_spec(peer, optional))
+            .collect()
+    }
+
+    fn is_deprecated(&self, package_name: &str, version: &str) -> bool {
+        self.locked(package_name, version)
+            .is_ok_and(|dependency| dependency.get_deprecated().is_some())
+    }
+}
+
+impl LockedMetadata<'_> {
+    /// The locked registry versions of `package_name` that `requested`
+    /// allows, highest first.
+    fn satisfying_versions(
+        &self,
+        package_name: &str,
+        requested: &str,
+    ) -> Result<Vec<String>, ResolutionError> {
+        let (registry_name, range) = registry_target(package_name, requested);
+        let locked = self
+            .lockfile
+            .get_packages()
+            .into_iter()
+            .filter(|(_, dependency)| {
+                dependency.get_name() == package_name
+                    && dependency.get_source().is_none()
+                    && dependency
+                        .get_alias_of()
+                        .unwrap_or_else(|| package_name.to_string())
+                        == registry_name
+            })
+            .map(|(_, dependency)| dependency.get_version())
+            .collect::<Vec<_>>();
+        let selection_error =
+            |source| ResolutionError::version_selection(package_name, requested, source);
+        let range = range.parse::<semver::Range>().map_err(selection_error)?;
+        let satisfying = locked.iter().map(String::as_str).filter(|version| {
+            version
+                .parse::<semver::Version>()
+                .is_ok_and(|parsed| range.satisfies(&parsed))
+        });
+        let mut versions = semver::rsort(satisfying)
+            .map_err(selection_error)?
+            .into_iter()
+            .map(str::to_string)
+            .collect::<Vec<_>>();
+        versions.dedup();
+        Ok(versions)
+    }
+
+    fn locked(&self, package_name: &str, version: &str) -> Result<&Dependency, ResolutionError> {
+        self.lockfile
+            .get_packages()
+            .into_iter()
+            .find(|(_, dependency)| {
+                dependency.get_name() == package_name && dependency.get_version() == version
+            })
+            .map(|(_, dependency)| dependency)
+            .ok_or_else(|| ResolutionError::MissingMetadata {
+                package_name: package_name.to_string(),
+            })
+    }
+}
+
+fn missing_entry_error(key: &str) -> std::io::Error {
+    Error::new(
+        ErrorKind::InvalidData,
+        format!("rpm.lock has no entry {key}; run `rpm install` to bring rpm.lock up to date"),
+    )
+}
+
+fn resolution_error_to_io(error: ResolutionError) -> std::io::Error {
+    Error::new(
+        ErrorKind::InvalidData,
+        format!("resolve failed: {error}; run `rpm install` to bring rpm.lock up to date"),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{lockfile::LockEntry, util::test_support::TempProject};
+
+    #[test]
+    fn moves_requests_onto_the_fewest_locked_versions_and_drops_the_rest() {
+        let project = TempProject::new("dedupe-command").unwrap();
+        project
+            .write(
+                "package.json",
+                r#"{"name":"demo","version":"0.1.0","dependencies":{"a":"^1.0.0","b":"^1.0.0"},"devDependencies":{"shared":"^1.2.0"}}"#,
+            )
+            .unwrap();
+        let lockfile_path = project.path().join("rpm.lock");
+        let mut lockfile = LockFile::load_from_path(&lockfile_path).unwrap();
+        for (key, requested, relationship, dependencies) in [
+            (
+                "a@1.0.0",
+                "^1.0.0",
+                Relationship::Direct,
+                vec!["shared@^1.2.0"],
+            ),
+            (
+                "b@1.0.0",
+                "^1.0.0",
+                Relationship::Direct,
+                vec!["shared@^1.4.0"],
+            ),
+            (
+                "shared@1.2.0",
+                "^1.2.0",
+                Relationship::Dev,
+                vec!["old@^1.0.0"],
+            ),
+            ("shared@1.4.0", "^1.4.0", Relationship::Transitive, vec![]),
+            ("old@1.0.0", "^1.0.0", Relationship::Transitive, vec![]),
+        ] {
+            let (name, version) = key.rsplit_once('@').unwrap();
+            lockfile.add_dependency_entry(
+                key,
+                LockEntry {
+                    name: name.to_string(),
+                    requested: requested.to_string(),
+                    version: version.to_string(),
+                    relationship,
+                    dependencies: dependencies.into_iter().map(str::to_string).collect(),
+                    ..Default::default()
+                },
+            );
+        }
+        lockfile.save_to_path(&lockfile_path).unwrap();
+
+        let outcome = dedupe(project.path()).unwrap();
+
+        assert_eq!(outcome.kept, 3);
+        assert_eq!(outcome.removed, vec!["old@1.0.0", "shared@1.2.0"]);
+        let lockfile = LockFile::load_from_path(&lockfile_path).unwrap();
+        let shared = lockfile.get_dependency("shared@1.4.0").unwrap();
+        assert_eq!(shared.get_requested(), "^1.2.0");
+        assert_eq!(shared.get_relationship(), Relationship::Dev);
+        assert_eq!(
+            lockfile
+                .get_packages()
+                .into_iter()
+                .map(|(key, _)| key.as_str())
+                .collect::<Vec<_>>(),
+            vec!["a@1.0.0", "b@1.0.0", "shared@1.4.0"]
+        );
+
+        let again = dedupe(project.path()).unwrap();
+        assert_eq!(again.kept, 3);
+        assert!(again.removed.is_empty());
+    }
+
+    #[test]
+    fn keeps_the_entry_keyed_by_name_and_version_and_reports_a_missing_one() {
+        let project = TempProject::new("dedupe-keys").unwrap();
+        project
+            .write(
+                "package.json",
+                r#"{"name":"demo","version":"0.1.0","dependencies":{"a":"^1.0.0"}}"#,
+            )
+            .unwrap();
+        let lockfile_path = project.path().join("rpm.lock");
+        let write_entries = |keys: &[&str]| {
+            if lockfile_path.exists() {
+                std::fs::remove_file(&lockfile_path).unwrap();
+            }
+            let mut lockfile = LockFile::load_from_path(&lockfile_path).unwrap();
+            for key in keys {
+                lockfile.add_dependency_entry(
+                    key,
+                    LockEntry {
+                        name: "a".to_string(),
+                        requested: "^1.0.0".to_string(),
+                        version: "1.0.0".to_string(),
+                        relationship: Relationship::Direct,
+                        ..Default::default()
+                    },
+                );
+            }
+            lockfile.save_to_path(&lockfile_path).unwrap();
+        };
+
+        // Two entries for a@1.0.0 stay apart; only the canonical key is kept.
+        write_entries(&["a@1.0.0", "a@legacy"]);
+        let outcome = dedupe(project.path()).unwrap();
+        assert_eq!(outcome.kept, 1);
+        assert_eq!(outcome.removed, vec!["a@legacy"]);
+
+        write_entries(&["a@legacy"]);
+        let error = dedupe(project.path()).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+        assert!(
+            error.to_string().contains("rpm.lock has no entry a@1.0.0"),
+            "{error}"
+        );
+        let lockfile = LockFile::load_from_path(&lockfile_path).unwrap();
+        assert!(lockfile.get_dependency("a@legacy").is_some());
+    }
+
+    #[test]
+    fn runs_the_configured_strategy_with_the_manifest_overrides() {
+        let project = TempProject::new("dedupe-overrides").unwrap();
+        project
+            .write(
+                "package.json",
+                r#"{"name":"demo","version":"0.1.0","dependencies":{"left":"^1.0.0","right":"^1.0.0"},"overrides":{"shared":"2.0.0"}}"#,
+            )
+            .unwrap();
+        let lockfile_path = project.path().join("rpm.lock");
+        let mut lockfile = LockFile::load_from_path(&lockfile_path).unwrap();
+        // What a backtracking install locks: `right` asks for `shared@^1.0.0`
+        // but the override put it on 2.0.0.
+        for (key, requested, relationship, dependencies) in [
+            (
+                "left@1.0.0",
+                "^1.0.0",
+                Relationship::Direct,
+                vec!["shared@^2.0.0"],
+            ),
+            (
+                "right@1.0.0",
+                "^1.0.0",
+                Relationship::Direct,
+                vec!["shared@^1.0.0"],
+            ),
+            ("shared@2.0.0", "^2.0.0", Relationship::Transitive, vec![]),
+        ] {
+            let (name, version) = key.rsplit_once('@').unwrap();
+            lockfile.add_dependency_entry(
+                key,
+                LockEntry {
+                    name: name.to_string(),
+                    requested: requested.to_string(),
+                    version: version.to_string(),
+                    relationship,
+                    dependencies: dependencies.into_iter().map(str::to_string).collect(),
+                    ..Default::default()
+                },
+            );
+        }
+        lockfile.save_to_path(&lockfile_path).unwrap();
+        project
+            .write(".npmrc", "resolution-strategy=backtracking\n")
+            .unwrap();
+
+        let outcome = dedupe(project.path()).unwrap();
+
+        assert_eq!(outcome.kept, 3);
+        assert!(outcome.removed.is_empty());
+        let lockfile = LockFile::load_from_path(&lockfile_path).unwrap();
+        assert_eq!(
+            lockfile
+                .get_dependency("shared@2.0.0")
+                .unwrap()
+                .get_requested(),
+            "^2.0.0"
+        );
+
+        // FIFO ignores overrides, so nothing locked satisfies `right`.
+        project
+            .write(".npmrc", "resolution-strategy=fifo\n")
+            .unwrap();
+        let error = dedupe(project.path()).unwrap_err();
+        assert!(error.to_string().contains("resolve failed"), "{error}");
+    }
+
+    #[test]
+    fn keeps_entries_only_a_peer_reaches() {
+        let project = TempProject::new("dedupe-peers").unwrap();
+        project
+            .write(
+                "package.json",
+                r#"{"name":"demo","version":"0.1.0","dependencies":{"plugin":"^1.0.0"}}"#,
+            )
+            .unwrap();
+        let lockfile_path = project.path().join("rpm.lock");
+        let mut lockfile = LockFile::load_from_path(&lockfile_path).unwrap();
+        for (key, relationship) in [
+            ("plugin@1.0.0", Relationship::Direct),
+            ("host@1.0.0", Relationship::Transitive),
+        ] {
+            let (name, version) = key.rsplit_once('@').unwrap();
+            lockfile.add_dependency_entry(
+                key,
+                LockEntry {
+                    name: name.to_string(),
+                    requested: "^1.0.0".to_string(),
+                    version: version.to_string(),
+                    relationship,
+                    ..Default::default()
+                },
+            );
+        }
+        lockfile.set_peer_dependencies("plugin@1.0.0", &[("host@^1.0.0".to_string(), false)]);
+        lockfile.save_to_path(&lockfile_path).unwrap();
+
+        let outcome = dedupe(project.path()).unwrap();
+
+        assert_eq!(outcome.kept, 2);
+        assert!(outcome.removed.is_empty());
+        let lockfile = LockFile::load_from_path(&lockfile_path).unwrap();
+        assert_eq!(
+            lockfile
+                .get_dependency("plugin@1.0.0")
+                .unwrap()
+                .get_peer_dependencies(),
+            vec![("host@^1.0.0".to_string(), false)]
+        );
+    }
+}
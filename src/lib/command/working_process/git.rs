@@ -0,0 +1,96 @@
+use std::{
+    fs,
+    io::Error,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    api::RegistryClient,
+    command::working_process::{
+        install_in,
+        source::{unique_suffix, SourcePackage},
+    },
+    git,
+    lockfile::PackageSource,
+    pack::pack_project,
+    package_manifest::PackageManifest,
+    script_runner::{script_path, shell_command},
+};
+
+/// Check out `committish` of `url`, run its `prepare` script when it has one,
+/// and pack it.
+///
+/// `prepare` runs after the checkout's own dependencies and devDependencies
+/// are installed into it, the way npm prepares a git dependency.
+pub(crate) async fn build_git_package(
+    package_name: &str,
+    url: &str,
+    committish: &str,
+    client: &impl RegistryClient,
+) -> std::io::Result<SourcePackage> {
+    let checkout = Checkout::new()?;
+    let commit = git::checkout(url, committish, checkout.path()).await?;
+    let manifest_path = checkout.path().join("package.json");
+    let manifest = PackageManifest::read_from_path(&manifest_path)?;
+    if let Some(prepare) = manifest.get_scripts().get("prepare") {
+        // The nested install rewrites package.json; pack the one in the commit.
+        let committed_manifest = fs::read(&manifest_path)?;
+        Box::pin(install_in(checkout.path(), client))
+            .await
+            .map_err(|error| {
+                Error::new(
+                    error.kind(),
+                    format!("failed to install dependencies to prepare {package_name}: {error}"),
+                )
+            })?;
+        fs::write(&manifest_path, committed_manifest)?;
+        let status = tokio::process::Command::from(shell_command(prepare))
+            .current_dir(checkout.path())
+            .env("PATH", script_path(checkout.path())?)
+            .status()
+            .await?;
+        if !status.success() {
+            return Err(Error::other(format!(
+                "prepare script of {package_name} at {url}#{commit} failed with {status}"
+            )));
+        }
+    }
+
+    let packed = pack_project(checkout.path())?;
+    let mut package = SourcePackage::new(
+        PackageSource::Git,
+        git::locked_tarball(url, &commit),
+        &manifest,
+        packed.bytes,
+    )?;
+    // Re-packing the same commit need not reproduce the same bytes once
+    // `prepare` has run, so a git package locks its commit and no digest.
+    package.integrity = None;
+    package.shasum = None;
+    Ok(package)
+}
+
+/// A temporary clone directory, removed on drop.
+struct Checkout {
+    path: PathBuf,
+}
+
+impl Checkout {
+    fn new() -> std::io::Result<Self> {
+        let parent = std::env::temp_dir();
+        fs::create_dir_all(&parent)?;
+        Ok(Self {
+            path: parent.join(format!("rpm-git-{}", unique_suffix())),
+        })
+    }
+
+    fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for Checkout {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
@@ -0,0 +1,461 @@
+use std::{
+    fmt::Write as _,
+    io::{Error, ErrorKind},
+    path::Path,
+};
+
+use serde_json::{json, Map, Value};
+
+use crate::{
+    api::{HttpRegistryClient, RegistryClient},
+    core::resolver::semver,
+    registry::{AuthorType, Maintainer, Registry},
+    util::parse_library_name,
+};
+
+#[derive(Debug, Default)]
+pub struct InfoOptions {
+    /// Fields to print instead of the whole document, as dotted paths
+    /// (`dist.tarball`).
+    pub fields: Vec<String>,
+    /// `--json`: print JSON instead of the human-readable summary.
+    pub json: bool,
+}
+
+/// `rpm info <pkg>[@range] [field...]`: describe the registry metadata of the
+/// version of `spec` an install would select. Returns the text to print.
+pub async fn info(
+    project_root: &Path,
+    spec: &str,
+    options: &InfoOptions,
+) -> std::io::Result<String> {
+    let client = HttpRegistryClient::for_project(project_root)?;
+    info_with_client(spec, options, &client).await
+}
+
+pub(crate) async fn info_with_client(
+    spec: &str,
+    options: &InfoOptions,
+    client: &impl RegistryClient,
+) -> std::io::Result<String> {
+    let (name, requested) = parse_library_name(spec.trim().to_string());
+    let registry = client.get_full_registry(&name).await?;
+    let version = registry.select_version(&requested).map_err(|error| {
+        Error::new(
+            ErrorKind::NotFound,
+            format!("no version of {name} matches {requested:?}: {error}"),
+        )
+    })?;
+    let document = info_document(&registry, &version, &requested);
+    Ok(render(&document, options))
+}
+
+/// The `rpm info` view of `version` of a package, shaped like the package's
+/// registry document. `versions` lists the versions matching `requested` when
+/// it is a range, and every version otherwise.
+fn info_document(registry: &Registry, version: &str, requested: &str) -> Value {
+    let metadata = registry.version_metadata(version);
+    let is_tag = requested.is_empty()
+        || registry
+            .get_dist_tags()
+            .is_some_and(|tags| tags.contains_key(requested));
+    let mut versions = registry.get_sorted_versions();
+    if !is_tag {
+        versions.retain(|candidate| semver::satisfies(candidate, requested).unwrap_or(false));
+    }
+
+    let mut document = Map::new();
+    document.insert("name".to_string(), json!(registry.name));
+    document.insert("version".to_string(), json!(version));
+    let description = metadata
+        .and_then(|metadata| metadata.description.as_ref())
+        .or(registry.description.as_ref());
+    insert_some(&mut document, "description", description);
+    insert_some(&mut document, "license", registry.license.as_ref());
+    insert_some(
+        &mut document,
+        "homepage",
+        registry.homepage.as_ref().map(|homepage| &homepage.0),
+    );
+    insert_some(&mut document, "keywords", registry.keywords.as_ref());
+    insert_some(
+        &mut document,
+        "author",
+        registry.author.as_ref().map(author_text),
+    );
+    insert_some(&mut document, "dist-tags", registry.get_dist_tags());
+    document.insert("versions".to_string(), json!(versions));
+    let dependencies = match metadata {
+        Some(metadata) => metadata.dependencies.as_ref(),
+        None => registry.dependencies.as_ref(),
+    };
+    document.insert(
+        "dependencies".to_string(),
+        json!(dependencies.cloned().unwrap_or_default()),
+    );
+    if let Some(dist) = registry.get_dist_for_version(version) {
+        let mut fields = Map::new();
+        fields.insert("tarball".to_string(), json!(dist.tarball));
+        insert_some(&mut fields, "shasum", dist.shasum.as_ref());
+        insert_some(&mut fields, "integrity", dist.integrity.as_ref());
+        insert_some(&mut fields, "unpackedSize", dist.unpacked_size.as_ref());
+        insert_some(&mut fields, "fileCount", dist.file_count.as_ref());
+        document.insert("dist".to_string(), Value::Object(fields));
+    }
+    if let Some(maintainers) = &registry.maintainers {
+        let maintainers = maintainers.iter().map(maintainer_text).collect::<Vec<_>>();
+        document.insert("maintainers".to_string(), json!(maintainers));
+    }
+    if let Some(time) = &registry.time {
+        let mut times = Map::new();
+        times.insert("created".to_string(), json!(time.created().to_rfc3339()));
+        times.insert("modified".to_string(), json!(time.modified().to_rfc3339()));
+        for listed in &versions {
+            if let Some(published) = time.version(listed) {
+                times.insert(listed.clone(), json!(published.to_rfc3339()));
+            }
+        }
+        document.insert("time".to_string(), Value::Object(times));
+    }
+    Value::Object(document)
+}
+
+fn insert_some(document: &mut Map<String, Value>, key: &str, value: Option<impl serde::Serialize>) {
+    if let Some(value) = value {
+        document.insert(key.to_string(), json!(value));
+    }
+}
+
+/// `name <email> (url)`, the form npm prints people in.
+fn person_text(name: Option<&str>, email: Option<&str>, url: Option<&str>) -> String {
+    let mut text = name.unwrap_or_default().to_string();
+    if let Some(email) = email {
+        let _ = write!(text, " <{email}>");
+    }
+    if let Some(url) = url {
+        let _ = write!(text, " ({url})");
+    }
+    text.trim().to_string()
+}
+
+fn maintainer_text(maintainer: &Maintainer) -> String {
+    person_text(
+        maintainer.name.as_deref(),
+        maintainer.email.as_deref(),
+        maintainer.url.as_deref(),
+    )
+}
+
+fn author_text(author: &AuthorType) -> String {
+    match author {
+        AuthorType::String(author) => author.clone(),
+        AuthorType::Object(author) => person_text(
+            author.name.as_deref(),
+            author.email.as_deref(),
+            author.url.as_deref(),
+        ),
+    }
+}
+
+fn render(document: &Value, options: &InfoOptions) -> String {
+    let selected = options
+        .fields
+        .iter()
+        .filter_map(|field| Some((field.as_str(), select_field(document, field)?)))
+        .collect::<Vec<_>>();
+    match (options.fields.len(), options.json) {
+        (0, true) => pretty_json(document),
+        (0, false) => summary(document),
+        (1, true) => selected
+            .first()
+            .map(|(_, value)| pretty_json(value))
+            .unwrap_or_default(),
+        (1, false) => selected
+            .first()
+            .map(|(_, value)| match value {
+                Value::String(text) => text.clone(),
+                value => pretty_json(value),
+            })
+            .unwrap_or_default(),
+        (_, true) => {
+            let fields = selected
+                .into_iter()
+                .map(|(field, value)| (field.to_string(), value.clone()))
+                .collect::<Map<_, _>>();
+            pretty_json(&Value::Object(fields))
+        }
+        (_, false) => selected
+            .into_iter()
+            .map(|(field, value)| format!("{field} = {}", inline_text(value)))
+            .collect::<Vec<_>>()
+            .join("\n"),
+    }
+}
+
+/// The value at a dotted `field` path. A key that itself contains a dot, such
+/// as a version under `time`, is matched whole before the path is split.
+fn select_field<'a>(document: &'a Value, field: &str) -> Option<&'a Value> {
+    if let Some(value) = document.get(field) {
+        return Some(value);
+    }
+    let (head, rest) = field.split_once('.')?;
+    let mut prefix = head.to_string();
+    let mut rest = rest;
+    loop {
+        if let Some(value) = document.get(&prefix) {
+            if let Some(found) = select_field(value, rest) {
+                return Some(found);
+            }
+        }
+        let (next, remaining) = rest.split_once('.')?;
+        prefix = format!("{prefix}.{next}");
+        rest = remaining;
+    }
+}
+
+fn pretty_json(value: &Value) -> String {
+    serde_json::to_string_pretty(value).unwrap_or_default()
+}
+
+fn inline_text(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        value => value.to_string(),
+    }
+}
+
+/// The human-readable form of a document from [`info_document`].
+fn summary(document: &Value) -> String {
+    let text = |key: &str| document.get(key).and_then(Value::as_str);
+    let object = |key: &str| document.get(key).and_then(Value::as_object);
+    let versions = document
+        .get("versions")
+        .and_then(Value::as_array)
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+    let dependencies = object("dependencies").map(Map::len).unwrap_or_default();
+
+    let mut out = format!(
+        "{}@{} | {} | deps: {dependencies} | versions: {}",
+        text("name").unwrap_or_default(),
+        text("version").unwrap_or_default(),
+        text("license").unwrap_or("no license"),
+        versions.len(),
+    );
+    for key in ["description", "homepage"] {
+        if let Some(value) = text(key) {
+            let _ = write!(out, "\n{value}");
+        }
+    }
+    if let Some(keywords) = document.get("keywords").and_then(Value::as_array) {
+        let keywords = keywords.iter().map(inline_text).collect::<Vec<_>>();
+        let _ = write!(out, "\nkeywords: {}", keywords.join(", "));
+    }
+
+    if let Some(dist) = object("dist") {
+        out.push_str("\n\ndist");
+        for (key, value) in dist {
+            let value = match (key.as_str(), value.as_u64()) {
+                ("unpackedSize", Some(bytes)) => format_size(bytes),
+                _ => inline_text(value),
+            };
+            let _ = write!(out, "\n.{key}: {value}");
+        }
+    }
+    if let Some(dependencies) = object("dependencies").filter(|map| !map.is_empty()) {
+        out.push_str("\n\ndependencies:");
+        for (name, range) in dependencies {
+            let _ = write!(out, "\n{name}: {}", inline_text(range));
+        }
+    }
+    if let Some(maintainers) = document.get("maintainers").and_then(Value::as_array) {
+        if !maintainers.is_empty() {
+            out.push_str("\n\nmaintainers:");
+            for maintainer in maintainers {
+                let _ = write!(out, "\n- {}", inline_text(maintainer));
+            }
+        }
+    }
+    if let Some(tags) = object("dist-tags") {
+        out.push_str("\n\ndist-tags:");
+        for (tag, version) in tags {
+            let _ = write!(out, "\n{tag}: {}", inline_text(version));
+        }
+    }
+    if !versions.is_empty() {
+        let versions = versions.iter().map(inline_text).collect::<Vec<_>>();
+        let _ = write!(out, "\n\nversions:\n{}", versions.join(", "));
+    }
+
+    let published = object("time")
+        .zip(text("version"))
+        .and_then(|(time, version)| time.get(version))
+        .and_then(Value::as_str);
+    if let Some(published) = published {
+        let _ = write!(out, "\n\npublished {published}");
+        if let Some(author) = text("author") {
+            let _ = write!(out, " by {author}");
+        }
+    }
+    out
+}
+
+/// A byte count the way npm prints tarball sizes, in powers of 1000.
+fn format_size(bytes: u64) -> String {
+    match bytes {
+        0..=999 => format!("{bytes} B"),
+        1_000..=999_999 => format!("{:.1} kB", bytes as f64 / 1e3),
+        _ => format!("{:.1} MB", bytes as f64 / 1e6),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{api::DirectoryRegistryClient, util::test_support::TempProject};
+
+    const PACKUMENT: &str = r#"{
+      "name": "@scope/widget",
+      "description": "Widgets",
+      "license": "MIT",
+      "homepage": "https://widget.example",
+      "maintainers": [{ "name": "ada", "email": "ada@example.com" }],
+      "dist-tags": { "latest": "1.10.0", "next": "2.0.0-rc.1" },
+      "time": {
+        "created": "2020-01-01T00:00:00.000Z",
+        "modified": "2021-01-01T00:00:00.000Z",
+        "1.2.0": "2020-02-01T00:00:00.000Z",
+        "1.10.0": "2020-03-01T00:00:00.000Z",
+        "2.0.0-rc.1": "2020-12-01T00:00:00.000Z"
+      },
+      "versions": {
+        "1.2.0": {
+          "dependencies": { "left-pad": "^1.0.0" },
+          "dist": { "tarball": "https://host/@scope/widget/-/widget-1.2.0.tgz" }
+        },
+        "1.10.0": {
+          "description": "Widgets, faster",
+          "dependencies": { "left-pad": "^1.3.0", "chalk": "^4" },
+          "dist": {
+            "tarball": "https://host/@scope/widget/-/widget-1.10.0.tgz",
+            "shasum": "abc123",
+            "integrity": "sha512-xyz",
+            "unpackedSize": 15300,
+            "fileCount": 4
+          }
+        },
+        "2.0.0-rc.1": {
+          "dist": { "tarball": "https://host/@scope/widget/-/widget-2.0.0-rc.1.tgz" }
+        }
+      }
+    }"#;
+
+    async fn run_info(spec: &str, fields: &[&str], json: bool) -> std::io::Result<String> {
+        let registry = TempProject::new("info-registry").unwrap();
+        registry.write("@scope/widget.json", PACKUMENT).unwrap();
+        let options = InfoOptions {
+            fields: fields.iter().map(|field| field.to_string()).collect(),
+            json,
+        };
+        info_with_client(
+            spec,
+            &options,
+            &DirectoryRegistryClient::new(registry.path()),
+        )
+        .await
+    }
+
+    #[tokio::test]
+    async fn summarizes_the_selected_version() {
+        let output = run_info("@scope/widget", &[], false).await.unwrap();
+        assert_eq!(
+            output,
+            "@scope/widget@1.10.0 | MIT | deps: 2 | versions: 3\n\
+             Widgets, faster\n\
+             https://widget.example\n\
+             \n\
+             dist\n\
+             .fileCount: 4\n\
+             .integrity: sha512-xyz\n\
+             .shasum: abc123\n\
+             .tarball: https://host/@scope/widget/-/widget-1.10.0.tgz\n\
+             .unpackedSize: 15.3 kB\n\
+             \n\
+             dependencies:\n\
+             chalk: ^4\n\
+             left-pad: ^1.3.0\n\
+             \n\
+             maintainers:\n\
+             - ada <ada@example.com>\n\
+             \n\
+             dist-tags:\n\
+             latest: 1.10.0\n\
+             next: 2.0.0-rc.1\n\
+             \n\
+             versions:\n\
+             1.2.0, 1.10.0, 2.0.0-rc.1\n\
+             \n\
+             published 2020-03-01T00:00:00+00:00"
+        );
+    }
+
+    #[tokio::test]
+    async fn ranges_select_the_highest_match_and_list_every_match() {
+        let output = run_info("@scope/widget@<1.5", &["version", "versions"], true)
+            .await
+            .unwrap();
+        let value: Value = serde_json::from_str(&output).unwrap();
+        assert_eq!(value, json!({ "version": "1.2.0", "versions": ["1.2.0"] }));
+
+        let output = run_info("@scope/widget@next", &["version"], false)
+            .await
+            .unwrap();
+        assert_eq!(output, "2.0.0-rc.1");
+    }
+
+    #[tokio::test]
+    async fn selects_fields_by_dotted_path() {
+        assert_eq!(
+            run_info("@scope/widget", &["dist.tarball"], false)
+                .await
+                .unwrap(),
+            "https://host/@scope/widget/-/widget-1.10.0.tgz"
+        );
+        assert_eq!(
+            run_info("@scope/widget", &["time.1.10.0", "dist-tags.next"], false)
+                .await
+                .unwrap(),
+            "time.1.10.0 = 2020-03-01T00:00:00+00:00\ndist-tags.next = 2.0.0-rc.1"
+        );
+        let versions = run_info("@scope/widget", &["versions"], true)
+            .await
+            .unwrap();
+        assert_eq!(
+            serde_json::from_str::<Value>(&versions).unwrap(),
+            json!(["1.2.0", "1.10.0", "2.0.0-rc.1"])
+        );
+        assert_eq!(
+            run_info("@scope/widget", &["missing"], false)
+                .await
+                .unwrap(),
+            ""
+        );
+
+        let document = run_info("@scope/widget", &[], true).await.unwrap();
+        let document: Value = serde_json::from_str(&document).unwrap();
+        assert_eq!(document["dependencies"]["chalk"], "^4");
+        assert_eq!(document["maintainers"], json!(["ada <ada@example.com>"]));
+    }
+
+    #[tokio::test]
+    async fn reports_unmatched_ranges_and_missing_packages() {
+        let error = run_info("@scope/widget@^3", &[], false).await.unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::NotFound);
+        assert_eq!(
+            error.to_string(),
+            "no version of @scope/widget matches \"^3\": unsatisfied range ^3"
+        );
+        let error = run_info("absent", &[], false).await.unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::NotFound);
+    }
+}
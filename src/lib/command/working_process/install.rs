@@ -1,5 +1,13 @@
 use crate::{
-    command::working_process::add_with_cache_dir, lockfile::LockFile, node_linker::NodeModules,
+    api::{
+        HttpRegistryClient, MinimumReleaseAge, NetworkMode, RegistryClient, ResolutionStrategyKind,
+        SignatureKeySource,
+    },
+    command::working_process::{resolve_requests_in, InstallReport},
+    core::resolver::{DependencyRequest, DependencyRequestKind},
+    lockfile::{LockEntry, LockFile},
+    node_linker::NodeModules,
+    npmrc::NpmConfig,
     package_manifest::PackageManifest,
 };
 use std::{
@@ -9,45 +17,139 @@ use std::{
     time::{SystemTime, UNIX_EPOCH},
 };
 
-pub async fn install() -> std::io::Result<()> {
-    install_in(Path::new(".")).await
+use chrono::{DateTime, NaiveDate, Utc};
+
+/// Options for `rpm install`.
+#[derive(Debug, Clone, Default)]
+pub struct InstallOptions {
+    /// `--offline` / `--prefer-offline`: whether registry reads may reach the
+    /// network.
+    pub network_mode: NetworkMode,
+    /// `--network-concurrency`: tarball downloads kept in flight, or the
+    /// client default when unset.
+    pub network_concurrency: Option<usize>,
+    /// `--verify-signatures` / `--registry-keys`: where registry signing keys
+    /// come from, or `None` to skip signature verification.
+    pub signature_keys: Option<SignatureKeySource>,
+    /// `--before`: resolve only versions published before this instant, and
+    /// record it in `rpm.lock` for later installs. `None` keeps the cutoff
+    /// the lockfile already records, if any.
+    pub before: Option<DateTime<Utc>>,
+    /// `resolution-strategy` in `.npmrc`: the resolver the install runs.
+    pub resolution_strategy: ResolutionStrategyKind,
+    /// `minimum-release-age` and `minimum-release-age-exclude` in `.npmrc`:
+    /// registry versions younger than the policy allows are held back.
+    pub minimum_release_age: Option<MinimumReleaseAge>,
 }
 
-async fn install_in(project_root: &Path) -> std::io::Result<()> {
+impl InstallOptions {
+    /// The options `.npmrc` sets for the project at `project_root`, read from
+    /// the user and project files; the rest keep their defaults.
+    pub fn from_npmrc(project_root: &Path) -> std::io::Result<Self> {
+        let config = NpmConfig::load(project_root)?;
+        Ok(Self {
+            resolution_strategy: ResolutionStrategyKind::from_config(&config)?,
+            minimum_release_age: MinimumReleaseAge::from_config(&config)?,
+            ..Self::default()
+        })
+    }
+}
+
+/// Parse a `--before` value: an RFC 3339 timestamp, or a `YYYY-MM-DD` date
+/// meaning midnight UTC at its start.
+pub fn parse_before(value: &str) -> Result<DateTime<Utc>, String> {
+    let value = value.trim();
+    if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        return Ok(DateTime::from_utc(
+            date.and_hms_opt(0, 0, 0).unwrap_or_default(),
+            Utc,
+        ));
+    }
+    DateTime::parse_from_rfc3339(value)
+        .map(|before| before.with_timezone(&Utc))
+        .map_err(|_| {
+            format!("invalid date {value:?}: expected YYYY-MM-DD or an RFC 3339 timestamp")
+        })
+}
+
+pub async fn install(options: &InstallOptions) -> std::io::Result<InstallReport> {
+    install_with_options_in(Path::new("."), options).await
+}
+
+#[cfg(test)]
+async fn install_default_in(project_root: &Path) -> std::io::Result<InstallReport> {
+    install_with_options_in(project_root, &InstallOptions::default()).await
+}
+
+async fn install_with_options_in(
+    project_root: &Path,
+    options: &InstallOptions,
+) -> std::io::Result<InstallReport> {
+    let mut client =
+        HttpRegistryClient::for_project(project_root)?.with_network_mode(options.network_mode);
+    if let Some(network_concurrency) = options.network_concurrency {
+        client = client.with_download_concurrency(network_concurrency);
+    }
+    install_with_client_in(project_root, &client, options).await
+}
+
+/// Install the project at `project_root` from `client`: resolve and fetch its
+/// dependencies, run lifecycle scripts, and publish `node_modules`,
+/// `rpm.lock`, and `package.json` together. The report covers both the
+/// dependencies and the devDependencies.
+pub async fn install_in(
+    project_root: &Path,
+    client: &impl RegistryClient,
+) -> std::io::Result<InstallReport> {
+    install_with_client_in(project_root, client, &InstallOptions::default()).await
+}
+
+/// [`install_in`] under the resolution `options`. A `before` cutoff that
+/// differs from the one `rpm.lock` records re-resolves every package under
+/// it; `None` keeps the recorded cutoff. The network options are the
+/// client's and are not read here.
+pub(crate) async fn install_with_client_in(
+    project_root: &Path,
+    client: &impl RegistryClient,
+    options: &InstallOptions,
+) -> std::io::Result<InstallReport> {
     let package_path = project_root.join("package.json");
     let lockfile_path = project_root.join("rpm.lock");
     let cache_dir = project_root.join(".rpm").join(".cache");
     let node_modules_path = project_root.join("node_modules");
 
     let mut package_manifest = PackageManifest::read_from_path(&package_path)?;
-    let dependencies = package_manifest.get_dependencies();
+    let mut dependencies = package_manifest.get_dependencies();
+    dependencies.sort();
     let mut lockfile = LockFile::load_from_path(&lockfile_path)?;
-    let libs = dependencies
-        .iter()
-        .map(|(lib_name, version)| format!("{}@{}", lib_name, version))
-        .collect::<Vec<String>>();
-    add_with_cache_dir(
-        &mut package_manifest,
-        &mut lockfile,
-        libs,
-        false,
-        false,
-        &cache_dir,
-    )
-    .await?;
-
-    let dev_deps = package_manifest.get_dev_dependencies();
-    let dev_libs = dev_deps
+    if let Some(before) = options.before {
+        lockfile.set_before(before);
+    }
+    let mut dev_dependencies = package_manifest.get_dev_dependencies();
+    dev_dependencies.sort();
+    // Both lists resolve in one pass, in name order, so the graph does not
+    // depend on manifest key order and a peer either list declares is shared.
+    let requests = dependencies
         .iter()
-        .map(|(lib_name, version)| format!("{}@{}", lib_name, version))
-        .collect::<Vec<String>>();
-    add_with_cache_dir(
+        .map(|dependency| (dependency, DependencyRequestKind::DirectProduction))
+        .chain(
+            dev_dependencies
+                .iter()
+                .map(|dependency| (dependency, DependencyRequestKind::DirectDevelopment)),
+        )
+        .map(|((lib_name, version), kind)| {
+            DependencyRequest::from_spec(format!("{}@{}", lib_name, version), kind)
+        })
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|error| Error::new(ErrorKind::InvalidData, format!("resolve failed: {error}")))?;
+    let report = resolve_requests_in(
         &mut package_manifest,
         &mut lockfile,
-        dev_libs,
-        true,
+        requests,
         false,
-        &cache_dir,
+        project_root,
+        client,
+        options,
     )
     .await?;
 
@@ -120,7 +222,7 @@ async fn install_in(project_root: &Path) -> std::io::Result<()> {
         return Err(restore_after(&mut backups, error));
     }
     commit_install_state(backups)?;
-    Ok(())
+    Ok(report)
 }
 
 struct StateBackup {
@@ -348,176 +450,1619 @@ fn merge_generated_packages(hooked: &mut LockFile, generated: &LockFile) {
             .to_owned();
         hooked.add_dependency_entry(
             key,
-            package_name,
-            dependency.get_requested(),
-            dependency.get_version(),
-            dependency.get_relationship(),
-            dependency.get_tarball(),
-            dependency.get_integrity(),
-            dependency.get_shasum(),
-            dependency.get_scripts(),
-            &dependency.get_dependencies(),
+            LockEntry {
+                name: package_name,
+                requested: dependency.get_requested(),
+                version: dependency.get_version(),
+                relationship: dependency.get_relationship(),
+                source: dependency.get_source(),
+                alias_of: dependency.get_alias_of(),
+                deprecated: dependency.get_deprecated(),
+                tarball: dependency.get_tarball(),
+                integrity: dependency.get_integrity(),
+                shasum: dependency.get_shasum(),
+                scripts: dependency.get_scripts(),
+                dependencies: dependency.get_dependencies(),
+            },
+        );
+    }
+}
+
+fn commit_install_state(backups: Vec<StateBackup>) -> io::Result<()> {
+    for mut backup in backups {
+        if let Some(backup_path) = backup.backup_path.take() {
+            remove_path(&backup_path)?;
+        }
+    }
+    Ok(())
+}
+
+fn sibling_state_path(path: &Path, kind: &str) -> PathBuf {
+    let parent = path
+        .parent()
+        .filter(|path| !path.as_os_str().is_empty())
+        .unwrap_or_else(|| Path::new("."));
+    let file_name = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or("install-state");
+    parent.join(format!(".{file_name}.rpm-{kind}-{}", unique_suffix()))
+}
+
+fn unique_suffix() -> String {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_nanos())
+        .unwrap_or(0);
+    format!("{}-{nanos}", std::process::id())
+}
+
+fn remove_path(path: &Path) -> io::Result<()> {
+    let file_type = fs::symlink_metadata(path)?.file_type();
+    if file_type.is_dir() && !file_type.is_symlink() {
+        fs::remove_dir_all(path)
+    } else {
+        fs::remove_file(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        backup_install_state, capture_install_state, install_default_in, install_in,
+        install_with_client_in, install_with_options_in, parse_before, restore_snapshot_after,
+        InstallOptions,
+    };
+    use crate::{
+        api::{
+            bind_registry_listener, test_support::FixtureRegistryClient, CacheRegistryServer,
+            DirectoryRegistryClient, MinimumReleaseAge, NetworkMode, RegistryClient,
+            ResolutionStrategyKind, SignatureKeySource, TarballBody,
+        },
+        command::working_process::{add_in, run::run_script},
+        core::resolver::PeerProblem,
+        lockfile::{LockFile, PackageSource, Relationship},
+        package_manifest::PackageManifest,
+        registry::{Registry, RegistryKeys},
+        util::test_support::{
+            fixture_path, package_tarball, registry_signature, GitRepository, TempProject,
+            REGISTRY_KEY_ID,
+        },
+    };
+    use base64::{engine::general_purpose::STANDARD as BASE64_STANDARD, Engine as _};
+    use chrono::Utc;
+    use sha2::{Digest, Sha512};
+    use std::{
+        collections::BTreeMap,
+        fs,
+        future::Future,
+        io,
+        os::unix::fs::symlink,
+        os::unix::fs::PermissionsExt,
+        path::{Path, PathBuf},
+        sync::atomic::{AtomicUsize, Ordering},
+        thread,
+        time::Duration,
+    };
+
+    #[tokio::test]
+    async fn installs_performance_small_fixture_from_deterministic_inputs() {
+        let _guard = TestEnvLock::acquire().unwrap();
+        let repo_root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        let root_before = root_fingerprints(&repo_root).unwrap();
+        let fixture_root = fixture_path(&["install-projects", "performance-small"]);
+        let project = TempProject::new("performance-small-install").unwrap();
+        let package_path = project
+            .copy_fixture(fixture_root.join("package.json"), "package.json")
+            .unwrap();
+        let project_root = package_path.parent().unwrap();
+
+        let client = FixtureRegistryClient::new(&fixture_root.join("registry")).unwrap();
+        install_in(project_root, &client).await.unwrap();
+
+        let lock_path = project_root.join("rpm.lock");
+        let lock = LockFile::load_from_path(&lock_path).unwrap();
+        let expected = fs::read_to_string(fixture_root.join("expected/resolved-packages.txt"))
+            .unwrap()
+            .lines()
+            .map(str::to_owned)
+            .collect::<Vec<_>>();
+        assert_eq!(resolved_packages(&lock), expected);
+        assert_eq!(
+            lock.get_dependency("@rpm-fixture/alpha@1.0.0")
+                .map(|dependency| dependency.get_relationship()),
+            Some(Relationship::Direct)
+        );
+        assert_eq!(
+            lock.get_dependency("@rpm-fixture/beta@1.0.0")
+                .map(|dependency| dependency.get_relationship()),
+            Some(Relationship::Direct)
+        );
+        assert_eq!(
+            lock.get_dependency("@rpm-fixture/shared@1.0.0")
+                .map(|dependency| dependency.get_relationship()),
+            Some(Relationship::Transitive)
+        );
+
+        let package = PackageManifest::read_from_path(&package_path).unwrap();
+        assert_eq!(package.get_name(), "performance-small");
+        assert_eq!(
+            sorted_dependencies(package.get_dependencies()),
+            vec![
+                ("@rpm-fixture/alpha".to_string(), "^1.0.0".to_string()),
+                ("@rpm-fixture/beta".to_string(), "^1.0.0".to_string()),
+            ]
+        );
+
+        let node_modules = project_root.join("node_modules");
+        assert!(node_modules
+            .join("@rpm-fixture")
+            .join("alpha")
+            .join("package.json")
+            .is_file());
+        assert!(node_modules
+            .join("@rpm-fixture")
+            .join("beta")
+            .join("package.json")
+            .is_file());
+        assert!(node_modules
+            .join("@rpm-fixture")
+            .join("shared")
+            .join("package.json")
+            .is_file());
+        assert_eq!(
+            fs::read_link(
+                node_modules
+                    .join("@rpm-fixture")
+                    .join("alpha")
+                    .join("node_modules")
+                    .join("@rpm-fixture")
+                    .join("shared")
+            )
+            .unwrap(),
+            PathBuf::from("../../../../@rpm-fixture/shared")
+        );
+        assert_eq!(
+            fs::read_link(
+                node_modules
+                    .join("@rpm-fixture")
+                    .join("beta")
+                    .join("node_modules")
+                    .join("@rpm-fixture")
+                    .join("shared")
+            )
+            .unwrap(),
+            PathBuf::from("../../../../@rpm-fixture/shared")
+        );
+
+        assert_eq!(
+            sorted_cache_entries(&project_root.join(".rpm/.cache")).unwrap(),
+            vec![
+                "@rpm-fixture-alpha@1.0.0.tgz".to_string(),
+                "@rpm-fixture-beta@1.0.0.tgz".to_string(),
+                "@rpm-fixture-shared@1.0.0.tgz".to_string(),
+            ]
+        );
+        assert_eq!(root_fingerprints(&repo_root).unwrap(), root_before);
+    }
+
+    #[tokio::test]
+    async fn offline_install_uses_lockfile_and_cached_tarballs_only() {
+        let _guard = TestEnvLock::acquire().unwrap();
+        let fixture_root = fixture_path(&["install-projects", "performance-small"]);
+        let project = TempProject::new("offline-install").unwrap();
+        let package_path = project
+            .copy_fixture(fixture_root.join("package.json"), "package.json")
+            .unwrap();
+        let project_root = package_path.parent().unwrap();
+        {
+            let client = FixtureRegistryClient::new(&fixture_root.join("registry")).unwrap();
+            install_in(project_root, &client).await.unwrap();
+        }
+        let lock_path = project_root.join("rpm.lock");
+        let lock_before = fs::read(&lock_path).unwrap();
+        let offline = InstallOptions {
+            network_mode: NetworkMode::Offline,
+            ..InstallOptions::default()
+        };
+        fs::remove_dir_all(project_root.join("node_modules")).unwrap();
+
+        // No fixture registry is configured here, so any metadata or tarball
+        // request would fail: the install must come from local state alone.
+        install_with_options_in(project_root, &offline)
+            .await
+            .unwrap();
+
+        assert_eq!(fs::read(&lock_path).unwrap(), lock_before);
+        assert!(project_root
+            .join("node_modules/@rpm-fixture/shared/package.json")
+            .is_file());
+
+        fs::remove_file(project_root.join(".rpm/.cache/@rpm-fixture-beta@1.0.0.tgz")).unwrap();
+        fs::remove_file(project_root.join(".rpm/.cache/@rpm-fixture-shared@1.0.0.tgz")).unwrap();
+        let error = install_with_options_in(project_root, &offline)
+            .await
+            .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::NotFound);
+        assert_eq!(
+            error.to_string(),
+            "fetch failed: offline install is missing cached tarballs for: \
+             @rpm-fixture/beta@1.0.0, @rpm-fixture/shared@1.0.0"
+        );
+        assert_eq!(fs::read(&lock_path).unwrap(), lock_before);
+    }
+
+    #[tokio::test]
+    async fn install_in_runs_against_a_directory_registry() {
+        let registry = TempProject::new("directory-registry-install").unwrap();
+        for (name, version, dependencies) in [
+            ("@corp/app-kit", "1.2.0", r#"{"left-pad":"^1.0.0"}"#),
+            ("left-pad", "1.3.0", "{}"),
+        ] {
+            let basename = name.rsplit('/').next().unwrap();
+            let tarball_path = format!("{name}/-/{basename}-{version}.tgz");
+            let tarball = package_tarball(&[(
+                "package.json",
+                &format!(r#"{{"name":"{name}","version":"{version}"}}"#),
+            )]);
+            let integrity = format!(
+                "sha512-{}",
+                BASE64_STANDARD.encode(Sha512::digest(&tarball))
+            );
+            registry.write(&tarball_path, &tarball).unwrap();
+            registry
+                .write(
+                    format!("{name}.json"),
+                    format!(
+                        r#"{{"name":"{name}","dist-tags":{{"latest":"{version}"}},"versions":{{"{version}":{{"name":"{name}","version":"{version}","dependencies":{dependencies},"dist":{{"tarball":"https://registry.example.invalid/{tarball_path}","integrity":"{integrity}"}}}}}}}}"#
+                    ),
+                )
+                .unwrap();
+        }
+        let project = TempProject::new("directory-registry-project").unwrap();
+        project
+            .write(
+                "package.json",
+                r#"{"name":"directory-app","version":"0.0.0","dependencies":{"@corp/app-kit":"^1.0.0"}}"#,
+            )
+            .unwrap();
+        let client = DirectoryRegistryClient::new(registry.path());
+
+        install_in(project.path(), &client).await.unwrap();
+
+        let lock = LockFile::load_from_path(project.path().join("rpm.lock")).unwrap();
+        assert_eq!(
+            resolved_packages(&lock),
+            vec![
+                "@corp/app-kit@1.2.0 requested ^1.0.0",
+                "left-pad@1.3.0 requested ^1.0.0"
+            ]
+        );
+        let installed = fs::read_to_string(
+            project
+                .path()
+                .join("node_modules/@corp/app-kit/package.json"),
+        )
+        .unwrap();
+        assert!(installed.contains(r#""version":"1.2.0""#));
+        assert!(project
+            .path()
+            .join("node_modules/left-pad/package.json")
+            .is_file());
+    }
+
+    /// Serves `abbreviated/` documents as abbreviated packuments and `full/`
+    /// ones on request, counting the full documents read.
+    struct AbbreviatedRegistryClient {
+        abbreviated: DirectoryRegistryClient,
+        full: DirectoryRegistryClient,
+        full_reads: AtomicUsize,
+    }
+
+    impl RegistryClient for AbbreviatedRegistryClient {
+        fn network_mode(&self) -> NetworkMode {
+            self.full.network_mode()
+        }
+
+        fn download_concurrency(&self) -> usize {
+            self.full.download_concurrency()
+        }
+
+        fn get_registry(
+            &self,
+            lib_name: &str,
+        ) -> impl Future<Output = io::Result<Registry>> + Send {
+            let registry = self.abbreviated.get_registry(lib_name);
+            async move {
+                let mut registry = registry.await?;
+                registry.mark_abbreviated();
+                Ok(registry)
+            }
+        }
+
+        fn get_full_registry(
+            &self,
+            lib_name: &str,
+        ) -> impl Future<Output = io::Result<Registry>> + Send {
+            self.full_reads.fetch_add(1, Ordering::SeqCst);
+            self.full.get_registry(lib_name)
+        }
+
+        fn get_tarball(
+            &self,
+            tarball_url: &str,
+        ) -> impl Future<Output = io::Result<TarballBody>> + Send {
+            self.full.get_tarball(tarball_url)
+        }
+
+        fn get_registry_keys(&self) -> impl Future<Output = io::Result<RegistryKeys>> + Send {
+            self.full.get_registry_keys()
+        }
+    }
+
+    #[tokio::test]
+    async fn fetches_the_full_document_only_when_the_locked_version_has_install_scripts() {
+        let registry = TempProject::new("abbreviated-registry").unwrap();
+        // `native` locks the version with an install script; `tool` locks one
+        // without, though an older version has one.
+        for (name, locked, older) in [("native", true, false), ("tool", false, true)] {
+            let mut abbreviated = Vec::new();
+            let mut full = Vec::new();
+            for (version, install_script) in [("1.0.0", older), ("1.1.0", locked)] {
+                let tarball_path = format!("{name}/-/{name}-{version}.tgz");
+                registry
+                    .write(
+                        format!("full/{tarball_path}"),
+                        package_tarball(&[(
+                            "package.json",
+                            &format!(r#"{{"name":"{name}","version":"{version}"}}"#),
+                        )]),
+                    )
+                    .unwrap();
+                let dist = format!(
+                    r#""dist":{{"tarball":"https://registry.example.invalid/{tarball_path}"}}"#
+                );
+                let (flag, scripts) = if install_script {
+                    (
+                        r#""hasInstallScript":true,"#,
+                        r#""scripts":{"postinstall":"exit 0"},"#,
+                    )
+                } else {
+                    ("", "")
+                };
+                abbreviated.push(format!(r#""{version}":{{{flag}{dist}}}"#));
+                full.push(format!(
+                    r#""{version}":{{"name":"{name}","version":"{version}",{scripts}{dist}}}"#
+                ));
+            }
+            for (layout, versions) in [("abbreviated", abbreviated), ("full", full)] {
+                registry
+                    .write(
+                        format!("{layout}/{name}.json"),
+                        format!(
+                            r#"{{"name":"{name}","dist-tags":{{"latest":"1.1.0"}},"versions":{{{}}}}}"#,
+                            versions.join(",")
+                        ),
+                    )
+                    .unwrap();
+            }
+        }
+        let project = TempProject::new("abbreviated-registry-project").unwrap();
+        project
+            .write(
+                "package.json",
+                r#"{"name":"abbreviated-app","version":"0.0.0","dependencies":{"native":"^1.0.0","tool":"^1.0.0"}}"#,
+            )
+            .unwrap();
+        let client = AbbreviatedRegistryClient {
+            abbreviated: DirectoryRegistryClient::new(registry.path().join("abbreviated")),
+            full: DirectoryRegistryClient::new(registry.path().join("full")),
+            full_reads: AtomicUsize::new(0),
+        };
+
+        install_in(project.path(), &client).await.unwrap();
+
+        // Only `native` needed its full document, for the scripts it locks.
+        assert_eq!(client.full_reads.load(Ordering::SeqCst), 1);
+        let lock = LockFile::load_from_path(project.path().join("rpm.lock")).unwrap();
+        let scripts = |key: &str| {
+            lock.get_packages()
+                .into_iter()
+                .find(|(candidate, _)| candidate.as_str() == key)
+                .and_then(|(_, dependency)| dependency.get_scripts())
+                .unwrap()
+        };
+        assert_eq!(
+            scripts("native@1.1.0"),
+            [("postinstall".to_string(), "exit 0".to_string())].into()
+        );
+        assert!(scripts("tool@1.1.0").is_empty());
+    }
+
+    #[tokio::test]
+    async fn installs_git_dependencies_at_the_locked_commit() {
+        let registry = TempProject::new("git-dependency-registry").unwrap();
+        let tarball =
+            package_tarball(&[("package.json", r#"{"name":"left-pad","version":"1.3.0"}"#)]);
+        let integrity = format!(
+            "sha512-{}",
+            BASE64_STANDARD.encode(Sha512::digest(&tarball))
+        );
+        registry
+            .write("left-pad/-/left-pad-1.3.0.tgz", &tarball)
+            .unwrap();
+        registry
+            .write(
+                "left-pad.json",
+                format!(
+                    r#"{{"name":"left-pad","dist-tags":{{"latest":"1.3.0"}},"versions":{{"1.3.0":{{"name":"left-pad","version":"1.3.0","dist":{{"tarball":"https://registry.example.invalid/left-pad/-/left-pad-1.3.0.tgz","integrity":"{integrity}"}}}}}}}}"#
+                ),
+            )
+            .unwrap();
+        let client = DirectoryRegistryClient::new(registry.path());
+
+        let remote = TempProject::new("git-dependency-remote").unwrap();
+        let repo = GitRepository::new(remote.path());
+        let manifest = |version: &str| {
+            format!(
+                r#"{{"name":"lib","version":"{version}","dependencies":{{"left-pad":"^1.0.0"}}}}"#
+            )
+        };
+        repo.commit(&[("package.json", &manifest("1.0.0"))], Some("v1.0.0"));
+        let locked_commit = repo.commit(
+            &[("package.json", &manifest("1.4.0")), ("index.js", "")],
+            Some("v1.4.0"),
+        );
+        let requested = format!("git+{}#semver:^1.0.0", repo.url());
+
+        let project = TempProject::new("git-dependency-project").unwrap();
+        project
+            .write(
+                "package.json",
+                format!(
+                    r#"{{"name":"git-app","version":"0.0.0","dependencies":{{"lib":"{requested}"}}}}"#
+                ),
+            )
+            .unwrap();
+        install_in(project.path(), &client).await.unwrap();
+
+        let lock_path = project.path().join("rpm.lock");
+        let lock = LockFile::load_from_path(&lock_path).unwrap();
+        assert_eq!(
+            resolved_packages(&lock),
+            vec![
+                "left-pad@1.3.0 requested ^1.0.0".to_string(),
+                format!("lib@1.4.0 requested {requested}"),
+            ]
+        );
+        let (_, lib) = lock
+            .get_packages()
+            .into_iter()
+            .find(|(key, _)| key.as_str() == "lib@1.4.0")
+            .unwrap();
+        assert_eq!(
+            lib.get_tarball(),
+            Some(format!("git+{}#{locked_commit}", repo.url()))
+        );
+        assert_eq!(lib.get_source(), Some(PackageSource::Git));
+        assert_eq!(lib.get_integrity(), None);
+        assert!(project.path().join("node_modules/lib/index.js").is_file());
+        assert!(project
+            .path()
+            .join("node_modules/left-pad/package.json")
+            .is_file());
+
+        // A newer matching tag does not move a locked install, and an empty
+        // cache is refilled by re-packing the locked commit.
+        repo.commit(&[("package.json", &manifest("1.5.0"))], Some("v1.5.0"));
+        let lock_before = fs::read(&lock_path).unwrap();
+        fs::remove_dir_all(project.path().join("node_modules")).unwrap();
+        fs::remove_dir_all(project.path().join(".rpm")).unwrap();
+        install_in(project.path(), &client).await.unwrap();
+
+        assert_eq!(fs::read(&lock_path).unwrap(), lock_before);
+        let installed =
+            fs::read_to_string(project.path().join("node_modules/lib/package.json")).unwrap();
+        assert!(installed.contains(r#""version":"1.4.0""#));
+    }
+
+    #[tokio::test]
+    async fn git_dependencies_run_prepare_before_packing() {
+        let remote = TempProject::new("git-prepare-remote").unwrap();
+        let repo = GitRepository::new(remote.path());
+        repo.commit(
+            &[(
+                "package.json",
+                r#"{"name":"built","version":"0.1.0","files":["dist"],
+                    "scripts":{"prepare":"mkdir -p dist && echo built > dist/index.js && echo $$ > dist/build-id"}}"#,
+            )],
+            None,
+        );
+        let project = TempProject::new("git-prepare-project").unwrap();
+        project
+            .write(
+                "package.json",
+                format!(
+                    r#"{{"name":"git-app","version":"0.0.0","dependencies":{{"built":"git+{}"}}}}"#,
+                    repo.url()
+                ),
+            )
+            .unwrap();
+        let registry = TempProject::new("git-prepare-registry").unwrap();
+        let client = DirectoryRegistryClient::new(registry.path());
+
+        install_in(project.path(), &client).await.unwrap();
+
+        assert_eq!(
+            fs::read_to_string(project.path().join("node_modules/built/dist/index.js")).unwrap(),
+            "built\n"
+        );
+        let build_id = |project: &TempProject| {
+            fs::read_to_string(project.path().join("node_modules/built/dist/build-id")).unwrap()
+        };
+        let first_build = build_id(&project);
+
+        // Re-preparing the locked commit on a cache miss packs different
+        // bytes, which a git package's lock entry does not pin.
+        fs::remove_dir_all(project.path().join("node_modules")).unwrap();
+        fs::remove_dir_all(project.path().join(".rpm")).unwrap();
+        install_in(project.path(), &client).await.unwrap();
+
+        assert_ne!(build_id(&project), first_build);
+    }
+
+    #[tokio::test]
+    async fn installs_remote_and_local_tarball_dependencies() {
+        let registry = TempProject::new("tarball-dependency-registry").unwrap();
+        let left_pad =
+            package_tarball(&[("package.json", r#"{"name":"left-pad","version":"1.3.0"}"#)]);
+        let integrity = format!(
+            "sha512-{}",
+            BASE64_STANDARD.encode(Sha512::digest(&left_pad))
+        );
+        registry
+            .write("left-pad/-/left-pad-1.3.0.tgz", &left_pad)
+            .unwrap();
+        registry
+            .write(
+                "left-pad.json",
+                format!(
+                    r#"{{"name":"left-pad","dist-tags":{{"latest":"1.3.0"}},"versions":{{"1.3.0":{{"name":"left-pad","version":"1.3.0","dist":{{"tarball":"https://registry.example.invalid/left-pad/-/left-pad-1.3.0.tgz","integrity":"{integrity}"}}}}}}}}"#
+                ),
+            )
+            .unwrap();
+        let remote = package_tarball(&[
+            (
+                "package.json",
+                r#"{"name":"foo","version":"1.0.0","dependencies":{"left-pad":"^1.0.0"}}"#,
+            ),
+            ("index.js", ""),
+        ]);
+        registry.write("files/foo-1.0.0.tgz", &remote).unwrap();
+        let client = DirectoryRegistryClient::new(registry.path());
+
+        let project = TempProject::new("tarball-dependency-project").unwrap();
+        let local = package_tarball(&[("package.json", r#"{"name":"bar","version":"2.0.0"}"#)]);
+        project.write("vendor/bar-2.0.0.tgz", &local).unwrap();
+        let remote_url = "https://tarballs.example.invalid/files/foo-1.0.0.tgz";
+        project
+            .write(
+                "package.json",
+                format!(
+                    r#"{{"name":"tarball-app","version":"0.0.0","dependencies":{{"foo":"{remote_url}","bar":"file:vendor/bar-2.0.0.tgz"}}}}"#
+                ),
+            )
+            .unwrap();
+        install_in(project.path(), &client).await.unwrap();
+
+        let lock_path = project.path().join("rpm.lock");
+        let lock = LockFile::load_from_path(&lock_path).unwrap();
+        assert_eq!(
+            resolved_packages(&lock),
+            vec![
+                "bar@2.0.0 requested file:vendor/bar-2.0.0.tgz".to_string(),
+                format!("foo@1.0.0 requested {remote_url}"),
+                "left-pad@1.3.0 requested ^1.0.0".to_string(),
+            ]
+        );
+        let packages = lock.get_packages();
+        let locked = |key: &str| {
+            packages
+                .iter()
+                .find(|(candidate, _)| candidate.as_str() == key)
+                .map(|(_, dependency)| {
+                    (
+                        dependency.get_source(),
+                        dependency.get_tarball(),
+                        dependency.get_integrity(),
+                    )
+                })
+                .unwrap()
+        };
+        let sri = |bytes: &[u8]| {
+            Some(format!(
+                "sha512-{}",
+                BASE64_STANDARD.encode(Sha512::digest(bytes))
+            ))
+        };
+        assert_eq!(
+            locked("foo@1.0.0"),
+            (
+                Some(PackageSource::Remote),
+                Some(remote_url.to_string()),
+                sri(&remote)
+            )
+        );
+        assert_eq!(
+            locked("bar@2.0.0"),
+            (
+                Some(PackageSource::File),
+                Some("file:vendor/bar-2.0.0.tgz".to_string()),
+                sri(&local)
+            )
+        );
+        assert_eq!(locked("left-pad@1.3.0").0, None);
+        assert!(project.path().join("node_modules/foo/index.js").is_file());
+        assert!(project
+            .path()
+            .join("node_modules/bar/package.json")
+            .is_file());
+
+        // A clean install refetches both tarballs from the lock, and refuses
+        // one whose bytes no longer match the locked integrity.
+        let lock_before = fs::read(&lock_path).unwrap();
+        fs::remove_dir_all(project.path().join("node_modules")).unwrap();
+        fs::remove_dir_all(project.path().join(".rpm")).unwrap();
+        install_in(project.path(), &client).await.unwrap();
+        assert_eq!(fs::read(&lock_path).unwrap(), lock_before);
+        assert!(project.path().join("node_modules/foo/index.js").is_file());
+
+        project
+            .write(
+                "vendor/bar-2.0.0.tgz",
+                package_tarball(&[(
+                    "package.json",
+                    r#"{"name":"bar","version":"2.0.0","description":"changed"}"#,
+                )]),
+            )
+            .unwrap();
+        fs::remove_dir_all(project.path().join(".rpm")).unwrap();
+        let error = install_in(project.path(), &client).await.unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("integrity check failed for bar@2.0.0"));
+    }
+
+    #[tokio::test]
+    async fn installs_file_and_link_directory_dependencies() {
+        let registry = TempProject::new("directory-dependency-registry").unwrap();
+        let left_pad =
+            package_tarball(&[("package.json", r#"{"name":"left-pad","version":"1.3.0"}"#)]);
+        let integrity = format!(
+            "sha512-{}",
+            BASE64_STANDARD.encode(Sha512::digest(&left_pad))
+        );
+        registry
+            .write("left-pad/-/left-pad-1.3.0.tgz", &left_pad)
+            .unwrap();
+        registry
+            .write(
+                "left-pad.json",
+                format!(
+                    r#"{{"name":"left-pad","dist-tags":{{"latest":"1.3.0"}},"versions":{{"1.3.0":{{"name":"left-pad","version":"1.3.0","dist":{{"tarball":"https://registry.example.invalid/left-pad/-/left-pad-1.3.0.tgz","integrity":"{integrity}"}}}}}}}}"#
+                ),
+            )
+            .unwrap();
+        let client = DirectoryRegistryClient::new(registry.path());
+
+        let project = TempProject::new("directory-dependency-project").unwrap();
+        project
+            .write(
+                "packages/shared/package.json",
+                r#"{"name":"shared","version":"1.0.0","files":["index.js"],"dependencies":{"left-pad":"^1.0.0"}}"#,
+            )
+            .unwrap();
+        project.write("packages/shared/index.js", "v1").unwrap();
+        project.write("packages/shared/notes.txt", "").unwrap();
+        project
+            .write(
+                "packages/tool/package.json",
+                r#"{"name":"tool","version":"0.2.0","bin":"cli.js","dependencies":{"left-pad":"^1.0.0"}}"#,
+            )
+            .unwrap();
+        project.write("packages/tool/cli.js", "").unwrap();
+        project
+            .write(
+                "package.json",
+                r#"{"name":"directory-app","version":"0.0.0","dependencies":{"shared":"file:packages/shared","tool":"link:packages/tool"}}"#,
+            )
+            .unwrap();
+        install_in(project.path(), &client).await.unwrap();
+
+        let lock = LockFile::load_from_path(project.path().join("rpm.lock")).unwrap();
+        assert_eq!(
+            resolved_packages(&lock),
+            vec![
+                "left-pad@1.3.0 requested ^1.0.0".to_string(),
+                "shared@1.0.0 requested file:packages/shared".to_string(),
+                "tool@0.2.0 requested link:packages/tool".to_string(),
+            ]
+        );
+        let packages = lock.get_packages();
+        let locked = |key: &str| {
+            packages
+                .iter()
+                .find(|(candidate, _)| candidate.as_str() == key)
+                .map(|(_, dependency)| {
+                    (
+                        dependency.get_source(),
+                        dependency.get_tarball(),
+                        dependency.get_integrity(),
+                    )
+                })
+                .unwrap()
+        };
+        assert_eq!(
+            locked("shared@1.0.0"),
+            (
+                Some(PackageSource::Directory),
+                Some("file:packages/shared".to_string()),
+                None
+            )
+        );
+        assert_eq!(
+            locked("tool@0.2.0"),
+            (
+                Some(PackageSource::Link),
+                Some("link:packages/tool".to_string()),
+                None
+            )
+        );
+
+        // `file:` copies the packed files and links the package's own
+        // dependencies; `link:` symlinks the directory as it is.
+        let node_modules = project.path().join("node_modules");
+        assert!(!node_modules.join("shared").is_symlink());
+        assert_eq!(
+            fs::read_to_string(node_modules.join("shared/index.js")).unwrap(),
+            "v1"
+        );
+        assert!(!node_modules.join("shared/notes.txt").exists());
+        assert!(node_modules
+            .join("shared/node_modules/left-pad/package.json")
+            .is_file());
+        assert_eq!(
+            fs::read_link(node_modules.join("tool")).unwrap(),
+            Path::new("../packages/tool")
+        );
+        assert!(!node_modules.join("tool/node_modules").exists());
+        assert!(node_modules.join(".bin/tool").exists());
+        assert!(!project.path().join(".rpm/.cache/shared@1.0.0.tgz").exists());
+
+        // The locked directory is copied afresh on the next install.
+        project.write("packages/shared/index.js", "v2").unwrap();
+        install_in(project.path(), &client).await.unwrap();
+        assert_eq!(
+            fs::read_to_string(node_modules.join("shared/index.js")).unwrap(),
+            "v2"
+        );
+    }
+
+    #[tokio::test]
+    async fn installs_npm_aliases_under_the_alias_name() {
+        let registry = TempProject::new("alias-registry").unwrap();
+        let mut versions = Vec::new();
+        for version in ["4.2.3", "5.1.2"] {
+            let tarball = package_tarball(&[
+                (
+                    "package.json",
+                    &format!(r#"{{"name":"string-width","version":"{version}"}}"#),
+                ),
+                ("index.js", version),
+            ]);
+            registry
+                .write(
+                    format!("string-width/-/string-width-{version}.tgz"),
+                    &tarball,
+                )
+                .unwrap();
+            versions.push(format!(
+                r#""{version}":{{"name":"string-width","version":"{version}","dist":{{"tarball":"https://registry.example.invalid/string-width/-/string-width-{version}.tgz","integrity":"sha512-{}"}}}}"#,
+                BASE64_STANDARD.encode(Sha512::digest(&tarball))
+            ));
+        }
+        registry
+            .write(
+                "string-width.json",
+                format!(
+                    r#"{{"name":"string-width","dist-tags":{{"latest":"5.1.2"}},"versions":{{{}}}}}"#,
+                    versions.join(",")
+                ),
+            )
+            .unwrap();
+        let client = DirectoryRegistryClient::new(registry.path());
+
+        let project = TempProject::new("alias-project").unwrap();
+        project
+            .write(
+                "package.json",
+                r#"{"name":"alias-app","version":"0.0.0","dependencies":{"string-width":"^5.0.0","string-width-cjs":"npm:string-width@^4.2.0"}}"#,
+            )
+            .unwrap();
+        install_in(project.path(), &client).await.unwrap();
+
+        let lock_path = project.path().join("rpm.lock");
+        let lock = LockFile::load_from_path(&lock_path).unwrap();
+        assert_eq!(
+            resolved_packages(&lock),
+            vec![
+                "string-width-cjs@4.2.3 requested npm:string-width@^4.2.0".to_string(),
+                "string-width@5.1.2 requested ^5.0.0".to_string(),
+            ]
+        );
+        let alias = lock.get_dependency("string-width-cjs@4.2.3").unwrap();
+        assert_eq!(alias.get_alias_of(), Some("string-width".to_string()));
+        assert_eq!(
+            alias.get_tarball(),
+            Some(
+                "https://registry.example.invalid/string-width/-/string-width-4.2.3.tgz"
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            lock.get_dependency("string-width@5.1.2")
+                .unwrap()
+                .get_alias_of(),
+            None
+        );
+        let node_modules = project.path().join("node_modules");
+        assert_eq!(
+            fs::read_to_string(node_modules.join("string-width-cjs/index.js")).unwrap(),
+            "4.2.3"
+        );
+        assert_eq!(
+            fs::read_to_string(node_modules.join("string-width/index.js")).unwrap(),
+            "5.1.2"
+        );
+        assert_eq!(
+            sorted_cache_entries(&project.path().join(".rpm/.cache")).unwrap(),
+            vec!["string-width@4.2.3.tgz", "string-width@5.1.2.tgz"]
+        );
+
+        // The locked alias re-installs from the lock alone.
+        let lock_before = fs::read(&lock_path).unwrap();
+        fs::remove_dir_all(project.path().join("node_modules")).unwrap();
+        fs::remove_dir_all(project.path().join(".rpm")).unwrap();
+        install_in(project.path(), &client).await.unwrap();
+        assert_eq!(fs::read(&lock_path).unwrap(), lock_before);
+        assert_eq!(
+            fs::read_to_string(node_modules.join("string-width-cjs/index.js")).unwrap(),
+            "4.2.3"
+        );
+    }
+
+    #[tokio::test]
+    async fn reports_deprecated_packages_with_the_path_that_pulled_them_in() {
+        let registry = TempProject::new("deprecated-registry").unwrap();
+        for (name, releases) in [
+            (
+                "app-kit",
+                vec![("1.0.0", None, serde_json::json!({ "request": "~2.88.0" }))],
+            ),
+            (
+                "request",
+                vec![
+                    (
+                        "2.87.0",
+                        Some("request has been deprecated"),
+                        serde_json::json!({}),
+                    ),
+                    (
+                        "2.88.2",
+                        Some("request has been deprecated"),
+                        serde_json::json!({}),
+                    ),
+                ],
+            ),
+            (
+                "left-pad",
+                vec![
+                    ("1.2.0", None, serde_json::json!({})),
+                    (
+                        "1.3.0",
+                        Some("use String.prototype.padStart()"),
+                        serde_json::json!({}),
+                    ),
+                ],
+            ),
+        ] {
+            let mut versions = serde_json::Map::new();
+            for (version, deprecated, dependencies) in releases {
+                let tarball = package_tarball(&[(
+                    "package.json",
+                    &format!(r#"{{"name":"{name}","version":"{version}"}}"#),
+                )]);
+                let tarball_path = format!("{name}/-/{name}-{version}.tgz");
+                registry.write(&tarball_path, &tarball).unwrap();
+                let mut metadata = serde_json::json!({
+                    "name": name,
+                    "version": version,
+                    "dependencies": dependencies,
+                    "dist": {
+                        "tarball": format!("https://registry.example.invalid/{tarball_path}"),
+                        "integrity": format!(
+                            "sha512-{}",
+                            BASE64_STANDARD.encode(Sha512::digest(&tarball))
+                        ),
+                    },
+                });
+                if let Some(message) = deprecated {
+                    metadata["deprecated"] = message.into();
+                }
+                versions.insert(version.to_string(), metadata);
+            }
+            let latest = versions.keys().max().unwrap().clone();
+            registry
+                .write(
+                    format!("{name}.json"),
+                    serde_json::json!({
+                        "name": name,
+                        "dist-tags": { "latest": latest },
+                        "versions": versions,
+                    })
+                    .to_string(),
+                )
+                .unwrap();
+        }
+        let client = DirectoryRegistryClient::new(registry.path());
+        let project = TempProject::new("deprecated-project").unwrap();
+        project
+            .write(
+                "package.json",
+                r#"{"name":"deprecated-app","version":"0.0.0","dependencies":{"app-kit":"^1.0.0","left-pad":"^1.0.0"},"devDependencies":{"request":"2.87.0"}}"#,
+            )
+            .unwrap();
+
+        let report = install_in(project.path(), &client).await.unwrap();
+
+        // left-pad@1.3.0 is deprecated, so the range settles on 1.2.0; every
+        // request version is deprecated, so the highest is installed anyway.
+        let lock = LockFile::load_from_path(project.path().join("rpm.lock")).unwrap();
+        assert_eq!(
+            resolved_packages(&lock),
+            vec![
+                "app-kit@1.0.0 requested ^1.0.0",
+                "left-pad@1.2.0 requested ^1.0.0",
+                "request@2.87.0 requested 2.87.0",
+                "request@2.88.2 requested ~2.88.0",
+            ]
+        );
+        assert_eq!(
+            report.deprecation_summary().unwrap(),
+            "2 deprecated packages:\n  \
+             request@2.87.0: request has been deprecated (direct dependency)\n  \
+             request@2.88.2: request has been deprecated (via app-kit@1.0.0 > request@2.88.2)"
+        );
+
+        // A lock-only reinstall reads the messages back from rpm.lock.
+        assert_eq!(
+            lock.get_dependency("request@2.88.2")
+                .unwrap()
+                .get_deprecated(),
+            Some("request has been deprecated".to_string())
+        );
+        assert_eq!(install_in(project.path(), &client).await.unwrap(), report);
+    }
+
+    #[tokio::test]
+    async fn before_resolves_versions_published_before_the_cutoff_and_later_adds_keep_it() {
+        let registry = TempProject::new("before-registry").unwrap();
+        write_timed_packument(
+            &registry,
+            "clock",
+            &[
+                ("1.0.0", "2025-01-01T00:00:00Z".to_string()),
+                ("1.1.0", "2025-06-01T00:00:00Z".to_string()),
+                ("1.2.0", "2026-04-01T00:00:00Z".to_string()),
+            ],
+            serde_json::json!({ "tick": "^1.0.0" }),
+        );
+        write_timed_packument(
+            &registry,
+            "tick",
+            &[
+                ("1.0.0", "2025-02-01T00:00:00Z".to_string()),
+                ("1.1.0", "2026-05-01T00:00:00Z".to_string()),
+            ],
+            serde_json::json!({}),
+        );
+        let client = DirectoryRegistryClient::new(registry.path());
+        let project = TempProject::new("before-project").unwrap();
+        project
+            .write(
+                "package.json",
+                r#"{"name":"before-app","version":"0.0.0","dependencies":{"clock":"^1.0.0"}}"#,
+            )
+            .unwrap();
+        let lock_path = project.path().join("rpm.lock");
+
+        install_in(project.path(), &client).await.unwrap();
+        let lock = LockFile::load_from_path(&lock_path).unwrap();
+        assert_eq!(
+            resolved_packages(&lock),
+            vec![
+                "clock@1.2.0 requested ^1.0.0",
+                "tick@1.1.0 requested ^1.0.0"
+            ]
+        );
+
+        // A new cutoff re-resolves what the lockfile already held.
+        let before = parse_before("2026-03-01").unwrap();
+        let options = InstallOptions {
+            before: Some(before),
+            ..InstallOptions::default()
+        };
+        install_with_client_in(project.path(), &client, &options)
+            .await
+            .unwrap();
+        let mut lock = LockFile::load_from_path(&lock_path).unwrap();
+        assert_eq!(lock.get_before(), Some(before));
+        assert_eq!(
+            resolved_packages(&lock),
+            vec![
+                "clock@1.1.0 requested ^1.0.0",
+                "tick@1.0.0 requested ^1.0.0"
+            ]
+        );
+
+        let mut manifest =
+            PackageManifest::read_from_path(project.path().join("package.json")).unwrap();
+        add_in(
+            &mut manifest,
+            &mut lock,
+            vec!["tick@latest".to_string()],
+            true,
+            false,
+            project.path(),
+            &client,
+        )
+        .await
+        .unwrap();
+        assert_eq!(lock.get_before(), Some(before));
+        assert!(lock.get_dependency("tick@1.1.0").is_none());
+    }
+
+    #[tokio::test]
+    async fn minimum_release_age_holds_back_young_versions_outside_excluded_scopes() {
+        let registry = TempProject::new("release-age-registry").unwrap();
+        let ago = |hours: i64| (Utc::now() - chrono::Duration::hours(hours)).to_rfc3339();
+        write_timed_packument(
+            &registry,
+            "clock",
+            &[("1.0.0", ago(24 * 30)), ("1.1.0", ago(2))],
+            serde_json::json!({}),
+        );
+        write_timed_packument(
+            &registry,
+            "@acme/ui",
+            &[("1.0.0", ago(24 * 30)), ("1.1.0", ago(1))],
+            serde_json::json!({}),
+        );
+        // Deduplication moves the direct `shared@^1.0.0` onto the 1.0.0 that
+        // `pin` needs; its newest version is old enough, so nothing held it.
+        write_timed_packument(
+            &registry,
+            "shared",
+            &[("1.0.0", ago(24 * 30)), ("1.1.0", ago(24 * 20))],
+            serde_json::json!({}),
+        );
+        write_timed_packument(
+            &registry,
+            "pin",
+            &[("1.0.0", ago(24 * 30))],
+            serde_json::json!({ "shared": "1.0.0" }),
+        );
+        let client = DirectoryRegistryClient::new(registry.path());
+        let options = InstallOptions {
+            minimum_release_age: Some(MinimumReleaseAge {
+                age: Duration::from_secs(24 * 60 * 60),
+                exclude: vec!["@acme/*".to_string()],
+            }),
+            ..InstallOptions::default()
+        };
+        let project = TempProject::new("release-age-project").unwrap();
+        project
+            .write(
+                "package.json",
+                r#"{"name":"release-age-app","version":"0.0.0","dependencies":{"clock":"latest","@acme/ui":"^1.0.0","pin":"1.0.0","shared":"^1.0.0"}}"#,
+            )
+            .unwrap();
+
+        let report = install_with_client_in(project.path(), &client, &options)
+            .await
+            .unwrap();
+
+        let lock = LockFile::load_from_path(project.path().join("rpm.lock")).unwrap();
+        assert_eq!(
+            resolved_packages(&lock),
+            vec![
+                "@acme/ui@1.1.0 requested ^1.0.0",
+                "clock@1.0.0 requested latest",
+                "pin@1.0.0 requested 1.0.0",
+                "shared@1.0.0 requested ^1.0.0",
+            ]
+        );
+        assert_eq!(
+            report
+                .held_back
+                .iter()
+                .map(|package| format!(
+                    "{}@{} < {}",
+                    package.package_name, package.version, package.newest
+                ))
+                .collect::<Vec<_>>(),
+            vec!["clock@1.0.0 < 1.1.0"]
+        );
+
+        // The locked version is kept and no longer reported.
+        let report = install_with_client_in(project.path(), &client, &options)
+            .await
+            .unwrap();
+        assert!(report.held_back.is_empty());
+    }
+
+    #[tokio::test]
+    async fn backtracking_install_explains_conflicts_and_honors_overrides() {
+        let registry = TempProject::new("backtracking-registry").unwrap();
+        let published = |day: u32| format!("2026-01-{day:02}T00:00:00Z");
+        write_timed_packument(
+            &registry,
+            "left",
+            &[("1.0.0", published(1))],
+            serde_json::json!({ "shared": "^2.0.0" }),
+        );
+        write_timed_packument(
+            &registry,
+            "right",
+            &[("1.0.0", published(1))],
+            serde_json::json!({ "shared": "^1.0.0" }),
+        );
+        write_timed_packument(
+            &registry,
+            "shared",
+            &[("1.0.0", published(1)), ("2.0.0", published(2))],
+            serde_json::json!({}),
+        );
+        let client = DirectoryRegistryClient::new(registry.path());
+        let options = InstallOptions {
+            resolution_strategy: ResolutionStrategyKind::Backtracking,
+            ..InstallOptions::default()
+        };
+        let project = TempProject::new("backtracking-project").unwrap();
+        project
+            .write(
+                "package.json",
+                r#"{"name":"backtracking-app","version":"0.0.0","dependencies":{"left":"^1.0.0","right":"^1.0.0"}}"#,
+            )
+            .unwrap();
+
+        let error = install_with_client_in(project.path(), &client, &options)
+            .await
+            .unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("left@1.0.0 needs shared@^2.0.0, but right@1.0.0 needs shared@^1.0.0"),
+            "{error}"
         );
-    }
-}
-
-fn commit_install_state(backups: Vec<StateBackup>) -> io::Result<()> {
-    for mut backup in backups {
-        if let Some(backup_path) = backup.backup_path.take() {
-            remove_path(&backup_path)?;
-        }
-    }
-    Ok(())
-}
 
-fn sibling_state_path(path: &Path, kind: &str) -> PathBuf {
-    let parent = path
-        .parent()
-        .filter(|path| !path.as_os_str().is_empty())
-        .unwrap_or_else(|| Path::new("."));
-    let file_name = path
-        .file_name()
-        .and_then(|name| name.to_str())
-        .unwrap_or("install-state");
-    parent.join(format!(".{file_name}.rpm-{kind}-{}", unique_suffix()))
-}
+        project
+            .write(
+                "package.json",
+                r#"{"name":"backtracking-app","version":"0.0.0","dependencies":{"left":"^1.0.0","right":"^1.0.0"},"overrides":{"shared":"2.0.0"}}"#,
+            )
+            .unwrap();
+        let report = install_with_client_in(project.path(), &client, &options)
+            .await
+            .unwrap();
+        assert!(report.ignored_overrides.is_empty());
 
-fn unique_suffix() -> String {
-    let nanos = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .map(|duration| duration.as_nanos())
-        .unwrap_or(0);
-    format!("{}-{nanos}", std::process::id())
-}
+        let lock = LockFile::load_from_path(project.path().join("rpm.lock")).unwrap();
+        assert_eq!(
+            resolved_packages(&lock),
+            vec![
+                "left@1.0.0 requested ^1.0.0",
+                "right@1.0.0 requested ^1.0.0",
+                "shared@2.0.0 requested ^2.0.0",
+            ]
+        );
 
-fn remove_path(path: &Path) -> io::Result<()> {
-    let file_type = fs::symlink_metadata(path)?.file_type();
-    if file_type.is_dir() && !file_type.is_symlink() {
-        fs::remove_dir_all(path)
-    } else {
-        fs::remove_file(path)
+        // FIFO does not apply the override, and the report says so.
+        let report = install_in(project.path(), &client).await.unwrap();
+        assert_eq!(report.ignored_overrides, vec!["shared"]);
     }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::{backup_install_state, capture_install_state, install_in, restore_snapshot_after};
-    use crate::{
-        command::working_process::run::run_script,
-        lockfile::{LockFile, Relationship},
-        package_manifest::PackageManifest,
-        util::test_support::{fixture_path, TempProject},
-    };
-    use std::{
-        collections::BTreeMap,
-        ffi::OsString,
-        fs, io,
-        os::unix::fs::symlink,
-        os::unix::fs::PermissionsExt,
-        path::{Path, PathBuf},
-        thread,
-        time::Duration,
-    };
 
     #[tokio::test]
-    async fn installs_performance_small_fixture_from_deterministic_inputs() {
-        let _guard = TestEnvLock::acquire().unwrap();
-        let repo_root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
-        let root_before = root_fingerprints(&repo_root).unwrap();
-        let fixture_root = fixture_path(&["install-projects", "performance-small"]);
-        let project = TempProject::new("performance-small-install").unwrap();
-        let package_path = project
-            .copy_fixture(fixture_root.join("package.json"), "package.json")
+    async fn peers_link_to_the_project_copy_and_unmet_ones_are_reported() {
+        let registry = TempProject::new("peer-registry").unwrap();
+        let published = "2026-01-01T00:00:00Z".to_string();
+        for (name, versions) in [
+            ("react", vec!["17.0.0", "18.0.0"]),
+            ("plugin", vec!["1.0.0"]),
+            ("old-plugin", vec!["1.0.0"]),
+            ("widget", vec!["1.0.0"]),
+            ("helper", vec!["1.0.0"]),
+        ] {
+            let releases = versions
+                .into_iter()
+                .map(|version| (version, published.clone()))
+                .collect::<Vec<_>>();
+            write_timed_packument(&registry, name, &releases, serde_json::json!({}));
+        }
+        for (name, peers) in [
+            ("plugin", serde_json::json!({ "react": "^18.0.0" })),
+            ("old-plugin", serde_json::json!({ "react": "^17.0.0" })),
+            (
+                "widget",
+                serde_json::json!({ "helper": "^1.0.0", "ghost": "^1.0.0", "extra": "*" }),
+            ),
+        ] {
+            let path = registry.path().join(format!("{name}.json"));
+            let mut packument: serde_json::Value =
+                serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+            let release = &mut packument["versions"]["1.0.0"];
+            release["peerDependencies"] = peers;
+            release["peerDependenciesMeta"] = serde_json::json!({ "extra": { "optional": true } });
+            fs::write(path, packument.to_string()).unwrap();
+        }
+        let client = DirectoryRegistryClient::new(registry.path());
+        let project = TempProject::new("peer-project").unwrap();
+        project
+            .write(
+                "package.json",
+                r#"{"name":"peer-app","version":"0.0.0","dependencies":{"plugin":"^1.0.0","old-plugin":"^1.0.0","widget":"^1.0.0"},"devDependencies":{"react":"^18.0.0"}}"#,
+            )
             .unwrap();
-        let project_root = package_path.parent().unwrap();
 
-        let _env = FixtureInstallEnv::new(&fixture_root.join("registry"));
-        install_in(project_root).await.unwrap();
+        let report = install_in(project.path(), &client).await.unwrap();
 
-        let lock_path = project_root.join("rpm.lock");
-        let lock = LockFile::load_from_path(&lock_path).unwrap();
-        let expected = fs::read_to_string(fixture_root.join("expected/resolved-packages.txt"))
-            .unwrap()
-            .lines()
-            .map(str::to_owned)
-            .collect::<Vec<_>>();
-        assert_eq!(resolved_packages(&lock), expected);
-        assert_eq!(
-            lock.get_dependency("@rpm-fixture/alpha@1.0.0")
-                .map(|dependency| dependency.get_relationship()),
-            Some(Relationship::Direct)
-        );
+        // The dev dependency on react serves the production plugin's peer;
+        // widget's required peer nothing else provides is installed for it.
+        let lock = LockFile::load_from_path(project.path().join("rpm.lock")).unwrap();
         assert_eq!(
-            lock.get_dependency("@rpm-fixture/beta@1.0.0")
-                .map(|dependency| dependency.get_relationship()),
-            Some(Relationship::Direct)
+            resolved_packages(&lock),
+            vec![
+                "helper@1.0.0 requested ^1.0.0",
+                "old-plugin@1.0.0 requested ^1.0.0",
+                "plugin@1.0.0 requested ^1.0.0",
+                "react@18.0.0 requested ^18.0.0",
+                "widget@1.0.0 requested ^1.0.0",
+            ]
         );
+        let widget = lock.get_dependency("widget@1.0.0").unwrap();
         assert_eq!(
-            lock.get_dependency("@rpm-fixture/shared@1.0.0")
-                .map(|dependency| dependency.get_relationship()),
-            Some(Relationship::Transitive)
+            widget.get_peer_dependencies(),
+            vec![
+                ("extra@*".to_string(), true),
+                ("ghost@^1.0.0".to_string(), false),
+                ("helper@^1.0.0".to_string(), false),
+            ]
         );
-
-        let package = PackageManifest::read_from_path(&package_path).unwrap();
-        assert_eq!(package.get_name(), "performance-small");
+        let node_modules = project.path().join("node_modules");
+        for (package, peer) in [
+            ("plugin", "react"),
+            ("old-plugin", "react"),
+            ("widget", "helper"),
+        ] {
+            let link = node_modules.join(package).join("node_modules").join(peer);
+            assert_eq!(
+                fs::canonicalize(link).unwrap(),
+                fs::canonicalize(node_modules.join(peer)).unwrap()
+            );
+        }
+        assert!(!node_modules.join("widget/node_modules/ghost").exists());
+
+        // Each problem names the declaring package, the peer and its range,
+        // and the version an incompatible peer resolved to.
+        let problems = report
+            .peer_problems
+            .iter()
+            .map(|problem| match problem {
+                PeerProblem::Missing {
+                    package_name,
+                    peer_name,
+                    requested,
+                    ..
+                } => format!("missing {package_name} {peer_name}@{requested}"),
+                PeerProblem::Incompatible {
+                    package_name,
+                    peer_name,
+                    requested,
+                    resolved_version,
+                    ..
+                } => format!(
+                    "incompatible {package_name} {peer_name}@{requested} {resolved_version}"
+                ),
+            })
+            .collect::<Vec<_>>();
         assert_eq!(
-            sorted_dependencies(package.get_dependencies()),
+            problems,
             vec![
-                ("@rpm-fixture/alpha".to_string(), "^1.0.0".to_string()),
-                ("@rpm-fixture/beta".to_string(), "^1.0.0".to_string()),
+                "incompatible old-plugin react@^17.0.0 18.0.0",
+                "missing widget ghost@^1.0.0",
             ]
         );
 
-        let node_modules = project_root.join("node_modules");
-        assert!(node_modules
-            .join("@rpm-fixture")
-            .join("alpha")
-            .join("package.json")
-            .is_file());
-        assert!(node_modules
-            .join("@rpm-fixture")
-            .join("beta")
-            .join("package.json")
-            .is_file());
-        assert!(node_modules
-            .join("@rpm-fixture")
-            .join("shared")
-            .join("package.json")
-            .is_file());
+        // A reinstall from the lockfile reaches the same result.
+        assert_eq!(install_in(project.path(), &client).await.unwrap(), report);
+    }
+
+    #[test]
+    fn install_options_read_resolution_and_release_age_from_the_project_npmrc() {
+        let project = TempProject::new("install-options-npmrc").unwrap();
         assert_eq!(
-            fs::read_link(
-                node_modules
-                    .join("@rpm-fixture")
-                    .join("alpha")
-                    .join("node_modules")
-                    .join("@rpm-fixture")
-                    .join("shared")
+            InstallOptions::from_npmrc(project.path())
+                .unwrap()
+                .resolution_strategy,
+            ResolutionStrategyKind::Fifo
+        );
+
+        project
+            .write(
+                ".npmrc",
+                "resolution-strategy=backtracking\nminimum-release-age=2h\n",
             )
-            .unwrap(),
-            PathBuf::from("../../../../@rpm-fixture/shared")
+            .unwrap();
+        let options = InstallOptions::from_npmrc(project.path()).unwrap();
+        assert_eq!(
+            options.resolution_strategy,
+            ResolutionStrategyKind::Backtracking
         );
         assert_eq!(
-            fs::read_link(
-                node_modules
-                    .join("@rpm-fixture")
-                    .join("beta")
-                    .join("node_modules")
-                    .join("@rpm-fixture")
-                    .join("shared")
+            options.minimum_release_age.map(|policy| policy.age),
+            Some(Duration::from_secs(2 * 60 * 60))
+        );
+
+        project
+            .write(".npmrc", "resolution-strategy=newest\n")
+            .unwrap();
+        let error = InstallOptions::from_npmrc(project.path()).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn parses_before_dates_and_timestamps() {
+        assert_eq!(
+            parse_before("2026-03-01").unwrap().to_rfc3339(),
+            "2026-03-01T00:00:00+00:00"
+        );
+        assert_eq!(
+            parse_before("2026-03-01T09:30:00+02:00")
+                .unwrap()
+                .to_rfc3339(),
+            "2026-03-01T07:30:00+00:00"
+        );
+        assert!(parse_before("March 1st")
+            .unwrap_err()
+            .contains("expected YYYY-MM-DD"));
+    }
+
+    #[tokio::test]
+    async fn installs_from_a_registry_served_out_of_a_warmed_cache() {
+        let _guard = TestEnvLock::acquire().unwrap();
+        let warmed = TempProject::new("served-cache-seed").unwrap();
+        for (name, version, dependencies) in [
+            ("@corp/app-kit", "1.2.0", r#"{"left-pad":"^1.0.0"}"#),
+            ("left-pad", "1.3.0", "{}"),
+        ] {
+            let tarball = package_tarball(&[(
+                "package.json",
+                &format!(r#"{{"name":"{name}","version":"{version}"}}"#),
+            )]);
+            let integrity = format!(
+                "sha512-{}",
+                BASE64_STANDARD.encode(Sha512::digest(&tarball))
+            );
+            let cache_name = name.replace('/', "-");
+            warmed
+                .write(format!(".rpm/.cache/{cache_name}@{version}.tgz"), &tarball)
+                .unwrap();
+            warmed
+                .write(
+                    format!(".rpm/.metadata/{cache_name}.full.json"),
+                    format!(
+                        r#"{{"url":"https://registry.npmjs.org/{name}/","etag":null,"last_modified":null,"fetched_at":0,"abbreviated":false,"body":{{"name":"{name}","dist-tags":{{"latest":"{version}"}},"versions":{{"{version}":{{"dependencies":{dependencies},"dist":{{"tarball":"https://registry.npmjs.org/{name}/-/x.tgz","integrity":"{integrity}"}}}}}}}}}}"#
+                    ),
+                )
+                .unwrap();
+        }
+
+        // Each project installs from the registry served out of the previous
+        // project's caches, so the second hop serves what an install cached.
+        let mut upstream = warmed;
+        for hop in ["first", "second"] {
+            let listener = bind_registry_listener("127.0.0.1", 0).await.unwrap();
+            let registry_url = format!("http://{}", listener.local_addr().unwrap());
+            let server = tokio::spawn(
+                CacheRegistryServer::for_project(upstream.path()).serve(listener, |_| {}),
+            );
+            let project = TempProject::new(&format!("served-cache-{hop}")).unwrap();
+            project
+                .write(".npmrc", format!("registry={registry_url}/\n"))
+                .unwrap();
+            project
+                .write(
+                    "package.json",
+                    r#"{"name":"lan-app","version":"0.0.0","dependencies":{"@corp/app-kit":"^1.0.0"}}"#,
+                )
+                .unwrap();
+
+            install_default_in(project.path()).await.unwrap();
+            server.abort();
+
+            let lock = LockFile::load_from_path(project.path().join("rpm.lock")).unwrap();
+            assert_eq!(
+                resolved_packages(&lock),
+                vec![
+                    "@corp/app-kit@1.2.0 requested ^1.0.0",
+                    "left-pad@1.3.0 requested ^1.0.0"
+                ],
+                "{hop}"
+            );
+            let lock_text = fs::read_to_string(project.path().join("rpm.lock")).unwrap();
+            assert!(
+                lock_text.contains(&format!("{registry_url}/left-pad/-/left-pad-1.3.0.tgz")),
+                "{hop}"
+            );
+            assert!(project
+                .path()
+                .join("node_modules/@corp/app-kit/package.json")
+                .is_file());
+            upstream = project;
+        }
+    }
+
+    #[tokio::test]
+    async fn verify_signatures_rejects_bad_signatures_before_caching() {
+        let registry = TempProject::new("signed-registry").unwrap();
+        registry
+            .copy_fixture(
+                fixture_path(&["signatures", "keys.json"]),
+                "-/npm/v1/keys.json",
+            )
+            .unwrap();
+        let publish = |signed_key: &str| {
+            let tarball = package_tarball(&[("package.json", r#"{"name":"left-pad"}"#)]);
+            let integrity = format!(
+                "sha512-{}",
+                BASE64_STANDARD.encode(Sha512::digest(&tarball))
+            );
+            let sig = registry_signature(signed_key, &integrity);
+            registry
+                .write("left-pad/-/left-pad-1.3.0.tgz", &tarball)
+                .unwrap();
+            registry
+                .write(
+                    "left-pad.json",
+                    format!(
+                        r#"{{"name":"left-pad","dist-tags":{{"latest":"1.3.0"}},"versions":{{"1.3.0":{{"dist":{{"tarball":"left-pad/-/left-pad-1.3.0.tgz","integrity":"{integrity}","signatures":[{{"keyid":"{REGISTRY_KEY_ID}","sig":"{sig}"}}]}}}}}}}}"#
+                    ),
+                )
+                .unwrap();
+        };
+        let project = TempProject::new("signed-project").unwrap();
+        project
+            .write(
+                "package.json",
+                r#"{"name":"signed-app","version":"0.0.0","dependencies":{"left-pad":"^1.0.0"}}"#,
+            )
+            .unwrap();
+        let cached = project.path().join(".rpm/.cache/left-pad@1.3.0.tgz");
+        let client = DirectoryRegistryClient::new(registry.path());
+        let verifying = InstallOptions {
+            signature_keys: Some(SignatureKeySource::Registry),
+            ..InstallOptions::default()
+        };
+
+        publish("left-pad@1.3.0");
+        install_with_client_in(project.path(), &client, &verifying)
+            .await
+            .unwrap();
+        assert!(cached.is_file());
+
+        // The locked reinstall reads the signatures from the registry
+        // document, which now signs another version.
+        publish("left-pad@1.3.1");
+        fs::remove_file(&cached).unwrap();
+        let error = install_with_client_in(project.path(), &client, &verifying)
+            .await
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            format!(
+                "integrity failed: signature verification failed for left-pad@1.3.0: \
+                 invalid signature by key {REGISTRY_KEY_ID}"
             )
-            .unwrap(),
-            PathBuf::from("../../../../@rpm-fixture/shared")
         );
+        assert!(!cached.exists());
+
+        install_in(project.path(), &client).await.unwrap();
+        let missing_keys = InstallOptions {
+            signature_keys: Some(SignatureKeySource::File(
+                project.path().join("missing-keys.json"),
+            )),
+            ..InstallOptions::default()
+        };
+        let error = install_with_client_in(project.path(), &client, &missing_keys)
+            .await
+            .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::NotFound);
+        assert!(error
+            .to_string()
+            .starts_with("fetch failed: failed to read registry keys"));
+    }
+
+    #[tokio::test]
+    async fn lockfile_bytes_do_not_depend_on_download_concurrency() {
+        let _guard = TestEnvLock::acquire().unwrap();
+        let fixture_root = fixture_path(&["install-projects", "performance-small"]);
+        let mut lockfiles = Vec::new();
+
+        for (run, network_concurrency) in [Some(1), Some(16), None, Some(1)].into_iter().enumerate()
+        {
+            let project = TempProject::new(&format!("download-concurrency-{run}")).unwrap();
+            let package_path = project
+                .copy_fixture(fixture_root.join("package.json"), "package.json")
+                .unwrap();
+            let project_root = package_path.parent().unwrap();
+            let mut client = FixtureRegistryClient::new(&fixture_root.join("registry")).unwrap();
+            if let Some(network_concurrency) = network_concurrency {
+                client = client.with_download_concurrency(network_concurrency);
+            }
+
+            install_in(project_root, &client).await.unwrap();
+
+            assert_eq!(
+                sorted_cache_entries(&project_root.join(".rpm/.cache")).unwrap(),
+                vec![
+                    "@rpm-fixture-alpha@1.0.0.tgz".to_string(),
+                    "@rpm-fixture-beta@1.0.0.tgz".to_string(),
+                    "@rpm-fixture-shared@1.0.0.tgz".to_string(),
+                ]
+            );
+            lockfiles.push(fs::read(project_root.join("rpm.lock")).unwrap());
+        }
+
+        assert!(lockfiles.windows(2).all(|pair| pair[0] == pair[1]));
+    }
+
+    #[tokio::test]
+    async fn offline_install_without_lockfile_lists_missing_metadata() {
+        let _guard = TestEnvLock::acquire().unwrap();
+        let fixture_root = fixture_path(&["install-projects", "performance-small"]);
+        let project = TempProject::new("offline-install-missing-metadata").unwrap();
+        let package_path = project
+            .copy_fixture(fixture_root.join("package.json"), "package.json")
+            .unwrap();
+        let project_root = package_path.parent().unwrap();
+
+        let error = install_with_options_in(
+            project_root,
+            &InstallOptions {
+                network_mode: NetworkMode::Offline,
+                ..InstallOptions::default()
+            },
+        )
+        .await
+        .unwrap_err();
 
         assert_eq!(
-            sorted_cache_entries(&project_root.join(".rpm/.cache")).unwrap(),
-            vec![
-                "@rpm-fixture-alpha@1.0.0.tgz".to_string(),
-                "@rpm-fixture-beta@1.0.0.tgz".to_string(),
-                "@rpm-fixture-shared@1.0.0.tgz".to_string(),
-            ]
+            error.to_string(),
+            "fetch failed: offline install is missing cached registry metadata for: \
+             @rpm-fixture/alpha@^1.0.0, @rpm-fixture/beta@^1.0.0"
         );
-        assert_eq!(root_fingerprints(&repo_root).unwrap(), root_before);
+        assert!(!project_root.join("node_modules").exists());
+        assert!(!project_root.join(".rpm/.cache").exists());
     }
 
     #[tokio::test]
@@ -537,8 +2082,8 @@ mod tests {
         read_only_permissions.set_mode(0o444);
         fs::set_permissions(&package_path, read_only_permissions).unwrap();
 
-        let _env = FixtureInstallEnv::new(&fixture_root.join("registry"));
-        let error = install_in(project_root).await.unwrap_err();
+        let client = FixtureRegistryClient::new(&fixture_root.join("registry")).unwrap();
+        let error = install_in(project_root, &client).await.unwrap_err();
         fs::set_permissions(&package_path, original_permissions).unwrap();
 
         assert!(error.to_string().contains("package.json is read-only"));
@@ -566,8 +2111,8 @@ mod tests {
         let original_package = fs::read(&package_path).unwrap();
         let original_lockfile = fs::read(&lockfile_path).unwrap();
 
-        let _env = FixtureInstallEnv::new(&fixture_root.join("registry"));
-        let error = install_in(project_root).await.unwrap_err();
+        let client = FixtureRegistryClient::new(&fixture_root.join("registry")).unwrap();
+        let error = install_in(project_root, &client).await.unwrap_err();
 
         assert_expected_error(&fixture_root, &error);
         assert_eq!(fs::read(&package_path).unwrap(), original_package);
@@ -599,8 +2144,8 @@ mod tests {
         let original_package = fs::read(&package_path).unwrap();
         let original_lockfile = fs::read(&lockfile_path).unwrap();
 
-        let _env = FixtureInstallEnv::new(&fixture_root.join("registry"));
-        let error = install_in(project_root).await.unwrap_err();
+        let client = FixtureRegistryClient::new(&fixture_root.join("registry")).unwrap();
+        let error = install_in(project_root, &client).await.unwrap_err();
 
         assert_expected_error(&fixture_root, &error);
         assert_eq!(fs::read(&package_path).unwrap(), original_package);
@@ -629,8 +2174,8 @@ mod tests {
         let original_package = fs::read(&package_path).unwrap();
         let original_lockfile = fs::read(&lockfile_path).unwrap();
 
-        let _env = FixtureInstallEnv::new(&fixture_root.join("registry"));
-        let error = install_in(project_root).await.unwrap_err();
+        let client = FixtureRegistryClient::new(&fixture_root.join("registry")).unwrap();
+        let error = install_in(project_root, &client).await.unwrap_err();
 
         assert_expected_error(&fixture_root, &error);
         assert_eq!(fs::read(&package_path).unwrap(), original_package);
@@ -658,8 +2203,8 @@ mod tests {
             .unwrap();
         let project_root = package_path.parent().unwrap();
 
-        let _env = FixtureInstallEnv::new(&fixture_root.join("registry"));
-        install_in(project_root).await.unwrap();
+        let client = FixtureRegistryClient::new(&fixture_root.join("registry")).unwrap();
+        install_in(project_root, &client).await.unwrap();
 
         // Fingerprint the install output (the project's `node_modules`, lockfile,
         // and cache) instead of the repository root so the non-mutation contract
@@ -718,8 +2263,8 @@ mod tests {
             .unwrap();
         let project_root = package_path.parent().unwrap();
 
-        let _env = FixtureInstallEnv::new(&fixture_root.join("registry"));
-        install_in(project_root).await.unwrap();
+        let client = FixtureRegistryClient::new(&fixture_root.join("registry")).unwrap();
+        install_in(project_root, &client).await.unwrap();
 
         // A binary the installed packages do not expose must remain a readable
         // non-zero status, not a reinstall or a silent success.
@@ -746,8 +2291,8 @@ mod tests {
             .unwrap();
         let project_root = package_path.parent().unwrap();
 
-        let _env = FixtureInstallEnv::new(&fixture_root.join("registry"));
-        install_in(project_root).await.unwrap();
+        let client = FixtureRegistryClient::new(&fixture_root.join("registry")).unwrap();
+        install_in(project_root, &client).await.unwrap();
 
         // The resolved package's `preinstall` hook wrote a proof file inside
         // its installed package directory during the `scripts` phase.
@@ -778,7 +2323,7 @@ mod tests {
         )
         .unwrap();
 
-        install_in(project_root).await.unwrap();
+        install_default_in(project_root).await.unwrap();
 
         assert_eq!(
             fs::read_to_string(project_root.join("root-only-proof.txt")).unwrap(),
@@ -807,8 +2352,8 @@ mod tests {
         .unwrap();
         let original_lock = fs::read(&lock_path).unwrap();
 
-        let _env = FixtureInstallEnv::new(&fixture_root.join("registry"));
-        let error = install_in(project_root).await.unwrap_err();
+        let client = FixtureRegistryClient::new(&fixture_root.join("registry")).unwrap();
+        let error = install_in(project_root, &client).await.unwrap_err();
 
         assert_expected_error(&fixture_root, &error);
         // A failed `scripts` phase must not publish partial install state: the
@@ -880,8 +2425,8 @@ mod tests {
         fs::write(&existing_file, "existing node_modules content").unwrap();
         let original_package = fs::read(&package_path).unwrap();
 
-        let _env = FixtureInstallEnv::new(&fixture_root.join("registry"));
-        let error = install_in(project_root).await.unwrap_err();
+        let client = FixtureRegistryClient::new(&fixture_root.join("registry")).unwrap();
+        let error = install_in(project_root, &client).await.unwrap_err();
 
         assert_expected_error(&fixture_root, &error);
         assert_eq!(fs::read(&package_path).unwrap(), original_package);
@@ -901,10 +2446,10 @@ mod tests {
             .unwrap();
         let project_root = package_path.parent().unwrap();
 
-        let _env = FixtureInstallEnv::new(&fixture_root.join("registry"));
+        let client = FixtureRegistryClient::new(&fixture_root.join("registry")).unwrap();
         // A wrong-type `scripts` value is discarded as absent by the manifest
         // deserializer, so the install completes normally.
-        install_in(project_root).await.unwrap();
+        install_in(project_root, &client).await.unwrap();
 
         let node_modules = project_root.join("node_modules");
         assert!(node_modules
@@ -924,8 +2469,8 @@ mod tests {
             .unwrap();
         let project_root = package_path.parent().unwrap();
 
-        let _env = FixtureInstallEnv::new(&fixture_root.join("registry"));
-        install_in(project_root).await.unwrap();
+        let client = FixtureRegistryClient::new(&fixture_root.join("registry")).unwrap();
+        install_in(project_root, &client).await.unwrap();
 
         // The root manifest's `preinstall` hook ran with the project root as
         // its working directory.
@@ -950,8 +2495,8 @@ mod tests {
         )
         .unwrap();
 
-        let _env = FixtureInstallEnv::new(&fixture_root.join("registry"));
-        install_in(project_root).await.unwrap();
+        let client = FixtureRegistryClient::new(&fixture_root.join("registry")).unwrap();
+        install_in(project_root, &client).await.unwrap();
 
         let package = PackageManifest::read_from_path(&package_path).unwrap();
         assert_eq!(package.get_name(), "hook-mutated");
@@ -994,8 +2539,8 @@ mod tests {
         )
         .unwrap();
 
-        let _env = FixtureInstallEnv::new(&registry_root);
-        install_in(project_root).await.unwrap();
+        let client = FixtureRegistryClient::new(&registry_root).unwrap();
+        install_in(project_root, &client).await.unwrap();
 
         assert_eq!(
             fs::read_to_string(project_root.join("package-hook-count.txt")).unwrap(),
@@ -1027,7 +2572,7 @@ mod tests {
         .unwrap();
         let expected = fs::read(&package_path).unwrap();
 
-        let error = install_in(project_root).await.unwrap_err();
+        let error = install_default_in(project_root).await.unwrap_err();
 
         assert_eq!(crate::node_linker::lifecycle_exit_status(&error), Some(9));
         assert_eq!(fs::read(&package_path).unwrap(), expected);
@@ -1059,7 +2604,7 @@ mod tests {
         let original = fs::read(&package_path).unwrap();
         let original_permissions = fs::metadata(&package_path).unwrap().permissions();
 
-        let error = install_in(package_path.parent().unwrap())
+        let error = install_default_in(package_path.parent().unwrap())
             .await
             .unwrap_err();
 
@@ -1089,8 +2634,8 @@ mod tests {
         )
         .unwrap();
 
-        let _env = FixtureInstallEnv::new(&fixture_root.join("registry"));
-        install_in(project_root).await.unwrap();
+        let client = FixtureRegistryClient::new(&fixture_root.join("registry")).unwrap();
+        install_in(project_root, &client).await.unwrap();
 
         assert!(!project_root
             .join("node_modules/@rpm-fixture/locked-parent/node_modules/@rpm-fixture/locked-child")
@@ -1110,8 +2655,8 @@ mod tests {
             .copy_fixture(fixture_root.join("rpm.lock"), "rpm.lock")
             .unwrap();
 
-        let _env = FixtureInstallEnv::new(&fixture_root.join("registry"));
-        install_in(project_root).await.unwrap();
+        let client = FixtureRegistryClient::new(&fixture_root.join("registry")).unwrap();
+        install_in(project_root, &client).await.unwrap();
 
         assert_eq!(
             fs::read_to_string(
@@ -1136,6 +2681,58 @@ mod tests {
         }
     }
 
+    /// Write a packument for `name` whose `time` map records when each of
+    /// `releases` was published, with every release depending on
+    /// `dependencies`.
+    fn write_timed_packument(
+        registry: &TempProject,
+        name: &str,
+        releases: &[(&str, String)],
+        dependencies: serde_json::Value,
+    ) {
+        let mut versions = serde_json::Map::new();
+        let mut time = serde_json::json!({
+            "created": releases[0].1,
+            "modified": releases[releases.len() - 1].1,
+        });
+        let basename = name.rsplit('/').next().unwrap();
+        for (version, published) in releases {
+            let tarball = package_tarball(&[(
+                "package.json",
+                &format!(r#"{{"name":"{name}","version":"{version}"}}"#),
+            )]);
+            let tarball_path = format!("{name}/-/{basename}-{version}.tgz");
+            registry.write(&tarball_path, &tarball).unwrap();
+            versions.insert(
+                version.to_string(),
+                serde_json::json!({
+                    "dependencies": dependencies,
+                    "dist": {
+                        "tarball": format!("https://registry.example.invalid/{tarball_path}"),
+                        "integrity": format!(
+                            "sha512-{}",
+                            BASE64_STANDARD.encode(Sha512::digest(&tarball))
+                        ),
+                    },
+                }),
+            );
+            time[*version] = published.as_str().into();
+        }
+        let latest = versions.keys().max().unwrap().clone();
+        registry
+            .write(
+                format!("{name}.json"),
+                serde_json::json!({
+                    "name": name,
+                    "dist-tags": { "latest": latest },
+                    "time": time,
+                    "versions": versions,
+                })
+                .to_string(),
+            )
+            .unwrap();
+    }
+
     fn resolved_packages(lock: &LockFile) -> Vec<String> {
         let mut packages = lock
             .get_packages()
@@ -1184,29 +2781,6 @@ mod tests {
         }
     }
 
-    struct FixtureInstallEnv {
-        previous_fixture_root: Option<OsString>,
-    }
-
-    impl FixtureInstallEnv {
-        fn new(registry_root: &Path) -> Self {
-            let previous_fixture_root = std::env::var_os("RPM_REGISTRY_FIXTURE_ROOT");
-            std::env::set_var("RPM_REGISTRY_FIXTURE_ROOT", registry_root);
-            Self {
-                previous_fixture_root,
-            }
-        }
-    }
-
-    impl Drop for FixtureInstallEnv {
-        fn drop(&mut self) {
-            match &self.previous_fixture_root {
-                Some(value) => std::env::set_var("RPM_REGISTRY_FIXTURE_ROOT", value),
-                None => std::env::remove_var("RPM_REGISTRY_FIXTURE_ROOT"),
-            }
-        }
-    }
-
     #[derive(Debug, PartialEq, Eq)]
     enum PathFingerprint {
         Missing,
@@ -1,7 +1,23 @@
 mod add;
+mod dedupe;
+mod git;
+mod info;
 mod install;
+mod pack;
+mod publish;
+mod registry;
+mod report;
 mod run;
+mod source;
 pub use add::add;
-pub(crate) use add::add_with_cache_dir;
-pub use install::install;
+#[cfg(test)]
+pub(crate) use add::add_in;
+pub(crate) use add::resolve_requests_in;
+pub use dedupe::{dedupe, DedupeOutcome};
+pub use info::{info, InfoOptions};
+pub use install::{install, install_in, parse_before, InstallOptions};
+pub use pack::pack;
+pub use publish::{publish, PublishOptions, PublishOutcome};
+pub use registry::serve_registry;
+pub use report::{DeprecatedPackage, HeldBackPackage, InstallReport};
 pub use run::run;
@@ -0,0 +1,54 @@
+use std::{
+    fs,
+    io::Error,
+    path::{Path, PathBuf},
+};
+
+use crate::pack::{pack_project, PackedTarball};
+
+/// `rpm pack`: build the tarball of the project at `project_root` and write it
+/// to `destination` (the project directory when `None`). Returns the tarball
+/// and the path it was written to.
+pub fn pack(
+    project_root: &Path,
+    destination: Option<&Path>,
+) -> std::io::Result<(PackedTarball, PathBuf)> {
+    let packed = pack_project(project_root)?;
+    let destination = destination.unwrap_or(project_root);
+    let path = destination.join(packed.file_name());
+    fs::create_dir_all(destination)
+        .and_then(|()| fs::write(&path, &packed.bytes))
+        .map_err(|error| {
+            Error::new(
+                error.kind(),
+                format!("failed to write {}: {error}", path.display()),
+            )
+        })?;
+    Ok((packed, path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::test_support::TempProject;
+
+    #[test]
+    fn writes_the_tarball_to_the_project_or_destination() {
+        let project = TempProject::new("pack-command").unwrap();
+        project
+            .write("package.json", r#"{"name":"demo","version":"0.1.0"}"#)
+            .unwrap();
+        project.write(".npmignore", "*.tgz\n").unwrap();
+        project.write("index.js", "").unwrap();
+
+        let (packed, path) = pack(project.path(), None).unwrap();
+        assert_eq!(path, project.path().join("demo-0.1.0.tgz"));
+        assert_eq!(fs::read(&path).unwrap(), packed.bytes);
+
+        let destination = project.path().join("out");
+        let (repacked, path) = pack(project.path(), Some(&destination)).unwrap();
+        assert_eq!(path, destination.join("demo-0.1.0.tgz"));
+        assert_eq!(repacked.files, vec!["index.js", "package.json"]);
+        assert_eq!(repacked.bytes, packed.bytes);
+    }
+}
@@ -0,0 +1,301 @@
+use std::{
+    fs,
+    io::{Error, ErrorKind},
+    path::Path,
+    str::FromStr,
+};
+
+use base64::{engine::general_purpose::STANDARD as BASE64_STANDARD, Engine as _};
+use serde_json::{json, Map, Value};
+
+use crate::{
+    api::{publish_package, publish_registry, HttpRegistryClient},
+    core::resolver::semver::{Range, Version},
+    npmrc::NpmConfig,
+    pack::{pack_project, PackedTarball},
+    package_manifest::PackageManifest,
+};
+
+/// The dist-tag a publish moves when neither `--tag` nor `publishConfig.tag`
+/// names one.
+const DEFAULT_TAG: &str = "latest";
+
+#[derive(Debug, Default)]
+pub struct PublishOptions {
+    /// `--tag`; overrides `publishConfig.tag`.
+    pub tag: Option<String>,
+    /// `--dry-run`: pack and build the upload, but do not send it.
+    pub dry_run: bool,
+}
+
+#[derive(Debug)]
+pub struct PublishOutcome {
+    pub packed: PackedTarball,
+    /// Registry base URL the package was (or would be) published to.
+    pub registry: String,
+    pub tag: String,
+    pub dry_run: bool,
+}
+
+/// `rpm publish`: pack the project at `project_root` and upload it to its
+/// registry with the `.npmrc` credentials for that registry.
+pub async fn publish(
+    project_root: &Path,
+    options: PublishOptions,
+) -> std::io::Result<PublishOutcome> {
+    let config = NpmConfig::load(project_root)?;
+    publish_with_config(project_root, options, config).await
+}
+
+pub(crate) async fn publish_with_config(
+    project_root: &Path,
+    options: PublishOptions,
+    config: NpmConfig,
+) -> std::io::Result<PublishOutcome> {
+    let manifest_path = project_root.join("package.json");
+    let manifest = PackageManifest::read_from_path(&manifest_path)?;
+    let name = manifest.get_name();
+    if manifest.is_private() {
+        return Err(Error::new(
+            ErrorKind::PermissionDenied,
+            format!("refusing to publish {name}: package.json is marked private"),
+        ));
+    }
+    let version = manifest.get_version();
+    if Version::from_str(&version).is_err() {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            format!("invalid version in package.json: {version}"),
+        ));
+    }
+    let publish_config = manifest.publish_config.clone().unwrap_or_default();
+    let tag = options
+        .tag
+        .or(publish_config.tag)
+        .map(|tag| tag.trim().to_string())
+        .unwrap_or_else(|| DEFAULT_TAG.to_string());
+    validate_tag(&tag)?;
+    let access = match publish_config.access.as_deref() {
+        None => None,
+        Some(access @ ("public" | "restricted")) => Some(access.to_string()),
+        Some(access) => {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("invalid publishConfig.access: {access} (expected public or restricted)"),
+            ))
+        }
+    };
+
+    let packed = pack_project(project_root)?;
+    let registry = publish_registry(&config, &name, publish_config.registry.as_deref());
+    let raw_manifest = read_raw_manifest(&manifest_path)?;
+    let document = publish_document(&packed, raw_manifest, &registry, &tag, access.as_deref());
+
+    if !options.dry_run {
+        let client = HttpRegistryClient::from_config(config)?;
+        publish_package(&client, &registry, &name, &document).await?;
+    }
+    Ok(PublishOutcome {
+        packed,
+        registry,
+        tag,
+        dry_run: options.dry_run,
+    })
+}
+
+/// npm refuses tags that could be read as a version range, since
+/// `rpm add pkg@<tag>` would then resolve the range instead.
+fn validate_tag(tag: &str) -> std::io::Result<()> {
+    if tag.is_empty() || tag.contains(char::is_whitespace) || Range::from_str(tag).is_ok() {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!("invalid tag {tag:?}: a tag must be a name that is not a semver range"),
+        ));
+    }
+    Ok(())
+}
+
+/// The manifest exactly as written, so fields RPM does not model still reach
+/// the registry.
+fn read_raw_manifest(path: &Path) -> std::io::Result<Map<String, Value>> {
+    let text = fs::read_to_string(path).map_err(|error| {
+        Error::new(
+            error.kind(),
+            format!(
+                "failed to read package manifest {}: {error}",
+                path.display()
+            ),
+        )
+    })?;
+    serde_json::from_str(&text).map_err(|error| {
+        Error::new(
+            ErrorKind::InvalidData,
+            format!(
+                "failed to parse package manifest {}: {error}",
+                path.display()
+            ),
+        )
+    })
+}
+
+/// Build the document npm registries accept on `PUT /<name>`: the version's
+/// manifest with its `dist`, the dist-tag to move, and the tarball as a base64
+/// attachment.
+fn publish_document(
+    packed: &PackedTarball,
+    mut manifest: Map<String, Value>,
+    registry: &str,
+    tag: &str,
+    access: Option<&str>,
+) -> Value {
+    let name = &packed.name;
+    let version = &packed.version;
+    let basename = name.rsplit('/').next().unwrap_or(name);
+    manifest.insert("_id".to_string(), json!(format!("{name}@{version}")));
+    manifest.insert(
+        "dist".to_string(),
+        json!({
+            "integrity": packed.integrity,
+            "shasum": packed.shasum,
+            "tarball": format!("{registry}/{name}/-/{basename}-{version}.tgz"),
+        }),
+    );
+    let description = manifest.get("description").cloned().unwrap_or(Value::Null);
+
+    json!({
+        "_id": name,
+        "name": name,
+        "description": description,
+        "dist-tags": { tag: version },
+        "versions": { version.as_str(): manifest },
+        "access": access,
+        "_attachments": {
+            format!("{name}-{version}.tgz"): {
+                "content_type": "application/octet-stream",
+                "data": BASE64_STANDARD.encode(&packed.bytes),
+                "length": packed.bytes.len(),
+            }
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::test_support::{spawn_http_server, TempProject, TestResponse};
+
+    fn project(manifest: &str) -> TempProject {
+        let project = TempProject::new("publish").unwrap();
+        project.write("package.json", manifest).unwrap();
+        project.write("index.js", "module.exports = 1;\n").unwrap();
+        project
+    }
+
+    fn config(base_url: &str) -> NpmConfig {
+        NpmConfig::parse(&format!(
+            "registry=https://unused.example/\n//{}/:_authToken=publish-token\n",
+            base_url.trim_start_matches("http://")
+        ))
+    }
+
+    #[tokio::test]
+    async fn publishes_the_packed_tarball_to_the_publish_config_registry() {
+        let (base_url, server) =
+            spawn_http_server(vec![TestResponse::new(200, r#"{"ok":true}"#)]).await;
+        let project = project(&format!(
+            r#"{{"name":"@corp/tool","version":"1.2.0","description":"demo","custom":42,
+                "publishConfig":{{"registry":"{base_url}/","tag":"beta","access":"public"}}}}"#
+        ));
+
+        let outcome = publish_with_config(
+            project.path(),
+            PublishOptions {
+                tag: Some("next".to_string()),
+                dry_run: false,
+            },
+            config(&base_url),
+        )
+        .await
+        .unwrap();
+        assert_eq!(outcome.registry, base_url);
+        assert_eq!(outcome.tag, "next");
+
+        let requests = server.await.unwrap();
+        let (head, body) = requests[0].split_once("\n\n").unwrap();
+        assert!(head.starts_with("PUT /@corp%2Ftool "));
+        assert!(head.contains("authorization: Bearer publish-token"));
+
+        let document: Value = serde_json::from_str(body).unwrap();
+        assert_eq!(document["name"], "@corp/tool");
+        assert_eq!(document["dist-tags"], json!({"next": "1.2.0"}));
+        assert_eq!(document["access"], "public");
+        let version = &document["versions"]["1.2.0"];
+        assert_eq!(version["_id"], "@corp/tool@1.2.0");
+        assert_eq!(version["custom"], 42);
+        assert_eq!(version["dist"]["integrity"], outcome.packed.integrity);
+        assert_eq!(
+            version["dist"]["tarball"],
+            format!("{base_url}/@corp/tool/-/tool-1.2.0.tgz")
+        );
+        let attachment = &document["_attachments"]["@corp/tool-1.2.0.tgz"];
+        assert_eq!(attachment["length"], outcome.packed.bytes.len());
+        assert_eq!(
+            BASE64_STANDARD
+                .decode(attachment["data"].as_str().unwrap())
+                .unwrap(),
+            outcome.packed.bytes
+        );
+    }
+
+    #[tokio::test]
+    async fn dry_run_and_guards_send_nothing() {
+        let dry_run = project(r#"{"name":"tool","version":"1.0.0"}"#);
+        let outcome = publish_with_config(
+            dry_run.path(),
+            PublishOptions {
+                tag: None,
+                dry_run: true,
+            },
+            NpmConfig::parse("registry=http://127.0.0.1:9/\n"),
+        )
+        .await
+        .unwrap();
+        assert!(outcome.dry_run);
+        assert_eq!(outcome.tag, "latest");
+        assert_eq!(outcome.registry, "http://127.0.0.1:9");
+
+        let error = |manifest: &str, tag: Option<&str>| {
+            let project = project(manifest);
+            let options = PublishOptions {
+                tag: tag.map(str::to_string),
+                dry_run: false,
+            };
+            let config = NpmConfig::parse("registry=http://127.0.0.1:9/\n");
+            async move {
+                publish_with_config(project.path(), options, config)
+                    .await
+                    .unwrap_err()
+            }
+        };
+
+        let private = error(r#"{"name":"tool","version":"1.0.0","private":true}"#, None).await;
+        assert_eq!(private.kind(), ErrorKind::PermissionDenied);
+        assert_eq!(
+            private.to_string(),
+            "refusing to publish tool: package.json is marked private"
+        );
+        let range_tag = error(r#"{"name":"tool","version":"1.0.0"}"#, Some("1.x")).await;
+        assert_eq!(range_tag.kind(), ErrorKind::InvalidInput);
+        let bad_version = error(r#"{"name":"tool","version":"one"}"#, None).await;
+        assert_eq!(
+            bad_version.to_string(),
+            "invalid version in package.json: one"
+        );
+        let bad_access = error(
+            r#"{"name":"tool","version":"1.0.0","publishConfig":{"access":"everyone"}}"#,
+            None,
+        )
+        .await;
+        assert_eq!(bad_access.kind(), ErrorKind::InvalidInput);
+    }
+}
@@ -0,0 +1,19 @@
+use std::path::Path;
+
+use crate::api::{bind_registry_listener, CacheRegistryServer};
+
+/// `rpm registry serve`: serve the caches of the project at `project_root` as
+/// an npm-compatible registry on `host:port` until interrupted.
+pub async fn serve_registry(project_root: &Path, host: &str, port: u16) -> std::io::Result<()> {
+    let listener = bind_registry_listener(host, port).await?;
+    println!(
+        "serving {} at http://{}",
+        project_root.join(".rpm").display(),
+        listener.local_addr()?
+    );
+    tokio::select! {
+        result = CacheRegistryServer::for_project(project_root)
+            .serve(listener, |served| println!("{served}")) => result,
+        _ = tokio::signal::ctrl_c() => Ok(()),
+    }
+}
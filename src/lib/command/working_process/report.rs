@@ -0,0 +1,252 @@
+use chrono::{DateTime, SecondsFormat, Utc};
+use std::fmt;
+
+use crate::core::resolver::PeerProblem;
+
+/// What `add` and `install` report besides the files they write.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct InstallReport {
+    /// Deprecated packages in the resolved graph, in graph order.
+    pub deprecated: Vec<DeprecatedPackage>,
+    /// Packages resolved to an older version because every newer one that
+    /// satisfies the request is younger than `minimum-release-age`.
+    pub held_back: Vec<HeldBackPackage>,
+    /// Peer dependencies the resolved graph leaves missing or incompatible,
+    /// in graph order.
+    pub peer_problems: Vec<PeerProblem>,
+    /// Package names in the manifest's `overrides` that were not applied,
+    /// because the FIFO strategy ignores them.
+    pub ignored_overrides: Vec<String>,
+}
+
+/// A resolved package whose registry version is deprecated.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeprecatedPackage {
+    pub package_name: String,
+    pub version: String,
+    pub message: String,
+    /// `name@version` of each package from the direct dependency that pulled
+    /// this one in down to the package itself.
+    pub path: Vec<String>,
+}
+
+/// A package installed at an older version than its request selects, because
+/// the newer one is younger than `minimum-release-age`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HeldBackPackage {
+    pub package_name: String,
+    pub version: String,
+    /// The version selected without the policy.
+    pub newest: String,
+    /// When `newest` was published.
+    pub published: DateTime<Utc>,
+}
+
+impl InstallReport {
+    /// The end-of-install summary of deprecated packages, or `None` when
+    /// there are none.
+    pub fn deprecation_summary(&self) -> Option<String> {
+        if self.deprecated.is_empty() {
+            return None;
+        }
+        let mut summary = match self.deprecated.len() {
+            1 => "1 deprecated package:".to_string(),
+            count => format!("{count} deprecated packages:"),
+        };
+        for package in &self.deprecated {
+            summary.push_str(&format!("\n  {package}"));
+        }
+        Some(summary)
+    }
+
+    /// The end-of-install summary of packages `minimum-release-age` held
+    /// back, or `None` when there are none.
+    pub fn held_back_summary(&self) -> Option<String> {
+        if self.held_back.is_empty() {
+            return None;
+        }
+        let mut summary = match self.held_back.len() {
+            1 => "1 package held back by minimum-release-age:".to_string(),
+            count => format!("{count} packages held back by minimum-release-age:"),
+        };
+        for package in &self.held_back {
+            summary.push_str(&format!("\n  {package}"));
+        }
+        Some(summary)
+    }
+
+    /// The end-of-install summary of unmet peer dependencies, or `None` when
+    /// every peer is met.
+    pub fn peer_summary(&self) -> Option<String> {
+        if self.peer_problems.is_empty() {
+            return None;
+        }
+        let mut summary = match self.peer_problems.len() {
+            1 => "1 unmet peer dependency:".to_string(),
+            count => format!("{count} unmet peer dependencies:"),
+        };
+        for problem in &self.peer_problems {
+            summary.push_str(&format!("\n  {problem}"));
+        }
+        Some(summary)
+    }
+
+    /// The end-of-install note on `overrides` the FIFO strategy ignored, or
+    /// `None` when every override applied.
+    pub fn ignored_overrides_summary(&self) -> Option<String> {
+        if self.ignored_overrides.is_empty() {
+            return None;
+        }
+        let mut summary = match self.ignored_overrides.len() {
+            1 => "1 override ignored".to_string(),
+            count => format!("{count} overrides ignored"),
+        };
+        summary.push_str(
+            " (only resolution-strategy=backtracking applies overrides; \
+             set it in .npmrc):",
+        );
+        for package_name in &self.ignored_overrides {
+            summary.push_str(&format!("\n  {package_name}"));
+        }
+        Some(summary)
+    }
+}
+
+impl fmt::Display for DeprecatedPackage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}@{}: {}",
+            self.package_name, self.version, self.message
+        )?;
+        if self.path.len() > 1 {
+            write!(f, " (via {})", self.path.join(" > "))
+        } else {
+            write!(f, " (direct dependency)")
+        }
+    }
+}
+
+impl fmt::Display for HeldBackPackage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}@{} ({} was published {})",
+            self.package_name,
+            self.version,
+            self.newest,
+            self.published.to_rfc3339_opts(SecondsFormat::Secs, true)
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn deprecated(package_name: &str, path: &[&str]) -> DeprecatedPackage {
+        DeprecatedPackage {
+            package_name: package_name.to_string(),
+            version: "1.0.0".to_string(),
+            message: format!("{package_name} is unmaintained"),
+            path: path.iter().map(|key| key.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn summarizes_deprecations_with_their_dependency_paths() {
+        assert_eq!(InstallReport::default().deprecation_summary(), None);
+
+        let report = InstallReport {
+            deprecated: vec![
+                deprecated("old", &["old@1.0.0"]),
+                deprecated("inner", &["app@2.0.0", "mid@1.1.0", "inner@1.0.0"]),
+            ],
+            ..InstallReport::default()
+        };
+
+        assert_eq!(
+            report.deprecation_summary().unwrap(),
+            "2 deprecated packages:\n  \
+             old@1.0.0: old is unmaintained (direct dependency)\n  \
+             inner@1.0.0: inner is unmaintained (via app@2.0.0 > mid@1.1.0 > inner@1.0.0)"
+        );
+    }
+
+    #[test]
+    fn summarizes_held_back_packages_with_the_newer_publish_time() {
+        let held_back = HeldBackPackage {
+            package_name: "left-pad".to_string(),
+            version: "1.2.0".to_string(),
+            newest: "1.3.0".to_string(),
+            published: "2026-10-17T12:00:00Z".parse().unwrap(),
+        };
+        assert_eq!(InstallReport::default().held_back_summary(), None);
+
+        let report = InstallReport {
+            held_back: vec![held_back],
+            ..InstallReport::default()
+        };
+
+        assert_eq!(
+            report.held_back_summary().unwrap(),
+            "1 package held back by minimum-release-age:\n  \
+             left-pad@1.2.0 (1.3.0 was published 2026-10-17T12:00:00Z)"
+        );
+    }
+
+    #[test]
+    fn summarizes_overrides_the_fifo_strategy_ignored() {
+        assert_eq!(InstallReport::default().ignored_overrides_summary(), None);
+
+        let report = InstallReport {
+            ignored_overrides: vec!["left-pad".to_string(), "shared".to_string()],
+            ..InstallReport::default()
+        };
+
+        assert_eq!(
+            report.ignored_overrides_summary().unwrap(),
+            "2 overrides ignored (only resolution-strategy=backtracking applies \
+             overrides; set it in .npmrc):\n  left-pad\n  shared"
+        );
+    }
+
+    #[test]
+    fn summarizes_missing_and_incompatible_peers() {
+        assert_eq!(InstallReport::default().peer_summary(), None);
+
+        let report = InstallReport {
+            peer_problems: vec![
+                PeerProblem::Missing {
+                    package_name: "plugin".to_string(),
+                    version: "1.0.0".to_string(),
+                    peer_name: "host".to_string(),
+                    requested: "^3.0.0".to_string(),
+                },
+                PeerProblem::Incompatible {
+                    package_name: "widget".to_string(),
+                    version: "2.1.0".to_string(),
+                    peer_name: "host".to_string(),
+                    requested: "^1.0.0".to_string(),
+                    resolved_version: "2.0.0".to_string(),
+                },
+            ],
+            ..InstallReport::default()
+        };
+
+        // Peer diagnostic wording is not frozen: check that each problem is
+        // one line naming the package, the peer and range, and for the
+        // incompatible case the installed version.
+        let summary = report.peer_summary().unwrap();
+        let lines = summary.lines().skip(1).collect::<Vec<_>>();
+        assert_eq!(lines.len(), 2);
+        for (line, expected) in lines.iter().zip([
+            &["missing", "plugin@1.0.0", "host@^3.0.0"][..],
+            &["incompatible", "widget@2.1.0", "host@^1.0.0", "host@2.0.0"][..],
+        ]) {
+            for part in expected {
+                assert!(line.contains(part), "{line:?} should name {part}");
+            }
+        }
+    }
+}
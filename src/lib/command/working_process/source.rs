@@ -0,0 +1,361 @@
+use std::{
+    collections::HashMap,
+    fs,
+    io::{Error, ErrorKind, Read},
+    path::Path,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use flate2::read::GzDecoder;
+use tar::Archive;
+
+use crate::{
+    api::RegistryClient,
+    lockfile::PackageSource,
+    pack::tarball_digests,
+    package_manifest::PackageManifest,
+    registry::{tarball_cache_file_name, Registry},
+};
+
+/// A package installed from outside the registry, with the tarball it
+/// installs from. A local directory has no tarball, digests or bytes.
+pub(crate) struct SourcePackage {
+    pub source: PackageSource,
+    /// The lockfile `tarball` locator the package is fetched from again.
+    pub tarball: String,
+    pub version: String,
+    pub integrity: Option<String>,
+    pub shasum: Option<String>,
+    pub scripts: HashMap<String, String>,
+    /// `dependencies` of the package's `package.json`, as `name@range` specs.
+    pub dependencies: Vec<String>,
+    /// Tarball bytes still to be cached. A remote tarball is streamed into
+    /// the cache as it downloads and has none.
+    pub bytes: Option<Vec<u8>>,
+}
+
+impl SourcePackage {
+    /// Describe a package from its tarball `bytes` and the `manifest` it
+    /// carries.
+    pub(crate) fn new(
+        source: PackageSource,
+        tarball: String,
+        manifest: &PackageManifest,
+        bytes: Vec<u8>,
+    ) -> std::io::Result<Self> {
+        let (integrity, shasum) = tarball_digests(&bytes);
+        Ok(Self {
+            bytes: Some(bytes),
+            ..Self::cached(source, tarball, manifest, integrity, shasum)?
+        })
+    }
+
+    /// Describe a package whose tarball is already cached, by the `manifest`
+    /// it carries and the digests it hashed to.
+    pub(crate) fn cached(
+        source: PackageSource,
+        tarball: String,
+        manifest: &PackageManifest,
+        integrity: String,
+        shasum: String,
+    ) -> std::io::Result<Self> {
+        Ok(Self {
+            integrity: Some(integrity),
+            shasum: Some(shasum),
+            ..Self::local(source, tarball, manifest)?
+        })
+    }
+
+    /// Describe a package installed straight from its directory. A linked
+    /// package keeps its own `node_modules`, so its dependencies are not
+    /// resolved and its lifecycle scripts do not run.
+    pub(crate) fn local(
+        source: PackageSource,
+        tarball: String,
+        manifest: &PackageManifest,
+    ) -> std::io::Result<Self> {
+        let version = manifest.get_version();
+        if version.trim().is_empty() {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("{tarball} has no version in its package.json"),
+            ));
+        }
+        let (scripts, mut dependencies) = if source == PackageSource::Link {
+            (HashMap::new(), Vec::new())
+        } else {
+            let dependencies = manifest
+                .get_dependencies()
+                .into_iter()
+                .map(|(name, range)| format!("{name}@{range}"))
+                .collect::<Vec<_>>();
+            (manifest.get_scripts(), dependencies)
+        };
+        dependencies.sort();
+        Ok(Self {
+            source,
+            tarball,
+            version,
+            integrity: None,
+            shasum: None,
+            scripts,
+            dependencies,
+            bytes: None,
+        })
+    }
+
+    /// Describe a package tarball by the `package.json` inside it.
+    pub(crate) fn from_tarball(
+        source: PackageSource,
+        tarball: String,
+        bytes: Vec<u8>,
+    ) -> std::io::Result<Self> {
+        let manifest = tarball_manifest(bytes.as_slice())
+            .map_err(|error| manifest_read_error(&tarball, error))?;
+        Self::new(source, tarball, &manifest, bytes)
+    }
+}
+
+fn manifest_read_error(tarball: &str, error: Error) -> Error {
+    Error::new(
+        error.kind(),
+        format!("failed to read package.json from {tarball}: {error}"),
+    )
+}
+
+/// Stream a remote tarball into `cache_dir` as `package_name` at the version
+/// its `package.json` declares. The tarball is hashed on the way in and never
+/// held in memory whole; only its `package.json` is read back.
+pub(crate) async fn fetch_remote_package(
+    url: &str,
+    package_name: &str,
+    cache_dir: &Path,
+    client: &impl RegistryClient,
+) -> std::io::Result<SourcePackage> {
+    // The version is unknown until the manifest is read, so the download
+    // lands under a unique key and moves once it is.
+    let staging_key = format!("{package_name}@remote-{}", unique_suffix());
+    let cached = Registry::download_tarball_to_cache(&staging_key, url, cache_dir, client).await?;
+    let path = cached.path.clone();
+    let package = async {
+        let manifest =
+            tokio::task::spawn_blocking(move || fs::File::open(&path).and_then(tarball_manifest))
+                .await
+                .map_err(Error::other)?
+                .map_err(|error| manifest_read_error(url, error))?;
+        let package = SourcePackage::cached(
+            PackageSource::Remote,
+            url.to_string(),
+            &manifest,
+            cached.integrity,
+            cached.shasum,
+        )?;
+        let target = cache_dir.join(tarball_cache_file_name(package_name, &package.version));
+        tokio::fs::rename(&cached.path, &target)
+            .await
+            .map_err(|error| {
+                Error::new(
+                    error.kind(),
+                    format!("failed to cache {}: {error}", target.display()),
+                )
+            })?;
+        Ok(package)
+    }
+    .await;
+    if package.is_err() {
+        let _ = tokio::fs::remove_file(&cached.path).await;
+    }
+    package
+}
+
+/// Read a `file:` tarball, resolving a relative `path` against the project
+/// root.
+pub(crate) fn read_local_tarball(project_root: &Path, path: &str) -> std::io::Result<Vec<u8>> {
+    let path = project_root.join(path);
+    fs::read(&path).map_err(|error| {
+        Error::new(
+            error.kind(),
+            format!("failed to read {}: {error}", path.display()),
+        )
+    })
+}
+
+/// Read the `package.json` of a `file:` or `link:` directory, resolving a
+/// relative `path` against the project root.
+pub(crate) fn read_local_manifest(
+    project_root: &Path,
+    path: &str,
+) -> std::io::Result<PackageManifest> {
+    let manifest_path = project_root.join(path).join("package.json");
+    if !manifest_path.is_file() {
+        return Err(Error::new(
+            ErrorKind::NotFound,
+            format!("no package.json in {}", project_root.join(path).display()),
+        ));
+    }
+    PackageManifest::read_from_path(&manifest_path)
+}
+
+/// The `package.json` at the top of a package tarball. npm tarballs keep it
+/// under `package/`, but any single top-level directory is accepted.
+fn tarball_manifest(reader: impl Read) -> std::io::Result<PackageManifest> {
+    let mut archive = Archive::new(GzDecoder::new(reader));
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let path = entry.path()?.into_owned();
+        let mut components = path.components();
+        let is_manifest = components.next().is_some()
+            && components.next().map(|name| name.as_os_str()) == Some("package.json".as_ref())
+            && components.next().is_none();
+        if !is_manifest {
+            continue;
+        }
+        let mut text = Vec::new();
+        entry.read_to_end(&mut text)?;
+        return serde_json::from_slice(&text)
+            .map_err(|error| Error::new(ErrorKind::InvalidData, error.to_string()));
+    }
+    Err(Error::new(
+        ErrorKind::NotFound,
+        "no package.json in tarball",
+    ))
+}
+
+/// Cache tarball `bytes` as `package_name` at `version`, writing through a
+/// sibling file and renaming so a reader never sees a partial tarball.
+pub(crate) fn cache_tarball(
+    cache_dir: &Path,
+    package_name: &str,
+    version: &str,
+    bytes: &[u8],
+) -> std::io::Result<()> {
+    let path = cache_dir.join(tarball_cache_file_name(package_name, version));
+    let staging = path.with_extension(format!("tgz.{}", unique_suffix()));
+    fs::create_dir_all(cache_dir)
+        .and_then(|()| fs::write(&staging, bytes))
+        .and_then(|()| fs::rename(&staging, &path))
+        .map_err(|error| {
+            let _ = fs::remove_file(&staging);
+            Error::new(
+                error.kind(),
+                format!("failed to cache {}: {error}", path.display()),
+            )
+        })
+}
+
+pub(crate) fn unique_suffix() -> String {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_nanos())
+        .unwrap_or_default();
+    format!(
+        "{}-{nanos}-{}",
+        std::process::id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        api::DirectoryRegistryClient,
+        util::test_support::{package_tarball, TempProject},
+    };
+
+    #[test]
+    fn describes_a_tarball_by_its_own_manifest() {
+        let bytes = package_tarball(&[(
+            "package.json",
+            r#"{"name":"foo","version":"1.0.0","dependencies":{"b":"^2","a":"1.x"},
+                "scripts":{"postinstall":"node setup.js"}}"#,
+        )]);
+        let package = SourcePackage::from_tarball(
+            PackageSource::File,
+            "file:vendor/foo-1.0.0.tgz".to_string(),
+            bytes.clone(),
+        )
+        .unwrap();
+
+        assert_eq!(package.version, "1.0.0");
+        assert_eq!(package.dependencies, vec!["a@1.x", "b@^2"]);
+        assert_eq!(package.scripts["postinstall"], "node setup.js");
+        let (integrity, shasum) = tarball_digests(&bytes);
+        assert_eq!(
+            (package.integrity, package.shasum),
+            (Some(integrity), Some(shasum))
+        );
+
+        let error = SourcePackage::from_tarball(
+            PackageSource::Remote,
+            "https://host/empty.tgz".to_string(),
+            package_tarball(&[("index.js", "")]),
+        )
+        .err()
+        .unwrap();
+        assert_eq!(
+            error.to_string(),
+            "failed to read package.json from https://host/empty.tgz: no package.json in tarball"
+        );
+    }
+
+    #[tokio::test]
+    async fn streams_a_remote_tarball_into_the_cache_under_its_version() {
+        let registry = TempProject::new("remote-source-registry").unwrap();
+        let bytes = package_tarball(&[(
+            "package.json",
+            r#"{"name":"foo","version":"1.2.3","dependencies":{"a":"^1"}}"#,
+        )]);
+        registry.write("foo/-/foo-1.2.3.tgz", &bytes).unwrap();
+        let client = DirectoryRegistryClient::new(registry.path());
+        let project = TempProject::new("remote-source-project").unwrap();
+        let cache_dir = project.path().join("cache");
+
+        let package = fetch_remote_package(
+            "https://host/foo/-/foo-1.2.3.tgz",
+            "foo",
+            &cache_dir,
+            &client,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(package.version, "1.2.3");
+        assert_eq!(package.dependencies, vec!["a@^1"]);
+        assert!(package.bytes.is_none());
+        let (integrity, shasum) = tarball_digests(&bytes);
+        assert_eq!(
+            (package.integrity, package.shasum),
+            (Some(integrity), Some(shasum))
+        );
+        let cached = fs::read_dir(&cache_dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(cached, vec!["foo@1.2.3.tgz"]);
+        assert_eq!(fs::read(cache_dir.join("foo@1.2.3.tgz")).unwrap(), bytes);
+
+        registry
+            .write(
+                "empty/-/empty-1.0.0.tgz",
+                package_tarball(&[("index.js", "")]),
+            )
+            .unwrap();
+        let error = fetch_remote_package(
+            "https://host/empty/-/empty-1.0.0.tgz",
+            "empty",
+            &cache_dir,
+            &client,
+        )
+        .await
+        .err()
+        .unwrap();
+        assert!(
+            error.to_string().contains("no package.json in tarball"),
+            "{error}"
+        );
+        assert_eq!(fs::read_dir(&cache_dir).unwrap().count(), 1);
+    }
+}
@@ -0,0 +1,215 @@
+//! Git access for git dependencies, through the `git` executable on `PATH`.
+//!
+//! `docs/specs/core/resolver/SPEC.md` owns how a git request resolves to a
+//! commit; `docs/specs/core/lockfile/SPEC.md` owns how that commit is locked.
+
+use std::{
+    io::{Error, ErrorKind},
+    path::Path,
+};
+
+use tokio::process::Command;
+
+use crate::core::resolver::{
+    semver,
+    source::{GitCommittish, GitSource},
+};
+
+/// The lockfile `tarball` of a git package: the clone URL and the commit it
+/// was packed from, `git+<url>#<sha>`.
+pub fn locked_tarball(url: &str, commit: &str) -> String {
+    format!("git+{url}#{commit}")
+}
+
+/// Split a lockfile `tarball` written by [`locked_tarball`] back into the
+/// clone URL and commit, or `None` for a registry tarball URL.
+pub fn parse_locked_tarball(tarball: &str) -> Option<(&str, &str)> {
+    tarball
+        .strip_prefix("git+")?
+        .rsplit_once('#')
+        .filter(|(url, commit)| !url.is_empty() && !commit.is_empty())
+}
+
+/// Resolve `source`'s commit-ish against the remote's refs without cloning.
+///
+/// A `#semver:` range picks the highest satisfying tag, a branch or tag name
+/// picks the commit it points at, and no fragment picks `HEAD`. A fragment
+/// that names no ref but looks like a commit SHA is returned as-is for
+/// [`checkout`] to resolve.
+pub async fn resolve_committish(source: &GitSource) -> std::io::Result<String> {
+    if let GitCommittish::Ref(reference) = &source.committish {
+        if reference.len() == 40 && is_hex(reference) {
+            return Ok(reference.to_ascii_lowercase());
+        }
+    }
+
+    let listing = git(&["ls-remote", &source.url], None).await?;
+    let mut refs = Vec::<(String, String)>::new();
+    for line in listing.lines() {
+        let Some((sha, name)) = line.split_once('\t') else {
+            continue;
+        };
+        // An annotated tag is listed twice; the peeled `^{}` line names the
+        // commit rather than the tag object.
+        match name.strip_suffix("^{}") {
+            Some(tag) => match refs.iter_mut().find(|(_, name)| name == tag) {
+                Some(entry) => entry.0 = sha.to_string(),
+                None => refs.push((sha.to_string(), tag.to_string())),
+            },
+            None => refs.push((sha.to_string(), name.to_string())),
+        }
+    }
+    let lookup = |name: &str| {
+        refs.iter()
+            .find(|(_, candidate)| candidate == name)
+            .map(|(sha, _)| sha.clone())
+    };
+
+    match &source.committish {
+        GitCommittish::Default => lookup("HEAD").ok_or_else(|| {
+            Error::new(
+                ErrorKind::NotFound,
+                format!("{} has no HEAD to install", source.url),
+            )
+        }),
+        GitCommittish::Ref(reference) => lookup(&format!("refs/tags/{reference}"))
+            .or_else(|| lookup(&format!("refs/heads/{reference}")))
+            .or_else(|| lookup(reference))
+            .or_else(|| (reference.len() >= 4 && is_hex(reference)).then(|| reference.clone()))
+            .ok_or_else(|| {
+                Error::new(
+                    ErrorKind::NotFound,
+                    format!("{} has no ref named {reference}", source.url),
+                )
+            }),
+        GitCommittish::Semver(range) => {
+            let tags = refs
+                .iter()
+                .filter_map(|(sha, name)| {
+                    let tag = name.strip_prefix("refs/tags/")?;
+                    Some((semver::clean(tag)?, sha))
+                })
+                .collect::<Vec<_>>();
+            let selected = semver::max_satisfying(tags.iter().map(|(v, _)| v.as_str()), range)
+                .map_err(|error| {
+                    Error::new(
+                        ErrorKind::InvalidInput,
+                        format!("invalid semver range {range}: {error}"),
+                    )
+                })?;
+            selected
+                .and_then(|version| tags.iter().find(|(v, _)| v == version))
+                .map(|(_, sha)| (*sha).clone())
+                .ok_or_else(|| {
+                    Error::new(
+                        ErrorKind::NotFound,
+                        format!("no tag in {} satisfies semver:{range}", source.url),
+                    )
+                })
+        }
+    }
+}
+
+/// Clone `url` into `destination` (which must not exist yet), check out
+/// `committish` detached, and return the full SHA of the checked-out commit.
+pub async fn checkout(url: &str, committish: &str, destination: &Path) -> std::io::Result<String> {
+    let destination_arg = destination.to_string_lossy();
+    git(
+        &["clone", "--quiet", "--no-checkout", url, &destination_arg],
+        None,
+    )
+    .await?;
+    git(
+        &["checkout", "--quiet", "--detach", committish],
+        Some(destination),
+    )
+    .await?;
+    let commit = git(&["rev-parse", "HEAD"], Some(destination)).await?;
+    Ok(commit.trim().to_string())
+}
+
+fn is_hex(text: &str) -> bool {
+    text.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+async fn git(args: &[&str], current_dir: Option<&Path>) -> std::io::Result<String> {
+    let mut command = Command::new("git");
+    command.args(args).env("GIT_TERMINAL_PROMPT", "0");
+    if let Some(current_dir) = current_dir {
+        command.current_dir(current_dir);
+    }
+    let output = command.output().await.map_err(|error| {
+        Error::new(
+            error.kind(),
+            format!("git dependencies need git on PATH: {error}"),
+        )
+    })?;
+    if !output.status.success() {
+        return Err(Error::other(format!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::test_support::{GitRepository, TempProject};
+
+    #[tokio::test]
+    async fn resolves_tags_branches_and_semver_ranges() {
+        let project = TempProject::new("git-resolve").unwrap();
+        let repo = GitRepository::new(project.path());
+        let manifest = |version: &str| format!(r#"{{"name":"lib","version":"{version}"}}"#);
+        let v1 = repo.commit(&[("package.json", &manifest("1.0.0"))], Some("v1.0.0"));
+        let v14 = repo.commit(&[("package.json", &manifest("1.4.0"))], Some("v1.4.0"));
+        let head = repo.commit(&[("package.json", &manifest("2.0.0"))], None);
+        let source = |committish| GitSource {
+            url: repo.url(),
+            committish,
+        };
+
+        assert_eq!(
+            resolve_committish(&source(GitCommittish::Default))
+                .await
+                .unwrap(),
+            head
+        );
+        assert_eq!(
+            resolve_committish(&source(GitCommittish::Ref("v1.0.0".to_string())))
+                .await
+                .unwrap(),
+            v1
+        );
+        assert_eq!(
+            resolve_committish(&source(GitCommittish::Ref("main".to_string())))
+                .await
+                .unwrap(),
+            head
+        );
+        assert_eq!(
+            resolve_committish(&source(GitCommittish::Semver("^1.0.0".to_string())))
+                .await
+                .unwrap(),
+            v14
+        );
+        let missing = resolve_committish(&source(GitCommittish::Semver("^3".to_string())))
+            .await
+            .unwrap_err();
+        assert_eq!(missing.kind(), ErrorKind::NotFound);
+
+        let destination = project.path().join("checkout");
+        let commit = checkout(&repo.url(), &v1[..10], &destination)
+            .await
+            .unwrap();
+        assert_eq!(commit, v1);
+        assert_eq!(
+            parse_locked_tarball(&locked_tarball("file:///repo.git", &commit)),
+            Some(("file:///repo.git", commit.as_str()))
+        );
+        assert_eq!(parse_locked_tarball("https://registry/x.tgz"), None);
+    }
+}
@@ -1,9 +1,10 @@
 pub(crate) mod constraint;
 
+use chrono::{DateTime, SecondsFormat, Utc};
 use constraint::LOCK_FILE_PATH;
 use serde::{Deserialize, Serialize};
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
     fs::OpenOptions,
     io::{Error, ErrorKind, Read, Result, Write},
     path::Path,
@@ -23,6 +24,54 @@ pub enum Relationship {
     Transitive,
 }
 
+/// Where a package that did not come from the registry was installed from.
+/// Registry packages record no source.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PackageSource {
+    /// A git repository; `tarball` is `git+<url>#<commit>`.
+    Git,
+    /// A tarball URL; `tarball` is that URL.
+    Remote,
+    /// A tarball file; `tarball` is `file:<path>`.
+    File,
+    /// A package directory copied into `node_modules`; `tarball` is
+    /// `file:<path>`.
+    Directory,
+    /// A package directory symlinked into `node_modules`; `tarball` is
+    /// `link:<path>`.
+    Link,
+}
+
+impl PackageSource {
+    /// Whether the package installs straight from a directory rather than
+    /// from a cached tarball.
+    pub fn is_local_directory(self) -> bool {
+        matches!(self, Self::Directory | Self::Link)
+    }
+}
+
+/// A package to record with [`LockFile::add_dependency_entry`]. Only the
+/// fields a caller knows need setting; the rest come from
+/// `..Default::default()`.
+#[derive(Debug, Clone, Default)]
+pub struct LockEntry {
+    pub name: String,
+    pub requested: String,
+    pub version: String,
+    pub relationship: Relationship,
+    pub source: Option<PackageSource>,
+    /// The registry package an `npm:` alias installs under `name`.
+    pub alias_of: Option<String>,
+    pub deprecated: Option<String>,
+    pub tarball: Option<String>,
+    pub integrity: Option<String>,
+    pub shasum: Option<String>,
+    pub scripts: Option<HashMap<String, String>>,
+    /// Dependency edges as requested package references.
+    pub dependencies: Vec<String>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Dependency {
     #[serde(default)]
@@ -34,15 +83,29 @@ pub struct Dependency {
     #[serde(default)]
     relationship: Relationship,
     #[serde(default, skip_serializing_if = "Option::is_none")]
+    source: Option<PackageSource>,
+    /// The registry package an `npm:` alias installs under `name`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    alias_of: Option<String>,
+    /// The registry's deprecation message for this version.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    deprecated: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     tarball: Option<String>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     integrity: Option<String>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     shasum: Option<String>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
-    scripts: Option<HashMap<String, String>>,
+    scripts: Option<BTreeMap<String, String>>,
     #[serde(default)]
-    dependencies: HashSet<String>,
+    dependencies: BTreeSet<String>,
+    /// `peerDependencies` as `name@range` specs.
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    peer_dependencies: BTreeSet<String>,
+    /// Names of the peers `peerDependenciesMeta` marks optional.
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    optional_peers: BTreeSet<String>,
 }
 
 impl Dependency {
@@ -52,14 +115,23 @@ impl Dependency {
             requested: version.clone(),
             version,
             relationship: Relationship::Transitive,
+            source: None,
+            alias_of: None,
+            deprecated: None,
             tarball: None,
             integrity: None,
             shasum: None,
             scripts: None,
-            dependencies: dependencies.unwrap_or_default(),
+            dependencies: dependencies.unwrap_or_default().into_iter().collect(),
+            peer_dependencies: BTreeSet::new(),
+            optional_peers: BTreeSet::new(),
         }
     }
 
+    pub fn get_name(&self) -> String {
+        self.name.clone()
+    }
+
     pub fn get_version(&self) -> String {
         self.version.clone()
     }
@@ -82,9 +154,37 @@ impl Dependency {
     }
 
     pub fn get_dependencies(&self) -> Vec<String> {
-        let mut dependencies = self.dependencies.iter().cloned().collect::<Vec<_>>();
-        dependencies.sort();
-        dependencies
+        self.dependencies.iter().cloned().collect()
+    }
+
+    pub fn get_peer_dependencies_name(&self) -> HashSet<String> {
+        self.peer_dependencies
+            .iter()
+            .map(|peer| parse_library_name(peer.clone()).0)
+            .collect()
+    }
+
+    /// `peerDependencies` specs, each with whether it is optional.
+    pub fn get_peer_dependencies(&self) -> Vec<(String, bool)> {
+        self.peer_dependencies
+            .iter()
+            .map(|peer| {
+                let (name, _) = parse_library_name(peer.clone());
+                (peer.clone(), self.optional_peers.contains(&name))
+            })
+            .collect()
+    }
+
+    pub fn get_source(&self) -> Option<PackageSource> {
+        self.source
+    }
+
+    pub fn get_alias_of(&self) -> Option<String> {
+        self.alias_of.clone()
+    }
+
+    pub fn get_deprecated(&self) -> Option<String> {
+        self.deprecated.clone()
     }
 
     pub fn get_tarball(&self) -> Option<String> {
@@ -100,15 +200,19 @@ impl Dependency {
     }
 
     pub fn get_scripts(&self) -> Option<HashMap<String, String>> {
-        self.scripts.clone()
+        self.scripts
+            .as_ref()
+            .map(|scripts| scripts.clone().into_iter().collect())
     }
 
     #[cfg(test)]
     pub(crate) fn set_scripts(&mut self, scripts: Option<HashMap<String, String>>) {
-        self.scripts = scripts;
+        self.scripts = scripts.map(|scripts| scripts.into_iter().collect());
     }
 }
 
+/// Entries, dependency lists, and scripts are kept in ordered collections so
+/// the same resolved graph always serializes to the same bytes.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LockFile {
     lockfile_version: u32,
@@ -116,8 +220,12 @@ pub struct LockFile {
     name: String,
     #[serde(default)]
     version: String,
+    /// `--before` cutoff, in RFC 3339: only versions published before it
+    /// were resolved.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    before: Option<String>,
     #[serde(flatten)]
-    dependencies: HashMap<String, Dependency>,
+    dependencies: BTreeMap<String, Dependency>,
 }
 
 impl LockFile {
@@ -126,7 +234,8 @@ impl LockFile {
             lockfile_version: LOCKFILE_VERSION,
             name: String::new(),
             version: String::new(),
-            dependencies: HashMap::new(),
+            before: None,
+            dependencies: BTreeMap::new(),
         }
     }
 
@@ -183,6 +292,17 @@ impl LockFile {
                 ),
             )
         })?;
+        if let Some(before) = &lock.before {
+            before.parse::<DateTime<Utc>>().map_err(|error| {
+                Error::new(
+                    ErrorKind::InvalidData,
+                    format!(
+                        "invalid before {before:?} in {}: {error}",
+                        path.as_ref().display()
+                    ),
+                )
+            })?;
+        }
         lock.normalize_entries();
 
         Ok(lock)
@@ -209,69 +329,122 @@ impl LockFile {
         self.version = version;
     }
 
-    pub fn add_dependency(&mut self, name: &String, version: String, dependencies: &[String]) {
-        let package_name = package_name_from_lock_key(name);
+    /// The publish-date cutoff this lockfile was resolved under, if any.
+    pub fn get_before(&self) -> Option<DateTime<Utc>> {
+        self.before.as_ref().and_then(|before| before.parse().ok())
+    }
+
+    /// Resolve under the publish-date cutoff `before`. Entries locked under a
+    /// different cutoff (or none) are dropped so they resolve again under
+    /// this one.
+    pub fn set_before(&mut self, before: DateTime<Utc>) {
+        if self.get_before() != Some(before) {
+            self.dependencies.clear();
+        }
+        self.before = Some(before.to_rfc3339_opts(SecondsFormat::AutoSi, true));
+    }
+
+    /// Keep only the entries `keep` names, recording the requested range and
+    /// relationship it gives each. Used by `rpm dedupe` to drop entries the
+    /// deduplicated graph no longer reaches.
+    pub(crate) fn retain_packages(&mut self, mut keep: HashMap<String, (String, Relationship)>) {
+        self.dependencies
+            .retain(|key, dependency| match keep.remove(key) {
+                Some((requested, relationship)) => {
+                    dependency.requested = requested;
+                    dependency.relationship = relationship;
+                    true
+                }
+                None => false,
+            });
+    }
+
+    pub fn add_dependency(&mut self, name: &str, version: String, dependencies: &[String]) {
         self.add_dependency_entry(
             name,
-            package_name,
-            version.clone(),
-            version,
-            Relationship::Transitive,
-            None,
-            None,
-            None,
-            None,
-            dependencies,
+            LockEntry {
+                name: package_name_from_lock_key(name),
+                requested: version.clone(),
+                version,
+                dependencies: dependencies.to_vec(),
+                ..Default::default()
+            },
         );
     }
 
-    #[allow(clippy::too_many_arguments)]
-    pub fn add_dependency_entry(
-        &mut self,
-        key: &String,
-        package_name: String,
-        requested: String,
-        version: String,
-        relationship: Relationship,
-        tarball: Option<String>,
-        integrity: Option<String>,
-        shasum: Option<String>,
-        scripts: Option<HashMap<String, String>>,
-        dependencies: &[String],
-    ) {
+    /// Record `entry` under `key`. An existing entry takes the new fields,
+    /// keeps the stronger relationship (and the `requested` range that came
+    /// with it), and gains the new dependency edges.
+    pub fn add_dependency_entry(&mut self, key: &str, entry: LockEntry) {
+        let LockEntry {
+            name,
+            requested,
+            version,
+            relationship,
+            source,
+            alias_of,
+            deprecated,
+            tarball,
+            integrity,
+            shasum,
+            scripts,
+            dependencies,
+        } = entry;
+        let scripts = scripts.map(|scripts| scripts.into_iter().collect());
         if let Some(dep) = self.dependencies.get_mut(key) {
             let merged_relationship = merge_relationship(&dep.relationship, relationship.clone());
-            dep.name = package_name;
+            dep.name = name;
             if should_replace_requested(&dep.relationship, &relationship) {
                 dep.requested = requested;
             }
             dep.version = version;
             dep.relationship = merged_relationship;
+            dep.source = source;
+            dep.alias_of = alias_of;
+            dep.deprecated = deprecated;
             dep.tarball = tarball;
             dep.integrity = integrity;
             dep.shasum = shasum;
             dep.scripts = scripts;
-            dependencies.iter().for_each(|value| {
-                dep.dependencies.insert(value.clone());
-            });
+            dep.dependencies.extend(dependencies);
         } else {
             self.dependencies.insert(
-                key.clone(),
+                key.to_string(),
                 Dependency {
-                    name: package_name,
+                    name,
                     requested,
                     version,
                     relationship,
+                    source,
+                    alias_of,
+                    deprecated,
                     tarball,
                     integrity,
                     shasum,
                     scripts,
-                    dependencies: HashSet::from_iter(dependencies.iter().cloned()),
+                    dependencies: dependencies.into_iter().collect(),
+                    peer_dependencies: BTreeSet::new(),
+                    optional_peers: BTreeSet::new(),
                 },
             );
         }
     }
 
+    /// Record the `peerDependencies` of the entry at `key`, each a
+    /// `name@range` spec with whether it is optional. Does nothing when no
+    /// entry has that key.
+    pub fn set_peer_dependencies(&mut self, key: &str, peers: &[(String, bool)]) {
+        let Some(dependency) = self.dependencies.get_mut(key) else {
+            return;
+        };
+        dependency.peer_dependencies = peers.iter().map(|(peer, _)| peer.clone()).collect();
+        dependency.optional_peers = peers
+            .iter()
+            .filter(|(_, optional)| *optional)
+            .map(|(peer, _)| parse_library_name(peer.clone()).0)
+            .collect();
+    }
+
     pub fn save(&self) -> Result<()> {
         self.save_to_path(LOCK_FILE_PATH)
     }
@@ -409,27 +582,23 @@ mod lock_file_test {
         let key = "shared@1.0.0".to_string();
         lock.add_dependency_entry(
             &key,
-            "shared".to_string(),
-            "^1.0.0".to_string(),
-            "1.0.0".to_string(),
-            Relationship::Direct,
-            None,
-            None,
-            None,
-            None,
-            &[],
+            LockEntry {
+                name: "shared".to_string(),
+                requested: "^1.0.0".to_string(),
+                version: "1.0.0".to_string(),
+                relationship: Relationship::Direct,
+                ..Default::default()
+            },
         );
         lock.add_dependency_entry(
             &key,
-            "shared".to_string(),
-            "1.0.0".to_string(),
-            "1.0.0".to_string(),
-            Relationship::Transitive,
-            None,
-            None,
-            None,
-            None,
-            &[],
+            LockEntry {
+                name: "shared".to_string(),
+                requested: "1.0.0".to_string(),
+                version: "1.0.0".to_string(),
+                relationship: Relationship::Transitive,
+                ..Default::default()
+            },
         );
 
         assert_eq!(
@@ -454,6 +623,36 @@ mod lock_file_test {
         assert_eq!(lock.version, "0.1.0");
     }
 
+    #[test]
+    fn before_cutoff_round_trips_and_a_new_cutoff_drops_locked_entries() {
+        let temp = crate::util::test_support::TempProject::new("lockfile-before").unwrap();
+        let path = temp.path().join("rpm.lock");
+        let before = "2026-03-01T00:00:00Z".parse::<DateTime<Utc>>().unwrap();
+        let mut lock = LockFile::empty();
+        lock.add_dependency("tiny@1.0.0", "1.0.0".to_string(), &[]);
+
+        lock.set_before(before);
+        assert!(lock.get_packages().is_empty());
+        lock.add_dependency("tiny@1.0.0", "1.0.0".to_string(), &[]);
+        lock.set_before(before);
+        assert_eq!(lock.get_packages().len(), 1);
+        lock.save_to_path(&path).unwrap();
+
+        let saved = std::fs::read_to_string(&path).unwrap();
+        assert!(saved.contains("before = \"2026-03-01T00:00:00Z\""));
+        let loaded = LockFile::load_from_path(&path).unwrap();
+        assert_eq!(loaded.get_before(), Some(before));
+
+        std::fs::write(
+            &path,
+            saved.replace("2026-03-01T00:00:00Z", "the first of March"),
+        )
+        .unwrap();
+        let error = LockFile::load_from_path(&path).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+        assert!(error.to_string().contains("invalid before"));
+    }
+
     #[test]
     fn load_rejects_non_empty_lockfile_without_version_marker() {
         let error =
@@ -475,7 +674,7 @@ mod lock_file_test {
             )
             .unwrap();
         let mut lock = LockFile::empty();
-        lock.add_dependency(&"tiny@1.0.0".to_string(), "1.0.0".to_string(), &[]);
+        lock.add_dependency("tiny@1.0.0", "1.0.0".to_string(), &[]);
 
         lock.save_to_path(&path).unwrap();
 
@@ -483,4 +682,81 @@ mod lock_file_test {
         assert!(saved.contains("[\"tiny@1.0.0\"]"));
         assert!(!saved.contains("stale-package"));
     }
+
+    #[test]
+    fn save_to_path_output_does_not_depend_on_insertion_order() {
+        let temp = crate::util::test_support::TempProject::new("lockfile-order").unwrap();
+        let entries = [
+            (
+                "zeta@1.0.0",
+                vec!["beta@^1.0.0".to_string(), "alpha@^2.0.0".to_string()],
+            ),
+            ("alpha@2.0.0", vec![]),
+            ("beta@1.0.0", vec!["alpha@^2.0.0".to_string()]),
+        ];
+        let scripts = HashMap::from([
+            ("preinstall".to_string(), "echo pre".to_string()),
+            ("install".to_string(), "echo install".to_string()),
+            ("postinstall".to_string(), "echo post".to_string()),
+        ]);
+        let mut saved = Vec::new();
+
+        for (index, order) in [[0, 1, 2], [2, 1, 0], [1, 0, 2]].iter().enumerate() {
+            let mut lock = LockFile::empty();
+            for &entry in order {
+                let (key, dependencies) = &entries[entry];
+                lock.add_dependency(key, "1.0.0".to_string(), dependencies);
+            }
+            lock.dependencies
+                .get_mut("zeta@1.0.0")
+                .unwrap()
+                .set_scripts(Some(scripts.clone()));
+            let path = temp.path().join(format!("rpm-{index}.lock"));
+            lock.save_to_path(&path).unwrap();
+            saved.push(std::fs::read(path).unwrap());
+        }
+
+        assert!(saved.windows(2).all(|pair| pair[0] == pair[1]));
+        let text = String::from_utf8(saved.remove(0)).unwrap();
+        let alpha = text.find("[\"alpha@2.0.0\"]").unwrap();
+        let beta = text.find("[\"beta@1.0.0\"]").unwrap();
+        let zeta = text.find("[\"zeta@1.0.0\"]").unwrap();
+        assert!(alpha < beta && beta < zeta);
+        assert!(text.contains("dependencies = [\"alpha@^2.0.0\", \"beta@^1.0.0\"]"));
+    }
+
+    #[test]
+    fn peer_dependencies_round_trip_and_are_omitted_when_empty() {
+        let temp = crate::util::test_support::TempProject::new("lockfile-peers").unwrap();
+        let path = temp.path().join("rpm.lock");
+        let mut lock = LockFile::empty();
+        lock.add_dependency("plugin@1.0.0", "1.0.0".to_string(), &[]);
+        lock.add_dependency("host@2.0.0", "2.0.0".to_string(), &[]);
+        lock.set_peer_dependencies(
+            "plugin@1.0.0",
+            &[
+                ("host@^2.0.0".to_string(), false),
+                ("typescript@>=5".to_string(), true),
+            ],
+        );
+        lock.save_to_path(&path).unwrap();
+
+        let text = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(text.matches("peer_dependencies").count(), 1);
+        assert_eq!(text.matches("optional_peers").count(), 1);
+        let lock = LockFile::load_from_path(&path).unwrap();
+        let plugin = lock.get_dependency("plugin@1.0.0").unwrap();
+        assert_eq!(
+            plugin.get_peer_dependencies(),
+            vec![
+                ("host@^2.0.0".to_string(), false),
+                ("typescript@>=5".to_string(), true)
+            ]
+        );
+        assert!(lock
+            .get_dependency("host@2.0.0")
+            .unwrap()
+            .get_peer_dependencies()
+            .is_empty());
+    }
 }
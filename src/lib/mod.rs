@@ -6,8 +6,11 @@ pub mod core;
 pub mod api;
 pub mod command;
 pub mod common;
+pub mod git;
 pub mod lockfile;
 pub mod node_linker;
+pub mod npmrc;
+pub mod pack;
 pub mod package_manifest;
 pub mod parser;
 pub mod registry;
@@ -11,7 +11,8 @@ use std::{
 use crate::{
     common::constraint::CACHE_DIR,
     lockfile::constraint::LOCK_FILE_PATH,
-    lockfile::{Dependency, LockFile},
+    lockfile::{Dependency, LockFile, PackageSource},
+    pack::collect_files,
     package_manifest::{BinField, PackageManifest},
     registry::tarball_cache_file_name,
 };
@@ -255,7 +256,7 @@ impl NodeModules {
                 result.map(|_| None)
             }
         } else {
-            Self::build_staged(&staging_dir, lock_file, cache_dir)
+            Self::build_staged(&staging_dir, lock_file, cache_dir, project_root)
                 .and_then(|_| {
                     Self::run_scripts(
                         project_root,
@@ -304,6 +305,7 @@ impl NodeModules {
         staging_dir: P,
         lock_file: &LockFile,
         cache_dir: R,
+        project_root: &Path,
     ) -> Result<Self, std::io::Error>
     where
         P: AsRef<Path>,
@@ -317,7 +319,8 @@ impl NodeModules {
                 Error::new(ErrorKind::InvalidData, "lockfile has no packages to link"),
             ));
         }
-        let cache_resolver = NodeResolver::new(cache_dir.as_ref().to_path_buf());
+        let cache_resolver =
+            NodeResolver::new(cache_dir.as_ref().to_path_buf(), project_root.to_path_buf());
         cache_resolver
             .resolve_deps(&mut modules, &packages)
             .map_err(|error| phase_error("extract", error))?;
@@ -339,7 +342,13 @@ impl NodeModules {
             print!("\r\x1B[K");
             let name = package_name_from_lock_key(key)?;
             let root = self.get_path();
-            for dep_name in dependency.get_dependencies_name() {
+            // A peer links to the copy its parent sees, the flat one, when it
+            // is installed at all; a missing peer was reported at resolve.
+            let peers = dependency
+                .get_peer_dependencies_name()
+                .into_iter()
+                .filter(|peer| root.join(peer).exists());
+            for dep_name in dependency.get_dependencies_name().into_iter().chain(peers) {
                 validate_package_name(&dep_name, &dep_name)?;
                 let destination = root.join(name).join("node_modules").join(&dep_name);
                 let dest_node_modules = destination.parent().ok_or_else(|| {
@@ -776,11 +785,16 @@ fn validate_package_name(name: &str, key: &str) -> Result<(), std::io::Error> {
 
 struct NodeResolver {
     cache_dir: PathBuf,
+    /// Root that `file:` and `link:` directory paths are relative to.
+    project_root: PathBuf,
 }
 
 impl NodeResolver {
-    fn new(cache_dir: PathBuf) -> Self {
-        Self { cache_dir }
+    fn new(cache_dir: PathBuf, project_root: PathBuf) -> Self {
+        Self {
+            cache_dir,
+            project_root,
+        }
     }
 
     fn resolve_deps(
@@ -808,10 +822,26 @@ impl NodeResolver {
         dependency: &Dependency,
     ) -> Result<(), std::io::Error> {
         let name = package_name_from_lock_key(&key)?;
+        match dependency.get_source() {
+            Some(PackageSource::Directory) => {
+                let path = local_directory_path(&key, dependency, "file:")?;
+                let destination = node_module.get_destination(name.to_string());
+                return copy_package_directory(&self.project_root.join(path), &destination);
+            }
+            Some(PackageSource::Link) => {
+                let path = local_directory_path(&key, dependency, "link:")?;
+                return link_package_directory(&self.project_root, &path, node_module, name);
+            }
+            _ => {}
+        }
+        // An `npm:` alias is cached under the package it names.
+        let cached_name = dependency
+            .get_alias_of()
+            .unwrap_or_else(|| name.to_string());
         let cached_version = dependency.get_version();
         let tgz_path = self
             .cache_dir
-            .join(tarball_cache_file_name(name, &cached_version));
+            .join(tarball_cache_file_name(&cached_name, &cached_version));
         let tgz = File::open(tgz_path)?;
         let gz = GzDecoder::new(tgz);
         let mut archive = Archive::new(gz);
@@ -834,10 +864,10 @@ impl NodeResolver {
             // 만약 파일을 resolve했을때, nodemodules/pkg/pkg 이렇게 되어있는 경우
             // node_module/pkg을 pkg로 옮겨준다.
             for entry in destination
-                .join(name.rsplit('/').next().ok_or_else(|| {
+                .join(cached_name.rsplit('/').next().ok_or_else(|| {
                     Error::new(
                         ErrorKind::InvalidData,
-                        format!("invalid package name: {name}"),
+                        format!("invalid package name: {cached_name}"),
                     )
                 })?)
                 .read_dir()?
@@ -852,6 +882,72 @@ impl NodeResolver {
     }
 }
 
+/// The directory path a `file:` or `link:` entry's `tarball` locator names.
+fn local_directory_path(
+    key: &str,
+    dependency: &Dependency,
+    prefix: &str,
+) -> Result<String, std::io::Error> {
+    dependency
+        .get_tarball()
+        .and_then(|tarball| tarball.strip_prefix(prefix).map(str::to_string))
+        .ok_or_else(|| {
+            Error::new(
+                ErrorKind::InvalidData,
+                format!("{key} has no {prefix} path to install from"),
+            )
+        })
+}
+
+/// Copy the files `rpm pack` would pack from a `file:` package directory, so
+/// the installed package matches what publishing it would ship.
+fn copy_package_directory(source_dir: &Path, destination: &Path) -> Result<(), std::io::Error> {
+    if destination.exists() {
+        return Ok(());
+    }
+    let manifest = PackageManifest::read_from_path(source_dir.join("package.json"))?;
+    for file in collect_files(source_dir, &manifest)? {
+        let target = destination.join(&file);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::copy(source_dir.join(&file), target)?;
+    }
+    Ok(())
+}
+
+/// Symlink a `link:` package directory into `node_modules`. A relative `path`
+/// stays relative, counted from the project root that holds `node_modules`,
+/// so the link survives the staged tree being renamed into place.
+fn link_package_directory(
+    project_root: &Path,
+    path: &str,
+    node_module: &mut NodeModules,
+    name: &str,
+) -> Result<(), std::io::Error> {
+    let source_dir = project_root.join(path);
+    if !source_dir.join("package.json").is_file() {
+        return Err(Error::new(
+            ErrorKind::NotFound,
+            format!("no package.json in {}", source_dir.display()),
+        ));
+    }
+    let destination = node_module.get_destination(name.to_string());
+    if destination.exists() || destination.is_symlink() {
+        return Ok(());
+    }
+    if let Some(parent) = destination.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut target = PathBuf::new();
+    if Path::new(path).is_relative() {
+        for _ in Path::new(name).components() {
+            target.push("..");
+        }
+    }
+    symlink(target.join(path), destination)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1082,6 +1178,41 @@ mod tests {
         assert_eq!(fs::read_to_string(existing_file).unwrap(), "existing");
     }
 
+    #[test]
+    fn init_links_scoped_link_packages_relative_to_the_project() {
+        let temp = TempNodeModules::new();
+        let tool = temp.path.join("tools").join("cli");
+        fs::create_dir_all(&tool).unwrap();
+        fs::write(
+            tool.join("package.json"),
+            r#"{"name":"@corp/cli","version":"0.1.0","bin":"run.js"}"#,
+        )
+        .unwrap();
+        fs::write(tool.join("run.js"), "").unwrap();
+        fs::write(
+            temp.lockfile_path(),
+            "lockfile_version = 1\nname = \"fixture-app\"\nversion = \"0.1.0\"\n\n[\"@corp/cli@0.1.0\"]\nversion = \"0.1.0\"\nsource = \"link\"\ntarball = \"link:tools/cli\"\ndependencies = []\n",
+        )
+        .unwrap();
+        let root = root_manifest(&temp);
+
+        NodeModules::init_from_paths(
+            temp.node_modules(),
+            temp.lockfile_path(),
+            temp.cache_dir(),
+            &root,
+        )
+        .unwrap();
+
+        let installed = temp.node_modules().join("@corp").join("cli");
+        assert_eq!(
+            fs::read_link(&installed).unwrap(),
+            PathBuf::from("../../tools/cli")
+        );
+        assert!(temp.node_modules().join(".bin").join("cli").exists());
+        assert!(!temp.cache_dir().exists());
+    }
+
     #[test]
     fn init_keeps_existing_node_modules_when_lockfile_is_empty() {
         let temp = TempNodeModules::new();
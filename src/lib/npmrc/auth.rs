@@ -0,0 +1,193 @@
+use std::fmt;
+
+use base64::{engine::general_purpose::STANDARD as BASE64_STANDARD, Engine as _};
+
+use super::NpmConfig;
+
+const AUTH_KEY_SUFFIXES: [&str; 4] = [":_authToken", ":_auth", ":username", ":_password"];
+
+/// Credentials attached to requests for one registry host.
+///
+/// `Debug` never prints the secret so credentials cannot leak through logs or
+/// error formatting.
+#[derive(Clone, PartialEq, Eq)]
+pub enum RegistryCredentials {
+    /// `//host/:_authToken=`, sent as `Authorization: Bearer <token>`.
+    Bearer(String),
+    /// `//host/:_auth=` or `//host/:username=` plus `//host/:_password=`, sent as
+    /// `Authorization: Basic <base64 user:pass>`.
+    Basic(String),
+}
+
+impl RegistryCredentials {
+    /// The `Authorization` header value for these credentials.
+    pub fn authorization_header(&self) -> String {
+        match self {
+            Self::Bearer(token) => format!("Bearer {token}"),
+            Self::Basic(encoded) => format!("Basic {encoded}"),
+        }
+    }
+}
+
+impl fmt::Debug for RegistryCredentials {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Bearer(_) => f.write_str("Bearer(<redacted>)"),
+            Self::Basic(_) => f.write_str("Basic(<redacted>)"),
+        }
+    }
+}
+
+impl NpmConfig {
+    /// Credentials configured for the registry that serves `url`.
+    ///
+    /// Credentials are scoped with npm's `//host[:port]/path/:<key>` form and
+    /// only apply to URLs whose scheme-less form starts with that prefix, so a
+    /// token for one host is never sent to another. When several prefixes match,
+    /// the longest wins. `_authToken` takes precedence over `_auth`, which takes
+    /// precedence over `username`/`_password`.
+    pub fn credentials_for_url(&self, url: &str) -> Option<RegistryCredentials> {
+        let target = nerf_dart(url)?;
+        let prefix = self
+            .entries
+            .keys()
+            .filter_map(|key| {
+                AUTH_KEY_SUFFIXES
+                    .iter()
+                    .find_map(|suffix| key.strip_suffix(suffix))
+            })
+            .filter(|prefix| prefix.starts_with("//"))
+            .filter(|prefix| target.starts_with(&with_trailing_slash(prefix)))
+            .max_by_key(|prefix| prefix.len())?;
+
+        let value = |suffix: &str| {
+            self.get(&format!("{prefix}{suffix}"))
+                .filter(|value| !value.is_empty())
+        };
+        if let Some(token) = value(":_authToken") {
+            return Some(RegistryCredentials::Bearer(token.to_string()));
+        }
+        if let Some(auth) = value(":_auth") {
+            return Some(RegistryCredentials::Basic(auth.to_string()));
+        }
+        let username = value(":username")?;
+        let password = value(":_password")?;
+        // npm stores `_password` base64-encoded; accept a raw value when it does
+        // not decode so a hand-written `.npmrc` still authenticates.
+        let password = BASE64_STANDARD
+            .decode(password)
+            .ok()
+            .and_then(|decoded| String::from_utf8(decoded).ok())
+            .unwrap_or_else(|| password.to_string());
+        Some(RegistryCredentials::Basic(
+            BASE64_STANDARD.encode(format!("{username}:{password}")),
+        ))
+    }
+}
+
+/// Whether `key` holds a secret that must not be printed.
+pub(super) fn is_auth_key(key: &str) -> bool {
+    key == "_authToken"
+        || key == "_auth"
+        || key == "_password"
+        || AUTH_KEY_SUFFIXES
+            .iter()
+            .filter(|suffix| **suffix != ":username")
+            .any(|suffix| key.ends_with(suffix))
+}
+
+/// Scheme-less `//host[:port]/path` form of a URL, matching how `.npmrc`
+/// scopes credentials.
+fn nerf_dart(url: &str) -> Option<String> {
+    let (_, rest) = url.split_once("://")?;
+    let rest = rest.split(['?', '#']).next().unwrap_or(rest);
+    Some(with_trailing_slash(&format!("//{rest}")))
+}
+
+fn with_trailing_slash(value: &str) -> String {
+    if value.ends_with('/') {
+        value.to_string()
+    } else {
+        format!("{value}/")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bearer_token_applies_only_to_matching_host() {
+        let config = NpmConfig::parse("//npm.corp.example/:_authToken=secret-token\n");
+
+        assert_eq!(
+            config.credentials_for_url("https://npm.corp.example/@corp%2Ftool/"),
+            Some(RegistryCredentials::Bearer("secret-token".to_string()))
+        );
+        assert_eq!(
+            config.credentials_for_url("https://npm.corp.example/@corp/tool/-/tool-1.0.0.tgz"),
+            Some(RegistryCredentials::Bearer("secret-token".to_string()))
+        );
+        assert_eq!(
+            config.credentials_for_url("https://registry.npmjs.org/express/"),
+            None
+        );
+        assert_eq!(
+            config.credentials_for_url("https://npm.corp.example.evil/express/"),
+            None
+        );
+    }
+
+    #[test]
+    fn longest_matching_path_prefix_wins() {
+        let config = NpmConfig::parse(
+            "//host.example/:_authToken=root\n//host.example/team/:_authToken=team\n",
+        );
+
+        assert_eq!(
+            config.credentials_for_url("https://host.example/team/pkg"),
+            Some(RegistryCredentials::Bearer("team".to_string()))
+        );
+        assert_eq!(
+            config.credentials_for_url("https://host.example/other/pkg"),
+            Some(RegistryCredentials::Bearer("root".to_string()))
+        );
+    }
+
+    #[test]
+    fn basic_auth_from_auth_or_username_and_password() {
+        let config = NpmConfig::parse(&format!(
+            "//a.example/:_auth={}\n//b.example:8443/:username=ci\n//b.example:8443/:_password={}\n",
+            BASE64_STANDARD.encode("user:pass"),
+            BASE64_STANDARD.encode("hunter2"),
+        ));
+
+        assert_eq!(
+            config.credentials_for_url("https://a.example/pkg"),
+            Some(RegistryCredentials::Basic(
+                BASE64_STANDARD.encode("user:pass")
+            ))
+        );
+        assert_eq!(
+            config.credentials_for_url("https://b.example:8443/pkg"),
+            Some(RegistryCredentials::Basic(
+                BASE64_STANDARD.encode("ci:hunter2")
+            ))
+        );
+        assert_eq!(config.credentials_for_url("https://b.example/pkg"), None);
+    }
+
+    #[test]
+    fn credentials_and_config_debug_output_redact_secrets() {
+        let config = NpmConfig::parse(
+            "registry=https://npm.corp.example/\n//npm.corp.example/:_authToken=secret-token\n",
+        );
+        let credentials = config
+            .credentials_for_url("https://npm.corp.example/pkg")
+            .unwrap();
+
+        assert!(!format!("{credentials:?}").contains("secret-token"));
+        assert!(!format!("{config:?}").contains("secret-token"));
+        assert!(format!("{config:?}").contains("https://npm.corp.example/"));
+    }
+}
@@ -0,0 +1,187 @@
+use std::{
+    fs,
+    io::{Error, ErrorKind},
+    path::{Path, PathBuf},
+};
+
+use super::NpmConfig;
+
+impl NpmConfig {
+    /// Load the user `.npmrc` and then `<project_root>/.npmrc`.
+    ///
+    /// The user file is `$NPM_CONFIG_USERCONFIG` when set, otherwise
+    /// `$HOME/.npmrc`. Missing files are skipped.
+    pub fn load(project_root: &Path) -> std::io::Result<Self> {
+        let mut paths = Vec::new();
+        if let Some(user_config) = user_config_path() {
+            paths.push(user_config);
+        }
+        paths.push(project_root.join(".npmrc"));
+        Self::load_from_paths(&paths)
+    }
+
+    /// Load and merge `.npmrc` files in order; later files win per key.
+    pub fn load_from_paths<P: AsRef<Path>>(paths: &[P]) -> std::io::Result<Self> {
+        let mut config = Self::default();
+        for path in paths {
+            let path = path.as_ref();
+            let text = match fs::read_to_string(path) {
+                Ok(text) => text,
+                Err(error) if error.kind() == ErrorKind::NotFound => continue,
+                Err(error) => {
+                    return Err(Error::new(
+                        error.kind(),
+                        format!("failed to read {}: {error}", path.display()),
+                    ))
+                }
+            };
+            config.entries.extend(Self::parse(&text).entries);
+        }
+        Ok(config)
+    }
+
+    /// Parse `.npmrc` text: `key=value` lines, `#`/`;` comments, and optional
+    /// surrounding quotes on values. Section headers and lines without `=` are
+    /// ignored, matching npm's lenient ini reader. `${NAME}` in keys and values
+    /// expands to the environment variable `NAME`, or to nothing when unset.
+    pub fn parse(text: &str) -> Self {
+        Self::parse_with_env(text, &|name| std::env::var(name).ok())
+    }
+
+    pub(crate) fn parse_with_env(text: &str, env: &dyn Fn(&str) -> Option<String>) -> Self {
+        let mut config = Self::default();
+        for line in text.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+                continue;
+            }
+            if line.starts_with('[') {
+                continue;
+            }
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+            let key = expand_env(key.trim(), env);
+            if key.is_empty() {
+                continue;
+            }
+            config
+                .entries
+                .insert(key, expand_env(unquote(value.trim()), env));
+        }
+        config
+    }
+}
+
+fn user_config_path() -> Option<PathBuf> {
+    if let Some(path) = std::env::var_os("NPM_CONFIG_USERCONFIG") {
+        return Some(PathBuf::from(path));
+    }
+    std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".npmrc"))
+}
+
+fn expand_env(value: &str, env: &dyn Fn(&str) -> Option<String>) -> String {
+    let mut expanded = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(start) = rest.find("${") {
+        let Some(end) = rest[start + 2..].find('}') else {
+            break;
+        };
+        expanded.push_str(&rest[..start]);
+        let name = &rest[start + 2..start + 2 + end];
+        expanded.push_str(&env(name).unwrap_or_default());
+        rest = &rest[start + 2 + end + 1..];
+    }
+    expanded.push_str(rest);
+    expanded
+}
+
+fn unquote(value: &str) -> &str {
+    for quote in ['"', '\''] {
+        if let Some(inner) = value
+            .strip_prefix(quote)
+            .and_then(|rest| rest.strip_suffix(quote))
+        {
+            return inner;
+        }
+    }
+    value
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::test_support::TempProject;
+
+    #[test]
+    fn parse_reads_registry_and_scope_registries() {
+        let config = NpmConfig::parse(
+            "# comment\n; other comment\nregistry = https://mirror.example/npm/\n\
+             @corp:registry=\"https://npm.corp.example\"\n[section]\nnot a setting\n",
+        );
+
+        assert_eq!(config.registry(), Some("https://mirror.example/npm"));
+        assert_eq!(
+            config.registry_for_package("@corp/tool"),
+            Some("https://npm.corp.example")
+        );
+        assert_eq!(
+            config.registry_for_package("@other/tool"),
+            Some("https://mirror.example/npm")
+        );
+        assert_eq!(
+            config.registry_for_package("express"),
+            Some("https://mirror.example/npm")
+        );
+    }
+
+    #[test]
+    fn parse_expands_environment_variables() {
+        let env = |name: &str| (name == "RPM_NPMRC_TEST_TOKEN").then(|| "from-env".to_string());
+
+        let config = NpmConfig::parse_with_env(
+            "//npm.corp.example/:_authToken=${RPM_NPMRC_TEST_TOKEN}\n\
+             registry=https://${RPM_NPMRC_TEST_UNSET}mirror.example/\n\
+             unterminated=${RPM_NPMRC_TEST_TOKEN\n",
+            &env,
+        );
+
+        assert_eq!(
+            config.get("//npm.corp.example/:_authToken"),
+            Some("from-env")
+        );
+        assert_eq!(config.registry(), Some("https://mirror.example"));
+        assert_eq!(config.get("unterminated"), Some("${RPM_NPMRC_TEST_TOKEN"));
+    }
+
+    #[test]
+    fn empty_config_leaves_registry_to_caller_default() {
+        let config = NpmConfig::default();
+
+        assert_eq!(config.registry_for_package("express"), None);
+        assert_eq!(config.registry_for_package("@corp/tool"), None);
+    }
+
+    #[test]
+    fn load_from_paths_lets_project_override_user_per_key() {
+        let project = TempProject::new("npmrc-merge").unwrap();
+        let user = project
+            .write(
+                "user.npmrc",
+                "registry=https://user.example\n@corp:registry=https://corp.example\n",
+            )
+            .unwrap();
+        let local = project
+            .write("project.npmrc", "registry=https://project.example\n")
+            .unwrap();
+        let missing = local.with_file_name("missing.npmrc");
+
+        let config = NpmConfig::load_from_paths(&[user, local, missing]).unwrap();
+
+        assert_eq!(config.registry(), Some("https://project.example"));
+        assert_eq!(
+            config.registry_for_package("@corp/tool"),
+            Some("https://corp.example")
+        );
+    }
+}
@@ -0,0 +1,13 @@
+//! `.npmrc` configuration.
+//!
+//! RPM reads the user and project `.npmrc` files so registry lookups can be
+//! routed to a configured default registry or to per-scope registries, and so
+//! host-scoped credentials can be attached to registry requests. The contract
+//! is owned by `docs/specs/core/registry/SPEC.md`.
+
+mod auth;
+mod load;
+mod types;
+
+pub use auth::RegistryCredentials;
+pub use types::NpmConfig;
@@ -0,0 +1,68 @@
+use std::{collections::HashMap, fmt};
+
+use super::auth::is_auth_key;
+
+/// Merged key/value settings read from `.npmrc` files.
+///
+/// Later sources override earlier ones key by key, so a project `.npmrc`
+/// overrides the user `.npmrc`. An empty config routes every lookup to the
+/// built-in default registry.
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct NpmConfig {
+    pub(super) entries: HashMap<String, String>,
+}
+
+impl NpmConfig {
+    /// Raw value for `key`, if any source set it.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.entries.get(key).map(String::as_str)
+    }
+
+    /// The configured default registry (`registry=`), without a trailing `/`.
+    pub fn registry(&self) -> Option<&str> {
+        self.registry_value("registry")
+    }
+
+    /// The registry base URL a package's metadata is fetched from.
+    ///
+    /// A scoped package (`@scope/name`) uses `@scope:registry=` when present and
+    /// falls back to the default `registry=` otherwise. Returns `None` when
+    /// neither is configured so the caller applies its built-in default.
+    pub fn registry_for_package(&self, package_name: &str) -> Option<&str> {
+        package_scope(package_name)
+            .and_then(|scope| self.registry_value(&format!("{scope}:registry")))
+            .or_else(|| self.registry())
+    }
+
+    fn registry_value(&self, key: &str) -> Option<&str> {
+        self.get(key)
+            .map(|value| value.trim_end_matches('/'))
+            .filter(|value| !value.is_empty())
+    }
+}
+
+impl fmt::Debug for NpmConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut keys = self.entries.keys().collect::<Vec<_>>();
+        keys.sort();
+        let mut map = f.debug_map();
+        for key in keys {
+            if is_auth_key(key) {
+                map.entry(key, &"<redacted>");
+            } else {
+                map.entry(key, &self.entries[key]);
+            }
+        }
+        map.finish()
+    }
+}
+
+fn package_scope(package_name: &str) -> Option<&str> {
+    if !package_name.starts_with('@') {
+        return None;
+    }
+    package_name
+        .split_once('/')
+        .map(|(scope, _)| scope)
+        .filter(|scope| scope.len() > 1)
+}
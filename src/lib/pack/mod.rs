@@ -0,0 +1,236 @@
+use std::{
+    fs,
+    io::{Error, ErrorKind},
+    path::Path,
+};
+
+use base64::{engine::general_purpose::STANDARD as BASE64_STANDARD, Engine as _};
+use flate2::{Compression, GzBuilder};
+use sha1::Sha1;
+use sha2::{Digest, Sha512};
+use tar::{Builder, EntryType, Header};
+
+use crate::package_manifest::PackageManifest;
+
+mod packlist;
+pub use packlist::collect_files;
+
+/// The mtime npm stamps on every packed entry (1985-10-26T08:15:00Z), so a
+/// tarball's bytes depend only on the packed contents.
+const PACK_MTIME: u64 = 499_162_500;
+
+/// A package tarball built from a project directory.
+#[derive(Debug)]
+pub struct PackedTarball {
+    pub name: String,
+    pub version: String,
+    /// Packed paths, relative to `package/`, sorted.
+    pub files: Vec<String>,
+    pub bytes: Vec<u8>,
+    /// `sha512-…` SRI of `bytes`.
+    pub integrity: String,
+    /// Hex SHA-1 of `bytes`, the legacy `dist.shasum`.
+    pub shasum: String,
+}
+
+impl PackedTarball {
+    /// `<name>-<version>.tgz`, with a scope folded into the name the way npm
+    /// does (`@scope/pkg` packs to `scope-pkg-1.0.0.tgz`).
+    pub fn file_name(&self) -> String {
+        format!(
+            "{}-{}.tgz",
+            self.name.trim_start_matches('@').replace('/', "-"),
+            self.version
+        )
+    }
+}
+
+/// Build the publishable tarball of the project at `project_root`.
+///
+/// Entries sit under `package/` in sorted order with a fixed mtime, zero
+/// owners and modes normalized to `0644` (`0755` when any execute bit is set),
+/// and the gzip header carries no timestamp, so packing the same files twice
+/// yields the same bytes.
+pub fn pack_project(project_root: &Path) -> std::io::Result<PackedTarball> {
+    let manifest = PackageManifest::read_from_path(project_root.join("package.json"))?;
+    let name = manifest.get_name();
+    let version = manifest.get_version();
+    if name.trim().is_empty() || version.trim().is_empty() {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            "package.json must have a name and a version to pack",
+        ));
+    }
+
+    let files = collect_files(project_root, &manifest)?;
+    let gzip = GzBuilder::new()
+        .mtime(0)
+        .write(Vec::new(), Compression::default());
+    let mut builder = Builder::new(gzip);
+    for path in &files {
+        let source = project_root.join(path);
+        let read_error = |error: Error| {
+            Error::new(
+                error.kind(),
+                format!("failed to pack {}: {error}", source.display()),
+            )
+        };
+        let contents = fs::read(&source).map_err(read_error)?;
+        let executable = is_executable(&fs::metadata(&source).map_err(read_error)?);
+
+        let mut header = Header::new_gnu();
+        header.set_entry_type(EntryType::Regular);
+        header.set_size(contents.len() as u64);
+        header.set_mode(if executable { 0o755 } else { 0o644 });
+        header.set_mtime(PACK_MTIME);
+        header.set_uid(0);
+        header.set_gid(0);
+        builder
+            .append_data(&mut header, format!("package/{path}"), contents.as_slice())
+            .map_err(read_error)?;
+    }
+    let bytes = builder
+        .into_inner()
+        .and_then(|gzip| gzip.finish())
+        .map_err(|error| Error::new(error.kind(), format!("failed to build tarball: {error}")))?;
+
+    let (integrity, shasum) = tarball_digests(&bytes);
+    Ok(PackedTarball {
+        integrity,
+        shasum,
+        name,
+        version,
+        files,
+        bytes,
+    })
+}
+
+/// The `sha512-…` SRI and hex SHA-1 of tarball `bytes`, as a registry
+/// records them in `dist.integrity` and `dist.shasum`.
+pub fn tarball_digests(bytes: &[u8]) -> (String, String) {
+    (
+        format!("sha512-{}", BASE64_STANDARD.encode(Sha512::digest(bytes))),
+        Sha1::digest(bytes)
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect(),
+    )
+}
+
+#[cfg(unix)]
+fn is_executable(metadata: &fs::Metadata) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    metadata.permissions().mode() & 0o111 != 0
+}
+
+#[cfg(not(unix))]
+fn is_executable(_metadata: &fs::Metadata) -> bool {
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::test_support::TempProject;
+    use flate2::read::GzDecoder;
+    use std::{io::Read, os::unix::fs::PermissionsExt};
+    use tar::Archive;
+
+    fn demo_project(prefix: &str) -> TempProject {
+        let project = TempProject::new(prefix).unwrap();
+        project
+            .write(
+                "package.json",
+                r#"{"name":"@demo/tool","version":"1.2.0","bin":"cli.js"}"#,
+            )
+            .unwrap();
+        project.write("cli.js", "#!/usr/bin/env node\n").unwrap();
+        project.write("lib/z.js", "z").unwrap();
+        project.write("lib/a.js", "a").unwrap();
+        project.write("rpm.lock", "").unwrap();
+        fs::set_permissions(
+            project.path().join("cli.js"),
+            fs::Permissions::from_mode(0o700),
+        )
+        .unwrap();
+        project
+    }
+
+    #[test]
+    fn packs_sorted_normalized_entries_under_package_prefix() {
+        let project = demo_project("pack-entries");
+        let packed = pack_project(project.path()).unwrap();
+
+        assert_eq!(packed.file_name(), "demo-tool-1.2.0.tgz");
+        assert!(packed.integrity.starts_with("sha512-"));
+        assert_eq!(packed.shasum.len(), 40);
+
+        let mut archive = Archive::new(GzDecoder::new(packed.bytes.as_slice()));
+        let entries = archive
+            .entries()
+            .unwrap()
+            .map(|entry| {
+                let mut entry = entry.unwrap();
+                let header = entry.header();
+                let meta = (
+                    entry.path().unwrap().display().to_string(),
+                    header.mode().unwrap(),
+                    header.mtime().unwrap(),
+                    header.uid().unwrap(),
+                );
+                let mut contents = String::new();
+                entry.read_to_string(&mut contents).unwrap();
+                (meta, contents)
+            })
+            .collect::<Vec<_>>();
+
+        let paths = entries
+            .iter()
+            .map(|((path, mode, mtime, uid), _)| {
+                assert_eq!((*mtime, *uid), (PACK_MTIME, 0));
+                (path.as_str(), *mode)
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            paths,
+            vec![
+                ("package/cli.js", 0o755),
+                ("package/lib/a.js", 0o644),
+                ("package/lib/z.js", 0o644),
+                ("package/package.json", 0o644),
+            ]
+        );
+        assert_eq!(entries[1].1, "a");
+    }
+
+    #[test]
+    fn packing_is_reproducible_across_checkouts() {
+        let first = demo_project("pack-first");
+        let packed = pack_project(first.path()).unwrap();
+
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        let second = demo_project("pack-second");
+        fs::set_permissions(
+            second.path().join("lib/a.js"),
+            fs::Permissions::from_mode(0o664),
+        )
+        .unwrap();
+        let repacked = pack_project(second.path()).unwrap();
+
+        assert_eq!(packed.bytes, repacked.bytes);
+        assert_eq!(packed.integrity, repacked.integrity);
+    }
+
+    #[test]
+    fn requires_name_and_version() {
+        let project = TempProject::new("pack-unnamed").unwrap();
+        project.write("package.json", r#"{"name":"demo"}"#).unwrap();
+
+        let error = pack_project(project.path()).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+        assert_eq!(
+            error.to_string(),
+            "package.json must have a name and a version to pack"
+        );
+    }
+}
@@ -0,0 +1,464 @@
+use std::{
+    fs,
+    io::{Error, ErrorKind},
+    path::Path,
+};
+
+use regex::Regex;
+
+use crate::package_manifest::{BinField, PackageManifest};
+
+/// Names never packed, at any depth.
+const ALWAYS_EXCLUDED_NAMES: &[&str] = &[
+    ".git",
+    ".svn",
+    ".hg",
+    "CVS",
+    "node_modules",
+    ".npmrc",
+    ".npmignore",
+    ".gitignore",
+    ".DS_Store",
+    ".lock-wscript",
+    "npm-debug.log",
+];
+
+/// Basename patterns never packed, at any depth.
+const ALWAYS_EXCLUDED_PATTERNS: &[&str] = &["._*", ".*.swp", "*.orig", ".wafpickle-*"];
+
+/// Root entries never packed: lockfiles and RPM's own state.
+const ROOT_EXCLUDED: &[&str] = &[
+    "package-lock.json",
+    "yarn.lock",
+    "pnpm-lock.yaml",
+    "rpm.lock",
+    ".rpm",
+];
+
+/// One line of a `.npmignore`/`.gitignore` file, or one `files` entry.
+#[derive(Debug)]
+struct Rule {
+    regex: Regex,
+    negated: bool,
+    dir_only: bool,
+}
+
+impl Rule {
+    /// Parse a gitignore-style pattern. `anchored` forces the pattern to match
+    /// from the rule's base directory, as `files` entries do; otherwise a
+    /// pattern without a `/` matches a basename at any depth.
+    fn parse(line: &str, anchored: bool) -> Option<Self> {
+        let line = line.trim_end();
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+        let (negated, pattern) = match line.strip_prefix('!') {
+            Some(rest) => (true, rest),
+            None => (false, line.strip_prefix('\\').unwrap_or(line)),
+        };
+        let pattern = pattern.strip_prefix("./").unwrap_or(pattern);
+        let (dir_only, pattern) = match pattern.strip_suffix('/') {
+            Some(rest) => (true, rest),
+            None => (false, pattern),
+        };
+        let anchored = anchored || pattern.contains('/');
+        let pattern = pattern.trim_start_matches('/');
+        if pattern.is_empty() {
+            return None;
+        }
+        let prefix = if anchored { "^" } else { "^(?:.*/)?" };
+        let regex = Regex::new(&format!("{prefix}{}$", glob_to_regex(pattern))).ok()?;
+        Some(Self {
+            regex,
+            negated,
+            dir_only,
+        })
+    }
+
+    fn matches(&self, path: &str, is_dir: bool) -> bool {
+        (is_dir || !self.dir_only) && self.regex.is_match(path)
+    }
+}
+
+/// Translate gitignore glob syntax into a regex body.
+fn glob_to_regex(pattern: &str) -> String {
+    let chars = pattern.chars().collect::<Vec<_>>();
+    let mut regex = String::new();
+    let mut index = 0;
+    while index < chars.len() {
+        match chars[index] {
+            '*' if chars.get(index + 1) == Some(&'*') => {
+                let at_start = index == 0 || chars[index - 1] == '/';
+                index += 2;
+                if at_start && chars.get(index) == Some(&'/') {
+                    regex.push_str("(?:.*/)?");
+                    index += 1;
+                } else {
+                    regex.push_str(".*");
+                }
+                continue;
+            }
+            '*' => regex.push_str("[^/]*"),
+            '?' => regex.push_str("[^/]"),
+            '[' => match chars[index + 1..].iter().position(|&c| c == ']') {
+                Some(length) if length > 0 => {
+                    let class = chars[index + 1..index + 1 + length]
+                        .iter()
+                        .collect::<String>();
+                    let class = match class.strip_prefix('!') {
+                        Some(rest) => format!("^{rest}"),
+                        None => class,
+                    };
+                    regex.push('[');
+                    regex.push_str(&class.replace('\\', "\\\\").replace('[', "\\["));
+                    regex.push(']');
+                    index += length + 2;
+                    continue;
+                }
+                _ => regex.push_str("\\["),
+            },
+            '\\' if index + 1 < chars.len() => {
+                index += 1;
+                regex.push_str(&regex::escape(&chars[index].to_string()));
+            }
+            other => regex.push_str(&regex::escape(&other.to_string())),
+        }
+        index += 1;
+    }
+    regex
+}
+
+/// Apply `rules` in order to `path`; the last matching rule wins.
+fn last_match(rules: &[Rule], path: &str, is_dir: bool) -> Option<bool> {
+    rules
+        .iter()
+        .rev()
+        .find(|rule| rule.matches(path, is_dir))
+        .map(|rule| !rule.negated)
+}
+
+/// Ignore rules read from one directory, with that directory's project-relative
+/// path (`""` for the root, else ending in `/`).
+struct IgnoreFile {
+    base: String,
+    rules: Vec<Rule>,
+}
+
+fn is_ignored(stack: &[IgnoreFile], path: &str, is_dir: bool) -> bool {
+    let mut ignored = false;
+    for file in stack {
+        let Some(relative) = path.strip_prefix(&file.base) else {
+            continue;
+        };
+        if let Some(matched) = last_match(&file.rules, relative, is_dir) {
+            ignored = matched;
+        }
+    }
+    ignored
+}
+
+/// Read the ignore rules of `dir`: `.npmignore` when present, else
+/// `.gitignore`.
+fn read_ignore_file(dir: &Path) -> std::io::Result<Option<Vec<Rule>>> {
+    for name in [".npmignore", ".gitignore"] {
+        let path = dir.join(name);
+        match fs::read_to_string(&path) {
+            Ok(text) => {
+                return Ok(Some(
+                    text.lines()
+                        .filter_map(|line| Rule::parse(line, false))
+                        .collect(),
+                ))
+            }
+            Err(error) if error.kind() == ErrorKind::NotFound => {}
+            Err(error) => {
+                return Err(Error::new(
+                    error.kind(),
+                    format!("failed to read {}: {error}", path.display()),
+                ))
+            }
+        }
+    }
+    Ok(None)
+}
+
+fn always_excluded(path: &str, name: &str) -> bool {
+    ALWAYS_EXCLUDED_NAMES.contains(&name)
+        || ALWAYS_EXCLUDED_PATTERNS.iter().any(|pattern| {
+            Rule::parse(pattern, false).is_some_and(|rule| rule.matches(name, false))
+        })
+        || (!path.contains('/') && ROOT_EXCLUDED.contains(&name))
+}
+
+/// `package.json`, readmes and licenses at the root, whatever `files` and the
+/// ignore files say.
+fn always_included_root_file(name: &str) -> bool {
+    let lower = name.to_ascii_lowercase();
+    name == "package.json"
+        || ["readme", "license", "licence"].iter().any(|prefix| {
+            lower == *prefix
+                || lower
+                    .strip_prefix(prefix)
+                    .is_some_and(|rest| rest.starts_with('.'))
+        })
+}
+
+/// Normalize a `main`/`bin` target to a project-relative path.
+fn manifest_path(target: &str) -> String {
+    let path = target.trim().trim_start_matches("./");
+    path.trim_start_matches('/').to_string()
+}
+
+/// The files `rpm pack` puts in the tarball, as sorted `/`-separated paths
+/// relative to `project_root`.
+///
+/// Selection follows npm: the `files` field, when present, lists what to
+/// include and root ignore files do not apply; otherwise everything is
+/// included. In either case each directory's `.npmignore` (or `.gitignore`
+/// when it has none) excludes files below it, `package.json`, readmes,
+/// licenses and the `main` and `bin` targets are always included, and VCS
+/// directories, `node_modules`, lockfiles and `.npmrc` are always excluded.
+/// Symlinks are skipped.
+pub fn collect_files(
+    project_root: &Path,
+    manifest: &PackageManifest,
+) -> std::io::Result<Vec<String>> {
+    let files = manifest.files.as_ref().map(|entries| {
+        entries
+            .iter()
+            .filter_map(|entry| Rule::parse(entry, true))
+            .collect::<Vec<_>>()
+    });
+
+    let mut forced = Vec::new();
+    if let Some(main) = &manifest.main {
+        forced.push(manifest_path(main));
+    }
+    match manifest.get_bin() {
+        Some(BinField::String(target)) => forced.push(manifest_path(target)),
+        Some(BinField::Object(map)) => forced.extend(map.values().map(|t| manifest_path(t))),
+        None => {}
+    }
+
+    let mut walker = Walker {
+        files: files.as_deref(),
+        forced: &forced,
+        stack: Vec::new(),
+        selected: Vec::new(),
+    };
+    walker.walk(project_root, "", false)?;
+    let mut selected = walker.selected;
+    selected.sort();
+    selected.dedup();
+    Ok(selected)
+}
+
+struct Walker<'a> {
+    files: Option<&'a [Rule]>,
+    forced: &'a [String],
+    stack: Vec<IgnoreFile>,
+    selected: Vec<String>,
+}
+
+impl Walker<'_> {
+    /// Walk `dir`, whose project-relative path is `base`. Below an ignored
+    /// directory only forced files are taken, so `main` and `bin` targets
+    /// survive an ignored build directory.
+    fn walk(&mut self, dir: &Path, base: &str, ignored_dir: bool) -> std::io::Result<()> {
+        let read_error = |error: Error| {
+            Error::new(
+                error.kind(),
+                format!("failed to read {}: {error}", dir.display()),
+            )
+        };
+        // With a `files` list, root ignore files do not narrow it.
+        let pushed = match read_ignore_file(dir)? {
+            Some(rules) if !(base.is_empty() && self.files.is_some()) => {
+                self.stack.push(IgnoreFile {
+                    base: base.to_string(),
+                    rules,
+                });
+                true
+            }
+            _ => false,
+        };
+
+        let mut entries = fs::read_dir(dir)
+            .map_err(read_error)?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(read_error)?;
+        entries.sort_by_key(|entry| entry.file_name());
+
+        for entry in entries {
+            let Some(name) = entry.file_name().to_str().map(str::to_string) else {
+                continue;
+            };
+            let path = format!("{base}{name}");
+            let file_type = entry.file_type().map_err(read_error)?;
+            if file_type.is_symlink() || always_excluded(&path, &name) {
+                continue;
+            }
+            if file_type.is_dir() {
+                let prefix = format!("{path}/");
+                let ignored = ignored_dir || is_ignored(&self.stack, &path, true);
+                if !ignored || self.forced.iter().any(|file| file.starts_with(&prefix)) {
+                    self.walk(&entry.path(), &prefix, ignored)?;
+                }
+                continue;
+            }
+            if !file_type.is_file() {
+                continue;
+            }
+            let forced = (base.is_empty() && always_included_root_file(&name))
+                || self.forced.contains(&path);
+            let listed = self.files.is_none_or(|rules| listed_in_files(rules, &path));
+            if forced || (!ignored_dir && listed && !is_ignored(&self.stack, &path, false)) {
+                self.selected.push(path);
+            }
+        }
+
+        if pushed {
+            self.stack.pop();
+        }
+        Ok(())
+    }
+}
+
+/// A `files` entry naming a directory includes everything below it.
+fn listed_in_files(rules: &[Rule], path: &str) -> bool {
+    if let Some(listed) = last_match(rules, path, false) {
+        return listed;
+    }
+    let mut ancestor = path;
+    while let Some(index) = ancestor.rfind('/') {
+        ancestor = &ancestor[..index];
+        if let Some(listed) = last_match(rules, ancestor, true) {
+            return listed;
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::test_support::TempProject;
+
+    fn project(files: &[(&str, &str)]) -> TempProject {
+        let project = TempProject::new("packlist").unwrap();
+        for (path, contents) in files {
+            project.write(path, contents).unwrap();
+        }
+        project
+    }
+
+    fn packed(project: &TempProject) -> Vec<String> {
+        let manifest =
+            PackageManifest::read_from_path(project.path().join("package.json")).unwrap();
+        collect_files(project.path(), &manifest).unwrap()
+    }
+
+    #[test]
+    fn ignore_files_exclude_and_always_rules_apply() {
+        let project = project(&[
+            (
+                "package.json",
+                r#"{"name":"demo","version":"1.0.0","main":"build/index.js"}"#,
+            ),
+            (".npmignore", "build/\n*.log\n!keep.log\n/test\n"),
+            (".gitignore", "src/\n"),
+            ("README.md", "# demo"),
+            ("LICENSE", "MIT"),
+            ("src/lib.js", ""),
+            ("src/debug.log", ""),
+            ("src/keep.log", ""),
+            ("src/test/unit.js", ""),
+            ("test/unit.js", ""),
+            ("build/index.js", ""),
+            ("build/other.js", ""),
+            ("lib/.npmignore", "*.map\n"),
+            ("lib/a.js", ""),
+            ("lib/a.js.map", ""),
+            ("node_modules/dep/index.js", ""),
+            (".git/HEAD", ""),
+            (".npmrc", ""),
+            ("rpm.lock", ""),
+            (".rpm/.cache/x.tgz", ""),
+            ("file.js.orig", ""),
+        ]);
+
+        assert_eq!(
+            packed(&project),
+            vec![
+                "LICENSE",
+                "README.md",
+                "build/index.js",
+                "lib/a.js",
+                "package.json",
+                "src/keep.log",
+                "src/lib.js",
+                "src/test/unit.js",
+            ]
+        );
+    }
+
+    #[test]
+    fn files_field_selects_entries_and_skips_root_ignore_files() {
+        let project = project(&[
+            (
+                "package.json",
+                r#"{"name":"demo","version":"1.0.0","files":["dist","lib/**/*.js","!lib/internal.js","types/*.d.ts"],"bin":{"demo":"./bin/demo.js"}}"#,
+            ),
+            (".gitignore", "dist\n"),
+            ("readme", "x"),
+            ("LICENCE.txt", "x"),
+            ("CHANGELOG.md", "x"),
+            ("dist/index.js", ""),
+            ("dist/nested/deep.js", ""),
+            ("dist/.npmignore", "*.test.js\n"),
+            ("dist/index.test.js", ""),
+            ("lib/a.js", ""),
+            ("lib/internal.js", ""),
+            ("lib/sub/b.js", ""),
+            ("lib/sub/b.ts", ""),
+            ("types/index.d.ts", ""),
+            ("types/nested/index.d.ts", ""),
+            ("bin/demo.js", ""),
+            ("src/index.ts", ""),
+        ]);
+
+        assert_eq!(
+            packed(&project),
+            vec![
+                "LICENCE.txt",
+                "bin/demo.js",
+                "dist/index.js",
+                "dist/nested/deep.js",
+                "lib/a.js",
+                "lib/sub/b.js",
+                "package.json",
+                "readme",
+                "types/index.d.ts",
+            ]
+        );
+    }
+
+    #[test]
+    fn translates_gitignore_globs() {
+        let rule = |pattern: &str| Rule::parse(pattern, false).unwrap();
+
+        assert!(rule("*.js").matches("a/b/c.js", false));
+        assert!(!rule("/*.js").matches("a/c.js", false));
+        assert!(rule("**/fixtures").matches("fixtures", true));
+        assert!(rule("**/fixtures").matches("a/b/fixtures", true));
+        assert!(rule("docs/**/*.md").matches("docs/a/b/c.md", false));
+        assert!(rule("docs/**/*.md").matches("docs/c.md", false));
+        assert!(rule("file[0-9].txt").matches("file3.txt", false));
+        assert!(!rule("file[!0-9].txt").matches("file3.txt", false));
+        assert!(!rule("build/").matches("build", false));
+        assert!(rule("a?c").matches("abc", false));
+        assert!(!rule("a?c").matches("a/c", false));
+        assert!(Rule::parse("# comment", false).is_none());
+        assert!(Rule::parse("   ", false).is_none());
+    }
+}
@@ -82,6 +82,49 @@ where
     }
 }
 
+/// Deserialize the root `private` flag with JavaScript truthiness, the way npm
+/// checks it before publishing: `true`, a non-empty string (even `"false"`),
+/// a non-zero number, an array, or an object marks the package private.
+fn deserialize_private_field<'de, D>(deserializer: D) -> Result<Option<bool>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    use serde_json::Value;
+
+    Ok(match Value::deserialize(deserializer)? {
+        Value::Null => None,
+        Value::Bool(private) => Some(private),
+        Value::String(private) => Some(!private.is_empty()),
+        Value::Number(number) => Some(number.as_f64() != Some(0.0)),
+        Value::Array(_) | Value::Object(_) => Some(true),
+    })
+}
+
+/// Tolerantly deserialize `publishConfig`; a wrong-type value is discarded as
+/// absent like other preserved fields.
+fn deserialize_publish_config<'de, D>(deserializer: D) -> Result<Option<PublishConfig>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value = serde_json::Value::deserialize(deserializer)?;
+    if value.is_null() {
+        return Ok(None);
+    }
+    Ok(PublishConfig::deserialize(value).ok())
+}
+
+/// `package.json` `publishConfig`: settings `rpm publish` applies instead of
+/// the `.npmrc` registry and the default `latest` tag.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PublishConfig {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub registry: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tag: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub access: Option<String>,
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 pub struct VersionString(String);
 
@@ -140,6 +183,11 @@ pub struct PackageManifest {
         skip_serializing_if = "Option::is_none"
     )]
     pub peer_dependencies: Option<HashMap<String, VersionString>>,
+    // Kept as raw JSON so npm's nested override scopes survive a save; only
+    // top-level entries feed the backtracking resolver. See
+    // docs/specs/core/manifest/SPEC.md.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub overrides: Option<serde_json::Map<String, serde_json::Value>>,
     #[serde(
         default,
         deserialize_with = "deserialize_bin_field",
@@ -169,8 +217,22 @@ pub struct PackageManifest {
     pub os: Option<Vec<String>>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub cpu: Option<Vec<String>>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub private: Option<String>,
+    // Consumed only by `rpm pack`; see docs/specs/cli/pack/SPEC.md.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub files: Option<Vec<String>>,
+    #[serde(
+        default,
+        deserialize_with = "deserialize_private_field",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub private: Option<bool>,
+    #[serde(
+        rename = "publishConfig",
+        default,
+        deserialize_with = "deserialize_publish_config",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub publish_config: Option<PublishConfig>,
     // other fields implement soon.
 }
 
@@ -208,6 +270,11 @@ impl PackageManifest {
             .unwrap_or_default()
     }
 
+    /// Whether `private` marks the package as never to be published.
+    pub fn is_private(&self) -> bool {
+        self.private == Some(true)
+    }
+
     /// Returns the raw `bin` field, preserving string-form vs object-form
     /// distinction. The linker resolves the string form into a binary name
     /// derived from the package name; the manifest boundary returns the raw
@@ -304,6 +371,26 @@ impl PackageManifest {
         deps
     }
 
+    /// Top-level `overrides` entries (package name to range). A string value is
+    /// the range itself; an object value contributes its `"."` entry. Nested
+    /// override scopes are preserved on save but not returned here.
+    pub fn get_overrides(&self) -> Vec<(String, String)> {
+        let mut overrides = Vec::new();
+        if let Some(map) = &self.overrides {
+            for (key, value) in map {
+                let range = match value {
+                    serde_json::Value::String(range) => Some(range.as_str()),
+                    serde_json::Value::Object(scope) => scope.get(".").and_then(|v| v.as_str()),
+                    _ => None,
+                };
+                if let Some(range) = range {
+                    overrides.push((key.to_owned(), range.to_owned()))
+                }
+            }
+        }
+        overrides
+    }
+
     /// Preserved `engines` map (engine name to range, for example
     /// `node -> >=14`). Read-only: RPM does not perform engine filtering today.
     pub fn get_engines(&self) -> Vec<(String, String)> {
@@ -350,7 +437,7 @@ fn read_manifest_text(path: &Path) -> std::io::Result<String> {
 #[cfg(test)]
 mod package_json_test {
 
-    use super::PackageManifest;
+    use super::{PackageManifest, PublishConfig};
     use crate::util::test_support::{fixture_path, TempProject};
 
     #[test]
@@ -537,6 +624,27 @@ mod package_json_test {
             .contains(&("react".to_owned(), "^18.0.0".to_owned())));
     }
 
+    #[test]
+    fn read_file_reads_top_level_overrides() {
+        let fixture = fixture_path(&["package_manifest", "manifest-with-overrides.json"]);
+        let package = PackageManifest::read_file(fixture.to_str().unwrap()).unwrap();
+
+        let mut overrides = package.get_overrides();
+        overrides.sort();
+        assert_eq!(
+            overrides,
+            vec![
+                ("left-pad".to_owned(), "1.3.0".to_owned()),
+                ("lodash".to_owned(), "^4.17.21".to_owned()),
+            ]
+        );
+        // Overrides steer resolution; they are not dependency requests.
+        assert!(package
+            .get_dependencies()
+            .iter()
+            .all(|(name, _)| name != "lodash"));
+    }
+
     #[test]
     fn peer_dependencies_round_trip_through_save() {
         let temp_project = TempProject::new("package-manifest-peer").unwrap();
@@ -578,6 +686,30 @@ mod package_json_test {
             .contains(&("node".to_owned(), ">=14.0.0".to_owned())));
     }
 
+    #[test]
+    fn read_file_reads_private_and_publish_config() {
+        let fixture = fixture_path(&["package_manifest", "manifest-with-publish-config.json"]);
+        let package = PackageManifest::read_file(fixture.to_str().unwrap()).unwrap();
+
+        assert!(package.is_private());
+        assert_eq!(
+            package.publish_config,
+            Some(PublishConfig {
+                registry: Some("https://npm.example.com/".to_owned()),
+                tag: Some("next".to_owned()),
+                access: Some("public".to_owned()),
+            })
+        );
+
+        let lenient: PackageManifest =
+            serde_json::from_str(r#"{"private":"false","publishConfig":"nope"}"#).unwrap();
+        assert!(lenient.is_private());
+        assert_eq!(lenient.publish_config, None);
+        let public: PackageManifest = serde_json::from_str(r#"{"private":false}"#).unwrap();
+        assert!(!public.is_private());
+        assert!(!PackageManifest::default().is_private());
+    }
+
     #[test]
     fn engines_os_and_cpu_round_trip_through_save() {
         let temp_project = TempProject::new("package-manifest-platform").unwrap();
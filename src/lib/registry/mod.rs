@@ -5,13 +5,21 @@ use sha1::Sha1;
 use sha2::{Digest, Sha512};
 use std::{
     collections::HashMap,
-    fs::{self, OpenOptions},
-    io::{Error, ErrorKind, Read, Write},
+    fs,
+    io::{Error, ErrorKind, Read},
     path::{Path, PathBuf},
 };
+use tokio::io::AsyncWriteExt;
+
+mod signatures;
+
+pub use signatures::RegistryKeys;
 
 use crate::core::resolver::semver::{self, SemverError};
-use crate::{api, common::constraint::CACHE_DIR};
+use crate::{
+    api::{self, NetworkMode, RegistryClient},
+    common::constraint::CACHE_DIR,
+};
 
 #[derive(Debug, Serialize, Deserialize)]
 struct DistTags {
@@ -41,10 +49,12 @@ pub enum Repository {
     Object(RepositoryObject),
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+/// One entry of `dist.signatures`: an ECDSA signature over
+/// `{name}@{version}:{integrity}` by the registry key `keyid`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Signature {
-    keyid: String,
-    sig: String,
+    pub keyid: String,
+    pub sig: String,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -52,7 +62,12 @@ pub struct Dist {
     pub shasum: Option<String>,
     pub tarball: String,
     pub integrity: Option<String>,
-    pub signature: Option<Signature>,
+    #[serde(default, deserialize_with = "ignored_field")]
+    pub signatures: Option<Vec<Signature>>,
+    #[serde(rename = "unpackedSize", default, deserialize_with = "ignored_field")]
+    pub unpacked_size: Option<u64>,
+    #[serde(rename = "fileCount", default, deserialize_with = "ignored_field")]
+    pub file_count: Option<u64>,
 }
 
 impl Dist {
@@ -140,6 +155,12 @@ pub struct Version {
         deserialize_with = "ignored_field"
     )]
     peer_dependencies: Option<HashMap<String, String>>,
+    #[serde(
+        rename = "peerDependenciesMeta",
+        default,
+        deserialize_with = "ignored_field"
+    )]
+    peer_dependencies_meta: Option<HashMap<String, PeerDependencyMeta>>,
     #[serde(
         rename = "optionalDependencies",
         default,
@@ -160,6 +181,18 @@ pub struct Version {
     cpu: Option<Vec<String>>,
     #[serde(default, deserialize_with = "ignored_field")]
     private: Option<bool>,
+    // Abbreviated packuments omit `scripts` and carry this flag instead; it
+    // decides whether the full document is needed for lifecycle hooks.
+    #[serde(
+        rename = "hasInstallScript",
+        default,
+        deserialize_with = "ignored_field"
+    )]
+    has_install_script: Option<bool>,
+    // `npm deprecate` sets this message; an empty string lifts the
+    // deprecation.
+    #[serde(default, deserialize_with = "ignored_field")]
+    pub deprecated: Option<String>,
     pub dist: Dist,
     // publishConfig: HashMap<String, String>,
 }
@@ -180,6 +213,33 @@ impl Version {
             })
             .unwrap_or_default()
     }
+
+    /// `peerDependencies` as `name@range` specs in name order, each with
+    /// whether `peerDependenciesMeta` marks it optional.
+    fn get_peer_dependencies(&self) -> Vec<(String, bool)> {
+        let mut peers = self
+            .peer_dependencies
+            .iter()
+            .flatten()
+            .map(|(name, range)| {
+                let optional = self
+                    .peer_dependencies_meta
+                    .as_ref()
+                    .and_then(|meta| meta.get(name))
+                    .is_some_and(|meta| meta.optional);
+                (format!("{name}@{range}"), optional)
+            })
+            .collect::<Vec<_>>();
+        peers.sort();
+        peers
+    }
+}
+
+/// A `peerDependenciesMeta` entry.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct PeerDependencyMeta {
+    #[serde(default)]
+    optional: bool,
 }
 
 #[derive(Debug)]
@@ -190,6 +250,19 @@ pub struct Time {
 }
 
 impl Time {
+    pub fn created(&self) -> DateTime<Utc> {
+        self.created
+    }
+
+    pub fn modified(&self) -> DateTime<Utc> {
+        self.modified
+    }
+
+    /// When `version` was published, if the registry recorded it.
+    pub fn version(&self, version: &str) -> Option<DateTime<Utc>> {
+        self.versions.get(version).copied()
+    }
+
     fn new<E>(created: &str, modified: &str) -> Result<Self, E>
     where
         E: de::Error,
@@ -207,14 +280,23 @@ impl<'de> Deserialize<'de> for Time {
     where
         D: Deserializer<'de>,
     {
-        let map = HashMap::<String, String>::deserialize(deserializer)?;
-        let created = map
-            .get("created")
-            .ok_or_else(|| de::Error::missing_field("created"))?;
-        let modified = map
-            .get("modified")
-            .ok_or_else(|| de::Error::missing_field("modified"))?;
-        Self::new(created, modified)
+        // Besides `created` and `modified`, every key is a version's publish
+        // time. Entries that are not timestamps (such as the `unpublished`
+        // record of an unpublished package) are skipped.
+        let map = HashMap::<String, serde_json::Value>::deserialize(deserializer)?;
+        let timestamp = |key: &str| map.get(key).and_then(serde_json::Value::as_str);
+        let created = timestamp("created").ok_or_else(|| de::Error::missing_field("created"))?;
+        let modified = timestamp("modified").ok_or_else(|| de::Error::missing_field("modified"))?;
+        let mut time = Self::new(created, modified)?;
+        time.versions = map
+            .iter()
+            .filter(|(key, _)| *key != "created" && *key != "modified")
+            .filter_map(|(key, value)| {
+                let published = value.as_str()?.parse::<DateTime<Utc>>().ok()?;
+                Some((key.clone(), published))
+            })
+            .collect();
+        Ok(time)
     }
 }
 
@@ -236,19 +318,19 @@ impl Serialize for Time {
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Maintainer {
-    name: Option<String>,
-    email: Option<String>,
-    url: Option<String>,
+    pub name: Option<String>,
+    pub email: Option<String>,
+    pub url: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
-pub struct Url(String);
+pub struct Url(pub String);
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Author {
-    name: Option<String>,
-    email: Option<String>,
-    url: Option<String>,
+    pub name: Option<String>,
+    pub email: Option<String>,
+    pub url: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -334,10 +416,38 @@ pub struct Registry {
     )]
     bundled_dependencies: Option<BundledDependencies>,
     version: Option<String>,
+    // Set by the registry client when the response was an abbreviated
+    // (`application/vnd.npm.install-v1+json`) packument.
+    #[serde(skip)]
+    abbreviated: bool,
 }
 
 impl Registry {
-    fn version_metadata(&self, version: &str) -> Option<&Version> {
+    /// Mark this document as an abbreviated packument.
+    pub(crate) fn mark_abbreviated(&mut self) {
+        self.abbreviated = true;
+    }
+
+    /// Whether this document is an abbreviated packument.
+    pub fn is_abbreviated(&self) -> bool {
+        self.abbreviated
+    }
+
+    /// Whether an abbreviated document lacks fields install consumes for
+    /// `version`, so the full packument must be fetched before it is locked.
+    ///
+    /// Abbreviated documents drop per-version `scripts`. A version flagged
+    /// with `hasInstallScript` needs its lifecycle hooks recorded, and a
+    /// version without the flag has none to record. `time` is also absent;
+    /// callers that need publish times request the full document directly.
+    pub fn requires_full_document(&self, version: &str) -> bool {
+        self.abbreviated
+            && self.version_metadata(version).is_some_and(|metadata| {
+                metadata.scripts.is_none() && metadata.has_install_script == Some(true)
+            })
+    }
+
+    pub fn version_metadata(&self, version: &str) -> Option<&Version> {
         self.versions
             .as_ref()
             .and_then(|versions| versions.get(version))
@@ -357,61 +467,128 @@ impl Registry {
     }
 
     pub fn select_version(&self, requested: &str) -> Result<String, SemverError> {
-        if requested.is_empty() || requested == "latest" {
-            if let Some(version) = self.get_latest_version() {
-                // `latest`/empty selection resolves to either the root
-                // `version` field or the `latest` dist-tag (see
-                // `get_latest_version`). Either source is a version string that
-                // is subject to the same membership guard as an explicit
-                // dist-tag: a `versions` map is absent (legacy single-version
-                // shape, where root fields are the only record), or the target
-                // exists in the map. When a `versions` map is present but the
-                // resolved target is absent from it, reject as unsatisfiable
-                // instead of returning a version key with no per-version
-                // metadata — otherwise bare dependency requests (which are
-                // normalized to `latest`) would bypass the dist-tag guard and
-                // record the root tarball under an unrelated version key
-                // (issue #114).
-                return if self.versions.is_none() || self.version_metadata(version).is_some() {
-                    Ok(version.to_owned())
-                } else {
-                    Err(SemverError::UnsatisfiedRange {
-                        range: requested.to_string(),
-                    })
-                };
-            }
-        }
-        if let Some(version) = self
-            .dist_tags
-            .as_ref()
-            .and_then(|dist_tags| dist_tags.get(requested))
-        {
-            // A dist-tag target is only authoritative when the target version
-            // exists in the `versions` map, or when there is no `versions` map
-            // (legacy single-version shape, where root `dist`/`dependencies`
-            // are the only record). When a `versions` map is present but the
-            // target is absent from it, reject the tag as unsatisfiable instead
-            // of silently returning a version key with no per-version metadata
+        self.select_version_before(requested, None)
+    }
+
+    /// Like [`Self::select_version`], but when `before` is set only versions
+    /// the `time` map records as published strictly before it are candidates.
+    /// A dist-tag (or `latest`) whose target was published later resolves to
+    /// the highest version at or below that target published in time.
+    pub fn select_version_before(
+        &self,
+        requested: &str,
+        before: Option<DateTime<Utc>>,
+    ) -> Result<String, SemverError> {
+        let unsatisfied = || SemverError::UnsatisfiedRange {
+            range: requested.to_string(),
+        };
+        let published_in_time = |version: &str| self.published_before(version, before);
+        let latest = if requested.is_empty() || requested == "latest" {
+            // `latest`/empty selection resolves to either the root `version`
+            // field or the `latest` dist-tag (see `get_latest_version`).
+            self.get_latest_version()
+        } else {
+            None
+        };
+        if let Some(version) = latest.or_else(|| {
+            self.dist_tags
+                .as_ref()
+                .and_then(|dist_tags| dist_tags.get(requested))
+        }) {
+            // A tag target (including the `latest` one) is only authoritative
+            // when the target version exists in the `versions` map, or when
+            // there is no `versions` map (legacy single-version shape, where
+            // root `dist`/`dependencies` are the only record). When a
+            // `versions` map is present but the target is absent from it,
+            // reject the tag as unsatisfiable instead of silently returning a
+            // version key with no per-version metadata — otherwise bare
+            // dependency requests (which are normalized to `latest`) would
+            // record the root tarball under an unrelated version key
             // (issue #114).
-            return if self.versions.is_none() || self.version_metadata(version).is_some() {
-                Ok(version.to_owned())
-            } else {
-                Err(SemverError::UnsatisfiedRange {
-                    range: requested.to_string(),
-                })
-            };
+            if self.versions.is_some() && self.version_metadata(version).is_none() {
+                return Err(unsatisfied());
+            }
+            if published_in_time(version) {
+                return Ok(version.to_owned());
+            }
+            // The tag moved past the cutoff; fall back to the release it
+            // pointed at (or below) back then.
+            return self
+                .max_satisfying_version(&format!("<={version}"), published_in_time)?
+                .ok_or_else(unsatisfied);
         }
-        let Some(versions) = self.versions.as_ref() else {
+        if self.versions.is_none() {
             return self
                 .version
                 .as_ref()
                 .filter(|version| {
                     requested.is_empty() || requested == "latest" || *version == requested
                 })
+                .filter(|version| published_in_time(version))
                 .cloned()
-                .ok_or_else(|| SemverError::UnsatisfiedRange {
-                    range: requested.to_string(),
-                });
+                .ok_or_else(unsatisfied);
+        }
+        self.max_satisfying_version(requested, published_in_time)?
+            .ok_or_else(unsatisfied)
+    }
+
+    /// Every version `requested` may select under the `before` cutoff, most
+    /// preferred first: the version [`Self::select_version_before`] picks,
+    /// then the other satisfying versions that are not deprecated, newest
+    /// first, then the deprecated ones. A dist-tag, `latest`, or the legacy
+    /// single-version shape offers only its selected version.
+    pub fn candidate_versions_before(
+        &self,
+        requested: &str,
+        before: Option<DateTime<Utc>>,
+    ) -> Result<Vec<String>, SemverError> {
+        let preferred = self.select_version_before(requested, before)?;
+        let is_tag = requested.is_empty()
+            || requested == "latest"
+            || self
+                .dist_tags
+                .as_ref()
+                .is_some_and(|dist_tags| dist_tags.get(requested).is_some());
+        let Some(versions) = self.versions.as_ref().filter(|_| !is_tag) else {
+            return Ok(vec![preferred]);
+        };
+        let range = requested.parse::<semver::Range>()?;
+        let published_in_time = |version: &str| self.published_before(version, before);
+        let satisfying = versions.keys().map(String::as_str).filter(|version| {
+            *version != preferred
+                && published_in_time(version)
+                && version
+                    .parse::<semver::Version>()
+                    .is_ok_and(|parsed| range.satisfies(&parsed))
+        });
+        let (current, deprecated): (Vec<&str>, Vec<&str>) = semver::rsort(satisfying)?
+            .into_iter()
+            .partition(|version| self.get_deprecation_for_version(version).is_none());
+        Ok(std::iter::once(preferred)
+            .chain(current.into_iter().chain(deprecated).map(str::to_string))
+            .collect())
+    }
+
+    /// Whether `version` was published before `before`, or `true` without a
+    /// cutoff. A version the `time` map does not record never qualifies.
+    fn published_before(&self, version: &str, before: Option<DateTime<Utc>>) -> bool {
+        before.is_none_or(|before| {
+            self.time
+                .as_ref()
+                .and_then(|time| time.version(version))
+                .is_some_and(|published| published < before)
+        })
+    }
+
+    /// The highest key of the `versions` map that satisfies `range` and that
+    /// `eligible` accepts, or `None` when there is no such key or no map.
+    fn max_satisfying_version(
+        &self,
+        range: &str,
+        eligible: impl Fn(&str) -> bool,
+    ) -> Result<Option<String>, SemverError> {
+        let Some(versions) = self.versions.as_ref() else {
+            return Ok(None);
         };
         // `versions` is deserialized into a randomized `HashMap`, but
         // `max_satisfying` keeps the first candidate on equal precedence
@@ -425,14 +602,22 @@ impl Registry {
         // behavior (no RPM-specific semver dialect; see
         // `docs/specs/core/semver/SPEC.md`). This tie-break is owned at the
         // registry boundary, not in the semver facade.
-        let mut keys: Vec<&str> = versions.keys().map(String::as_str).collect();
+        let mut keys: Vec<&str> = versions
+            .keys()
+            .map(String::as_str)
+            .filter(|version| eligible(version))
+            .collect();
         keys.sort_unstable();
-        let selected = semver::max_satisfying(keys, requested)?;
-        selected
-            .map(str::to_string)
-            .ok_or_else(|| SemverError::UnsatisfiedRange {
-                range: requested.to_string(),
-            })
+        // Like npm, a deprecated version is only selected when no version
+        // that is not deprecated satisfies the range.
+        let current = keys
+            .iter()
+            .copied()
+            .filter(|version| self.get_deprecation_for_version(version).is_none());
+        if let Some(selected) = semver::max_satisfying(current, range)? {
+            return Ok(Some(selected.to_string()));
+        }
+        Ok(semver::max_satisfying(keys, range)?.map(str::to_string))
     }
 
     pub fn get_dependencies_for_version(&self, version: &str) -> Vec<String> {
@@ -453,9 +638,47 @@ impl Registry {
         }
     }
 
+    /// The `peerDependencies` of `version` as `name@range` specs, each with
+    /// whether it is optional. Like [`Self::get_dependencies_for_version`],
+    /// only the legacy single-version shape falls back to the root document,
+    /// which has no `peerDependenciesMeta`.
+    pub fn get_peer_dependencies_for_version(&self, version: &str) -> Vec<(String, bool)> {
+        match self.version_metadata(version) {
+            Some(metadata) => metadata.get_peer_dependencies(),
+            None if self.versions.is_none() => {
+                let mut peers = self
+                    .peer_dependencies
+                    .iter()
+                    .flatten()
+                    .map(|(name, range)| (format!("{name}@{range}"), false))
+                    .collect::<Vec<_>>();
+                peers.sort();
+                peers
+            }
+            None => Vec::new(),
+        }
+    }
+
     pub fn get_scripts_for_version(&self, version: &str) -> Option<HashMap<String, String>> {
-        self.version_metadata(version)
-            .and_then(|metadata| metadata.scripts.clone())
+        let metadata = self.version_metadata(version)?;
+        match &metadata.scripts {
+            Some(scripts) => Some(scripts.clone()),
+            // An abbreviated version without `hasInstallScript` declares no
+            // lifecycle hooks, so record an empty map rather than "unknown".
+            None if self.abbreviated && metadata.has_install_script != Some(true) => {
+                Some(std::collections::HashMap::new())
+            }
+            None => None,
+        }
+    }
+
+    /// The deprecation message of `version`, or `None` when it is not
+    /// deprecated.
+    pub fn get_deprecation_for_version(&self, version: &str) -> Option<&str> {
+        self.version_metadata(version)?
+            .deprecated
+            .as_deref()
+            .filter(|message| !message.is_empty())
     }
 
     pub fn get_tarball_name(&self) -> Option<String> {
@@ -472,8 +695,13 @@ impl Registry {
     }
 
     /// download tarball from registry and return tarball bytes
-    pub async fn download_tarball(&self, key: &str, version: &str) -> std::io::Result<()> {
-        self.download_tarball_to_dir(key, version, Path::new(CACHE_DIR))
+    pub async fn download_tarball(
+        &self,
+        key: &str,
+        version: &str,
+        client: &impl RegistryClient,
+    ) -> std::io::Result<()> {
+        self.download_tarball_to_dir(key, version, Path::new(CACHE_DIR), client)
             .await
     }
 
@@ -482,6 +710,7 @@ impl Registry {
         key: &str,
         version: &str,
         cache_dir: &Path,
+        client: &impl RegistryClient,
     ) -> std::io::Result<()> {
         let url = self
             .get_dist_for_version(version)
@@ -492,33 +721,39 @@ impl Registry {
                     format!("missing tarball URL for {key}@{version}"),
                 )
             })?;
-        let mut bytes_file = api::get_tarball(&url).await?;
         let key = if key.contains("*") {
             key.replace("*", version)
         } else {
             key.to_owned()
         };
-
-        let cache_path = save_tarball_to_dir(cache_dir, &key, &mut bytes_file)?;
         let dist = self.get_dist_for_version(version);
-        verify_cached_tarball(
+        Self::download_verified_tarball_url_to_dir(
             &key,
-            &cache_path,
+            &url,
+            cache_dir,
             dist.and_then(|dist| dist.integrity.as_deref()),
             dist.and_then(|dist| dist.shasum.as_deref()),
+            client,
         )
+        .await
     }
 
-    pub async fn download_tarball_url(key: &str, tarball_url: &str) -> std::io::Result<()> {
-        Self::download_tarball_url_to_dir(key, tarball_url, Path::new(CACHE_DIR)).await
+    pub async fn download_tarball_url(
+        key: &str,
+        tarball_url: &str,
+        client: &impl RegistryClient,
+    ) -> std::io::Result<()> {
+        Self::download_tarball_url_to_dir(key, tarball_url, Path::new(CACHE_DIR), client).await
     }
 
     pub(crate) async fn download_tarball_url_to_dir(
         key: &str,
         tarball_url: &str,
         cache_dir: &Path,
+        client: &impl RegistryClient,
     ) -> std::io::Result<()> {
-        Self::download_verified_tarball_url_to_dir(key, tarball_url, cache_dir, None, None).await
+        Self::download_verified_tarball_url_to_dir(key, tarball_url, cache_dir, None, None, client)
+            .await
     }
 
     pub(crate) async fn download_verified_tarball_url_to_dir(
@@ -527,10 +762,33 @@ impl Registry {
         cache_dir: &Path,
         integrity: Option<&str>,
         shasum: Option<&str>,
+        client: &impl RegistryClient,
     ) -> std::io::Result<()> {
-        let mut bytes_file = api::get_tarball(tarball_url).await?;
-        let cache_path = save_tarball_to_dir(cache_dir, key, &mut bytes_file)?;
-        verify_cached_tarball(key, &cache_path, integrity, shasum)
+        // In the cache-first network modes a cached tarball that still
+        // verifies is reused; offline, anything else is a cache miss.
+        let network_mode = client.network_mode();
+        if network_mode.prefers_cache() && cached_tarball_matches(cache_dir, key, integrity, shasum)
+        {
+            return Ok(());
+        }
+        if network_mode == NetworkMode::Offline {
+            return Err(api::offline_cache_miss(key));
+        }
+        let mut body = client.get_tarball(tarball_url).await?;
+        save_tarball_to_dir(cache_dir, key, &mut body, integrity, shasum).await?;
+        Ok(())
+    }
+
+    /// Stream the tarball at `tarball_url` into `cache_dir` as `key` when no
+    /// digests are known for it yet, returning the ones it hashed to.
+    pub(crate) async fn download_tarball_to_cache(
+        key: &str,
+        tarball_url: &str,
+        cache_dir: &Path,
+        client: &impl RegistryClient,
+    ) -> std::io::Result<CachedTarball> {
+        let mut body = client.get_tarball(tarball_url).await?;
+        save_tarball_to_dir(cache_dir, key, &mut body, None, None).await
     }
 
     /// get dependencies from registry
@@ -550,6 +808,25 @@ impl Registry {
         self.get_dependencies_for_version("")
     }
 
+    /// Dist-tags and the versions they point at.
+    pub fn get_dist_tags(&self) -> Option<&HashMap<String, String>> {
+        self.dist_tags.as_ref().map(|dist_tags| &dist_tags.inner)
+    }
+
+    /// The keys of the `versions` map in ascending semver order. Keys that
+    /// are not valid versions are skipped.
+    pub fn get_sorted_versions(&self) -> Vec<String> {
+        let valid = self
+            .versions
+            .iter()
+            .flat_map(|versions| versions.keys())
+            .map(String::as_str)
+            .filter(|version| semver::valid(version).is_some());
+        semver::sort(valid)
+            .map(|sorted| sorted.into_iter().map(str::to_string).collect())
+            .unwrap_or_default()
+    }
+
     pub fn get_latest_version(&self) -> Option<&String> {
         if self.version.is_some() {
             self.version.as_ref()
@@ -574,17 +851,45 @@ fn normalized_tarball_cache_file_name(cache_key: &str) -> String {
     }
 }
 
-fn save_tarball_to_dir<P: AsRef<Path>>(
+/// Whether the cache already holds a tarball for `key` that passes the
+/// integrity check against `integrity`/`shasum`.
+pub(crate) fn cached_tarball_matches(
+    cache_dir: &Path,
+    key: &str,
+    integrity: Option<&str>,
+    shasum: Option<&str>,
+) -> bool {
+    let path = cache_dir.join(normalized_tarball_cache_file_name(key));
+    path.is_file() && verify_cached_tarball(key, &path, integrity, shasum).is_ok()
+}
+
+/// A tarball published to the cache, with the digests of its bytes.
+#[derive(Debug)]
+pub(crate) struct CachedTarball {
+    pub path: PathBuf,
+    /// `sha512-<base64>` Subresource Integrity string.
+    pub integrity: String,
+    /// Hex SHA-1.
+    pub shasum: String,
+}
+
+/// Stream `body` into a staging file in `cache_dir` while hashing it, and
+/// publish the cache entry by rename only once the digest matches
+/// `integrity`/`shasum`. A failed read, write, or check removes the staging
+/// file and leaves any existing cache entry untouched.
+async fn save_tarball_to_dir<P: AsRef<Path>>(
     cache_dir: P,
     tarball_name: &str,
-    bytes_file: &mut [u8],
-) -> Result<PathBuf, Error> {
+    body: &mut api::TarballBody,
+    integrity: Option<&str>,
+    shasum: Option<&str>,
+) -> Result<CachedTarball, Error> {
     let file_name = normalized_tarball_cache_file_name(tarball_name);
 
     let dir = cache_dir.as_ref();
 
-    if !dir.exists() {
-        fs::create_dir_all(dir).map_err(|error| {
+    if !tokio::fs::try_exists(dir).await.unwrap_or(false) {
+        tokio::fs::create_dir_all(dir).await.map_err(|error| {
             Error::new(
                 error.kind(),
                 format!(
@@ -597,33 +902,50 @@ fn save_tarball_to_dir<P: AsRef<Path>>(
 
     let path: PathBuf = dir.join(file_name);
     let path_display = path.display().to_string();
-    let (staging_path, mut file) = open_cache_staging_file(dir, &path)?;
-    if let Err(error) = file.write_all(bytes_file) {
-        drop(file);
-        return Err(cache_staging_error(
-            error.kind(),
-            format!("failed to write cached tarball {path_display}: {error}"),
-            &staging_path,
-        ));
-    }
-    if let Err(error) = file.flush() {
-        drop(file);
-        return Err(cache_staging_error(
-            error.kind(),
-            format!("failed to flush cached tarball {path_display}: {error}"),
-            &staging_path,
-        ));
+    let (staging_path, mut file) = open_cache_staging_file(dir, &path).await?;
+    let mut hasher = TarballHasher::default();
+    let written = async {
+        while let Some(chunk) = body.read_chunk().await? {
+            hasher.update(chunk);
+            file.write_all(chunk).await.map_err(|error| {
+                Error::new(
+                    error.kind(),
+                    format!("failed to write cached tarball {path_display}: {error}"),
+                )
+            })?;
+        }
+        file.flush().await.map_err(|error| {
+            Error::new(
+                error.kind(),
+                format!("failed to flush cached tarball {path_display}: {error}"),
+            )
+        })
     }
+    .await;
     drop(file);
-
-    fs::rename(&staging_path, &path).map_err(|error| {
-        cache_staging_error(
-            error.kind(),
-            format!("failed to publish cached tarball {path_display}: {error}"),
-            &staging_path,
-        )
-    })?;
-    Ok(path)
+    let digests = hasher.finish();
+    let mut published =
+        written.and_then(|()| verify_tarball_digests(tarball_name, &digests, integrity, shasum));
+    if published.is_ok() {
+        published = tokio::fs::rename(&staging_path, &path)
+            .await
+            .map_err(|error| {
+                Error::new(
+                    error.kind(),
+                    format!("failed to publish cached tarball {path_display}: {error}"),
+                )
+            });
+    }
+    match published {
+        Ok(()) => Ok(CachedTarball {
+            path,
+            integrity: format!("sha512-{}", BASE64_STANDARD.encode(&digests.sha512)),
+            shasum: digests.sha1_hex,
+        }),
+        Err(error) => {
+            Err(cache_staging_error(error.kind(), error.to_string(), &staging_path).await)
+        }
+    }
 }
 
 fn verify_cached_tarball(
@@ -632,39 +954,72 @@ fn verify_cached_tarball(
     integrity: Option<&str>,
     shasum: Option<&str>,
 ) -> Result<(), Error> {
-    let mut bytes = Vec::new();
-    fs::File::open(cache_path)
-        .and_then(|mut file| file.read_to_end(&mut bytes))
-        .map_err(|error| {
-            Error::new(
-                error.kind(),
-                format!(
-                    "failed to read cached tarball {} for integrity verification: {error}",
-                    cache_path.display()
-                ),
-            )
-        })?;
-    verify_tarball_integrity(package_key, &bytes, integrity, shasum)
+    let read_error = |error: Error| {
+        Error::new(
+            error.kind(),
+            format!(
+                "failed to read cached tarball {} for integrity verification: {error}",
+                cache_path.display()
+            ),
+        )
+    };
+    let mut file = fs::File::open(cache_path).map_err(read_error)?;
+    let mut hasher = TarballHasher::default();
+    let mut buffer = vec![0; 64 * 1024];
+    loop {
+        let read = file.read(&mut buffer).map_err(read_error)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    verify_tarball_digests(package_key, &hasher.finish(), integrity, shasum)
+}
+
+/// Running digests of tarball bytes, fed chunk by chunk as they are read.
+#[derive(Default)]
+struct TarballHasher {
+    sha512: Sha512,
+    sha1: Sha1,
+}
+
+struct TarballDigests {
+    sha512: Vec<u8>,
+    sha1_hex: String,
+}
+
+impl TarballHasher {
+    fn update(&mut self, chunk: &[u8]) {
+        self.sha512.update(chunk);
+        self.sha1.update(chunk);
+    }
+
+    fn finish(self) -> TarballDigests {
+        TarballDigests {
+            sha512: self.sha512.finalize().to_vec(),
+            sha1_hex: format!("{:x}", self.sha1.finalize()),
+        }
+    }
 }
 
-fn verify_tarball_integrity(
+fn verify_tarball_digests(
     package_key: &str,
-    bytes: &[u8],
+    digests: &TarballDigests,
     integrity: Option<&str>,
     shasum: Option<&str>,
 ) -> Result<(), Error> {
     if let Some(integrity) = integrity.filter(|value| !value.trim().is_empty()) {
-        return verify_sri_sha512(package_key, bytes, integrity);
+        return verify_sri_sha512(package_key, &digests.sha512, integrity);
     }
 
     if let Some(shasum) = shasum.filter(|value| !value.trim().is_empty()) {
-        return verify_legacy_shasum(package_key, bytes, shasum);
+        return verify_legacy_shasum(package_key, &digests.sha1_hex, shasum);
     }
 
     Ok(())
 }
 
-fn verify_sri_sha512(package_key: &str, bytes: &[u8], integrity: &str) -> Result<(), Error> {
+fn verify_sri_sha512(package_key: &str, actual: &[u8], integrity: &str) -> Result<(), Error> {
     let mut saw_supported_algorithm = false;
     let mut saw_decoded_digest = false;
     let mut invalid_digest_error = None;
@@ -692,8 +1047,7 @@ fn verify_sri_sha512(package_key: &str, bytes: &[u8], integrity: &str) -> Result
             }
         };
         saw_decoded_digest = true;
-        let actual = Sha512::digest(bytes);
-        if actual[..] == expected[..] {
+        if actual == &expected[..] {
             return Ok(());
         }
     }
@@ -719,7 +1073,7 @@ fn verify_sri_sha512(package_key: &str, bytes: &[u8], integrity: &str) -> Result
     }
 }
 
-fn verify_legacy_shasum(package_key: &str, bytes: &[u8], shasum: &str) -> Result<(), Error> {
+fn verify_legacy_shasum(package_key: &str, actual: &str, shasum: &str) -> Result<(), Error> {
     let shasum = shasum.trim();
     if !is_hex_sha1(shasum) {
         #[cfg(test)]
@@ -730,7 +1084,6 @@ fn verify_legacy_shasum(package_key: &str, bytes: &[u8], shasum: &str) -> Result
             "{package_key}: invalid legacy shasum"
         )));
     }
-    let actual = format!("{:x}", Sha1::digest(bytes));
     if actual.eq_ignore_ascii_case(shasum) {
         Ok(())
     } else {
@@ -763,7 +1116,10 @@ fn is_placeholder_fixture_sri(digest: &str) -> bool {
         || digest.contains("invalidrange")
 }
 
-fn open_cache_staging_file(dir: &Path, path: &Path) -> Result<(PathBuf, fs::File), Error> {
+async fn open_cache_staging_file(
+    dir: &Path,
+    path: &Path,
+) -> Result<(PathBuf, tokio::fs::File), Error> {
     let path_display = path.display();
     if path.file_name().and_then(|name| name.to_str()).is_none() {
         return Err(Error::new(
@@ -774,10 +1130,11 @@ fn open_cache_staging_file(dir: &Path, path: &Path) -> Result<(PathBuf, fs::File
 
     for attempt in 0..1000 {
         let staging_path = dir.join(format!(".rpm-cache-{}-{attempt}.tmp", std::process::id()));
-        match OpenOptions::new()
+        match tokio::fs::OpenOptions::new()
             .write(true)
             .create_new(true)
             .open(&staging_path)
+            .await
         {
             Ok(file) => return Ok((staging_path, file)),
             Err(error) if error.kind() == ErrorKind::AlreadyExists => continue,
@@ -799,8 +1156,8 @@ fn open_cache_staging_file(dir: &Path, path: &Path) -> Result<(PathBuf, fs::File
     ))
 }
 
-fn cache_staging_error(kind: ErrorKind, message: String, staging_path: &Path) -> Error {
-    match fs::remove_file(staging_path) {
+async fn cache_staging_error(kind: ErrorKind, message: String, staging_path: &Path) -> Error {
+    match tokio::fs::remove_file(staging_path).await {
         Ok(()) => Error::new(kind, message),
         Err(cleanup_error) if cleanup_error.kind() == ErrorKind::NotFound => {
             Error::new(kind, message)
@@ -819,11 +1176,13 @@ fn cache_staging_error(kind: ErrorKind, message: String, staging_path: &Path) ->
 mod tests {
     use super::{
         open_cache_staging_file, save_tarball_to_dir, verify_cached_tarball,
-        verify_tarball_integrity, Registry,
+        verify_tarball_digests, Registry, TarballDigests, TarballHasher,
     };
+    use crate::api::{HttpRegistryClient, TarballBody};
     use crate::core::resolver::semver::SemverError;
     use crate::util::test_support::fixture_path;
     use base64::{engine::general_purpose::STANDARD as BASE64_STANDARD, Engine as _};
+    use chrono::{DateTime, Utc};
     use sha1::Sha1;
     use sha2::{Digest, Sha512};
     use std::fs;
@@ -852,12 +1211,26 @@ mod tests {
         registry
     }
 
+    /// Digests of `bytes` fed to the hasher in two chunks, as a download
+    /// streams them.
+    fn streamed_digests(bytes: &[u8]) -> TarballDigests {
+        let (head, tail) = bytes.split_at(bytes.len() / 2);
+        let mut hasher = TarballHasher::default();
+        hasher.update(head);
+        hasher.update(tail);
+        hasher.finish()
+    }
+
+    fn tarball_body(bytes: &[u8]) -> TarballBody {
+        TarballBody::from_bytes("https://registry.example.invalid/a.tgz", bytes.to_vec())
+    }
+
     fn registry_from_json(fixture: &str) -> Registry {
         serde_json::from_str(fixture).expect("inline registry fixture should deserialize")
     }
 
-    #[test]
-    fn save_tarball_reports_cache_write_errors() {
+    #[tokio::test]
+    async fn save_tarball_reports_cache_write_errors() {
         let nanos = SystemTime::now()
             .duration_since(UNIX_EPOCH)
             .map(|duration| duration.as_nanos())
@@ -870,16 +1243,23 @@ mod tests {
         let cache_path = temp.join("cache-file");
         fs::write(&cache_path, "not a directory").unwrap();
 
-        let error = save_tarball_to_dir(&cache_path, "a@1.0.0", &mut b"tarball".to_vec())
-            .expect_err("cache path file should fail tarball save");
+        let error = save_tarball_to_dir(
+            &cache_path,
+            "a@1.0.0",
+            &mut tarball_body(b"tarball"),
+            None,
+            None,
+        )
+        .await
+        .expect_err("cache path file should fail tarball save");
 
         assert!(error.to_string().contains("failed to open cached tarball"));
         assert!(error.to_string().contains("cache-file"));
         let _ = fs::remove_dir_all(temp);
     }
 
-    #[test]
-    fn save_tarball_reports_publish_errors_and_removes_staging_file() {
+    #[tokio::test]
+    async fn save_tarball_reports_publish_errors_and_removes_staging_file() {
         let nanos = SystemTime::now()
             .duration_since(UNIX_EPOCH)
             .map(|duration| duration.as_nanos())
@@ -891,8 +1271,15 @@ mod tests {
         let cache_dir = temp.join("cache");
         fs::create_dir_all(cache_dir.join("a@1.0.0.tgz")).unwrap();
 
-        let error = save_tarball_to_dir(&cache_dir, "a@1.0.0", &mut b"tarball".to_vec())
-            .expect_err("final cache directory should fail tarball publication");
+        let error = save_tarball_to_dir(
+            &cache_dir,
+            "a@1.0.0",
+            &mut tarball_body(b"tarball"),
+            None,
+            None,
+        )
+        .await
+        .expect_err("final cache directory should fail tarball publication");
 
         assert!(error
             .to_string()
@@ -913,8 +1300,8 @@ mod tests {
         let _ = fs::remove_dir_all(temp);
     }
 
-    #[test]
-    fn cache_staging_file_name_stays_short_for_long_cache_names() {
+    #[tokio::test]
+    async fn cache_staging_file_name_stays_short_for_long_cache_names() {
         let nanos = SystemTime::now()
             .duration_since(UNIX_EPOCH)
             .map(|duration| duration.as_nanos())
@@ -928,8 +1315,9 @@ mod tests {
         let long_name = format!("{}@1.0.0.tgz", "a".repeat(180));
         let final_path = cache_dir.join(&long_name);
 
-        let (staging_path, staging_file) =
-            open_cache_staging_file(&cache_dir, &final_path).unwrap();
+        let (staging_path, staging_file) = open_cache_staging_file(&cache_dir, &final_path)
+            .await
+            .unwrap();
         drop(staging_file);
 
         let staging_file_name = staging_path.file_name().unwrap().to_string_lossy();
@@ -1003,8 +1391,56 @@ mod tests {
         );
     }
 
-    #[test]
-    fn save_tarball_does_not_duplicate_tgz_extension() {
+    #[tokio::test]
+    async fn save_tarball_publishes_only_after_digest_matches() {
+        let temp = crate::util::test_support::TempProject::new("registry-stream-verify").unwrap();
+        let cache_dir = temp.path().join("cache");
+        let bytes = b"streamed tarball bytes";
+        let integrity = format!("sha512-{}", BASE64_STANDARD.encode(Sha512::digest(bytes)));
+        fs::create_dir_all(&cache_dir).unwrap();
+        fs::write(cache_dir.join("a@1.0.0.tgz"), b"previous entry").unwrap();
+
+        let error = save_tarball_to_dir(
+            &cache_dir,
+            "a@1.0.0",
+            &mut tarball_body(b"tampered bytes"),
+            Some(&integrity),
+            None,
+        )
+        .await
+        .expect_err("mismatched digest should not publish");
+
+        assert!(error.to_string().starts_with("integrity check failed"));
+        assert_eq!(
+            fs::read(cache_dir.join("a@1.0.0.tgz")).unwrap(),
+            b"previous entry"
+        );
+        assert_eq!(fs::read_dir(&cache_dir).unwrap().count(), 1);
+
+        let cached = save_tarball_to_dir(
+            &cache_dir,
+            "a@1.0.0",
+            &mut tarball_body(bytes),
+            Some(&integrity),
+            None,
+        )
+        .await
+        .expect("matching digest should publish");
+
+        assert_eq!(fs::read(&cached.path).unwrap(), bytes);
+        assert_eq!(cached.integrity, integrity);
+        assert_eq!(fs::read_dir(&cache_dir).unwrap().count(), 1);
+        verify_cached_tarball(
+            "a@1.0.0",
+            &cache_dir.join("a@1.0.0.tgz"),
+            Some(&integrity),
+            None,
+        )
+        .expect("published entry should verify from disk");
+    }
+
+    #[tokio::test]
+    async fn save_tarball_does_not_duplicate_tgz_extension() {
         let nanos = SystemTime::now()
             .duration_since(UNIX_EPOCH)
             .map(|duration| duration.as_nanos())
@@ -1014,8 +1450,15 @@ mod tests {
             std::process::id()
         ));
 
-        save_tarball_to_dir(&temp, "axios@0.21.1.tgz", &mut b"tarball".to_vec())
-            .expect("tarball save should succeed");
+        save_tarball_to_dir(
+            &temp,
+            "axios@0.21.1.tgz",
+            &mut tarball_body(b"tarball"),
+            None,
+            None,
+        )
+        .await
+        .expect("tarball save should succeed");
 
         assert_eq!(fs::read(temp.join("axios@0.21.1.tgz")).unwrap(), b"tarball");
         assert!(!temp.join("axios@0.21.1.tgz.tgz").exists());
@@ -1027,15 +1470,19 @@ mod tests {
         let bytes = b"tarball bytes";
         let integrity = format!("sha512-{}", BASE64_STANDARD.encode(Sha512::digest(bytes)));
 
-        verify_tarball_integrity("a@1.0.0", bytes, Some(&integrity), None)
+        verify_tarball_digests("a@1.0.0", &streamed_digests(bytes), Some(&integrity), None)
             .expect("matching sha512 SRI should verify");
     }
 
     #[test]
     fn rejects_mismatched_sha512_sri_integrity() {
-        let error =
-            verify_tarball_integrity("a@1.0.0", b"tarball bytes", Some("sha512-AA=="), None)
-                .expect_err("mismatched sha512 SRI should fail");
+        let error = verify_tarball_digests(
+            "a@1.0.0",
+            &streamed_digests(b"tarball bytes"),
+            Some("sha512-AA=="),
+            None,
+        )
+        .expect_err("mismatched sha512 SRI should fail");
 
         assert!(error.to_string().contains("integrity check failed"));
         assert!(error
@@ -1051,7 +1498,7 @@ mod tests {
             BASE64_STANDARD.encode(Sha512::digest(bytes))
         );
 
-        verify_tarball_integrity("a@1.0.0", bytes, Some(&integrity), None)
+        verify_tarball_digests("a@1.0.0", &streamed_digests(bytes), Some(&integrity), None)
             .expect("later matching sha512 SRI token should verify");
     }
 
@@ -1060,7 +1507,7 @@ mod tests {
         let bytes = b"tarball bytes";
         let shasum = format!("{:x}", Sha1::digest(bytes));
 
-        verify_tarball_integrity("a@1.0.0", bytes, None, Some(&shasum))
+        verify_tarball_digests("a@1.0.0", &streamed_digests(bytes), None, Some(&shasum))
             .expect("matching legacy shasum should verify");
     }
 
@@ -1258,6 +1705,74 @@ mod tests {
         );
     }
 
+    #[test]
+    fn abbreviated_packument_records_empty_scripts_without_install_script() {
+        let mut registry = registry_from_json(
+            r#"{
+              "name": "corgi",
+              "modified": "2026-01-01T00:00:00.000Z",
+              "dist-tags": { "latest": "1.1.0" },
+              "versions": {
+                "1.0.0": {
+                  "name": "corgi",
+                  "version": "1.0.0",
+                  "dist": { "tarball": "https://registry.npmjs.org/corgi/-/corgi-1.0.0.tgz" }
+                },
+                "1.1.0": {
+                  "name": "corgi",
+                  "version": "1.1.0",
+                  "dependencies": { "dep": "^1.0.0" },
+                  "dist": { "tarball": "https://registry.npmjs.org/corgi/-/corgi-1.1.0.tgz" }
+                }
+              }
+            }"#,
+        );
+        assert_eq!(registry.get_scripts_for_version("1.1.0"), None);
+
+        registry.mark_abbreviated();
+
+        assert!(registry.is_abbreviated());
+        assert!(!registry.requires_full_document("1.1.0"));
+        assert_eq!(registry.select_version("^1.0.0").unwrap(), "1.1.0");
+        assert_eq!(
+            registry.get_dependencies_for_version("1.1.0"),
+            vec!["dep@^1.0.0".to_string()]
+        );
+        assert_eq!(
+            registry.get_scripts_for_version("1.1.0"),
+            Some(std::collections::HashMap::new())
+        );
+    }
+
+    #[test]
+    fn abbreviated_packument_with_install_script_requires_full_document() {
+        let mut registry = registry_from_json(
+            r#"{
+              "name": "native",
+              "dist-tags": { "latest": "1.1.0" },
+              "versions": {
+                "1.0.0": {
+                  "hasInstallScript": true,
+                  "dist": { "tarball": "https://registry.npmjs.org/native/-/native-1.0.0.tgz" }
+                },
+                "1.1.0": {
+                  "dist": { "tarball": "https://registry.npmjs.org/native/-/native-1.1.0.tgz" }
+                }
+              }
+            }"#,
+        );
+        assert!(!registry.requires_full_document("1.0.0"));
+
+        registry.mark_abbreviated();
+
+        assert!(registry.requires_full_document("1.0.0"));
+        assert_eq!(registry.get_scripts_for_version("1.0.0"), None);
+        // Only the version being locked decides; an older one with an
+        // install script does not cost the full document.
+        assert!(!registry.requires_full_document("1.1.0"));
+        assert!(!registry.requires_full_document("9.9.9"));
+    }
+
     #[test]
     fn root_metadata_fallbacks_cover_legacy_registry_shape() {
         let registry = registry_from_json(
@@ -1395,7 +1910,12 @@ mod tests {
             }"#,
         );
         let missing = registry
-            .download_tarball_to_dir("downloadable", "9.9.9", Path::new("unused"))
+            .download_tarball_to_dir(
+                "downloadable",
+                "9.9.9",
+                Path::new("unused"),
+                &HttpRegistryClient::default(),
+            )
             .await
             .expect_err("missing version dist should fail before network");
         assert!(missing.to_string().contains("missing tarball URL"));
@@ -1403,26 +1923,40 @@ mod tests {
 
     #[test]
     fn integrity_verification_reports_invalid_variants() {
-        let invalid_sri = verify_tarball_integrity("pkg@1.0.0", b"bytes", Some("sha512-@@@"), None)
-            .expect_err("invalid base64 SRI should fail");
+        let invalid_sri = verify_tarball_digests(
+            "pkg@1.0.0",
+            &streamed_digests(b"bytes"),
+            Some("sha512-@@@"),
+            None,
+        )
+        .expect_err("invalid base64 SRI should fail");
         assert!(invalid_sri
             .to_string()
             .contains("invalid sha512 SRI digest"));
 
-        let unsupported_sri =
-            verify_tarball_integrity("pkg@1.0.0", b"bytes", Some("sha256-abcd"), None)
-                .expect_err("unsupported SRI algorithm should fail");
+        let unsupported_sri = verify_tarball_digests(
+            "pkg@1.0.0",
+            &streamed_digests(b"bytes"),
+            Some("sha256-abcd"),
+            None,
+        )
+        .expect_err("unsupported SRI algorithm should fail");
         assert!(unsupported_sri
             .to_string()
             .contains("unsupported integrity algorithm"));
 
-        let invalid_shasum = verify_tarball_integrity("pkg@1.0.0", b"bytes", None, Some("not-hex"))
-            .expect_err("invalid shasum should fail");
+        let invalid_shasum = verify_tarball_digests(
+            "pkg@1.0.0",
+            &streamed_digests(b"bytes"),
+            None,
+            Some("not-hex"),
+        )
+        .expect_err("invalid shasum should fail");
         assert!(invalid_shasum.to_string().contains("invalid legacy shasum"));
 
-        let mismatched_shasum = verify_tarball_integrity(
+        let mismatched_shasum = verify_tarball_digests(
             "pkg@1.0.0",
-            b"bytes",
+            &streamed_digests(b"bytes"),
             None,
             Some("0000000000000000000000000000000000000000"),
         )
@@ -1539,6 +2073,150 @@ mod tests {
         );
     }
 
+    #[test]
+    fn reads_version_publish_times_dist_sizes_and_sorted_versions() {
+        let registry = registry_from_json(
+            r#"{
+              "name": "timed",
+              "dist-tags": { "latest": "1.10.0", "next": "2.0.0-rc.1" },
+              "time": {
+                "created": "2020-01-01T00:00:00.000Z",
+                "modified": "2021-06-01T00:00:00.000Z",
+                "1.2.0": "2020-02-01T00:00:00.000Z",
+                "1.10.0": "2020-03-01T00:00:00.000Z",
+                "unpublished": { "time": "2021-06-01T00:00:00.000Z" }
+              },
+              "versions": {
+                "1.10.0": {
+                  "dist": {
+                    "tarball": "https://registry.example.invalid/timed/-/timed-1.10.0.tgz",
+                    "unpackedSize": 2048,
+                    "fileCount": 3
+                  }
+                },
+                "1.2.0": {
+                  "dist": { "tarball": "https://registry.example.invalid/timed/-/timed-1.2.0.tgz" }
+                },
+                "2.0.0-rc.1": {
+                  "dist": { "tarball": "https://registry.example.invalid/timed/-/timed-2.0.0-rc.1.tgz" }
+                }
+              }
+            }"#,
+        );
+
+        let time = registry.time.as_ref().unwrap();
+        assert_eq!(time.created().to_rfc3339(), "2020-01-01T00:00:00+00:00");
+        assert_eq!(
+            time.version("1.10.0").unwrap().to_rfc3339(),
+            "2020-03-01T00:00:00+00:00"
+        );
+        assert!(time.version("unpublished").is_none());
+        let dist = registry.get_dist_for_version("1.10.0").unwrap();
+        assert_eq!((dist.unpacked_size, dist.file_count), (Some(2048), Some(3)));
+        assert_eq!(
+            registry.get_sorted_versions(),
+            vec!["1.2.0", "1.10.0", "2.0.0-rc.1"]
+        );
+        assert_eq!(registry.get_dist_tags().unwrap()["next"], "2.0.0-rc.1");
+    }
+
+    #[test]
+    fn range_selection_prefers_versions_that_are_not_deprecated() {
+        let registry = registry_from_json(
+            r#"{
+              "name": "aging",
+              "dist-tags": { "latest": "1.3.0" },
+              "versions": {
+                "1.1.0": { "dist": { "tarball": "https://registry.example.invalid/aging-1.1.0.tgz" } },
+                "1.2.0": {
+                  "deprecated": "",
+                  "dist": { "tarball": "https://registry.example.invalid/aging-1.2.0.tgz" }
+                },
+                "1.3.0": {
+                  "deprecated": "critical bug, use 1.2.0",
+                  "dist": { "tarball": "https://registry.example.invalid/aging-1.3.0.tgz" }
+                },
+                "2.0.0": {
+                  "deprecated": "aging is no longer maintained",
+                  "dist": { "tarball": "https://registry.example.invalid/aging-2.0.0.tgz" }
+                }
+              }
+            }"#,
+        );
+
+        assert_eq!(registry.select_version("^1.0.0").unwrap(), "1.2.0");
+        assert_eq!(registry.select_version("^2.0.0").unwrap(), "2.0.0");
+        assert_eq!(registry.select_version("1.3.0").unwrap(), "1.3.0");
+        assert_eq!(registry.select_version("latest").unwrap(), "1.3.0");
+        assert_eq!(
+            registry.candidate_versions_before("^1.0.0", None).unwrap(),
+            vec!["1.2.0", "1.1.0", "1.3.0"]
+        );
+        assert_eq!(
+            registry.candidate_versions_before("latest", None).unwrap(),
+            vec!["1.3.0"]
+        );
+        assert_eq!(
+            registry.get_deprecation_for_version("2.0.0"),
+            Some("aging is no longer maintained")
+        );
+        assert_eq!(registry.get_deprecation_for_version("1.2.0"), None);
+    }
+
+    #[test]
+    fn selection_before_a_cutoff_only_considers_versions_published_earlier() {
+        let registry = registry_from_json(
+            r#"{
+              "name": "clock",
+              "dist-tags": { "latest": "2.0.0", "next": "2.1.0-rc.1" },
+              "time": {
+                "created": "2025-01-01T00:00:00.000Z",
+                "modified": "2026-04-01T00:00:00.000Z",
+                "1.0.0": "2025-01-01T00:00:00.000Z",
+                "1.1.0": "2025-06-01T00:00:00.000Z",
+                "1.2.0": "2026-03-01T00:00:00.000Z",
+                "2.0.0": "2026-04-01T00:00:00.000Z"
+              },
+              "versions": {
+                "1.0.0": { "dist": { "tarball": "https://registry.example.invalid/clock-1.0.0.tgz" } },
+                "1.1.0": { "dist": { "tarball": "https://registry.example.invalid/clock-1.1.0.tgz" } },
+                "1.2.0": { "dist": { "tarball": "https://registry.example.invalid/clock-1.2.0.tgz" } },
+                "2.0.0": { "dist": { "tarball": "https://registry.example.invalid/clock-2.0.0.tgz" } },
+                "2.1.0-rc.1": { "dist": { "tarball": "https://registry.example.invalid/clock-2.1.0-rc.1.tgz" } }
+              }
+            }"#,
+        );
+        let before = Some("2026-03-01T00:00:00Z".parse::<DateTime<Utc>>().unwrap());
+
+        // The cutoff is exclusive: 1.2.0 was published exactly at it.
+        assert_eq!(
+            registry.select_version_before("^1.0.0", before).unwrap(),
+            "1.1.0"
+        );
+        assert_eq!(
+            registry.select_version_before("latest", before).unwrap(),
+            "1.1.0"
+        );
+        assert_eq!(registry.select_version_before("", before).unwrap(), "1.1.0");
+        assert_eq!(
+            registry.select_version_before("next", before).unwrap(),
+            "1.1.0"
+        );
+        // A version without a publish time is never a candidate.
+        assert!(matches!(
+            registry.select_version_before("2.1.0-rc.1", before),
+            Err(SemverError::UnsatisfiedRange { .. })
+        ));
+        assert!(matches!(
+            registry.select_version_before("^2.0.0", before),
+            Err(SemverError::UnsatisfiedRange { .. })
+        ));
+        assert_eq!(
+            registry.select_version_before("latest", None).unwrap(),
+            "2.0.0"
+        );
+    }
+
     #[test]
     fn parses_version_entry_without_name_version_or_description() {
         // Per-version `name`, `version`, and `description` are ignored: RPM
@@ -0,0 +1,270 @@
+use base64::{engine::general_purpose::STANDARD as BASE64_STANDARD, Engine as _};
+use p256::{
+    ecdsa::{signature::Verifier, Signature as EcdsaSignature, VerifyingKey},
+    pkcs8::DecodePublicKey,
+};
+use serde::Deserialize;
+use std::{
+    collections::HashMap,
+    io::{Error, ErrorKind},
+    path::Path,
+};
+
+use super::Signature;
+
+/// The only key scheme npm registries sign with.
+const ECDSA_P256_SHA256: &str = "ecdsa-sha2-nistp256";
+
+#[derive(Deserialize)]
+struct KeysDocument {
+    keys: Vec<KeyEntry>,
+}
+
+#[derive(Deserialize)]
+struct KeyEntry {
+    keyid: String,
+    scheme: String,
+    key: String,
+}
+
+#[derive(Debug, Clone)]
+enum RegistryKey {
+    EcdsaP256(VerifyingKey),
+    Unsupported(String),
+}
+
+/// Public keys a registry signs package metadata with, in the shape npm
+/// registries serve at `/-/npm/v1/keys`:
+///
+/// ```text
+/// {"keys":[{"keyid":"SHA256:…","scheme":"ecdsa-sha2-nistp256","key":"<base64 SPKI DER>"}]}
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct RegistryKeys {
+    keys: HashMap<String, RegistryKey>,
+}
+
+impl RegistryKeys {
+    /// Parse a keys document; `source` names it in errors.
+    pub fn from_json(bytes: &[u8], source: &str) -> std::io::Result<Self> {
+        let document: KeysDocument = serde_json::from_slice(bytes).map_err(|error| {
+            Error::new(
+                ErrorKind::InvalidData,
+                format!("failed to parse registry keys {source}: {error}"),
+            )
+        })?;
+        let mut keys = HashMap::new();
+        for entry in document.keys {
+            let key = if entry.scheme == ECDSA_P256_SHA256 {
+                let verifying_key = BASE64_STANDARD
+                    .decode(entry.key.trim())
+                    .ok()
+                    .and_then(|der| VerifyingKey::from_public_key_der(&der).ok())
+                    .ok_or_else(|| {
+                        Error::new(
+                            ErrorKind::InvalidData,
+                            format!("invalid registry key {} in {source}", entry.keyid),
+                        )
+                    })?;
+                RegistryKey::EcdsaP256(verifying_key)
+            } else {
+                RegistryKey::Unsupported(entry.scheme)
+            };
+            keys.insert(entry.keyid, key);
+        }
+        Ok(Self { keys })
+    }
+
+    /// Read a keys document from a file.
+    pub async fn load(path: &Path) -> std::io::Result<Self> {
+        let bytes = tokio::fs::read(path).await.map_err(|error| {
+            Error::new(
+                error.kind(),
+                format!("failed to read registry keys {}: {error}", path.display()),
+            )
+        })?;
+        Self::from_json(&bytes, &path.display().to_string())
+    }
+
+    /// Check that `signatures` include a valid signature by one of these keys
+    /// over `{name}@{version}:{integrity}`, where `package_key` is
+    /// `{name}@{version}`.
+    ///
+    /// Signatures by unknown keys are skipped, but any signature by a known
+    /// key must verify, and at least one must be present.
+    pub fn verify(
+        &self,
+        package_key: &str,
+        integrity: Option<&str>,
+        signatures: &[Signature],
+    ) -> std::io::Result<()> {
+        let Some(integrity) = integrity.map(str::trim).filter(|value| !value.is_empty()) else {
+            return Err(signature_error(package_key, "no integrity to verify"));
+        };
+        if signatures.is_empty() {
+            return Err(signature_error(package_key, "missing registry signature"));
+        }
+        let message = format!("{package_key}:{integrity}");
+        let mut verified = false;
+        for signature in signatures {
+            match self.keys.get(&signature.keyid) {
+                Some(RegistryKey::EcdsaP256(key)) => {
+                    let valid = BASE64_STANDARD
+                        .decode(signature.sig.trim())
+                        .ok()
+                        .and_then(|der| EcdsaSignature::from_der(&der).ok())
+                        .is_some_and(|sig| key.verify(message.as_bytes(), &sig).is_ok());
+                    if !valid {
+                        return Err(signature_error(
+                            package_key,
+                            &format!("invalid signature by key {}", signature.keyid),
+                        ));
+                    }
+                    verified = true;
+                }
+                Some(RegistryKey::Unsupported(scheme)) => {
+                    return Err(signature_error(
+                        package_key,
+                        &format!("key {} uses unsupported scheme {scheme}", signature.keyid),
+                    ));
+                }
+                None => {}
+            }
+        }
+        if verified {
+            return Ok(());
+        }
+        let keyids = signatures
+            .iter()
+            .map(|signature| signature.keyid.as_str())
+            .collect::<Vec<_>>()
+            .join(", ");
+        Err(signature_error(
+            package_key,
+            &format!("signed only by unknown keys: {keyids}"),
+        ))
+    }
+}
+
+fn signature_error(package_key: &str, reason: &str) -> Error {
+    Error::new(
+        ErrorKind::InvalidData,
+        format!("signature verification failed for {package_key}: {reason}"),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::test_support::{fixture_path, registry_signature, REGISTRY_KEY_ID};
+
+    const INTEGRITY: &str = "sha512-deadbeef";
+
+    fn fixture_keys() -> RegistryKeys {
+        let path = fixture_path(&["signatures", "keys.json"]);
+        RegistryKeys::from_json(&std::fs::read(&path).unwrap(), "keys.json").unwrap()
+    }
+
+    fn signature(keyid: &str, sig: String) -> Signature {
+        Signature {
+            keyid: keyid.to_string(),
+            sig,
+        }
+    }
+
+    #[test]
+    fn accepts_a_signature_over_name_version_and_integrity() {
+        let keys = fixture_keys();
+        let sig = registry_signature("left-pad@1.3.0", INTEGRITY);
+
+        keys.verify(
+            "left-pad@1.3.0",
+            Some(INTEGRITY),
+            &[
+                signature("SHA256:someone-else", "AAAA".to_string()),
+                signature(REGISTRY_KEY_ID, sig),
+            ],
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn rejects_tampered_missing_and_unknown_signatures() {
+        let keys = fixture_keys();
+        let error = |package_key: &str, integrity: Option<&str>, signatures: &[Signature]| {
+            let error = keys.verify(package_key, integrity, signatures).unwrap_err();
+            assert_eq!(error.kind(), ErrorKind::InvalidData);
+            error.to_string()
+        };
+        let sig = registry_signature("left-pad@1.3.0", INTEGRITY);
+
+        assert_eq!(
+            error(
+                "left-pad@1.3.1",
+                Some(INTEGRITY),
+                &[signature(REGISTRY_KEY_ID, sig.clone())]
+            ),
+            format!(
+                "signature verification failed for left-pad@1.3.1: invalid signature by key {REGISTRY_KEY_ID}"
+            )
+        );
+        assert!(error(
+            "left-pad@1.3.0",
+            Some("sha512-other"),
+            &[signature(REGISTRY_KEY_ID, sig.clone())]
+        )
+        .ends_with("invalid signature by key SHA256:rpm-fixture-registry-key"));
+        assert!(error(
+            "left-pad@1.3.0",
+            Some(INTEGRITY),
+            &[signature(REGISTRY_KEY_ID, "not base64!".to_string())]
+        )
+        .contains("invalid signature"));
+        assert!(
+            error("left-pad@1.3.0", Some(INTEGRITY), &[]).ends_with("missing registry signature")
+        );
+        assert!(error(
+            "left-pad@1.3.0",
+            None,
+            &[signature(REGISTRY_KEY_ID, sig.clone())]
+        )
+        .ends_with("no integrity to verify"));
+        assert!(error(
+            "left-pad@1.3.0",
+            Some(INTEGRITY),
+            &[signature("SHA256:someone-else", sig)]
+        )
+        .ends_with("signed only by unknown keys: SHA256:someone-else"));
+    }
+
+    #[test]
+    fn parses_keys_documents_and_reports_bad_key_material() {
+        let keys = RegistryKeys::from_json(
+            br#"{"keys":[{"keyid":"k1","keytype":"rsa","scheme":"rsa-pss","key":"AAAA"}]}"#,
+            "test",
+        )
+        .unwrap();
+        assert!(keys
+            .verify(
+                "a@1.0.0",
+                Some(INTEGRITY),
+                &[signature("k1", "AAAA".to_string())]
+            )
+            .unwrap_err()
+            .to_string()
+            .ends_with("key k1 uses unsupported scheme rsa-pss"));
+
+        let error = RegistryKeys::from_json(
+            br#"{"keys":[{"keyid":"k2","scheme":"ecdsa-sha2-nistp256","key":"AAAA"}]}"#,
+            "test",
+        )
+        .unwrap_err();
+        assert_eq!(error.to_string(), "invalid registry key k2 in test");
+
+        let error =
+            RegistryKeys::from_json(b"[]", "https://registry.example/-/npm/v1/keys").unwrap_err();
+        assert!(error
+            .to_string()
+            .starts_with("failed to parse registry keys https://registry.example/-/npm/v1/keys:"));
+    }
+}
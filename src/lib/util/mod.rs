@@ -14,7 +14,9 @@ pub fn parse_library_name(lib: String) -> (String, String) {
         return (lib, String::new());
     }
 
-    if let Some(version_separator) = lib.rfind('@') {
+    // The first `@` ends the name: a range never needs one, but a git URL
+    // (`git+ssh://git@host/repo.git`) does.
+    if let Some(version_separator) = lib.find('@') {
         if version_separator > 0 {
             return (
                 lib[..version_separator].to_string(),
@@ -61,6 +63,10 @@ pub(crate) mod test_support {
             Ok(Self { root })
         }
 
+        pub(crate) fn path(&self) -> &Path {
+            &self.root
+        }
+
         pub(crate) fn copy_fixture<P: AsRef<Path>, Q: AsRef<Path>>(
             &self,
             fixture: P,
@@ -73,6 +79,19 @@ pub(crate) mod test_support {
             fs::copy(fixture.as_ref(), &destination)?;
             Ok(destination)
         }
+
+        pub(crate) fn write<Q: AsRef<Path>, C: AsRef<[u8]>>(
+            &self,
+            destination: Q,
+            contents: C,
+        ) -> io::Result<PathBuf> {
+            let destination = self.root.join(destination.as_ref());
+            if let Some(parent) = destination.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(&destination, contents)?;
+            Ok(destination)
+        }
     }
 
     impl Drop for TempProject {
@@ -80,6 +99,257 @@ pub(crate) mod test_support {
             let _ = fs::remove_dir_all(&self.root);
         }
     }
+
+    /// Build a gzipped npm tarball holding `files` under the `package/` prefix.
+    pub(crate) fn package_tarball(files: &[(&str, &str)]) -> Vec<u8> {
+        use flate2::{write::GzEncoder, Compression};
+        use tar::{Builder, Header};
+
+        let mut builder = Builder::new(GzEncoder::new(Vec::new(), Compression::default()));
+        for (path, contents) in files {
+            let mut header = Header::new_gnu();
+            header.set_size(contents.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder
+                .append_data(&mut header, format!("package/{path}"), contents.as_bytes())
+                .unwrap();
+        }
+        builder.into_inner().unwrap().finish().unwrap()
+    }
+
+    /// A bare git repository under a test directory, fed from a work tree
+    /// whose commits are pushed to it, for git dependency tests.
+    pub(crate) struct GitRepository {
+        work: PathBuf,
+        /// The bare repository, cloneable as `file://<path>`.
+        pub(crate) path: PathBuf,
+    }
+
+    impl GitRepository {
+        pub(crate) fn new(root: &Path) -> Self {
+            let work = root.join("git-work");
+            let path = root.join("git-remote.git");
+            fs::create_dir_all(&work).unwrap();
+            git(
+                root,
+                &["init", "--quiet", "--bare", &path.to_string_lossy()],
+            );
+            git(&path, &["symbolic-ref", "HEAD", "refs/heads/main"]);
+            git(&work, &["init", "--quiet"]);
+            git(&work, &["symbolic-ref", "HEAD", "refs/heads/main"]);
+            Self { work, path }
+        }
+
+        pub(crate) fn url(&self) -> String {
+            format!("file://{}", self.path.display())
+        }
+
+        /// Write `files`, commit them (tagging the commit when `tag` is set),
+        /// push to the bare repository, and return the commit SHA.
+        pub(crate) fn commit(&self, files: &[(&str, &str)], tag: Option<&str>) -> String {
+            for (path, contents) in files {
+                let path = self.work.join(path);
+                fs::create_dir_all(path.parent().unwrap()).unwrap();
+                fs::write(path, contents).unwrap();
+            }
+            git(&self.work, &["add", "--all"]);
+            git(
+                &self.work,
+                &[
+                    "-c",
+                    "user.name=rpm",
+                    "-c",
+                    "user.email=rpm@example.invalid",
+                    "-c",
+                    "commit.gpgsign=false",
+                    "commit",
+                    "--quiet",
+                    "--message",
+                    "commit",
+                ],
+            );
+            if let Some(tag) = tag {
+                git(&self.work, &["tag", tag]);
+            }
+            let remote = self.path.to_string_lossy();
+            git(
+                &self.work,
+                &["push", "--quiet", "--tags", &remote, "HEAD:refs/heads/main"],
+            );
+            git(&self.work, &["rev-parse", "HEAD"]).trim().to_string()
+        }
+    }
+
+    fn git(current_dir: &Path, args: &[&str]) -> String {
+        let output = std::process::Command::new("git")
+            .args(args)
+            .current_dir(current_dir)
+            .output()
+            .unwrap();
+        assert!(
+            output.status.success(),
+            "git {args:?} failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        String::from_utf8(output.stdout).unwrap()
+    }
+
+    /// Key id of the `tests/fixtures/signatures` registry key.
+    pub(crate) const REGISTRY_KEY_ID: &str = "SHA256:rpm-fixture-registry-key";
+
+    /// Base64 DER signature of `{package_key}:{integrity}` by the fixture
+    /// registry key, as a registry would publish it in `dist.signatures`.
+    pub(crate) fn registry_signature(package_key: &str, integrity: &str) -> String {
+        use base64::{engine::general_purpose::STANDARD, Engine as _};
+        use p256::{
+            ecdsa::{signature::Signer, Signature, SigningKey},
+            pkcs8::DecodePrivateKey,
+        };
+
+        let pem = fs::read_to_string(fixture_path(&["signatures", "registry.key"])).unwrap();
+        let key = SigningKey::from_pkcs8_pem(&pem).unwrap();
+        let signature: Signature = key.sign(format!("{package_key}:{integrity}").as_bytes());
+        STANDARD.encode(signature.to_der().as_bytes())
+    }
+
+    /// One canned response served by [`spawn_http_server`].
+    pub(crate) struct TestResponse {
+        pub(crate) status: u16,
+        pub(crate) headers: Vec<(String, String)>,
+        pub(crate) body: Vec<u8>,
+    }
+
+    impl TestResponse {
+        pub(crate) fn new(status: u16, body: impl Into<Vec<u8>>) -> Self {
+            Self {
+                status,
+                headers: Vec::new(),
+                body: body.into(),
+            }
+        }
+
+        pub(crate) fn header(mut self, name: &str, value: &str) -> Self {
+            self.headers.push((name.to_string(), value.to_string()));
+            self
+        }
+    }
+
+    /// Serve `responses` in order, one per connection, on a loopback port.
+    ///
+    /// Returns the `http://127.0.0.1:<port>` base URL and a handle that yields
+    /// the raw requests (request line and headers, lower-cased header names,
+    /// then a blank line and the body when there is one) once every response
+    /// has been served. This stands in for a
+    /// registry without reaching the network.
+    pub(crate) async fn spawn_http_server(
+        responses: Vec<TestResponse>,
+    ) -> (String, tokio::task::JoinHandle<Vec<String>>) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let handle = tokio::spawn(async move {
+            let mut requests = Vec::new();
+            for response in responses {
+                let (stream, _) = listener.accept().await.unwrap();
+                requests.push(serve_connection(stream, response).await);
+            }
+            requests
+        });
+        (base_url, handle)
+    }
+
+    /// Like [`spawn_http_server`], but over TLS with the self-signed
+    /// `tests/fixtures/tls/localhost.crt` certificate, at
+    /// `https://localhost:<port>`. A connection whose handshake fails is
+    /// recorded as `tls handshake failed` and consumes its response.
+    pub(crate) async fn spawn_https_server(
+        responses: Vec<TestResponse>,
+    ) -> (String, tokio::task::JoinHandle<Vec<String>>) {
+        let certificate = fs::read(fixture_path(&["tls", "localhost.crt"])).unwrap();
+        let key = fs::read(fixture_path(&["tls", "localhost.key"])).unwrap();
+        let identity = native_tls::Identity::from_pkcs8(&certificate, &key).unwrap();
+        let acceptor =
+            tokio_native_tls::TlsAcceptor::from(native_tls::TlsAcceptor::new(identity).unwrap());
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!(
+            "https://localhost:{}",
+            listener.local_addr().unwrap().port()
+        );
+        let handle = tokio::spawn(async move {
+            let mut requests = Vec::new();
+            for response in responses {
+                let (stream, _) = listener.accept().await.unwrap();
+                match acceptor.accept(stream).await {
+                    Ok(stream) => requests.push(serve_connection(stream, response).await),
+                    Err(_) => requests.push("tls handshake failed".to_string()),
+                }
+            }
+            requests
+        });
+        (base_url, handle)
+    }
+
+    async fn serve_connection<S>(mut stream: S, response: TestResponse) -> String
+    where
+        S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin,
+    {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let mut buffer = Vec::new();
+        let mut chunk = [0_u8; 4096];
+        let head_end = loop {
+            let read = stream.read(&mut chunk).await.unwrap();
+            assert!(read > 0, "client closed before sending a request");
+            buffer.extend_from_slice(&chunk[..read]);
+            if let Some(end) = buffer.windows(4).position(|window| window == b"\r\n\r\n") {
+                break end + 4;
+            }
+        };
+        let head = String::from_utf8_lossy(&buffer[..head_end]).to_string();
+        let content_length = head
+            .lines()
+            .filter_map(|line| line.split_once(':'))
+            .find(|(name, _)| name.eq_ignore_ascii_case("content-length"))
+            .and_then(|(_, value)| value.trim().parse::<usize>().ok())
+            .unwrap_or(0);
+        while buffer.len() < head_end + content_length {
+            let read = stream.read(&mut chunk).await.unwrap();
+            if read == 0 {
+                break;
+            }
+            buffer.extend_from_slice(&chunk[..read]);
+        }
+        let mut request = head
+            .lines()
+            .enumerate()
+            .map(|(index, line)| match line.split_once(':') {
+                Some((name, value)) if index > 0 => {
+                    format!("{}:{value}", name.to_ascii_lowercase())
+                }
+                _ => line.to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        if buffer.len() > head_end {
+            request.push_str("\n\n");
+            request.push_str(&String::from_utf8_lossy(&buffer[head_end..]));
+        }
+
+        let mut reply = format!(
+            "HTTP/1.1 {} Test\r\ncontent-length: {}\r\nconnection: close\r\n",
+            response.status,
+            response.body.len()
+        );
+        for (name, value) in &response.headers {
+            reply.push_str(&format!("{name}: {value}\r\n"));
+        }
+        reply.push_str("\r\n");
+        stream.write_all(reply.as_bytes()).await.unwrap();
+        stream.write_all(&response.body).await.unwrap();
+        stream.shutdown().await.unwrap();
+        request
+    }
 }
 
 #[cfg(test)]
@@ -138,4 +408,12 @@ mod tests {
         assert_eq!(lib_name, "@rpm-fixture/comparator");
         assert_eq!(version, ">=1.0.0 <2.0.0");
     }
+
+    #[test]
+    fn parse_keeps_at_signs_inside_git_specs() {
+        let (lib_name, version) =
+            parse_library_name("lib@git+ssh://git@example.com/org/lib.git#v1".to_owned());
+        assert_eq!(lib_name, "lib");
+        assert_eq!(version, "git+ssh://git@example.com/org/lib.git#v1");
+    }
 }
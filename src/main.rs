@@ -1,4 +1,5 @@
-use rpm::command::{working_process, Command};
+use rpm::api::{NetworkMode, SignatureKeySource};
+use rpm::command::{working_process, Command, RegistryCommand};
 use rpm::lockfile::LockFile;
 use rpm::opt::Opt;
 use rpm::package_manifest::PackageManifest;
@@ -21,18 +22,63 @@ fn exit_with_status(status: i32) -> ! {
     std::process::exit(status);
 }
 
+fn print_report(report: &working_process::InstallReport) {
+    if let Some(summary) = report.held_back_summary() {
+        eprintln!("info: {summary}");
+    }
+    if let Some(summary) = report.deprecation_summary() {
+        eprintln!("warn: {summary}");
+    }
+    if let Some(summary) = report.peer_summary() {
+        eprintln!("warn: {summary}");
+    }
+    if let Some(summary) = report.ignored_overrides_summary() {
+        eprintln!("warn: {summary}");
+    }
+}
+
 async fn run(opt: Opt) -> std::io::Result<MainOutcome> {
     match opt.cmd {
-        Command::Install => {
+        Command::Install {
+            offline,
+            prefer_offline,
+            network_concurrency,
+            verify_signatures,
+            registry_keys,
+            before,
+        } => {
             println!("installing...");
             let time = std::time::Instant::now();
-            if let Err(error) = working_process::install().await {
-                if let Some(status) = rpm::node_linker::lifecycle_exit_status(&error) {
-                    eprintln!("rpm failed: {error}");
-                    return Ok(MainOutcome::ChildStatus(status));
+            let network_mode = if offline {
+                NetworkMode::Offline
+            } else if prefer_offline {
+                NetworkMode::PreferOffline
+            } else {
+                NetworkMode::Online
+            };
+            let signature_keys = match (verify_signatures, registry_keys) {
+                (false, _) => None,
+                (true, Some(path)) => Some(SignatureKeySource::File(path)),
+                (true, None) => Some(SignatureKeySource::Registry),
+            };
+            let options = working_process::InstallOptions {
+                network_mode,
+                network_concurrency,
+                signature_keys,
+                before,
+                ..working_process::InstallOptions::from_npmrc(std::path::Path::new("."))?
+            };
+            let report = match working_process::install(&options).await {
+                Ok(report) => report,
+                Err(error) => {
+                    if let Some(status) = rpm::node_linker::lifecycle_exit_status(&error) {
+                        eprintln!("rpm failed: {error}");
+                        return Ok(MainOutcome::ChildStatus(status));
+                    }
+                    return Err(error);
                 }
-                return Err(error);
-            }
+            };
+            print_report(&report);
             println!("time: {:.2}s", time.elapsed().as_secs_f32());
             Ok(MainOutcome::ExitCode(ExitCode::SUCCESS))
         }
@@ -40,12 +86,80 @@ async fn run(opt: Opt) -> std::io::Result<MainOutcome> {
             let time = std::time::Instant::now();
             let mut pkg = PackageManifest::read_default()?;
             let mut lockfile = LockFile::load()?;
-            working_process::add(&mut pkg, &mut lockfile, libs, dev, true).await?;
+            let report = working_process::add(&mut pkg, &mut lockfile, libs, dev, true).await?;
             lockfile.save()?;
             pkg.save_to_path("./package.json")?;
+            print_report(&report);
             println!("time: {:.2}s", time.elapsed().as_secs_f32());
             Ok(MainOutcome::ExitCode(ExitCode::SUCCESS))
         }
+        Command::Dedupe => {
+            let outcome = working_process::dedupe(std::path::Path::new("."))?;
+            for key in &outcome.removed {
+                println!("removed {key}");
+            }
+            println!(
+                "kept {} packages, removed {}; run `rpm install` to update node_modules",
+                outcome.kept,
+                outcome.removed.len()
+            );
+            Ok(MainOutcome::ExitCode(ExitCode::SUCCESS))
+        }
+        Command::Info {
+            package,
+            fields,
+            json,
+        } => {
+            let options = working_process::InfoOptions { fields, json };
+            let output =
+                working_process::info(std::path::Path::new("."), &package, &options).await?;
+            if !output.is_empty() {
+                println!("{output}");
+            }
+            Ok(MainOutcome::ExitCode(ExitCode::SUCCESS))
+        }
+        Command::Pack { pack_destination } => {
+            let (packed, path) =
+                working_process::pack(std::path::Path::new("."), pack_destination.as_deref())?;
+            println!("package: {}@{}", packed.name, packed.version);
+            for file in &packed.files {
+                println!("  {file}");
+            }
+            println!("files: {}", packed.files.len());
+            println!("size: {} bytes", packed.bytes.len());
+            println!("shasum: {}", packed.shasum);
+            println!("integrity: {}", packed.integrity);
+            println!("tarball: {}", path.display());
+            Ok(MainOutcome::ExitCode(ExitCode::SUCCESS))
+        }
+        Command::Publish { tag, dry_run } => {
+            let outcome = working_process::publish(
+                std::path::Path::new("."),
+                working_process::PublishOptions { tag, dry_run },
+            )
+            .await?;
+            let packed = &outcome.packed;
+            for file in &packed.files {
+                println!("  {file}");
+            }
+            println!("integrity: {}", packed.integrity);
+            let verb = if outcome.dry_run {
+                "would publish"
+            } else {
+                "published"
+            };
+            println!(
+                "{verb} {}@{} to {} with tag {}",
+                packed.name, packed.version, outcome.registry, outcome.tag
+            );
+            Ok(MainOutcome::ExitCode(ExitCode::SUCCESS))
+        }
+        Command::Registry {
+            cmd: RegistryCommand::Serve { host, port },
+        } => {
+            working_process::serve_registry(std::path::Path::new("."), &host, port).await?;
+            Ok(MainOutcome::ExitCode(ExitCode::SUCCESS))
+        }
         Command::Run { script_key } => {
             let result = working_process::run(script_key).await;
             match result {
@@ -113,7 +227,14 @@ mod tests {
         let previous = env::current_dir().unwrap();
         env::set_current_dir(&root).unwrap();
         let result = run(rpm::opt::Opt {
-            cmd: Command::Install,
+            cmd: Command::Install {
+                offline: false,
+                prefer_offline: false,
+                network_concurrency: None,
+                verify_signatures: false,
+                registry_keys: None,
+                before: None,
+            },
         })
         .await
         .unwrap();